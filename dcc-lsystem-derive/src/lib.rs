@@ -1,5 +1,7 @@
 extern crate proc_macro;
 
+mod lsystem;
+
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
@@ -25,6 +27,10 @@ pub fn derive_turtle_container(input: TokenStream) -> TokenStream {
                                 fn inner(&self) -> &dyn dcc_lsystem::turtle::MovingTurtle<Item = Self::Item> {
                                     &self.#field_ident
                                 }
+
+                                fn turtles_mut(&mut self) -> Vec<&mut dyn dcc_lsystem::turtle::MovingTurtle<Item = Self::Item>> {
+                                    vec![&mut self.#field_ident]
+                                }
                             }
                         });
                     }
@@ -39,3 +45,56 @@ pub fn derive_turtle_container(input: TokenStream) -> TokenStream {
         TokenStream::new()
     }
 }
+
+/// Builds a `TurtleLSystemBuilder` from a whole grammar at once.
+///
+/// The grammar has three parts:
+///
+/// * `tokens { ... }` associates each token with a `TurtleAction`.  Variants of `TurtleAction`
+///   can be written without the enum name, e.g. `Forward(10.0)`; any other expression is used
+///   as it is.
+/// * `axiom: ...;` sets the axiom.
+/// * `rules { ... }` holds the transformation rules, each ending with `;`.
+///
+/// Tokens are identifiers, numbers, single punctuation characters or string literals.
+/// Brackets in the axiom and rules are split into separate tokens, but must be quoted where they
+/// don't come in pairs, e.g. when registering them.
+///
+/// Every token used by the axiom and rules is checked against the registered tokens when the
+/// macro is expanded, so typos are compile errors.  The macro evaluates to a
+/// `Result<TurtleLSystemBuilder, LSystemError>`.
+///
+/// # Example
+/// ```rust
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use dcc_lsystem_derive::lsystem;
+///
+/// let builder = lsystem! {
+///     tokens {
+///         X => Nothing,
+///         F => Forward(10.0),
+///         "+" => Rotate(25.0),
+///         "-" => Rotate(-25.0),
+///         "[" => Push,
+///         "]" => Pop,
+///     }
+///     axiom: X;
+///     rules {
+///         X => F + [[X] - X] - F [- F X] + X;
+///         F => F F;
+///     }
+/// }?;
+///
+/// let (mut system, _renderer) = builder.finish()?;
+/// system.step();
+/// assert_eq!(system.render(), "F+[[X]-X]-F[-FX]+X");
+/// # Ok(())
+/// # }
+/// ```
+#[proc_macro]
+pub fn lsystem(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as lsystem::Grammar)
+        .expand()
+        .into()
+}
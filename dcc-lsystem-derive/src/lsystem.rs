@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{braced, Error, Expr, Ident, Lit, Result, Token};
+
+/// A symbol of the grammar, together with where it was written.
+struct Symbol {
+    name: String,
+    span: Span,
+}
+
+impl Symbol {
+    fn new<S: Into<String>>(name: S, span: Span) -> Self {
+        Self {
+            name: name.into(),
+            span,
+        }
+    }
+
+    /// Converts a single token into a symbol.  Bracketed groups can't stand on their own, so
+    /// they're rejected.
+    fn from_tree(tree: TokenTree) -> Result<Self> {
+        match tree {
+            TokenTree::Ident(ident) => Ok(Self::new(ident.to_string(), ident.span())),
+            TokenTree::Punct(punct) => Ok(Self::new(punct.as_char().to_string(), punct.span())),
+            TokenTree::Literal(literal) => {
+                let span = literal.span();
+
+                match Lit::new(literal) {
+                    Lit::Str(string) => {
+                        let name = string.value();
+
+                        if name.is_empty() || name.contains(char::is_whitespace) {
+                            return Err(Error::new(
+                                span,
+                                "tokens must be non-empty and can't contain whitespace",
+                            ));
+                        }
+
+                        Ok(Self::new(name, span))
+                    }
+                    Lit::Int(int) => Ok(Self::new(int.to_string(), span)),
+                    _ => Err(Error::new(span, "expected a token")),
+                }
+            }
+            TokenTree::Group(group) => Err(Error::new(
+                group.span(),
+                "brackets must be quoted when used on their own, e.g. \"[\"",
+            )),
+        }
+    }
+}
+
+/// Converts a sequence of tokens into symbols, splitting bracketed groups into their opening
+/// bracket, contents and closing bracket.
+fn symbols(stream: TokenStream, symbols: &mut Vec<Symbol>) -> Result<()> {
+    for tree in stream {
+        if let TokenTree::Group(group) = &tree {
+            let brackets = match group.delimiter() {
+                Delimiter::Bracket => Some(("[", "]")),
+                Delimiter::Parenthesis => Some(("(", ")")),
+                Delimiter::Brace => Some(("{", "}")),
+                Delimiter::None => None,
+            };
+
+            if let Some((open, _)) = brackets {
+                symbols.push(Symbol::new(open, group.span_open()));
+            }
+
+            self::symbols(group.stream(), symbols)?;
+
+            if let Some((_, close)) = brackets {
+                symbols.push(Symbol::new(close, group.span_close()));
+            }
+        } else {
+            symbols.push(Symbol::from_tree(tree)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses symbols up to the next `;`, which is consumed.
+fn parse_symbols(input: ParseStream) -> Result<Vec<Symbol>> {
+    let mut stream = TokenStream::new();
+
+    while !input.is_empty() && !input.peek(Token![;]) {
+        stream.extend([input.parse::<TokenTree>()?]);
+    }
+
+    input.parse::<Token![;]>()?;
+
+    let mut parsed = Vec::new();
+    symbols(stream, &mut parsed)?;
+    Ok(parsed)
+}
+
+/// A token together with the turtle action it performs.
+struct TokenEntry {
+    symbol: Symbol,
+    action: Expr,
+}
+
+impl Parse for TokenEntry {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let symbol = Symbol::from_tree(input.parse()?)?;
+        input.parse::<Token![=>]>()?;
+        let action = input.parse()?;
+
+        Ok(Self { symbol, action })
+    }
+}
+
+/// A transformation rule.
+struct Rule {
+    predecessor: Symbol,
+    successor: Vec<Symbol>,
+}
+
+impl Parse for Rule {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let predecessor = Symbol::from_tree(input.parse()?)?;
+        input.parse::<Token![=>]>()?;
+        let successor = parse_symbols(input)?;
+
+        Ok(Self {
+            predecessor,
+            successor,
+        })
+    }
+}
+
+/// The input to `lsystem!`.
+pub(crate) struct Grammar {
+    tokens: Vec<TokenEntry>,
+    axiom: Vec<Symbol>,
+    rules: Vec<Rule>,
+}
+
+impl Parse for Grammar {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut tokens = Vec::new();
+        let mut axiom = None;
+        let mut rules = Vec::new();
+
+        while !input.is_empty() {
+            let section: Ident = input.parse()?;
+
+            match section.to_string().as_str() {
+                "tokens" => {
+                    let content;
+                    braced!(content in input);
+                    tokens.extend(Punctuated::<TokenEntry, Token![,]>::parse_terminated(
+                        &content,
+                    )?);
+                }
+                "axiom" => {
+                    if axiom.is_some() {
+                        return Err(Error::new(section.span(), "the axiom is set twice"));
+                    }
+
+                    input.parse::<Token![:]>()?;
+                    let symbols = parse_symbols(input)?;
+
+                    if symbols.is_empty() {
+                        return Err(Error::new(section.span(), "the axiom can't be empty"));
+                    }
+
+                    axiom = Some(symbols);
+                }
+                "rules" => {
+                    let content;
+                    braced!(content in input);
+
+                    while !content.is_empty() {
+                        rules.push(content.parse()?);
+                    }
+                }
+                _ => {
+                    return Err(Error::new(
+                        section.span(),
+                        "expected `tokens`, `axiom` or `rules`",
+                    ))
+                }
+            }
+        }
+
+        let axiom = axiom.ok_or_else(|| Error::new(Span::call_site(), "missing `axiom`"))?;
+
+        Ok(Self {
+            tokens,
+            axiom,
+            rules,
+        })
+    }
+}
+
+/// Returns `action`, with bare `TurtleAction` variants such as `Forward(10.0)` or `Push`
+/// qualified by their enum.
+fn qualify(action: &Expr) -> TokenStream {
+    let variant = match action {
+        Expr::Call(call) => match &*call.func {
+            Expr::Path(path) => path.path.get_ident(),
+            _ => None,
+        },
+        Expr::Path(path) => path.path.get_ident(),
+        Expr::Struct(structure) => structure.path.get_ident(),
+        _ => None,
+    };
+
+    match variant {
+        // Lowercase names are left alone, as they're probably variables
+        Some(ident) if ident.to_string().starts_with(char::is_uppercase) => {
+            quote!(dcc_lsystem::turtle::TurtleAction::#action)
+        }
+        _ => quote!(#action),
+    }
+}
+
+impl Grammar {
+    /// Checks that every symbol used by the axiom and rules has been registered exactly once,
+    /// and that no symbol has more than one rule.
+    fn validate(&self) -> Result<()> {
+        let mut registered = HashSet::new();
+
+        for entry in &self.tokens {
+            if !registered.insert(entry.symbol.name.as_str()) {
+                return Err(Error::new(
+                    entry.symbol.span,
+                    format!("the token `{}` is registered twice", entry.symbol.name),
+                ));
+            }
+        }
+
+        let mut predecessors = HashSet::new();
+
+        for rule in &self.rules {
+            if !predecessors.insert(rule.predecessor.name.as_str()) {
+                return Err(Error::new(
+                    rule.predecessor.span,
+                    format!("the token `{}` already has a rule", rule.predecessor.name),
+                ));
+            }
+        }
+
+        let used = self.axiom.iter().chain(
+            self.rules
+                .iter()
+                .flat_map(|rule| std::iter::once(&rule.predecessor).chain(&rule.successor)),
+        );
+
+        for symbol in used {
+            if !registered.contains(symbol.name.as_str()) {
+                return Err(Error::new(
+                    symbol.span,
+                    format!("unknown token `{}`", symbol.name),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn expand(&self) -> TokenStream {
+        if let Err(error) = self.validate() {
+            return error.to_compile_error();
+        }
+
+        let join = |symbols: &[Symbol]| {
+            symbols
+                .iter()
+                .map(|symbol| symbol.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let tokens = self.tokens.iter().map(|entry| {
+            let name = &entry.symbol.name;
+            let action = qualify(&entry.action);
+            quote!(builder.token(#name, #action)?;)
+        });
+
+        let axiom = join(&self.axiom);
+
+        let rules = self.rules.iter().map(|rule| {
+            let rule = format!("{} => {}", rule.predecessor.name, join(&rule.successor));
+            quote!(builder.rule(#rule)?;)
+        });
+
+        quote! {
+            (|| -> ::core::result::Result<
+                dcc_lsystem::turtle::TurtleLSystemBuilder,
+                dcc_lsystem::LSystemError,
+            > {
+                let mut builder = dcc_lsystem::turtle::TurtleLSystemBuilder::new();
+                #(#tokens)*
+                builder.axiom(#axiom)?;
+                #(#rules)*
+                ::core::result::Result::Ok(builder)
+            })()
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use dcc_lsystem::{ArenaId, LSystem, LSystemBuilder};
+
+const STEPS: usize = 20;
+
+/// Builds the dragon curve `X -> X+YF+`, `Y -> -FX-Y` with axiom `FX`, returning
+/// the system together with its transformation rules.
+fn dragon_curve() -> (LSystem, HashMap<ArenaId, Vec<ArenaId>>) {
+    let mut builder = LSystemBuilder::new();
+
+    let x = builder.token("X").unwrap();
+    let y = builder.token("Y").unwrap();
+    let f = builder.token("F").unwrap();
+    let plus = builder.token("+").unwrap();
+    let minus = builder.token("-").unwrap();
+
+    let mut rules = HashMap::new();
+    rules.insert(x, vec![x, plus, y, f, plus]);
+    rules.insert(y, vec![minus, f, x, minus, y]);
+
+    builder.axiom(vec![f, x]).unwrap();
+    for (predecessor, successor) in rules.iter() {
+        builder
+            .transformation_rule(*predecessor, successor.clone())
+            .unwrap();
+    }
+
+    for constant in [f, plus, minus].iter() {
+        rules.insert(*constant, vec![*constant]);
+    }
+
+    (builder.finish().unwrap(), rules)
+}
+
+/// The expansion `LSystem::step` used to perform, cloning the successor of every symbol.
+fn step_by_cloning(state: &[ArenaId], rules: &HashMap<ArenaId, Vec<ArenaId>>) -> Vec<ArenaId> {
+    let mut next_state = Vec::new();
+
+    for id in state.iter() {
+        next_state.extend(rules[id].clone());
+    }
+
+    next_state
+}
+
+fn dragon_curve_step(c: &mut Criterion) {
+    let (system, rules) = dragon_curve();
+
+    let mut group = c.benchmark_group("dragon curve (20 steps)");
+    group.sample_size(10);
+
+    group.bench_function("step", |b| {
+        b.iter(|| {
+            let mut system = system.clone();
+            system.step_by(STEPS);
+            system.get_state().len()
+        })
+    });
+
+    group.bench_function("clone per symbol", |b| {
+        b.iter(|| {
+            let mut state = system.get_state().to_vec();
+            for _ in 0..STEPS {
+                state = step_by_cloning(&state, &rules);
+            }
+            state.len()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, dragon_curve_step);
+criterion_main!(benches);
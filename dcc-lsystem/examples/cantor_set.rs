@@ -48,7 +48,7 @@ fn main() -> Result<(), LSystemError> {
     }
 
     let padding: u32 = 5;
-    let render_width = 2 * padding + width as u32;
+    let render_width = 2 * padding + width;
     let render_height = 2 * padding + vertical_spacing * (step_limit - 1);
 
     let mut buffer = ImageBuffer::new(render_width, render_height);
@@ -3,22 +3,12 @@ use image::Rgb;
 use dcc_lsystem::image_renderer::save_png;
 use dcc_lsystem::renderer::ImageRendererOptionsBuilder;
 use dcc_lsystem::renderer::Renderer;
-use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
-use dcc_lsystem::LSystemError;
+use dcc_lsystem::{presets, LSystemError};
 use std::path::Path;
 
 fn main() -> Result<(), LSystemError> {
-    let mut builder = TurtleLSystemBuilder::new();
-
-    builder
-        .token("X", TurtleAction::Nothing)?
-        .token("Y", TurtleAction::Nothing)?
-        .token("F", TurtleAction::Forward(30))?
-        .token("+", TurtleAction::Rotate(-90))?
-        .token("-", TurtleAction::Rotate(90))?
-        .axiom("F X")?
-        .rule("X => X + Y F +")?
-        .rule("Y => - F X - Y")?;
+    let mut builder = presets::dragon_curve();
+    builder.scale(3.0);
 
     let (mut system, renderer) = builder.finish()?;
     system.step_by(15);
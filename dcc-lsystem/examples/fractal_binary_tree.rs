@@ -2,24 +2,11 @@ use image::Rgb;
 
 use dcc_lsystem::renderer::ImageRendererOptionsBuilder;
 use dcc_lsystem::renderer::Renderer;
-use dcc_lsystem::turtle::TurtleAction;
-use dcc_lsystem::turtle::TurtleLSystemBuilder;
-use dcc_lsystem::LSystemError;
+use dcc_lsystem::{presets, LSystemError};
 
 fn main() -> Result<(), LSystemError> {
-    let mut builder = TurtleLSystemBuilder::new();
-
-    builder
-        .token("0", TurtleAction::Forward(50))?
-        .token("1", TurtleAction::Forward(50))?
-        .token("L", TurtleAction::Rotate(45))?
-        .token("R", TurtleAction::Rotate(-45))?
-        .token("[", TurtleAction::Push)?
-        .token("]", TurtleAction::Pop)?
-        .axiom("0")?
-        .rule("1 => 1 1")?
-        .rule("0 => 1 [ L 0 ] R 0")?
-        .rotate(90);
+    let mut builder = presets::fractal_binary_tree();
+    builder.scale(5.0);
 
     let (mut system, renderer) = builder.finish()?;
     system.step_by(9);
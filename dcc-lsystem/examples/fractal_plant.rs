@@ -2,24 +2,12 @@ use image::Rgb;
 
 use dcc_lsystem::image_renderer::save_png;
 use dcc_lsystem::renderer::{ImageRendererOptionsBuilder, Renderer};
-use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
-use dcc_lsystem::LSystemError;
+use dcc_lsystem::{presets, LSystemError};
 use std::path::Path;
 
 fn main() -> Result<(), LSystemError> {
-    let mut builder = TurtleLSystemBuilder::new();
-
-    builder
-        .token("X", TurtleAction::Nothing)?
-        .token("F", TurtleAction::Forward(200))?
-        .token("+", TurtleAction::Rotate(25))?
-        .token("-", TurtleAction::Rotate(-25))?
-        .token("[", TurtleAction::Push)?
-        .token("]", TurtleAction::Pop)?
-        .axiom("X")?
-        .rule("X => F + [ [ X ] - X ] - F [ - F X ] + X")?
-        .rule("F => F F")?
-        .rotate(70);
+    let mut builder = presets::fractal_plant();
+    builder.scale(20.0);
 
     let (mut system, renderer) = builder.finish()?;
     system.step_by(6);
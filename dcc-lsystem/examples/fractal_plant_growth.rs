@@ -0,0 +1,26 @@
+use image::Rgb;
+
+use dcc_lsystem::renderer::{Renderer, StepAnimationRendererOptionsBuilder};
+use dcc_lsystem::{presets, LSystemError};
+
+fn main() -> Result<(), LSystemError> {
+    let mut builder = presets::fractal_plant();
+    builder.scale(2.0);
+
+    let (system, renderer) = builder.finish()?;
+
+    let options = StepAnimationRendererOptionsBuilder::new()
+        .filename("fractal_plant_growth.gif")
+        .fps(10)
+        .steps(6)
+        .interpolation_frames(9)
+        .padding(20)
+        .thickness(3.0)
+        .fill_color(Rgb([255u8, 255u8, 255u8]))
+        .line_color(Rgb([0u8, 100u8, 0u8]))
+        .build();
+
+    renderer.render(&system, &options)?;
+
+    Ok(())
+}
@@ -1,18 +1,11 @@
 use image::Rgb;
 
 use dcc_lsystem::renderer::{Renderer, VideoRendererOptionsBuilder};
-use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
-use dcc_lsystem::LSystemError;
+use dcc_lsystem::{presets, LSystemError};
 
 fn main() -> Result<(), LSystemError> {
-    let mut builder = TurtleLSystemBuilder::new();
-
-    builder
-        .token("F", TurtleAction::Forward(30))?
-        .token("+", TurtleAction::Rotate(90))?
-        .token("-", TurtleAction::Rotate(-90))?
-        .axiom("F")?
-        .rule("F => F + F - F - F + F")?;
+    let mut builder = presets::koch_curve();
+    builder.scale(3.0);
 
     let (mut system, renderer) = builder.finish()?;
     system.step_by(4);
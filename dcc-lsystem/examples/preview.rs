@@ -0,0 +1,19 @@
+use image::Rgb;
+
+use dcc_lsystem::renderer::PreviewOptionsBuilder;
+use dcc_lsystem::{presets, LSystemError};
+
+fn main() -> Result<(), LSystemError> {
+    let mut builder = presets::fractal_plant();
+    builder.scale(20.0);
+
+    let (mut system, renderer) = builder.finish()?;
+
+    // Press space to step the system, and escape to quit
+    let options = PreviewOptionsBuilder::new()
+        .title("Fractal plant")
+        .line_color(Rgb([0u8, 100u8, 0u8]))
+        .build();
+
+    renderer.preview(&mut system, &options)
+}
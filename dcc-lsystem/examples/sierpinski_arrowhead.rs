@@ -1,20 +1,11 @@
 use image::Rgb;
 
 use dcc_lsystem::renderer::{ImageRendererOptionsBuilder, Renderer};
-use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
-use dcc_lsystem::LSystemError;
+use dcc_lsystem::{presets, LSystemError};
 
 fn main() -> Result<(), LSystemError> {
-    let mut builder = TurtleLSystemBuilder::new();
-
-    builder
-        .token("A", TurtleAction::Forward(200))?
-        .token("B", TurtleAction::Forward(200))?
-        .token("+", TurtleAction::Rotate(60))?
-        .token("-", TurtleAction::Rotate(-60))?
-        .axiom("A")?
-        .rule("A => B - A - B")?
-        .rule("B => A + B + A")?;
+    let mut builder = presets::sierpinski_arrowhead();
+    builder.scale(20.0);
 
     let (mut system, renderer) = builder.finish()?;
     system.step_by(7);
@@ -1,20 +1,11 @@
 use image::Rgb;
 
 use dcc_lsystem::renderer::{ImageRendererOptionsBuilder, Renderer};
-use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
-use dcc_lsystem::LSystemError;
+use dcc_lsystem::{presets, LSystemError};
 
 fn main() -> Result<(), LSystemError> {
-    let mut builder = TurtleLSystemBuilder::new();
-
-    builder
-        .token("F", TurtleAction::Forward(200))?
-        .token("G", TurtleAction::Forward(200))?
-        .token("+", TurtleAction::Rotate(120))?
-        .token("-", TurtleAction::Rotate(-120))?
-        .axiom("F - G - G")?
-        .rule("F => F - G + F + G - F")?
-        .rule("G => G G")?;
+    let mut builder = presets::sierpinski_triangle();
+    builder.scale(20.0);
 
     let (mut system, renderer) = builder.finish()?;
     system.step_by(7);
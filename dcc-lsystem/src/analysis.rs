@@ -0,0 +1,309 @@
+//! Predicts how an [`LSystem`] grows without iterating it, and checks grammars for likely
+//! mistakes.
+//!
+//! The production matrix of a system has an entry `(i, j)` counting how many times token `j`
+//! appears in the successor of token `i`.  Multiplying the token counts of a state by this
+//! matrix gives the token counts after one more step, so raising the matrix to the `n`-th
+//! power predicts the state `n` steps ahead in `O(k³ log n)` time, where `k` is the number
+//! of distinct tokens.
+//!
+//! All arithmetic saturates at `usize::MAX`.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::LSystemBuilder;
+//!
+//! let mut builder = LSystemBuilder::new();
+//! let a = builder.token("A")?;
+//! let b = builder.token("B")?;
+//! builder.axiom(vec![a])?;
+//! builder.transformation_rule(a, vec![a, b])?;
+//! builder.transformation_rule(b, vec![a])?;
+//! let system = builder.finish()?;
+//!
+//! // Check the state will fit in memory before stepping
+//! assert_eq!(system.predicted_len(20), 17_711);
+//! assert_eq!(system.predicted_counts(20), vec![10_946, 6_765]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Typos in large grammars usually produce a wrong picture rather than an error.
+//! [`LSystemBuilder::analyze`](crate::LSystemBuilder::analyze) and
+//! [`TurtleLSystemBuilder::analyze`](crate::turtle::TurtleLSystemBuilder::analyze) look for
+//! the symptoms of such typos, returning a [`GrammarWarning`] for each one they find.
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{ArenaId, LSystem};
+
+/// A likely mistake in a grammar, found by
+/// [`LSystemBuilder::analyze`](crate::LSystemBuilder::analyze).  Each warning names the
+/// token it concerns.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum GrammarWarning {
+    /// The token never appears in the state, however many steps are taken.
+    Unreachable(String),
+    /// The token appears in the state and looks like a variable, but has no rule.
+    MissingRule(String),
+    /// The token has a rule, but its successors only contain tokens which look like variables
+    /// and have no rules.
+    UnproductiveRule(String),
+}
+
+impl fmt::Display for GrammarWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GrammarWarning::Unreachable(name) => {
+                write!(f, "`{}` can't be reached from the axiom", name)
+            }
+            GrammarWarning::MissingRule(name) => write!(f, "`{}` has no rule", name),
+            GrammarWarning::UnproductiveRule(name) => write!(
+                f,
+                "the rule for `{}` only produces tokens without rules",
+                name
+            ),
+        }
+    }
+}
+
+/// Checks a grammar for likely mistakes.  `successors` holds every successor of each token,
+/// indexed by [`ArenaId`], `axiom` is the axiom if there is one, and `is_variable` says
+/// whether a token is expected to have a rule.
+pub(crate) fn lint<F>(
+    names: &[&str],
+    successors: &[Vec<&[ArenaId]>],
+    axiom: Option<&[ArenaId]>,
+    is_variable: F,
+) -> Vec<GrammarWarning>
+where
+    F: Fn(ArenaId) -> bool,
+{
+    let has_rule = |id: ArenaId| !successors[id.0].is_empty();
+    let is_dead = |id: ArenaId| !has_rule(id) && is_variable(id);
+
+    // Without an axiom every token is treated as reachable
+    let mut reachable = vec![axiom.is_none(); names.len()];
+    let mut pending: Vec<ArenaId> = axiom.unwrap_or_default().to_vec();
+
+    while let Some(id) = pending.pop() {
+        if !core::mem::replace(&mut reachable[id.0], true) {
+            pending.extend(
+                successors[id.0]
+                    .iter()
+                    .flat_map(|successor| successor.iter()),
+            );
+        }
+    }
+
+    let mut warnings = Vec::new();
+
+    for (index, name) in names.iter().enumerate() {
+        let id = ArenaId(index);
+        let name = name.to_string();
+
+        if !reachable[index] {
+            warnings.push(GrammarWarning::Unreachable(name));
+        } else if is_dead(id) {
+            warnings.push(GrammarWarning::MissingRule(name));
+        } else {
+            let mut produced = successors[index]
+                .iter()
+                .flat_map(|successor| successor.iter())
+                .peekable();
+
+            if produced.peek().is_some() && produced.all(|id| is_dead(*id)) {
+                warnings.push(GrammarWarning::UnproductiveRule(name));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// A square matrix describing how many of each token a step produces.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProductionMatrix {
+    size: usize,
+    entries: Vec<usize>,
+}
+
+impl ProductionMatrix {
+    /// Returns the identity matrix for a system with `size` tokens.
+    pub fn identity(size: usize) -> Self {
+        let mut entries = vec![0; size * size];
+
+        for index in 0..size {
+            entries[index * size + index] = 1;
+        }
+
+        Self { size, entries }
+    }
+
+    /// Returns the production matrix used by the next step of `system`.
+    pub fn from_system(system: &LSystem) -> Self {
+        Self::at_step(system, system.steps())
+    }
+
+    /// Returns the production matrix used to go from step `step` to step `step + 1`.
+    fn at_step(system: &LSystem, step: usize) -> Self {
+        let size = system.token_count();
+        let productions = system.productions_at(step);
+        let mut entries = vec![0; size * size];
+
+        for from in 0..size {
+            for to in productions.successor(ArenaId(from)) {
+                entries[from * size + to.0] += 1;
+            }
+        }
+
+        Self { size, entries }
+    }
+
+    /// Returns the number of tokens the matrix describes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of times `to` appears when `from` is expanded.
+    pub fn get(&self, from: ArenaId, to: ArenaId) -> usize {
+        self.entries[from.0 * self.size + to.0]
+    }
+
+    /// Returns the product `self * other`, which applies `self` and then `other`.
+    pub fn multiply(&self, other: &Self) -> Self {
+        assert_eq!(self.size, other.size, "matrices must be the same size");
+
+        let size = self.size;
+        let mut entries = vec![0usize; size * size];
+
+        for row in 0..size {
+            for middle in 0..size {
+                let left = self.entries[row * size + middle];
+
+                if left == 0 {
+                    continue;
+                }
+
+                for column in 0..size {
+                    let entry = &mut entries[row * size + column];
+                    let product = left.saturating_mul(other.entries[middle * size + column]);
+                    *entry = entry.saturating_add(product);
+                }
+            }
+        }
+
+        Self { size, entries }
+    }
+
+    /// Returns the matrix raised to the `n`-th power, which applies it `n` times.
+    pub fn pow(&self, mut n: usize) -> Self {
+        let mut result = Self::identity(self.size);
+        let mut base = self.clone();
+
+        while n > 0 {
+            if n % 2 == 1 {
+                result = result.multiply(&base);
+            }
+
+            n /= 2;
+
+            if n > 0 {
+                base = base.multiply(&base);
+            }
+        }
+
+        result
+    }
+
+    /// Applies the matrix to a vector of token counts, indexed by [`ArenaId`].
+    pub fn apply(&self, counts: &[usize]) -> Vec<usize> {
+        assert_eq!(counts.len(), self.size, "wrong number of token counts");
+
+        let mut result = vec![0usize; self.size];
+
+        for (from, count) in counts.iter().enumerate() {
+            for (to, total) in result.iter_mut().enumerate() {
+                let produced = count.saturating_mul(self.entries[from * self.size + to]);
+                *total = total.saturating_add(produced);
+            }
+        }
+
+        result
+    }
+}
+
+/// Returns the matrix describing the next `n` steps of `system`.
+pub(crate) fn growth(system: &LSystem, n: usize) -> ProductionMatrix {
+    let mut result = ProductionMatrix::identity(system.token_count());
+    let mut step = system.steps();
+    let end = system.steps().saturating_add(n);
+
+    // The production rules only change at the edges of a rule table, so each run of
+    // steps between those edges can be exponentiated in one go.
+    while step < end {
+        let run_end = system
+            .next_table_boundary(step)
+            .map_or(end, |boundary| boundary.min(end));
+
+        let matrix = ProductionMatrix::at_step(system, step);
+        result = result.multiply(&matrix.pow(run_end - step));
+        step = run_end;
+    }
+
+    result
+}
+
+/// Returns the number of each token in the current state of `system`, indexed by [`ArenaId`].
+pub(crate) fn counts(system: &LSystem) -> Vec<usize> {
+    let mut counts = vec![0; system.token_count()];
+
+    for id in system.get_state() {
+        counts[id.0] += 1;
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LSystemBuilder;
+
+    #[test]
+    fn predictions_match_stepping() {
+        let mut builder = LSystemBuilder::new();
+
+        let x = builder.token("X").unwrap();
+        let f = builder.token("F").unwrap();
+        let plus = builder.token("+").unwrap();
+
+        builder.axiom(vec![x]).unwrap();
+        builder.transformation_rule(x, vec![x, plus, f, x]).unwrap();
+        builder.transformation_rule(f, vec![f, f]).unwrap();
+        builder.rule_table(3..5, vec![(x, vec![f])]).unwrap();
+
+        let mut system = builder.finish().unwrap();
+
+        for n in 0..8 {
+            let mut stepped = system.clone();
+            stepped.step_by(n);
+
+            assert_eq!(system.predicted_len(n), stepped.get_state().len());
+            assert_eq!(system.predicted_counts(n), super::counts(&stepped));
+        }
+
+        // Predictions start from the current state
+        system.step_by(2);
+        let mut stepped = system.clone();
+        stepped.step_by(4);
+        assert_eq!(system.predicted_len(4), stepped.get_state().len());
+
+        // Huge predictions saturate rather than overflow
+        assert_eq!(system.predicted_len(1000), usize::MAX);
+    }
+}
@@ -0,0 +1,237 @@
+//! Helpers for controlling the pacing and timing of animated renders.
+//!
+//! Line-by-line animations of large systems spend most of their time drawing the
+//! bulk of the figure.  A [`Pacing`] decides which partial renders become frames, which allows
+//! an animation to linger on the first few strokes and then speed through the rest.  A
+//! [`FrameDelay`] decides how long each of those frames is shown for.
+//!
+//! # Example
+//! ```rust
+//! use dcc_lsystem::animation::{Easing, Pacing};
+//!
+//! // Ten frames, with the amount of drawing between frames growing exponentially
+//! let pacing = Pacing::Eased {
+//!     frames: 10,
+//!     easing: Easing::Exponential(5.0),
+//! };
+//!
+//! let frames = pacing.frame_indices(1000);
+//! assert_eq!(frames.len(), 10);
+//! assert_eq!(frames.last(), Some(&999));
+//!
+//! // The first gap is far smaller than the last one
+//! assert!(frames[1] - frames[0] < frames[9] - frames[8]);
+//! ```
+
+/// An easing curve, mapping the proportion of time elapsed to the proportion of work done.
+///
+/// Every easing curve satisfies `apply(0.0) == 0.0` and `apply(1.0) == 1.0`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Easing {
+    /// Constant speed.
+    Linear,
+    /// Starts slowly and accelerates (`t^2`).
+    QuadraticIn,
+    /// Starts quickly and decelerates (`1 - (1 - t)^2`).
+    QuadraticOut,
+    /// Starts very slowly and accelerates hard (`t^3`).
+    CubicIn,
+    /// Exponential speed-up; larger rates give a slower start.  Negative rates
+    /// give an exponential slow-down instead.
+    Exponential(f64),
+}
+
+impl Easing {
+    /// Evaluates the easing curve at `t`, which is clamped to `[0, 1]`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::animation::Easing;
+    ///
+    /// assert_eq!(Easing::Linear.apply(0.25), 0.25);
+    /// assert_eq!(Easing::QuadraticIn.apply(0.5), 0.25);
+    /// assert_eq!(Easing::QuadraticOut.apply(0.5), 0.75);
+    /// assert_eq!(Easing::Exponential(3.0).apply(1.0), 1.0);
+    /// ```
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadraticIn => t * t,
+            Easing::QuadraticOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::CubicIn => t * t * t,
+            Easing::Exponential(rate) => {
+                if rate.abs() < f64::EPSILON {
+                    t
+                } else {
+                    (rate * t).exp_m1() / rate.exp_m1()
+                }
+            }
+        }
+    }
+}
+
+/// Determines which partial renders of a line-by-line animation become frames.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pacing {
+    /// Emit a frame after every `n`-th line (or after every line if `n` is zero).
+    SkipBy(usize),
+    /// Emit (at most) `frames` frames, where the proportion of lines drawn
+    /// in frame `k` is given by `easing` evaluated at `k / (frames - 1)`.
+    Eased { frames: usize, easing: Easing },
+}
+
+impl Pacing {
+    /// Returns the (sorted, distinct) indices of the lines after which a frame should be
+    /// emitted, for an animation consisting of `total` lines.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::animation::{Easing, Pacing};
+    ///
+    /// assert_eq!(Pacing::SkipBy(0).frame_indices(3), vec![0, 1, 2]);
+    /// assert_eq!(Pacing::SkipBy(2).frame_indices(5), vec![0, 2, 4]);
+    ///
+    /// let eased = Pacing::Eased { frames: 3, easing: Easing::Linear };
+    /// assert_eq!(eased.frame_indices(10), vec![0, 4, 9]);
+    /// ```
+    pub fn frame_indices(self, total: usize) -> Vec<usize> {
+        if total == 0 {
+            return Vec::new();
+        }
+
+        match self {
+            Pacing::SkipBy(n) => (0..total).filter(|i| n == 0 || i % n == 0).collect(),
+            Pacing::Eased { frames, easing } => {
+                let frames = frames.max(1);
+                let mut indices = Vec::with_capacity(frames);
+
+                for k in 0..frames {
+                    let t = if frames == 1 {
+                        1.0
+                    } else {
+                        k as f64 / (frames - 1) as f64
+                    };
+
+                    // The number of lines we want drawn in this frame
+                    let drawn = (easing.apply(t) * total as f64).ceil() as usize;
+                    let index = drawn.clamp(1, total) - 1;
+
+                    if indices.last() != Some(&index) {
+                        indices.push(index);
+                    }
+                }
+
+                indices
+            }
+        }
+    }
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Pacing::SkipBy(0)
+    }
+}
+
+/// Decides how long each frame of an animation is shown for.
+///
+/// # Example
+/// ```rust
+/// use dcc_lsystem::animation::{Easing, FrameDelay};
+///
+/// assert_eq!(FrameDelay::Fixed.delays(3, 4), vec![0.25, 0.25, 0.25]);
+///
+/// // Start slowly, then speed up
+/// let eased = FrameDelay::Eased { start: 1.0, end: 0.5, easing: Easing::Linear };
+/// assert_eq!(eased.delays(3, 20), vec![1.0, 0.75, 0.5]);
+///
+/// // Linger on every tenth frame
+/// let custom = FrameDelay::Custom(|index, _| if index % 10 == 0 { 1.0 } else { 0.05 });
+/// assert_eq!(custom.delays(2, 20), vec![1.0, 0.05]);
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameDelay {
+    /// Every frame is shown for `1 / fps` seconds.
+    #[default]
+    Fixed,
+    /// The first frame is shown for `start` seconds and the last for `end` seconds, with the
+    /// delays in between given by `easing`.
+    Eased {
+        start: f64,
+        end: f64,
+        easing: Easing,
+    },
+    /// The delay in seconds of each frame, given the index of the frame and the number of
+    /// frames.  Custom delays can't be (de)serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(fn(usize, usize) -> f64),
+}
+
+impl FrameDelay {
+    /// Returns the delay in seconds of each frame of an animation with `frames` frames, which
+    /// plays at `fps` frames per second unless told otherwise.  Negative delays are treated
+    /// as zero.
+    pub fn delays(self, frames: usize, fps: usize) -> Vec<f64> {
+        (0..frames)
+            .map(|index| {
+                let delay = match self {
+                    FrameDelay::Fixed => 1.0 / fps.max(1) as f64,
+                    FrameDelay::Eased { start, end, easing } => {
+                        let t = if frames == 1 {
+                            0.0
+                        } else {
+                            index as f64 / (frames - 1) as f64
+                        };
+
+                        start + (end - start) * easing.apply(t)
+                    }
+                    FrameDelay::Custom(delay) => delay(index, frames),
+                };
+
+                delay.max(0.0)
+            })
+            .collect()
+    }
+}
+
+/// The order the frames of an animation are played in.
+///
+/// # Example
+/// ```rust
+/// use dcc_lsystem::animation::Playback;
+///
+/// assert_eq!(Playback::Forward.order(4), vec![0, 1, 2, 3]);
+/// assert_eq!(Playback::Reverse.order(4), vec![3, 2, 1, 0]);
+/// assert_eq!(Playback::PingPong.order(4), vec![0, 1, 2, 3, 2, 1]);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Playback {
+    /// The drawing grows, from the first line to the last.
+    #[default]
+    Forward,
+    /// The drawing shrinks, from the last line to the first.
+    Reverse,
+    /// The drawing grows and then shrinks again, which loops smoothly.
+    PingPong,
+}
+
+impl Playback {
+    /// Returns the indices of the frames to play, in order, for an animation which draws
+    /// `frames` frames.  The first and last frames aren't repeated when ping-ponging, so
+    /// that the animation loops without stuttering.
+    pub fn order(self, frames: usize) -> Vec<usize> {
+        match self {
+            Playback::Forward => (0..frames).collect(),
+            Playback::Reverse => (0..frames).rev().collect(),
+            Playback::PingPong => (0..frames)
+                .chain((1..frames.saturating_sub(1)).rev())
+                .collect(),
+        }
+    }
+}
@@ -1,6 +1,8 @@
-use std::slice::{Iter, IterMut};
+use alloc::vec::Vec;
+use core::slice::{Iter, IterMut};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ArenaId(pub usize);
 
 /// A simple arena wrapping around a Vec<T>.
@@ -19,6 +21,7 @@ pub struct ArenaId(pub usize);
 /// assert_eq!(arena.get(u), Some(&1));
 /// ```
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Arena<T> {
     arena: Vec<T>,
 }
@@ -154,7 +157,7 @@ impl<T> Arena<T> {
     /// assert_eq!(iterator.next(), Some(&9));
     /// assert_eq!(iterator.next(), Some(&16));
     /// ```
-    pub fn iter_mut(&mut self) -> IterMut<T> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
         self.arena.iter_mut()
     }
 
@@ -299,7 +302,7 @@ impl<T> Arena<T> {
 /// }
 /// ```
 pub struct EnumerableArena<'a, T: 'a> {
-    inner: std::iter::Enumerate<std::slice::Iter<'a, T>>,
+    inner: core::iter::Enumerate<core::slice::Iter<'a, T>>,
 }
 
 impl<'a, T> Iterator for EnumerableArena<'a, T> {
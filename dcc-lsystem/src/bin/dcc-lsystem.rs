@@ -0,0 +1,342 @@
+//! Renders a turtle grammar file from the command line.
+//!
+//! The grammar file is either a TOML or JSON config, as described in the `config` module, or
+//! in the format accepted by `TurtleLSystemBuilder::from_grammar`.  Run `dcc-lsystem --help` for
+//! the available options.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde::de::DeserializeOwned;
+
+use dcc_lsystem::config::TurtleConfig;
+use dcc_lsystem::export::{ExportFormat, ExportRendererOptions};
+use dcc_lsystem::image_renderer::{
+    save_png_with, AnimationFormat, ImageRendererOptions, PngOptionsBuilder, VideoRendererOptions,
+};
+use dcc_lsystem::renderer::{
+    ExportRendererOptionsBuilder, ImageRendererOptionsBuilder, Renderer,
+    SvgAnimationRendererOptionsBuilder, VideoRendererOptionsBuilder,
+};
+use dcc_lsystem::svg::SvgAnimationRendererOptions;
+use dcc_lsystem::turtle::TurtleLSystemBuilder;
+use dcc_lsystem::LSystemError;
+
+const USAGE: &str = "Usage: dcc-lsystem <grammar> [options]
+
+Renders the turtle grammar in the file <grammar>.  Files ending in .toml or .json are read
+as configs, and anything else as a grammar.  The `renderer` entry of a config holds the
+options for the chosen format, which replace the defaults.
+
+Options:
+  -n, --steps <steps>          Step the system <steps> times before rendering [default: 0,
+                               or the steps given in a config]
+  -o, --output <file>          Write the render to <file> [default: <grammar>.<format>]
+  -f, --format <format>        One of png, svg, gif or json [default: from the output file]
+  -s, --seed <seed>            Seed the random number generator used by stochastic rules
+  -t, --thickness <thickness>  Set the thickness of the lines
+  -h, --help                   Print this message";
+
+/// The formats the command line tool can render to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+    Png,
+    Svg,
+    Gif,
+    Json,
+}
+
+impl Format {
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Format::Png),
+            "svg" => Some(Format::Svg),
+            "gif" => Some(Format::Gif),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+            Format::Gif => "gif",
+            Format::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    grammar: PathBuf,
+    steps: Option<usize>,
+    output: PathBuf,
+    format: Format,
+    seed: Option<u64>,
+    thickness: Option<f64>,
+}
+
+/// Parses the command line arguments, not including the name of the program.  Returns `None`
+/// if help was asked for.
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Args>, String> {
+    let mut args = args.into_iter();
+
+    let mut grammar = None;
+    let mut steps = None;
+    let mut output = None;
+    let mut format = None;
+    let mut seed = None;
+    let mut thickness = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("`{}` needs a value", arg))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-n" | "--steps" => {
+                let value = value()?;
+                steps = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("`{}` is not a valid number of steps", value))?,
+                );
+            }
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "-f" | "--format" => {
+                let value = value()?;
+                format = Some(
+                    Format::from_extension(&value)
+                        .ok_or_else(|| format!("`{}` is not a supported format", value))?,
+                );
+            }
+            "-s" | "--seed" => {
+                let value = value()?;
+                seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("`{}` is not a valid seed", value))?,
+                );
+            }
+            "-t" | "--thickness" => {
+                let value = value()?;
+                thickness = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("`{}` is not a valid thickness", value))?,
+                );
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ if grammar.is_none() => grammar = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    let grammar = grammar.ok_or_else(|| String::from("no grammar file was given"))?;
+
+    // The format is taken from the output file if it isn't given, and the output file is
+    // named after the grammar if it isn't given
+    let format = match (format, &output) {
+        (Some(format), _) => format,
+        (None, Some(output)) => output
+            .extension()
+            .and_then(|extension| Format::from_extension(&extension.to_string_lossy()))
+            .ok_or_else(|| {
+                format!(
+                    "can't tell the format of `{}`, use --format",
+                    output.display()
+                )
+            })?,
+        (None, None) => Format::Png,
+    };
+    let output = output.unwrap_or_else(|| grammar.with_extension(format.extension()));
+
+    if output == grammar {
+        return Err(format!(
+            "rendering to `{}` would overwrite the grammar, use --output",
+            output.display()
+        ));
+    }
+
+    Ok(Some(Args {
+        grammar,
+        steps,
+        output,
+        format,
+        seed,
+        thickness,
+    }))
+}
+
+/// Returns whether the file at `path` should be read as a config, rather than a grammar.
+fn is_config(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "toml" || extension == "json")
+}
+
+/// Returns the options under the `renderer` entry of `config`, if there are any.
+fn renderer_options<T: DeserializeOwned>(
+    config: Option<&TurtleConfig>,
+) -> Result<Option<T>, LSystemError> {
+    Ok(config
+        .map(TurtleConfig::renderer_options)
+        .transpose()?
+        .flatten())
+}
+
+fn render(args: &Args) -> Result<(), LSystemError> {
+    // Only grammars are embedded in PNGs, as that's the format recipes are read back in
+    let (mut builder, config, grammar, steps) = if is_config(&args.grammar) {
+        let config = TurtleConfig::from_path(&args.grammar)?;
+        let steps = config.steps();
+        (config.builder()?, Some(config), None, steps)
+    } else {
+        let grammar = fs::read_to_string(&args.grammar)?;
+        (
+            TurtleLSystemBuilder::from_grammar(&grammar)?,
+            None,
+            Some(grammar),
+            0,
+        )
+    };
+    let config = config.as_ref();
+
+    if let Some(seed) = args.seed {
+        builder.seed(seed);
+    }
+
+    let steps = args.steps.unwrap_or(steps);
+    let (mut system, renderer) = builder.finish()?;
+    system.step_by(steps);
+
+    match args.format {
+        Format::Png => {
+            let mut options = renderer_options::<ImageRendererOptions>(config)?
+                .map(ImageRendererOptionsBuilder::from)
+                .unwrap_or_default();
+
+            if let Some(thickness) = args.thickness {
+                options.thickness(thickness);
+            }
+
+            // The grammar is embedded in the image, so it can be drawn again later
+            let mut png_options = PngOptionsBuilder::new();
+
+            if let Some(grammar) = &grammar {
+                png_options.grammar(grammar).steps(steps);
+
+                if let Some(seed) = args.seed {
+                    png_options.seed(seed);
+                }
+            }
+
+            let buffer = renderer.render(&system, &options.build());
+            save_png_with(&buffer, &args.output, &png_options.build())?;
+        }
+        Format::Svg => {
+            // Without a config, the drawing is shown all at once rather than animated
+            let mut options = match renderer_options::<SvgAnimationRendererOptions>(config)? {
+                Some(options) => SvgAnimationRendererOptionsBuilder::from(options),
+                None => {
+                    let mut options = SvgAnimationRendererOptionsBuilder::new();
+                    options.duration(0.0);
+                    options
+                }
+            };
+
+            if let Some(thickness) = args.thickness {
+                options.thickness(thickness);
+            }
+
+            fs::write(&args.output, renderer.render(&system, &options.build()))?;
+        }
+        Format::Gif => {
+            let mut options = renderer_options::<VideoRendererOptions>(config)?
+                .map(VideoRendererOptionsBuilder::from)
+                .unwrap_or_default();
+            options
+                .filename(args.output.to_string_lossy())
+                .format(AnimationFormat::Gif)
+                .progress_bar(true);
+
+            if let Some(thickness) = args.thickness {
+                options.thickness(thickness);
+            }
+
+            renderer.render(&system, &options.build())?;
+        }
+        Format::Json => {
+            let options = renderer_options::<ExportRendererOptions>(config)?
+                .map(ExportRendererOptionsBuilder::from)
+                .unwrap_or_default()
+                .format(ExportFormat::Json)
+                .build();
+
+            fs::write(&args.output, renderer.render(&system, &options))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = render(&args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+
+    println!(
+        "Rendered {} to {}",
+        args.grammar.display(),
+        args.output.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn format_and_output_default_from_each_other() {
+        let args = parse(&["koch.txt", "--steps", "3", "-o", "koch.svg"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.steps, Some(3));
+        assert_eq!(args.format, Format::Svg);
+
+        let args = parse(&["koch.txt", "--format", "gif"]).unwrap().unwrap();
+        assert_eq!(args.output, PathBuf::from("koch.gif"));
+
+        assert_eq!(parse(&["koch.txt", "-h"]), Ok(None));
+        assert!(parse(&["koch.txt", "-o", "koch.bmp"]).is_err());
+        assert!(parse(&["--steps", "3"]).is_err());
+    }
+
+    #[test]
+    fn output_never_overwrites_the_grammar() {
+        assert!(parse(&["koch.json", "--format", "json"]).is_err());
+        assert!(parse(&["koch.png", "-o", "koch.png"]).is_err());
+
+        let args = parse(&["koch.json", "--format", "png"]).unwrap().unwrap();
+        assert_eq!(args.output, PathBuf::from("koch.png"));
+    }
+}
@@ -1,11 +1,20 @@
-use std::collections::HashMap;
-
+use crate::collections::HashMap;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Bound, RangeBounds};
+
+use crate::analysis::{self, GrammarWarning};
 use crate::arena::{Arena, ArenaId};
 use crate::errors::LSystemError;
-use crate::system::LSystem;
+use crate::grammar::{self, Statement};
+use crate::system::{LSystem, Productions, RuleTable};
 use crate::token::Token;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct TransformationRule {
     predecessor: ArenaId,
     successor: Vec<ArenaId>,
@@ -20,6 +29,23 @@ impl TransformationRule {
     }
 }
 
+/// A transformation rule which chooses between several weighted successors at random.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct StochasticRule {
+    predecessor: ArenaId,
+    alternatives: Vec<(f64, Vec<ArenaId>)>,
+}
+
+/// A set of transformation rules which only apply for some range of steps.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct TransformationTable {
+    start: usize,
+    end: Option<usize>,
+    rules: Vec<TransformationRule>,
+}
+
 /// A struct for constructing [`LSystem`]s.
 ///
 /// # Example
@@ -51,10 +77,17 @@ impl TransformationRule {
 /// }
 /// ```
 #[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LSystemBuilder {
     arena: Arena<Token>,
     axiom: Option<Vec<ArenaId>>,
     rules: Vec<TransformationRule>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    tables: Vec<TransformationTable>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    stochastic_rules: Vec<StochasticRule>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    seed: Option<u64>,
 }
 
 impl LSystemBuilder {
@@ -62,6 +95,56 @@ impl LSystemBuilder {
         Self::default()
     }
 
+    /// Constructs a builder from a grammar written in the text format described in
+    /// the [`grammar`](crate::grammar) module.  Tokens are registered in the order they
+    /// first appear in the grammar.
+    ///
+    /// Returns an [`LSystemError::ParseError`] if the grammar is malformed, or if it
+    /// contains a turtle action.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let builder = LSystemBuilder::from_grammar("axiom: 0\n1 => 1 1\n0 => 1 [ 0 ] 0")?;
+    ///
+    /// let mut system = builder.finish()?;
+    /// system.step();
+    /// assert_eq!(system.render(), "1[0]0");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_grammar(grammar: &str) -> Result<Self, LSystemError> {
+        let statements = grammar::parse(grammar)?;
+        let mut builder = Self::new();
+        let mut ids = HashMap::new();
+
+        for name in grammar::token_names(&statements) {
+            ids.insert(name, builder.token(name)?);
+        }
+
+        let lookup = |symbols: &[grammar::Symbol]| -> Vec<ArenaId> {
+            symbols.iter().map(|symbol| ids[symbol.name]).collect()
+        };
+
+        for statement in statements.iter() {
+            match statement {
+                Statement::Axiom(axiom) => builder.axiom(lookup(axiom))?,
+                Statement::Rule {
+                    predecessor,
+                    successor,
+                } => builder.transformation_rule(ids[predecessor.name], lookup(successor))?,
+                Statement::Action { symbol, .. } => {
+                    return Err(symbol.error("actions can only be used with a TurtleLSystemBuilder"))
+                }
+            }
+        }
+
+        Ok(builder)
+    }
+
     /// Register a new token.
     ///
     /// Returns a TokenId which can be used (in this LSystem) to refer to the registered token.
@@ -83,6 +166,30 @@ impl LSystemBuilder {
         Ok(self.arena.push(Token::new(name)?))
     }
 
+    /// Register several tokens at once, returning their TokenIds in the same order.
+    ///
+    /// The [`tokens!`](crate::tokens) macro is a shorthand for this function, which can also
+    /// return the TokenIds as a tuple.
+    ///
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let ids = builder.tokens(["F", "+", "-", "[", "]"])?;
+    /// assert_eq!(ids.len(), 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tokens<I, S>(&mut self, names: I) -> Result<Vec<ArenaId>, LSystemError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        names.into_iter().map(|name| self.token(name)).collect()
+    }
+
     fn validate_ids(&self, ids: &[ArenaId]) -> Result<(), LSystemError> {
         for &id in ids {
             if !self.arena.is_valid(id) {
@@ -93,9 +200,28 @@ impl LSystemBuilder {
         Ok(())
     }
 
+    /// Returns an [`LSystemError::DuplicateRule`] error if `predecessor` already has a rule.
+    fn check_unique(&self, predecessor: ArenaId) -> Result<(), LSystemError> {
+        let deterministic = self.rules.iter().map(|rule| rule.predecessor);
+        let stochastic = self.stochastic_rules.iter().map(|rule| rule.predecessor);
+
+        if deterministic.chain(stochastic).any(|id| id == predecessor) {
+            return Err(self.duplicate_rule(predecessor));
+        }
+
+        Ok(())
+    }
+
+    /// Returns an [`LSystemError::DuplicateRule`] error for the valid token `predecessor`.
+    fn duplicate_rule(&self, predecessor: ArenaId) -> LSystemError {
+        // unwrap: `predecessor` has already been validated
+        LSystemError::DuplicateRule(self.arena.get(predecessor).unwrap().name().to_string())
+    }
+
     /// Register a new transformation rule in this LSystem.
     ///
-    /// This function will return an error if any of the provided TokenId are invalid.
+    /// This function will return an error if any of the provided TokenId are invalid, or
+    /// an [`LSystemError::DuplicateRule`] error if `predecessor` already has a rule.
     ///
     /// ```rust
     /// # use dcc_lsystem::LSystemError;
@@ -119,6 +245,7 @@ impl LSystemBuilder {
         // Verify that all provided TokenId's correspond to a token in this LSystem.
         self.validate_ids(&[predecessor])?;
         self.validate_ids(&successor)?;
+        self.check_unique(predecessor)?;
 
         // Add the rule to this system
         self.rules
@@ -127,6 +254,344 @@ impl LSystemBuilder {
         Ok(())
     }
 
+    /// Replace the transformation rule for `predecessor`, or register one if it doesn't have a
+    /// rule yet.  Any [stochastic rule](LSystemBuilder::stochastic_rule) for `predecessor` is
+    /// also removed.
+    ///
+    /// This function will return an error if any of the provided TokenId are invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("a")?;
+    /// let b = builder.token("b")?;
+    /// builder.axiom(vec![a])?;
+    /// builder.transformation_rule(a, vec![a, b])?;
+    ///
+    /// // Change the rule to a -> ba
+    /// builder.replace_rule(a, vec![b, a])?;
+    /// assert_eq!(builder.rules(), vec![(a, &[b, a][..])]);
+    ///
+    /// let mut system = builder.finish()?;
+    /// system.step_by(2);
+    /// assert_eq!(system.render(), "bba");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_rule(
+        &mut self,
+        predecessor: ArenaId,
+        successor: Vec<ArenaId>,
+    ) -> Result<(), LSystemError> {
+        self.validate_ids(&[predecessor])?;
+        self.validate_ids(&successor)?;
+        self.remove_rule(predecessor)?;
+
+        self.transformation_rule(predecessor, successor)
+    }
+
+    /// Remove the transformation rule for `predecessor`, including any
+    /// [stochastic rule](LSystemBuilder::stochastic_rule), so that it is left unchanged by
+    /// each step.  Rules in [rule tables](LSystemBuilder::rule_table) are kept.  Returns
+    /// whether there was a rule to remove.
+    ///
+    /// This function will return an error if the provided TokenId is invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("a")?;
+    /// builder.axiom(vec![a])?;
+    /// builder.transformation_rule(a, vec![a, a])?;
+    ///
+    /// assert!(builder.remove_rule(a)?);
+    /// assert!(!builder.remove_rule(a)?);
+    /// assert!(builder.rules().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_rule(&mut self, predecessor: ArenaId) -> Result<bool, LSystemError> {
+        self.validate_ids(&[predecessor])?;
+
+        let count = self.rules.len() + self.stochastic_rules.len();
+
+        self.rules.retain(|rule| rule.predecessor != predecessor);
+        self.stochastic_rules
+            .retain(|rule| rule.predecessor != predecessor);
+
+        Ok(self.rules.len() + self.stochastic_rules.len() != count)
+    }
+
+    /// Returns the transformation rules registered with
+    /// [`LSystemBuilder::transformation_rule`], as pairs of a predecessor and its successor,
+    /// in the order they were registered.  Stochastic rules and rule tables aren't included.
+    pub fn rules(&self) -> Vec<(ArenaId, &[ArenaId])> {
+        self.rules
+            .iter()
+            .map(|rule| (rule.predecessor, rule.successor.as_slice()))
+            .collect()
+    }
+
+    /// Import the tokens and rules of `other` into this builder, returning a map from the
+    /// TokenIds of `other` to the TokenIds they have in this builder.  Tokens of `other` are
+    /// unified with tokens of the same name in this builder, and new tokens are registered
+    /// for the rest.  The axiom and seed of `other` aren't imported.
+    ///
+    /// This allows grammars to be built up from smaller, reusable pieces.  Rules which both
+    /// builders have are only kept once, but if `other` has a different rule for a token which
+    /// already has one, an [`LSystemError::DuplicateRule`] error is returned and this builder
+    /// is left unchanged.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// // A shared sub-grammar which grows a branch from each `B`
+    /// let branching = LSystemBuilder::from_grammar("B => F [ + B ] [ - B ]")?;
+    ///
+    /// let mut builder = LSystemBuilder::from_grammar("axiom: S\nS => F F B")?;
+    /// let translation = builder.merge(&branching)?;
+    /// assert_eq!(translation.len(), 6);
+    ///
+    /// let mut system = builder.finish()?;
+    /// system.step_by(2);
+    /// assert_eq!(system.render(), "FFF[+B][-B]");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge(
+        &mut self,
+        other: &LSystemBuilder,
+    ) -> Result<BTreeMap<ArenaId, ArenaId>, LSystemError> {
+        let mut merged = self.clone();
+        let mut translation = BTreeMap::new();
+
+        for (id, token) in other.arena.enumerate() {
+            let existing = merged
+                .arena
+                .enumerate()
+                .find_map(|(id, existing)| (existing == token).then_some(id));
+
+            let merged_id = match existing {
+                Some(id) => id,
+                None => merged.arena.push(token.clone()),
+            };
+
+            translation.insert(id, merged_id);
+        }
+
+        let translate =
+            |ids: &[ArenaId]| -> Vec<ArenaId> { ids.iter().map(|id| translation[id]).collect() };
+
+        for rule in other.rules.iter() {
+            let predecessor = translation[&rule.predecessor];
+            let successor = translate(&rule.successor);
+
+            let shared = merged
+                .rules
+                .iter()
+                .any(|rule| rule.predecessor == predecessor && rule.successor == successor);
+
+            if !shared {
+                merged.transformation_rule(predecessor, successor)?;
+            }
+        }
+
+        for rule in other.stochastic_rules.iter() {
+            let predecessor = translation[&rule.predecessor];
+            let alternatives: Vec<_> = rule
+                .alternatives
+                .iter()
+                .map(|(weight, successor)| (*weight, translate(successor)))
+                .collect();
+
+            let shared = merged
+                .stochastic_rules
+                .iter()
+                .any(|rule| rule.predecessor == predecessor && rule.alternatives == alternatives);
+
+            if !shared {
+                merged.stochastic_rule(predecessor, alternatives)?;
+            }
+        }
+
+        for table in other.tables.iter() {
+            merged.tables.push(TransformationTable {
+                start: table.start,
+                end: table.end,
+                rules: table
+                    .rules
+                    .iter()
+                    .map(|rule| {
+                        TransformationRule::new(
+                            translation[&rule.predecessor],
+                            translate(&rule.successor),
+                        )
+                    })
+                    .collect(),
+            });
+        }
+
+        *self = merged;
+
+        Ok(translation)
+    }
+
+    /// Register a stochastic transformation rule, which rewrites `predecessor` to one of several
+    /// successors chosen at random each time it is expanded.  Each successor is given with a
+    /// weight, and is chosen with probability proportional to its weight.
+    ///
+    /// A stochastic rule doesn't apply while a [rule table](LSystemBuilder::rule_table) with a
+    /// rule for `predecessor` applies.
+    /// Predictions of the state, such as [`LSystem::predicted_len`] and
+    /// [`LSystem::step_compressed`], ignore stochastic rules.
+    ///
+    /// This function will return an error if any of the provided TokenId are invalid, if
+    /// there are no successors or a weight isn't positive, or if `predecessor` already has a
+    /// rule.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("a")?;
+    /// let b = builder.token("b")?;
+    ///
+    /// // a -> ab two thirds of the time, and a -> ba otherwise
+    /// builder.axiom(vec![a])?;
+    /// builder.stochastic_rule(a, vec![(2.0, vec![a, b]), (1.0, vec![b, a])])?;
+    /// builder.seed(7);
+    ///
+    /// let mut system = builder.finish()?;
+    /// system.step_by(4);
+    /// assert_eq!(system.render().len(), 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stochastic_rule(
+        &mut self,
+        predecessor: ArenaId,
+        alternatives: Vec<(f64, Vec<ArenaId>)>,
+    ) -> Result<(), LSystemError> {
+        self.validate_ids(&[predecessor])?;
+        self.check_unique(predecessor)?;
+
+        for (weight, successor) in alternatives.iter() {
+            self.validate_ids(successor)?;
+
+            if !(weight.is_finite() && *weight > 0.0) {
+                return Err(LSystemError::InvalidRule(format!(
+                    "stochastic rule weights must be positive, not {}",
+                    weight
+                )));
+            }
+        }
+
+        if alternatives.is_empty() {
+            return Err(LSystemError::InvalidRule(String::from(
+                "stochastic rules need at least one successor",
+            )));
+        }
+
+        self.stochastic_rules.push(StochasticRule {
+            predecessor,
+            alternatives,
+        });
+
+        Ok(())
+    }
+
+    /// Seed the random number generator used by stochastic rules, so that the system grows
+    /// the same way every time, including after being [reset](LSystem::reset).
+    pub fn seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Register a table of transformation rules which is used instead of the rules registered
+    /// by [`LSystemBuilder::transformation_rule`] while the number of steps the system has taken
+    /// is in `steps`.  This allows for table L-systems, whose productions change as they grow.
+    ///
+    /// Tokens without a rule in the table fall back to the usual rules.  If several tables
+    /// apply to the same step, the one registered first is used.
+    ///
+    /// This function will return an error if any of the provided TokenId are invalid, or if
+    /// the table has more than one rule for the same token.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("a")?;
+    /// let b = builder.token("b")?;
+    /// builder.axiom(vec![a])?;
+    ///
+    /// // Grow for two steps, then start flowering
+    /// builder.transformation_rule(a, vec![a, a])?;
+    /// builder.rule_table(2.., vec![(a, vec![b, a])])?;
+    ///
+    /// let mut system = builder.finish()?;
+    /// system.step_by(2);
+    /// assert_eq!(system.render(), "aaaa");
+    ///
+    /// system.step();
+    /// assert_eq!(system.render(), "babababa");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rule_table<R: RangeBounds<usize>>(
+        &mut self,
+        steps: R,
+        rules: Vec<(ArenaId, Vec<ArenaId>)>,
+    ) -> Result<(), LSystemError> {
+        for (index, (predecessor, successor)) in rules.iter().enumerate() {
+            self.validate_ids(&[*predecessor])?;
+            self.validate_ids(successor)?;
+
+            if rules[..index].iter().any(|(other, _)| other == predecessor) {
+                return Err(self.duplicate_rule(*predecessor));
+            }
+        }
+
+        let start = match steps.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+
+        let end = match steps.end_bound() {
+            Bound::Included(&end) => Some(end.saturating_add(1)),
+            Bound::Excluded(&end) => Some(end),
+            Bound::Unbounded => None,
+        };
+
+        self.tables.push(TransformationTable {
+            start,
+            end,
+            rules: rules
+                .into_iter()
+                .map(|(predecessor, successor)| TransformationRule::new(predecessor, successor))
+                .collect(),
+        });
+
+        Ok(())
+    }
+
     /// Set the axiom for this LSystem.
     ///
     /// # Example
@@ -152,6 +617,73 @@ impl LSystemBuilder {
         Ok(())
     }
 
+    /// Checks the grammar for likely mistakes, returning a warning for each token which:
+    ///
+    /// * can't be reached from the axiom,
+    /// * looks like a variable, appears in the state, but has no rule, or
+    /// * has a rule whose successors only contain variables without rules.
+    ///
+    /// Tokens whose names contain a letter are taken to be variables, and other tokens, such
+    /// as `+` or `[`, to be constants.  [`TurtleLSystemBuilder::analyze`] instead uses the
+    /// action of each token.  Reachability isn't checked until an axiom has been set.
+    ///
+    /// [`TurtleLSystemBuilder::analyze`]: crate::turtle::TurtleLSystemBuilder::analyze
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::analysis::GrammarWarning;
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// // The rule for `B` was mistakenly written for `b`
+    /// let builder = LSystemBuilder::from_grammar("axiom: A\nA => A B\nb => A")?;
+    ///
+    /// assert_eq!(
+    ///     builder.analyze(),
+    ///     vec![
+    ///         GrammarWarning::MissingRule(String::from("B")),
+    ///         GrammarWarning::Unreachable(String::from("b")),
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn analyze(&self) -> Vec<GrammarWarning> {
+        self.analyze_with(|id| {
+            self.arena
+                .get(id)
+                .is_some_and(|token| token.name().chars().any(char::is_alphabetic))
+        })
+    }
+
+    /// Checks the grammar for likely mistakes, as described in [`LSystemBuilder::analyze`],
+    /// where `is_variable` says whether a token is expected to have a rule.
+    pub(crate) fn analyze_with<F>(&self, is_variable: F) -> Vec<GrammarWarning>
+    where
+        F: Fn(ArenaId) -> bool,
+    {
+        let names: Vec<&str> = self.arena.iter().map(Token::name).collect();
+        let mut successors = vec![Vec::new(); names.len()];
+
+        let rules = self
+            .rules
+            .iter()
+            .chain(self.tables.iter().flat_map(|table| table.rules.iter()));
+
+        for rule in rules {
+            successors[rule.predecessor.0].push(rule.successor.as_slice());
+        }
+
+        for rule in self.stochastic_rules.iter() {
+            for (_, successor) in rule.alternatives.iter() {
+                successors[rule.predecessor.0].push(successor.as_slice());
+            }
+        }
+
+        analysis::lint(&names, &successors, self.axiom.as_deref(), is_variable)
+    }
+
     /// Consumes the builder, returning an LSystem instance.  If an axiom has not been
     /// set then this function will return an [`LSystemError::MissingAxiom`] error.
     ///
@@ -193,7 +725,38 @@ impl LSystemBuilder {
         // contributes exactly one rule, so we check for that here.
         assert_eq!(self.arena.len(), rules_map.len());
 
-        Ok(LSystem::new(self.arena, axiom, rules_map))
+        // Each table is completed using the usual rules
+        let token_count = self.arena.len();
+        let tables = self
+            .tables
+            .into_iter()
+            .map(|table| {
+                let mut table_map = rules_map.clone();
+                let mut has_rule = vec![false; token_count];
+
+                for rule in table.rules.into_iter() {
+                    has_rule[rule.predecessor.0] = true;
+                    table_map.insert(rule.predecessor, rule.successor);
+                }
+
+                RuleTable {
+                    start: table.start,
+                    end: table.end,
+                    productions: Productions::new(token_count, &table_map),
+                    has_rule,
+                }
+            })
+            .collect();
+
+        let mut stochastic = vec![Vec::new(); token_count];
+
+        for rule in self.stochastic_rules.into_iter() {
+            stochastic[rule.predecessor.0] = rule.alternatives;
+        }
+
+        Ok(LSystem::new(
+            self.arena, axiom, rules_map, tables, stochastic, self.seed,
+        ))
     }
 }
 
@@ -223,16 +786,74 @@ fn build_rules_string(rules: &[TransformationRule], arena: &Arena<Token>) -> Str
     st.join(",")
 }
 
-impl std::fmt::Debug for LSystemBuilder {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+impl core::fmt::Debug for LSystemBuilder {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
         f.debug_struct("LSystemBuilder")
             .field("arena", &self.arena)
             .field("axiom", &self.axiom)
             .field("rules", &build_rules_string(&self.rules, &self.arena))
+            .field(
+                "tables",
+                &self
+                    .tables
+                    .iter()
+                    .map(|table| {
+                        let end = table.end.map(|end| end.to_string()).unwrap_or_default();
+                        let rules = build_rules_string(&table.rules, &self.arena);
+
+                        format!("{}..{}: {}", table.start, end, rules)
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .field("stochastic_rules", &self.stochastic_rules)
+            .field("seed", &self.seed)
             .finish()
     }
 }
 
+/// Registers several tokens with an [`LSystemBuilder`].
+///
+/// `tokens!(builder, "A", "B")` returns the TokenIds of the tokens in a `Vec`, while
+/// `tokens!(builder => "A", "B")` returns them as a tuple, which is convenient for giving each
+/// of them a name.  Either way the result is wrapped in a `Result`, which is an error if any of
+/// the tokens is invalid.
+///
+/// # Example
+/// ```rust
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use dcc_lsystem::{tokens, LSystemBuilder};
+///
+/// let mut builder = LSystemBuilder::new();
+/// let (a, b) = tokens!(builder => "A", "B")?;
+///
+/// builder.axiom(vec![a])?;
+/// builder.transformation_rule(a, vec![a, b])?;
+/// builder.transformation_rule(b, vec![a])?;
+///
+/// let mut system = builder.finish()?;
+/// system.step_by(3);
+/// assert_eq!(system.render(), "ABAAB");
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! tokens {
+    (@next $ids:ident $token:expr) => {
+        // unwrap: LSystemBuilder::tokens returns an id for every token
+        $ids.next().unwrap()
+    };
+    ($builder:expr => $($token:expr),+ $(,)?) => {
+        $builder.tokens([$($token),+]).map(|ids| {
+            let mut ids = ids.into_iter();
+            ($($crate::tokens!(@next ids $token),)+)
+        })
+    };
+    ($builder:expr, $($token:expr),+ $(,)?) => {
+        $builder.tokens([$($token),+])
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -276,4 +897,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_builder_replace_stochastic_rule() -> Result<(), LSystemError> {
+        let mut builder = LSystemBuilder::new();
+
+        let x = builder.token("x")?;
+        let y = builder.token("y")?;
+
+        builder.axiom(vec![x])?;
+        builder.stochastic_rule(x, vec![(1.0, vec![x, x]), (1.0, vec![y])])?;
+
+        // the stochastic rule is replaced by an ordinary one
+        builder.replace_rule(x, vec![x, y])?;
+        assert_eq!(builder.rules(), vec![(x, &[x, y][..])]);
+
+        let mut system = builder.finish()?;
+        system.step_by(3);
+        assert_eq!(system.render(), "xyyy");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_merge_conflicting_rules() -> Result<(), LSystemError> {
+        let mut builder = LSystemBuilder::from_grammar("axiom: x\nx => x y")?;
+        let shared = LSystemBuilder::from_grammar("x => x y\ny => z")?;
+        let conflicting = LSystemBuilder::from_grammar("w => w\nx => y")?;
+
+        // the shared rule for `x` is only kept once
+        builder.merge(&shared)?;
+        assert_eq!(builder.rules().len(), 2);
+
+        // a different rule for `x` is rejected, without importing `w`
+        assert!(matches!(
+            builder.merge(&conflicting),
+            Err(LSystemError::DuplicateRule(name)) if name == "x"
+        ));
+        assert_eq!(builder.arena.len(), 3);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,232 @@
+//! Colormaps for turning a scalar value into a color.
+//!
+//! A [`Colormap`] maps a value `t` in the interval `[0, 1]` to an [`Rgb<u8>`] color.  This is
+//! handy for coloring a rendering by some quantity (for example, how far along the path a segment
+//! is), and can be used from within your own color callbacks.
+//!
+//! # Example
+//! ```rust
+//! use image::Rgb;
+//! use dcc_lsystem::color::Colormap;
+//!
+//! assert_eq!(Colormap::Grayscale.sample(0.0), Rgb([0, 0, 0]));
+//! assert_eq!(Colormap::Grayscale.sample(1.0), Rgb([255, 255, 255]));
+//! assert_eq!(Colormap::Viridis.sample(0.0), Rgb([68, 1, 84]));
+//! ```
+use image::Rgb;
+
+use crate::palette;
+
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 45, 123],
+    [59, 82, 139],
+    [44, 114, 142],
+    [33, 145, 140],
+    [40, 174, 128],
+    [94, 201, 98],
+    [173, 220, 48],
+    [253, 231, 37],
+];
+
+const MAGMA: [[u8; 3]; 9] = [
+    [0, 0, 4],
+    [28, 16, 68],
+    [79, 18, 123],
+    [129, 37, 129],
+    [181, 54, 122],
+    [229, 80, 100],
+    [251, 135, 97],
+    [254, 194, 135],
+    [252, 253, 191],
+];
+
+const INFERNO: [[u8; 3]; 9] = [
+    [0, 0, 4],
+    [31, 12, 72],
+    [85, 15, 109],
+    [136, 34, 106],
+    [186, 54, 85],
+    [227, 89, 51],
+    [249, 140, 10],
+    [249, 201, 50],
+    [252, 255, 164],
+];
+
+const TURBO: [[u8; 3]; 9] = [
+    [48, 18, 59],
+    [70, 107, 227],
+    [40, 187, 236],
+    [49, 241, 153],
+    [164, 252, 60],
+    [237, 208, 58],
+    [251, 128, 34],
+    [210, 49, 5],
+    [122, 4, 3],
+];
+
+const RAINBOW: [[u8; 3]; 6] = [
+    [255, 0, 0],
+    [255, 255, 0],
+    [0, 255, 0],
+    [0, 255, 255],
+    [0, 0, 255],
+    [255, 0, 255],
+];
+
+const GRAYSCALE: [[u8; 3]; 2] = [[0, 0, 0], [255, 255, 255]];
+
+/// A standard colormap.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Colormap {
+    /// The perceptually uniform blue-green-yellow map from matplotlib.
+    Viridis,
+    /// The perceptually uniform black-purple-cream map from matplotlib.
+    Magma,
+    /// The perceptually uniform black-purple-yellow map from matplotlib.
+    Inferno,
+    /// Google's improved rainbow map.
+    Turbo,
+    /// Every fully saturated hue in turn, from red through green and blue to magenta.
+    Rainbow,
+    /// A linear ramp from black to white.
+    Grayscale,
+}
+
+impl Colormap {
+    /// Returns the color at position `t` along this colormap.  Values of `t` outside
+    /// of `[0, 1]` are clamped to the interval.
+    ///
+    /// # Example
+    /// ```rust
+    /// use image::Rgb;
+    /// use dcc_lsystem::color::Colormap;
+    ///
+    /// assert_eq!(Colormap::Grayscale.sample(0.5), Rgb([128, 128, 128]));
+    ///
+    /// // Out of range values are clamped
+    /// assert_eq!(Colormap::Magma.sample(-3.0), Colormap::Magma.sample(0.0));
+    /// assert_eq!(Colormap::Magma.sample(7.0), Colormap::Magma.sample(1.0));
+    /// ```
+    pub fn sample(self, t: f64) -> Rgb<u8> {
+        interpolate(self.stops(), t)
+    }
+
+    /// Returns the function from the [`palette`](crate::palette) module that samples this
+    /// colormap.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::color::Colormap;
+    ///
+    /// let inferno = Colormap::Inferno.function();
+    /// assert_eq!(inferno(0.3), Colormap::Inferno.sample(0.3));
+    /// ```
+    pub fn function(self) -> fn(f64) -> Rgb<u8> {
+        match self {
+            Colormap::Viridis => palette::viridis,
+            Colormap::Magma => palette::magma,
+            Colormap::Inferno => palette::inferno,
+            Colormap::Turbo => palette::turbo,
+            Colormap::Rainbow => palette::rainbow,
+            Colormap::Grayscale => palette::grayscale,
+        }
+    }
+
+    fn stops(self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Turbo => &TURBO,
+            Colormap::Rainbow => &RAINBOW,
+            Colormap::Grayscale => &GRAYSCALE,
+        }
+    }
+}
+
+/// Linearly interpolates between equally spaced color stops.
+pub(crate) fn interpolate(stops: &[[u8; 3]], t: f64) -> Rgb<u8> {
+    // NaN ends up at the start of the map
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+
+    let scaled = t * (stops.len() - 1) as f64;
+    let index = (scaled.floor() as usize).min(stops.len() - 2);
+    let frac = scaled - index as f64;
+
+    let (lower, upper) = (stops[index], stops[index + 1]);
+    let mut color = [0u8; 3];
+
+    for (channel, value) in color.iter_mut().enumerate() {
+        let a = lower[channel] as f64;
+        let b = upper[channel] as f64;
+        *value = (a + (b - a) * frac).round() as u8;
+    }
+
+    Rgb(color)
+}
+
+/// (De)serializes an [`Rgb<u8>`] as an array `[r, g, b]`, since `image` doesn't implement serde's traits.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_rgb {
+    use image::Rgb;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Rgb<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        color.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgb<u8>, D::Error> {
+        Ok(Rgb(<[u8; 3]>::deserialize(deserializer)?))
+    }
+}
+
+/// (De)serializes an [`Option<Rgb<u8>>`] as either `null` or an array `[r, g, b]`.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_rgb_option {
+    use image::Rgb;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        color: &Option<Rgb<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        color.map(|color| color.0).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Rgb<u8>>, D::Error> {
+        Ok(Option::<[u8; 3]>::deserialize(deserializer)?.map(Rgb))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colormap_endpoints() {
+        for colormap in &[
+            Colormap::Viridis,
+            Colormap::Magma,
+            Colormap::Inferno,
+            Colormap::Turbo,
+            Colormap::Rainbow,
+            Colormap::Grayscale,
+        ] {
+            let stops = colormap.stops();
+
+            assert_eq!(colormap.sample(0.0), Rgb(stops[0]));
+            assert_eq!(colormap.sample(1.0), Rgb(stops[stops.len() - 1]));
+            assert_eq!(colormap.sample(f64::NAN), Rgb(stops[0]));
+        }
+    }
+
+    #[test]
+    fn colormap_interpolates_between_stops() {
+        // Halfway between the first two viridis stops
+        assert_eq!(Colormap::Viridis.sample(0.0625), Rgb([70, 23, 104]));
+    }
+}
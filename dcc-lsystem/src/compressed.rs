@@ -0,0 +1,240 @@
+//! A compressed representation of the state of an [`LSystem`], for very deep expansions.
+//!
+//! Deterministic L-systems are massively self-similar: every occurrence of a token expands
+//! into exactly the same sequence of tokens.  Rather than storing the state as a flat
+//! `Vec<ArenaId>`, a [`CompressedState`] treats the state as a DAG whose nodes are pairs
+//! `(token, remaining steps)`, shared between every occurrence.  Only the length of each
+//! node is stored, so memory use grows with the number of steps rather than the length
+//! of the state.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::LSystemBuilder;
+//!
+//! let mut builder = LSystemBuilder::new();
+//! let a = builder.token("A")?;
+//! let b = builder.token("B")?;
+//! builder.axiom(vec![a])?;
+//! builder.transformation_rule(a, vec![a, b])?;
+//! builder.transformation_rule(b, vec![a])?;
+//! let system = builder.finish()?;
+//!
+//! // The state after 60 steps has more than a trillion tokens
+//! let state = system.step_compressed(60);
+//! assert_eq!(state.len(), 4_052_739_537_881);
+//!
+//! // but we can still stream through the start of it
+//! let start: Vec<_> = state.iter().take(5).collect();
+//! assert_eq!(start, vec![a, b, a, a, b]);
+//! # Ok(())
+//! # }
+//! ```
+use alloc::vec;
+use alloc::vec::Vec;
+use core::slice::Iter;
+
+use crate::{ArenaId, LSystem};
+
+/// The state of an [`LSystem`] after some number of steps, stored as a DAG of shared
+/// sub-expansions.  Obtained using [`LSystem::step_compressed()`].
+#[derive(Debug, Clone)]
+pub struct CompressedState<'a> {
+    system: &'a LSystem,
+    /// `lengths[d][id.0]` is the number of tokens `id` expands to over the last `d` steps.
+    lengths: Vec<Vec<usize>>,
+}
+
+impl<'a> CompressedState<'a> {
+    /// Create a compressed view of the current state of `system`.
+    pub(crate) fn new(system: &'a LSystem) -> Self {
+        Self {
+            system,
+            lengths: vec![vec![1; system.token_count()]],
+        }
+    }
+
+    /// Iterate the compressed state a single step.
+    pub fn step(&mut self) {
+        self.step_by(1);
+    }
+
+    /// Iterate the compressed state by `n` steps.
+    pub fn step_by(&mut self, n: usize) {
+        let steps = self.steps() + n;
+
+        // The production rules can depend on the step, so every row is recomputed
+        // to line up with the new final step.
+        self.lengths.truncate(1);
+
+        for d in 1..=steps {
+            let productions = self.system.productions_at(self.system.steps() + steps - d);
+            let previous = &self.lengths[d - 1];
+
+            let next = (0..previous.len())
+                .map(|index| {
+                    productions
+                        .successor(ArenaId(index))
+                        .iter()
+                        .fold(0usize, |length, id| length.saturating_add(previous[id.0]))
+                })
+                .collect();
+
+            self.lengths.push(next);
+        }
+    }
+
+    /// Returns the number of steps this state is ahead of the [`LSystem`] it was created from.
+    pub fn steps(&self) -> usize {
+        self.lengths.len() - 1
+    }
+
+    /// Returns the number of tokens in the state, saturating at `usize::MAX`.
+    pub fn len(&self) -> usize {
+        // unwrap: `lengths` always contains at least one row
+        let lengths = self.lengths.last().unwrap();
+
+        self.system
+            .get_state()
+            .iter()
+            .fold(0usize, |length, id| length.saturating_add(lengths[id.0]))
+    }
+
+    /// Returns `true` if the state contains no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the tokens of the state, which expands the state lazily.
+    pub fn iter(&self) -> Tokens<'a> {
+        Tokens {
+            system: self.system,
+            steps: self.steps(),
+            stack: vec![self.system.get_state().iter()],
+        }
+    }
+
+    /// Calls `f` on each token of the state in turn, without materializing the state.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("A")?;
+    /// let b = builder.token("B")?;
+    /// builder.axiom(vec![a])?;
+    /// builder.transformation_rule(a, vec![a, b, a])?;
+    /// let system = builder.finish()?;
+    ///
+    /// let mut bars = 0;
+    /// system.step_compressed(10).for_each_token(|id| {
+    ///     if id == a {
+    ///         bars += 1;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(bars, 1024);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_each_token<F: FnMut(ArenaId)>(&self, f: F) {
+        self.iter().for_each(f);
+    }
+}
+
+/// An iterator over the tokens of a [`CompressedState`].
+///
+/// Uses memory proportional to the number of steps of the state.
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    system: &'a LSystem,
+    steps: usize,
+    /// The remaining siblings at each level of the expansion; tokens in the `k`-th
+    /// entry still need to be expanded `steps - k` times.
+    stack: Vec<Iter<'a, ArenaId>>,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = ArenaId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = self.stack.last_mut()?.next();
+            let level = self.stack.len() - 1;
+
+            match next {
+                Some(id) if level == self.steps => return Some(*id),
+                Some(id) => {
+                    let productions = self.system.productions_at(self.system.steps() + level);
+                    self.stack.push(productions.successor(*id).iter());
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LSystemBuilder;
+
+    #[test]
+    fn compressed_state_matches_step() {
+        let mut builder = LSystemBuilder::new();
+
+        let x = builder.token("X").unwrap();
+        let y = builder.token("Y").unwrap();
+        let f = builder.token("F").unwrap();
+        let plus = builder.token("+").unwrap();
+        let minus = builder.token("-").unwrap();
+
+        builder.axiom(vec![f, x]).unwrap();
+        builder
+            .transformation_rule(x, vec![x, plus, y, f, plus])
+            .unwrap();
+        builder
+            .transformation_rule(y, vec![minus, f, x, minus, y])
+            .unwrap();
+
+        let initial = builder.finish().unwrap();
+        let mut system = initial.clone();
+        let mut compressed = initial.step_compressed(0);
+
+        for _ in 0..8 {
+            assert_eq!(compressed.len(), system.get_state().len());
+            assert_eq!(compressed.iter().collect::<Vec<_>>(), system.get_state());
+
+            compressed.step();
+            system.step();
+        }
+
+        // 30 steps of the dragon curve, which would need 32GiB to store flat
+        let deep = initial.step_compressed(30);
+        assert_eq!(deep.steps(), 30);
+        assert_eq!(deep.len(), 4_294_967_294);
+    }
+
+    #[test]
+    fn compressed_state_handles_empty_successors() {
+        let mut builder = LSystemBuilder::new();
+
+        let a = builder.token("A").unwrap();
+        let b = builder.token("B").unwrap();
+
+        builder.axiom(vec![a, b]).unwrap();
+        builder.transformation_rule(a, vec![]).unwrap();
+
+        let system = builder.finish().unwrap();
+        let state = system.step_compressed(3);
+
+        assert_eq!(state.len(), 1);
+        assert_eq!(state.iter().collect::<Vec<_>>(), vec![b]);
+        assert!(!system.step_compressed(0).is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+//! Loads turtle systems from declarative TOML or JSON documents.
+//!
+//! A document declares the action of each token, the axiom and the rules of the system, so
+//! artwork can be changed without recompiling any Rust code.  Actions are written as they are
+//! in a [grammar](crate::grammar), so `forward 5..10` moves the turtle forwards a uniformly
+//! random distance.  Everything but `tokens` and `axiom` is optional:
+//!
+//! ```toml
+//! axiom = "X"
+//! rules = ["X => F + [ [ X ] - X ] - F [ - F X ] + X", "F => F F"]
+//! steps = 5
+//! seed = 42
+//! rotate = 70
+//!
+//! [tokens]
+//! F = "forward 5..10"
+//! X = "nothing"
+//! "+" = "rotate 25"
+//! "-" = "rotate -25"
+//! "[" = "push"
+//! "]" = "pop"
+//! ```
+//!
+//! Rules can be stochastic, as described in [`TurtleLSystemBuilder::rule`].  The `renderer`
+//! entry can hold options for a renderer, in the form they're serialized in, which are read
+//! with [`TurtleConfig::renderer_options`].
+//!
+//! Requires the `config` feature.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::config::TurtleConfig;
+//!
+//! let config = TurtleConfig::from_toml(r#"
+//!     axiom = "F"
+//!     rules = ["F => F + F - F - F + F"]
+//!     steps = 2
+//!
+//!     [tokens]
+//!     F = "forward 10"
+//!     "+" = "rotate 90"
+//!     "-" = "rotate -90"
+//! "#)?;
+//!
+//! let (system, _renderer) = config.system()?;
+//! assert_eq!(system.render().matches('F').count(), 25);
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+use crate::renderer::TurtleRenderer;
+use crate::turtle::{self, TurtleLSystemBuilder, TurtleLSystemState};
+use crate::{LSystem, LSystemError};
+
+/// A turtle system, as declared by a TOML or JSON document.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TurtleConfig {
+    tokens: BTreeMap<String, String>,
+    axiom: String,
+    #[serde(default)]
+    rules: Vec<String>,
+    #[serde(default)]
+    steps: usize,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    rotate: Option<f64>,
+    #[serde(default)]
+    scale: Option<f64>,
+    #[serde(default)]
+    initial_heading: Option<f64>,
+    #[serde(default)]
+    renderer: Option<serde_json::Value>,
+}
+
+impl TurtleConfig {
+    /// Reads a config from a TOML document.
+    pub fn from_toml(text: &str) -> Result<Self, LSystemError> {
+        toml::from_str(text).map_err(|e| LSystemError::InvalidConfig(e.to_string()))
+    }
+
+    /// Reads a config from a JSON document.
+    pub fn from_json(text: &str) -> Result<Self, LSystemError> {
+        serde_json::from_str(text).map_err(|e| LSystemError::InvalidConfig(e.to_string()))
+    }
+
+    /// Reads a config from a document which is JSON if it starts with `{`, and TOML otherwise.
+    pub fn parse(text: &str) -> Result<Self, LSystemError> {
+        if text.trim_start().starts_with('{') {
+            Self::from_json(text)
+        } else {
+            Self::from_toml(text)
+        }
+    }
+
+    /// Reads a config from the file at `path`, which is JSON if it has a `.json` extension,
+    /// and TOML otherwise.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, LSystemError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::from_json(&text),
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    /// Returns the action of each token, as it would be written in a grammar.
+    pub fn tokens(&self) -> &BTreeMap<String, String> {
+        &self.tokens
+    }
+
+    pub fn axiom(&self) -> &str {
+        &self.axiom
+    }
+
+    pub fn rules(&self) -> &[String] {
+        &self.rules
+    }
+
+    /// Returns the number of times the system should be stepped before it's rendered.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Reads the options under the `renderer` entry, or returns `None` if there isn't one.
+    /// The options can be changed further by converting them into their builder, e.g. with
+    /// `ImageRendererOptionsBuilder::from`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::config::TurtleConfig;
+    /// use dcc_lsystem::export::ExportRendererOptions;
+    ///
+    /// let config = TurtleConfig::from_json(r#"{
+    ///     "tokens": { "F": "forward 1" },
+    ///     "axiom": "F",
+    ///     "renderer": { "format": "Csv", "precision": 2, "normalize": false }
+    /// }"#)?;
+    ///
+    /// let options: ExportRendererOptions = config.renderer_options()?.unwrap();
+    /// assert_eq!(options.precision(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn renderer_options<T: DeserializeOwned>(&self) -> Result<Option<T>, LSystemError> {
+        self.renderer
+            .clone()
+            .map(|options| {
+                serde_json::from_value(options)
+                    .map_err(|e| LSystemError::InvalidConfig(format!("renderer: {}", e)))
+            })
+            .transpose()
+    }
+
+    /// Returns a builder for the system.
+    pub fn builder(&self) -> Result<TurtleLSystemBuilder, LSystemError> {
+        let mut builder = TurtleLSystemBuilder::new();
+
+        for (name, action) in &self.tokens {
+            let action = turtle::parse_action_text(action).map_err(|e| match e {
+                LSystemError::ParseError { message, .. } => {
+                    LSystemError::InvalidConfig(format!("action of `{}`: {}", name, message))
+                }
+                e => e,
+            })?;
+
+            builder.token(name.as_str(), action)?;
+        }
+
+        builder.axiom(&self.axiom)?;
+
+        for rule in &self.rules {
+            builder.rule(rule.as_str())?;
+        }
+
+        if let Some(seed) = self.seed {
+            builder.seed(seed);
+        }
+
+        if let Some(rotate) = self.rotate {
+            builder.rotate(rotate);
+        }
+
+        if let Some(scale) = self.scale {
+            builder.scale(scale);
+        }
+
+        if let Some(initial_heading) = self.initial_heading {
+            builder.initial_heading(initial_heading);
+        }
+
+        Ok(builder)
+    }
+
+    /// Builds the system and steps it [`steps`](TurtleConfig::steps) times.
+    pub fn system(&self) -> Result<(LSystem, TurtleRenderer<TurtleLSystemState>), LSystemError> {
+        let (mut system, renderer) = self.builder()?.finish()?;
+        system.step_by(self.steps);
+
+        Ok((system, renderer))
+    }
+}
@@ -0,0 +1,215 @@
+//! Tracks where each symbol in the state of an [`LSystem`] came from.
+//!
+//! Once [`LSystem::track_derivation()`] has been called, every step records the symbols it
+//! produced in a [`Derivation`].  Each symbol is a [`DerivationNode`], which links back to the
+//! symbol it was expanded from and forward to the symbols it expanded into.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::LSystemBuilder;
+//!
+//! let mut builder = LSystemBuilder::new();
+//! let a = builder.token("A")?;
+//! let b = builder.token("B")?;
+//! builder.axiom(vec![a])?;
+//! builder.transformation_rule(a, vec![a, b])?;
+//! builder.transformation_rule(b, vec![a])?;
+//!
+//! let mut system = builder.finish()?;
+//! system.track_derivation();
+//! system.step_by(2);
+//! assert_eq!(system.render(), "ABA");
+//!
+//! // The final `A` was produced by the `B` from the first step
+//! let derivation = system.derivation().unwrap();
+//! let parents: Vec<_> = derivation
+//!     .ancestors(derivation.current_index(2))
+//!     .map(|node| node.token())
+//!     .collect();
+//!
+//! assert_eq!(parents, vec![b, a]);
+//! # Ok(())
+//! # }
+//! ```
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::ArenaId;
+
+/// A single symbol in a [`Derivation`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DerivationNode {
+    token: ArenaId,
+    step: usize,
+    parent: Option<usize>,
+    children: Range<usize>,
+}
+
+impl DerivationNode {
+    /// Returns the token of this symbol.
+    pub fn token(&self) -> ArenaId {
+        self.token
+    }
+
+    /// Returns the step which produced this symbol.  Symbols present when tracking started
+    /// have step `0`.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Returns the index of the symbol this symbol was expanded from, or `None` if this
+    /// symbol was present when tracking started.
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    /// Returns the indices of the symbols this symbol expanded into.  The range is empty
+    /// if the symbol hasn't been expanded yet, or if it expanded into nothing.
+    pub fn children(&self) -> Range<usize> {
+        self.children.clone()
+    }
+}
+
+/// The derivation tree of an [`LSystem`](crate::LSystem), obtained using
+/// [`LSystem::derivation()`](crate::LSystem::derivation).
+///
+/// Nodes are referred to by their index in [`Derivation::nodes()`].  The nodes produced
+/// by each step are stored together, in the same order as the state they make up.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Derivation {
+    nodes: Vec<DerivationNode>,
+    /// `generations[k]` is the index of the first node produced by the `k`-th tracked step.
+    generations: Vec<usize>,
+}
+
+impl Derivation {
+    /// Start tracking the derivation of `state`.
+    pub(crate) fn new(state: &[ArenaId]) -> Self {
+        let nodes = state
+            .iter()
+            .map(|token| DerivationNode {
+                token: *token,
+                step: 0,
+                parent: None,
+                children: 0..0,
+            })
+            .collect();
+
+        Self {
+            nodes,
+            generations: vec![0],
+        }
+    }
+
+    /// Records a step which expanded the `k`-th symbol of the current generation into
+    /// the next `lengths[k]` symbols of `state`.
+    pub(crate) fn grow<I: IntoIterator<Item = usize>>(&mut self, state: &[ArenaId], lengths: I) {
+        let parent_start = self.current_start();
+        let start = self.nodes.len();
+        let step = self.generations.len();
+        let mut next = start;
+
+        for (parent, length) in (parent_start..start).zip(lengths) {
+            self.nodes[parent].children = next..next + length;
+
+            for token in &state[next - start..next - start + length] {
+                self.nodes.push(DerivationNode {
+                    token: *token,
+                    step,
+                    parent: Some(parent),
+                    children: 0..0,
+                });
+            }
+
+            next += length;
+        }
+
+        self.generations.push(start);
+    }
+
+    /// Forgets every step after the first `steps` recorded steps.
+    pub(crate) fn truncate(&mut self, steps: usize) {
+        if steps >= self.steps() {
+            return;
+        }
+
+        let end = self.generations[steps + 1];
+        self.nodes.truncate(end);
+        self.generations.truncate(steps + 1);
+
+        // The symbols of the new current generation haven't been expanded yet
+        let start = self.current_start();
+        for node in self.nodes[start..].iter_mut() {
+            node.children = 0..0;
+        }
+    }
+
+    fn current_start(&self) -> usize {
+        // unwrap: `generations` always contains at least one entry
+        *self.generations.last().unwrap()
+    }
+
+    /// Returns every node in the derivation.
+    pub fn nodes(&self) -> &[DerivationNode] {
+        &self.nodes
+    }
+
+    /// Returns the node with index `index`, if there is one.
+    pub fn node(&self, index: usize) -> Option<&DerivationNode> {
+        self.nodes.get(index)
+    }
+
+    /// Returns the number of steps recorded.
+    pub fn steps(&self) -> usize {
+        self.generations.len() - 1
+    }
+
+    /// Returns the nodes produced by the `step`-th recorded step, where step `0` is the
+    /// state when tracking started.
+    pub fn generation(&self, step: usize) -> Option<&[DerivationNode]> {
+        let start = *self.generations.get(step)?;
+        let end = self
+            .generations
+            .get(step + 1)
+            .copied()
+            .unwrap_or(self.nodes.len());
+
+        Some(&self.nodes[start..end])
+    }
+
+    /// Returns the index of the node for the `position`-th symbol of the current state.
+    pub fn current_index(&self, position: usize) -> usize {
+        self.current_start() + position
+    }
+
+    /// Returns an iterator over the ancestors of the node with index `index`, starting
+    /// with its parent.
+    pub fn ancestors(&self, index: usize) -> Ancestors<'_> {
+        Ancestors {
+            derivation: self,
+            next: self.node(index).and_then(|node| node.parent),
+        }
+    }
+}
+
+/// An iterator over the ancestors of a node in a [`Derivation`].
+#[derive(Debug, Clone)]
+pub struct Ancestors<'a> {
+    derivation: &'a Derivation,
+    next: Option<usize>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = &'a DerivationNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.derivation.node(self.next?)?;
+        self.next = node.parent;
+        Some(node)
+    }
+}
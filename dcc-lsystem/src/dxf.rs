@@ -0,0 +1,212 @@
+//! Renders turtle output as a DXF drawing, for CAD tools and laser cutters.
+//!
+//! Lines are written on one layer per color, so that each color can be assigned a different
+//! operation (for example cutting and engraving).  Lines without a color are written to the
+//! layer `0`.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::renderer::{DxfRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .token("-", TurtleAction::Rotate(-90.0))?
+//!     .axiom("F")?
+//!     .rule("F => F + F - F - F + F")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(3);
+//!
+//! let options = DxfRendererOptionsBuilder::new().scale(0.5).build();
+//! let dxf = renderer.render(&system, &options);
+//!
+//! // The Koch curve is a single connected line
+//! assert_eq!(dxf.matches("LWPOLYLINE").count(), 1);
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Write;
+
+use image::Rgb;
+
+use crate::renderer::{polylines, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+pub struct DxfRendererOptionsBuilder {
+    options: DxfRendererOptions,
+}
+
+impl DxfRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: DxfRendererOptions {
+                scale: 1.0,
+                polylines: true,
+            },
+        }
+    }
+
+    /// Set the length, in millimetres, of a single turtle unit.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Join connected lines into `LWPOLYLINE` entities.  When disabled, every line is written
+    /// as a separate `LINE` entity, which older tools may handle better.
+    pub fn polylines(&mut self, polylines: bool) -> &mut Self {
+        self.options.polylines = polylines;
+        self
+    }
+
+    pub fn build(&mut self) -> DxfRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for DxfRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DxfRendererOptions {
+    scale: f64,
+    polylines: bool,
+}
+
+impl DxfRendererOptions {
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn polylines(&self) -> bool {
+        self.polylines
+    }
+}
+
+/// Returns the name of the layer lines of the given color are written to.
+fn layer(color: Option<Rgb<u8>>) -> String {
+    match color {
+        Some(Rgb([red, green, blue])) => format!("COLOR_{:02X}{:02X}{:02X}", red, green, blue),
+        None => String::from("0"),
+    }
+}
+
+/// Writes a single group code and value.
+fn group<T: std::fmt::Display>(output: &mut String, code: u16, value: T) {
+    // unwrap: writing to a String never fails
+    writeln!(output, "{}\n{}", code, value).unwrap();
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<DxfRendererOptions> for TurtleRenderer<Q> {
+    type Output = String;
+
+    fn render(&self, system: &LSystem, options: &DxfRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+        let paths = polylines(&state);
+
+        let mut colors: Vec<Option<Rgb<u8>>> = Vec::new();
+        for (color, _) in paths.iter() {
+            if color.is_some() && !colors.contains(color) {
+                colors.push(*color);
+            }
+        }
+
+        let mut output = String::new();
+
+        group(&mut output, 0, "SECTION");
+        group(&mut output, 2, "HEADER");
+        group(&mut output, 9, "$ACADVER");
+        group(&mut output, 1, "AC1015");
+        group(&mut output, 9, "$INSUNITS");
+        group(&mut output, 70, 4);
+        group(&mut output, 0, "ENDSEC");
+
+        // One layer per color, using a true color so that nothing is lost to the CAD palette
+        group(&mut output, 0, "SECTION");
+        group(&mut output, 2, "TABLES");
+        group(&mut output, 0, "TABLE");
+        group(&mut output, 2, "LAYER");
+        group(&mut output, 70, colors.len() + 1);
+
+        group(&mut output, 0, "LAYER");
+        group(&mut output, 2, "0");
+        group(&mut output, 70, 0);
+        group(&mut output, 62, 7);
+        group(&mut output, 6, "CONTINUOUS");
+
+        for color in colors.iter() {
+            // unwrap: `colors` only contains actual colors
+            let Rgb([red, green, blue]) = color.unwrap();
+
+            group(&mut output, 0, "LAYER");
+            group(&mut output, 2, layer(*color));
+            group(&mut output, 70, 0);
+            group(&mut output, 62, 7);
+            group(&mut output, 6, "CONTINUOUS");
+            group(
+                &mut output,
+                420,
+                (red as u32) << 16 | (green as u32) << 8 | blue as u32,
+            );
+        }
+
+        group(&mut output, 0, "ENDTAB");
+        group(&mut output, 0, "ENDSEC");
+
+        group(&mut output, 0, "SECTION");
+        group(&mut output, 2, "ENTITIES");
+
+        let scaled = |(x, y): (f64, f64)| (x * options.scale, y * options.scale);
+
+        for (color, path) in paths {
+            let layer = layer(color);
+
+            if options.polylines {
+                group(&mut output, 0, "LWPOLYLINE");
+                group(&mut output, 100, "AcDbEntity");
+                group(&mut output, 8, &layer);
+                group(&mut output, 100, "AcDbPolyline");
+                group(&mut output, 90, path.len());
+                group(&mut output, 70, 0);
+
+                for point in path {
+                    let (x, y) = scaled(point);
+                    group(&mut output, 10, x);
+                    group(&mut output, 20, y);
+                }
+            } else {
+                for pair in path.windows(2) {
+                    let (x1, y1) = scaled(pair[0]);
+                    let (x2, y2) = scaled(pair[1]);
+
+                    group(&mut output, 0, "LINE");
+                    group(&mut output, 100, "AcDbEntity");
+                    group(&mut output, 8, &layer);
+                    group(&mut output, 100, "AcDbLine");
+                    group(&mut output, 10, x1);
+                    group(&mut output, 20, y1);
+                    group(&mut output, 30, 0.0);
+                    group(&mut output, 11, x2);
+                    group(&mut output, 21, y2);
+                    group(&mut output, 31, 0.0);
+                }
+            }
+        }
+
+        group(&mut output, 0, "ENDSEC");
+        group(&mut output, 0, "EOF");
+
+        output
+    }
+}
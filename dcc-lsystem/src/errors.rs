@@ -1,30 +1,111 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+
 use crate::ArenaId;
-use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug)]
 #[non_exhaustive]
 pub enum LSystemError {
-    #[error("attempted to use unknown token `{0}`")]
     UnknownToken(String),
-    #[error("attempted to construct invalid token `{0}`")]
     InvalidToken(String),
-    #[error("invalid arena ID `{0:?}`")]
     InvalidArenaId(ArenaId),
-    #[error("invalid rule `{0}`")]
     InvalidRule(String),
-    #[error("axiom has not been defined")]
+    DuplicateRule(String),
     MissingAxiom,
-    #[error("io error")]
-    IOError(#[from] std::io::Error),
-    #[error("there was an unexpected error in another thread")]
+    ParseError {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    LimitExceeded {
+        step: usize,
+        length: usize,
+    },
+    InvalidFont,
+    InvalidRecipe(String),
+    InvalidConfig(String),
+    InvalidScript(String),
+    #[cfg(feature = "std")]
+    IOError(std::io::Error),
     ThreadError,
-    #[error("there was an unexpected error: {source}")]
     Other {
-        #[source]
-        source: Box<dyn std::error::Error + Send + Sync>,
+        source: Box<dyn core::error::Error + Send + Sync>,
     },
 }
 
+impl fmt::Display for LSystemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LSystemError::UnknownToken(token) => {
+                write!(f, "attempted to use unknown token `{}`", token)
+            }
+            LSystemError::InvalidToken(token) => {
+                write!(f, "attempted to construct invalid token `{}`", token)
+            }
+            LSystemError::InvalidArenaId(id) => write!(f, "invalid arena ID `{:?}`", id),
+            LSystemError::InvalidRule(rule) => write!(f, "invalid rule `{}`", rule),
+            LSystemError::DuplicateRule(token) => {
+                write!(f, "token `{}` already has a transformation rule", token)
+            }
+            LSystemError::MissingAxiom => write!(f, "axiom has not been defined"),
+            LSystemError::ParseError {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "parse error at line {}, column {}: {}",
+                line, column, message
+            ),
+            LSystemError::LimitExceeded { step, length } => write!(
+                f,
+                "step {} would grow the state to {} tokens, exceeding the step limits",
+                step, length
+            ),
+            LSystemError::InvalidFont => write!(f, "invalid font"),
+            LSystemError::InvalidRecipe(message) => write!(f, "invalid recipe: {}", message),
+            LSystemError::InvalidConfig(message) => write!(f, "invalid config: {}", message),
+            LSystemError::InvalidScript(message) => write!(f, "invalid script: {}", message),
+            #[cfg(feature = "std")]
+            LSystemError::IOError(_) => write!(f, "io error"),
+            LSystemError::ThreadError => {
+                write!(f, "there was an unexpected error in another thread")
+            }
+            LSystemError::Other { source } => {
+                write!(f, "there was an unexpected error: {}", source)
+            }
+        }
+    }
+}
+
+impl core::error::Error for LSystemError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            LSystemError::IOError(e) => Some(e),
+            LSystemError::Other { source } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for LSystemError {
+    fn from(e: std::io::Error) -> Self {
+        LSystemError::IOError(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<rand::distributions::WeightedError> for LSystemError {
+    fn from(e: rand::distributions::WeightedError) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
+
 #[cfg(feature = "image_renderer")]
 impl From<gifski::Error> for LSystemError {
     fn from(e: gifski::Error) -> Self {
@@ -33,3 +114,66 @@ impl From<gifski::Error> for LSystemError {
         }
     }
 }
+
+#[cfg(feature = "image_renderer")]
+impl From<image::ImageError> for LSystemError {
+    fn from(e: image::ImageError) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
+
+#[cfg(feature = "image_renderer")]
+impl From<png::EncodingError> for LSystemError {
+    fn from(e: png::EncodingError) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
+
+#[cfg(feature = "image_renderer")]
+impl From<png::DecodingError> for LSystemError {
+    fn from(e: png::DecodingError) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
+
+#[cfg(feature = "image_renderer")]
+impl From<gif::EncodingError> for LSystemError {
+    fn from(e: gif::EncodingError) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
+
+#[cfg(feature = "preview")]
+impl From<minifb::Error> for LSystemError {
+    fn from(e: minifb::Error) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl From<wgpu::RequestDeviceError> for LSystemError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl From<wgpu::BufferAsyncError> for LSystemError {
+    fn from(e: wgpu::BufferAsyncError) -> Self {
+        LSystemError::Other {
+            source: Box::new(e),
+        }
+    }
+}
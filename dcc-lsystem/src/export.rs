@@ -0,0 +1,223 @@
+//! Exports the lines drawn by the turtle as CSV, JSON or GeoJSON.
+//!
+//! CSV and JSON output contain every line, in the order it was drawn, as `x1, y1, x2, y2`.
+//! GeoJSON output contains a `LineString` feature for each run of connected visible lines,
+//! with the color of the lines (if any) stored in the `color` property.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::export::ExportFormat;
+//! use dcc_lsystem::renderer::{ExportRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .axiom("F + F")?;
+//!
+//! let (system, renderer) = builder.finish()?;
+//!
+//! let options = ExportRendererOptionsBuilder::new()
+//!     .format(ExportFormat::Csv)
+//!     .precision(1)
+//!     .normalize(true)
+//!     .build();
+//!
+//! assert_eq!(
+//!     renderer.render(&system, &options),
+//!     "x1,y1,x2,y2\n0.0,0.0,1.0,0.0\n1.0,0.0,1.0,1.0\n"
+//! );
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Write;
+
+use crate::renderer::{bounds, lines, polylines, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+/// The formats supported by the export renderer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExportFormat {
+    /// A header row followed by one row per line.
+    Csv,
+    /// An array of `[x1, y1, x2, y2]` arrays.
+    Json,
+    /// A GeoJSON `FeatureCollection` of `LineString`s.
+    GeoJson,
+}
+
+pub struct ExportRendererOptionsBuilder {
+    options: ExportRendererOptions,
+}
+
+impl ExportRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: ExportRendererOptions {
+                format: ExportFormat::Csv,
+                precision: 3,
+                normalize: false,
+            },
+        }
+    }
+
+    pub fn format(&mut self, format: ExportFormat) -> &mut Self {
+        self.options.format = format;
+        self
+    }
+
+    /// Set the number of digits written after the decimal point.
+    pub fn precision(&mut self, precision: usize) -> &mut Self {
+        self.options.precision = precision;
+        self
+    }
+
+    /// Scale and move the drawing so that it fits in the unit square, preserving its
+    /// aspect ratio.
+    pub fn normalize(&mut self, normalize: bool) -> &mut Self {
+        self.options.normalize = normalize;
+        self
+    }
+
+    pub fn build(&mut self) -> ExportRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for ExportRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<ExportRendererOptions> for ExportRendererOptionsBuilder {
+    fn from(options: ExportRendererOptions) -> Self {
+        Self { options }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExportRendererOptions {
+    format: ExportFormat,
+    precision: usize,
+    normalize: bool,
+}
+
+impl ExportRendererOptions {
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+
+    pub fn precision(&self) -> usize {
+        self.precision
+    }
+
+    pub fn normalize(&self) -> bool {
+        self.normalize
+    }
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<ExportRendererOptions> for TurtleRenderer<Q> {
+    type Output = String;
+
+    fn render(&self, system: &LSystem, options: &ExportRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+
+        let (width, height, min_x, min_y) = bounds(&state);
+        let size = width.max(height);
+
+        let transform = |x: f64, y: f64| {
+            if options.normalize && size > 0.0 {
+                ((x - min_x) / size, (y - min_y) / size)
+            } else {
+                (x, y)
+            }
+        };
+
+        let number = |value: f64| format!("{:.*}", options.precision, value);
+        let point = |(x, y): (f64, f64)| {
+            let (x, y) = transform(x, y);
+            format!("[{}, {}]", number(x), number(y))
+        };
+
+        // unwrap (here and below): writing to a String never fails
+        let mut output = String::new();
+
+        match options.format {
+            ExportFormat::Csv => {
+                output.push_str("x1,y1,x2,y2\n");
+
+                for (x1, y1, x2, y2) in lines(&state) {
+                    let (x1, y1) = transform(*x1, *y1);
+                    let (x2, y2) = transform(*x2, *y2);
+
+                    writeln!(
+                        output,
+                        "{},{},{},{}",
+                        number(x1),
+                        number(y1),
+                        number(x2),
+                        number(y2)
+                    )
+                    .unwrap();
+                }
+            }
+            ExportFormat::Json => {
+                let rows: Vec<_> = lines(&state)
+                    .map(|(x1, y1, x2, y2)| {
+                        let (x1, y1) = transform(*x1, *y1);
+                        let (x2, y2) = transform(*x2, *y2);
+
+                        format!(
+                            "[{}, {}, {}, {}]",
+                            number(x1),
+                            number(y1),
+                            number(x2),
+                            number(y2)
+                        )
+                    })
+                    .collect();
+
+                writeln!(output, "[{}]", rows.join(", ")).unwrap();
+            }
+            ExportFormat::GeoJson => {
+                let features: Vec<_> = polylines(&state)
+                    .into_iter()
+                    .map(|(color, path)| {
+                        let color = match color {
+                            Some(image::Rgb([red, green, blue])) => {
+                                format!("\"#{:02x}{:02x}{:02x}\"", red, green, blue)
+                            }
+                            None => String::from("null"),
+                        };
+
+                        let coordinates: Vec<_> = path.into_iter().map(point).collect();
+
+                        format!(
+                            "{{\"type\": \"Feature\", \"properties\": {{\"color\": {}}}, \
+                             \"geometry\": {{\"type\": \"LineString\", \"coordinates\": [{}]}}}}",
+                            color,
+                            coordinates.join(", ")
+                        )
+                    })
+                    .collect();
+
+                writeln!(
+                    output,
+                    "{{\"type\": \"FeatureCollection\", \"features\": [{}]}}",
+                    features.join(", ")
+                )
+                .unwrap();
+            }
+        }
+
+        output
+    }
+}
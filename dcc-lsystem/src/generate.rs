@@ -0,0 +1,250 @@
+//! Generates random branching systems, for exploring what a turtle can draw.
+//!
+//! Each generated system has the following symbols:
+//!
+//! | Symbol | Action                               |
+//! |--------|--------------------------------------|
+//! | `F`    | Move forwards                        |
+//! | `+`    | Rotate anticlockwise                 |
+//! | `-`    | Rotate clockwise                     |
+//! | `[`    | Push the turtle's position           |
+//! | `]`    | Pop the turtle's position            |
+//! | `X`    | Nothing, but is rewritten each step  |
+//! | `Y`    | Nothing, but is rewritten each step  |
+//!
+//! The axiom starts with `X`, and random rules are chosen for `X` and `Y`.  Every rule opens at
+//! least one branch, and every branch it opens is closed again.
+//!
+//! Most random systems aren't very interesting, so typically systems are generated in a loop
+//! until one passes some test.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::generate::RandomSystemBuilder;
+//! use dcc_lsystem::turtle::TurtleLSystemBuilder;
+//!
+//! let mut generator = RandomSystemBuilder::new();
+//! generator.seed(42).angle(25).rule_length(4, 10).weight('F', 8);
+//!
+//! let grammar = loop {
+//!     let grammar = generator.generate_grammar()?;
+//!
+//!     let builder = TurtleLSystemBuilder::from_grammar(&grammar)?;
+//!     let (mut system, renderer) = builder.finish()?;
+//!     system.step_by(4);
+//!
+//!     if renderer.metrics(&system).segments > 10 {
+//!         break grammar;
+//!     }
+//! };
+//!
+//! println!("{}", grammar);
+//! # Ok(())
+//! # }
+//! ```
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::turtle::TurtleLSystemBuilder;
+use crate::LSystemError;
+
+/// The symbols which can appear in the rules of a generated system.
+const SYMBOLS: [char; 7] = ['F', '+', '-', '[', ']', 'X', 'Y'];
+
+/// Generates random systems drawn by a turtle.  See the [`generate`](crate::generate) module.
+pub struct RandomSystemBuilder {
+    rng: StdRng,
+    angle: i32,
+    distance: i32,
+    weights: [u32; 7],
+    axiom_length: (usize, usize),
+    rule_length: (usize, usize),
+}
+
+impl RandomSystemBuilder {
+    pub fn new() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+            angle: 25,
+            distance: 100,
+            weights: [4, 4, 4, 6, 6, 3, 3],
+            axiom_length: (0, 2),
+            rule_length: (4, 10),
+        }
+    }
+
+    /// Seed the random number generator, so that the same sequence of systems is generated
+    /// every time.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Set the angle, in degrees, the turtle rotates by for `+` and `-`.
+    pub fn angle(&mut self, angle: i32) -> &mut Self {
+        self.angle = angle;
+        self
+    }
+
+    /// Set the distance the turtle moves forwards for `F`.
+    pub fn distance(&mut self, distance: i32) -> &mut Self {
+        self.distance = distance;
+        self
+    }
+
+    /// Set how often `symbol` is chosen for a rule, relative to the weights of the other
+    /// symbols.  Symbols that can't appear in a generated system are ignored.
+    pub fn weight(&mut self, symbol: char, weight: u32) -> &mut Self {
+        if let Some(index) = SYMBOLS.iter().position(|s| *s == symbol) {
+            self.weights[index] = weight;
+        }
+        self
+    }
+
+    /// Set the range of the number of symbols following the `X` at the start of the axiom.
+    pub fn axiom_length(&mut self, min: usize, max: usize) -> &mut Self {
+        self.axiom_length = (min, max.max(min));
+        self
+    }
+
+    /// Set the range of the number of symbols in each rule.
+    pub fn rule_length(&mut self, min: usize, max: usize) -> &mut Self {
+        self.rule_length = (min, max.max(min));
+        self
+    }
+
+    /// Generates a random system, written in the text format described in the
+    /// [`grammar`](crate::grammar) module.  This is useful for saving interesting systems.
+    ///
+    /// Returns an error if no valid rules can be generated, because the weights rule out
+    /// non-empty branches or rules are shorter than three symbols.
+    pub fn generate_grammar(&mut self) -> Result<String, LSystemError> {
+        // A branch needs `[`, `]`, and something to put between them
+        let branches = self.weights[3] > 0
+            && self.weights[4] > 0
+            && self.weights.iter().filter(|weight| **weight > 0).count() > 2;
+
+        if !branches || self.rule_length.1 < 3 {
+            return Err(LSystemError::InvalidRule(String::from(
+                "generated rules must be able to contain a branch",
+            )));
+        }
+
+        let mut axiom = vec!['X'];
+        let axiom_length = self
+            .rng
+            .gen_range(self.axiom_length.0..=self.axiom_length.1);
+
+        for _ in 0..axiom_length {
+            // `[` and `]` are left out of the axiom so that it's always balanced
+            axiom.push(*['F', '+', '-', 'X', 'Y'].choose(&mut self.rng).unwrap());
+        }
+
+        let x_rule = self.rule()?;
+        let y_rule = self.rule()?;
+
+        let join = |symbols: &[char]| {
+            symbols
+                .iter()
+                .map(|symbol| symbol.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        Ok(format!(
+            "F = forward {}\n+ = rotate {}\n- = rotate {}\n[ = push\n] = pop\naxiom: {}\nX => {}\nY => {}\n",
+            self.distance,
+            self.angle,
+            -self.angle,
+            join(&axiom),
+            join(&x_rule),
+            join(&y_rule)
+        ))
+    }
+
+    /// Generates a random system, returning a builder which is ready to be finished.
+    pub fn generate(&mut self) -> Result<TurtleLSystemBuilder, LSystemError> {
+        TurtleLSystemBuilder::from_grammar(&self.generate_grammar()?)
+    }
+
+    /// Chooses random rules until a valid one is found.
+    fn rule(&mut self) -> Result<Vec<char>, LSystemError> {
+        let choices: Vec<(char, u32)> = SYMBOLS.iter().copied().zip(self.weights).collect();
+
+        loop {
+            let length = self.rng.gen_range(self.rule_length.0..=self.rule_length.1);
+            let mut rule = Vec::with_capacity(length);
+
+            for _ in 0..length {
+                rule.push(choices.choose_weighted(&mut self.rng, |choice| choice.1)?.0);
+            }
+
+            if valid_rule(&rule) {
+                return Ok(rule);
+            }
+        }
+    }
+}
+
+impl Default for RandomSystemBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns true if `rule` opens at least one branch, closes every branch it opens, and has no
+/// empty branches.
+fn valid_rule(rule: &[char]) -> bool {
+    if !rule.contains(&'[') || rule.windows(2).any(|pair| pair == ['[', ']']) {
+        return false;
+    }
+
+    let mut level = 0usize;
+
+    for symbol in rule {
+        match symbol {
+            '[' => level += 1,
+            ']' => match level.checked_sub(1) {
+                Some(next) => level = next,
+                None => return false,
+            },
+            _ => {}
+        }
+    }
+
+    level == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_rules_have_balanced_branches() {
+        assert!(valid_rule(&['F', '[', '+', 'X', ']', 'F']));
+        assert!(!valid_rule(&['F', '+', 'X']));
+        assert!(!valid_rule(&['F', '[', ']', 'X']));
+        assert!(!valid_rule(&['F', ']', 'X', '[']));
+        assert!(!valid_rule(&['[', '[', 'X', ']']));
+    }
+
+    #[test]
+    fn seeded_generators_agree() -> Result<(), LSystemError> {
+        let mut first = RandomSystemBuilder::new();
+        let mut second = RandomSystemBuilder::new();
+        first.seed(7);
+        second.seed(7);
+
+        for _ in 0..5 {
+            assert_eq!(first.generate_grammar()?, second.generate_grammar()?);
+        }
+
+        first.weight('[', 0);
+        assert!(first.generate().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,530 @@
+//! Renders turtle output on the GPU using [`wgpu`], which is much faster than the image renderer
+//! for systems with millions of lines.
+//!
+//! Each line is drawn as an anti-aliased capsule: a rectangle with rounded ends, extending
+//! `thickness` pixels either side of the line.  This matches the size of the lines drawn by the
+//! CPU renderer, so the two can be used interchangeably.
+//!
+//! Rendering fails with [`LSystemError::Other`] if no GPU is available, or if the image is
+//! larger than the GPU supports.
+//!
+//! This module requires the `gpu` feature.
+//!
+//! # Example
+//! ```rust,no_run
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use image::Rgb;
+//!
+//! use dcc_lsystem::renderer::{GpuRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("X", TurtleAction::Nothing)?
+//!     .token("Y", TurtleAction::Nothing)?
+//!     .token("F", TurtleAction::Forward(30.0))?
+//!     .token("+", TurtleAction::Rotate(-90.0))?
+//!     .token("-", TurtleAction::Rotate(90.0))?
+//!     .axiom("F X")?
+//!     .rule("X => X + Y F +")?
+//!     .rule("Y => - F X - Y")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(18);
+//!
+//! let options = GpuRendererOptionsBuilder::new()
+//!     .thickness(4.0)
+//!     .line_color(Rgb([0u8, 0u8, 100u8]))
+//!     .build();
+//!
+//! let buffer = renderer.render(&system, &options)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::sync::mpsc;
+
+use image::{ImageBuffer, Rgb};
+use wgpu::util::DeviceExt;
+
+use crate::renderer::{bounds, segments, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::{LSystem, LSystemError};
+
+/// The number of `f32`s describing each line: both ends, the half width and the color.
+const INSTANCE_FLOATS: usize = 8;
+
+/// The size in bytes of the description of each line.
+const INSTANCE_SIZE: usize = INSTANCE_FLOATS * std::mem::size_of::<f32>();
+
+/// Draws every line as a quad which is large enough to contain it, and then works out how much
+/// of each pixel of the quad is covered by the line.  `SIZE` is filled in with the size of the
+/// image before the shader is compiled.
+const SHADER: &str = r#"
+const SIZE = vec2<f32>({width}, {height});
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) start: vec2<f32>,
+    @location(1) end: vec2<f32>,
+    @location(2) radius: f32,
+    @location(3) color: vec3<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @location(0) ends: vec4<f32>,
+    @location(1) radius: f32,
+    @location(2) color: vec3<f32>,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex];
+
+    let start = ends.xy;
+    let end = ends.zw;
+
+    var direction = vec2<f32>(1.0, 0.0);
+    if (distance(start, end) > 0.0) {
+        direction = normalize(end - start);
+    }
+    let normal = vec2<f32>(-direction.y, direction.x);
+
+    // Leave room for the rounded ends, and for a pixel of anti-aliasing
+    let extent = radius + 1.0;
+    var pixel = start - direction * extent;
+    if (corner.x > 0.0) {
+        pixel = end + direction * extent;
+    }
+    pixel = pixel + normal * extent * corner.y;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(pixel.x / SIZE.x * 2.0 - 1.0, 1.0 - pixel.y / SIZE.y * 2.0, 0.0, 1.0);
+    out.start = start;
+    out.end = end;
+    out.radius = radius;
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The coordinates of the middle of this pixel
+    let pixel = in.position.xy - vec2<f32>(0.5, 0.5);
+
+    // The distance from the pixel to the nearest point of the line
+    let line = in.end - in.start;
+    let offset = pixel - in.start;
+    let t = clamp(dot(offset, line) / max(dot(line, line), 0.000001), 0.0, 1.0);
+    let distance = length(offset - line * t);
+
+    let coverage = clamp(in.radius + 0.5 - distance, 0.0, 1.0);
+    if (coverage <= 0.0) {
+        discard;
+    }
+
+    return vec4<f32>(in.color, coverage);
+}
+"#;
+
+pub struct GpuRendererOptionsBuilder {
+    options: GpuRendererOptions,
+}
+
+impl GpuRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: GpuRendererOptions {
+                padding: 10,
+                thickness: 15.0,
+                fill_color: Rgb([255u8, 255u8, 255u8]),
+                line_color: Rgb([0u8, 0u8, 0u8]),
+            },
+        }
+    }
+
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
+
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
+
+    pub fn fill_color(&mut self, fill_color: Rgb<u8>) -> &mut Self {
+        self.options.fill_color = fill_color;
+        self
+    }
+
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
+
+    pub fn build(&mut self) -> GpuRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for GpuRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GpuRendererOptions {
+    padding: u32,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    fill_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+}
+
+impl GpuRendererOptions {
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn fill_color(&self) -> Rgb<u8> {
+        self.fill_color
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+}
+
+/// Returns the width and height of the image, together with the description of every visible
+/// line in image coordinates, ready to be uploaded to the GPU.
+fn instances<Q: TurtleContainer>(state: &Q, options: &GpuRendererOptions) -> (u32, u32, Vec<u8>) {
+    let (turtle_width, turtle_height, min_x, min_y) = bounds(state);
+
+    let padding = options.padding as f64;
+
+    let width = 2.0 * padding + turtle_width;
+    let height = 2.0 * padding + turtle_height;
+
+    // Helper functions for converting between the coordinate system used
+    // by the image crate and our coordinate system.  These functions also
+    // take care of the padding for us.
+    let xp = |x: f64| -> f64 { x - min_x + padding };
+    let yp = |y: f64| -> f64 { height - (y - min_y + padding) };
+
+    let mut data = Vec::new();
+
+    for ((x1, y1, x2, y2), style) in segments(state) {
+        let thickness = style.resolved_thickness(options.thickness);
+
+        if thickness > 0.0 {
+            let Rgb([red, green, blue]) = style.color.unwrap_or(options.line_color);
+
+            let instance = [
+                xp(*x1) as f32,
+                yp(*y1) as f32,
+                xp(*x2) as f32,
+                yp(*y2) as f32,
+                thickness as f32,
+                red as f32 / 255.0,
+                green as f32 / 255.0,
+                blue as f32 / 255.0,
+            ];
+
+            for value in instance.iter() {
+                data.extend_from_slice(&value.to_ne_bytes());
+            }
+        }
+    }
+
+    (width.ceil() as u32, height.ceil() as u32, data)
+}
+
+/// Returns an error with the given message.
+fn error(message: &str) -> LSystemError {
+    LSystemError::Other {
+        source: message.into(),
+    }
+}
+
+/// Draws the lines described by `data` into a `width` by `height` image.
+fn draw(
+    width: u32,
+    height: u32,
+    data: &[u8],
+    options: &GpuRendererOptions,
+) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, LSystemError> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))
+    .ok_or_else(|| error("no suitable GPU adapter found"))?;
+
+    let limits = adapter.limits();
+    if width > limits.max_texture_dimension_2d || height > limits.max_texture_dimension_2d {
+        return Err(error("the image is too large to render on the GPU"));
+    }
+
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: limits.clone(),
+        },
+        None,
+    ))?;
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(
+            SHADER
+                .replace("{width}", &format!("{:.1}", width as f32))
+                .replace("{height}", &format!("{:.1}", height as f32))
+                .into(),
+        ),
+    });
+
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: INSTANCE_SIZE as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32, 2 => Float32x3],
+            }],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    });
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    // Very large systems are split across several buffers, each within the GPU's limits
+    let chunk_size = (limits.max_buffer_size as usize).min(1 << 28) / INSTANCE_SIZE * INSTANCE_SIZE;
+    let buffers: Vec<_> = data
+        .chunks(chunk_size.max(INSTANCE_SIZE))
+        .map(|chunk| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: chunk,
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            (buffer, (chunk.len() / INSTANCE_SIZE) as u32)
+        })
+        .collect();
+
+    let Rgb([red, green, blue]) = options.fill_color;
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: red as f64 / 255.0,
+                        g: green as f64 / 255.0,
+                        b: blue as f64 / 255.0,
+                        a: 1.0,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(&pipeline);
+
+        for (buffer, count) in buffers.iter() {
+            pass.set_vertex_buffer(0, buffer.slice(..));
+            pass.draw(0..6, 0..*count);
+        }
+    }
+
+    // Rows copied out of a texture have to be padded to a fixed alignment
+    let row_size = width * 4;
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_row_size = row_size.div_ceil(alignment) * alignment;
+
+    let output = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: padded_row_size as wgpu::BufferAddress * height as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &output,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: Some(height),
+            },
+        },
+        size,
+    );
+
+    queue.submit(Some(encoder.finish()));
+
+    let slice = output.slice(..);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        // the receiver is only dropped once the buffer is mapped
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv().map_err(|_| LSystemError::ThreadError)??;
+
+    let mapped = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+
+    for row in mapped.chunks(padded_row_size as usize) {
+        for pixel in row[..row_size as usize].chunks(4) {
+            pixels.extend_from_slice(&pixel[..3]);
+        }
+    }
+
+    // unwrap: `pixels` has exactly three bytes for every pixel of the image
+    Ok(ImageBuffer::from_raw(width, height, pixels).unwrap())
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<GpuRendererOptions> for TurtleRenderer<Q> {
+    type Output = Result<ImageBuffer<Rgb<u8>, Vec<u8>>, LSystemError>;
+
+    fn render(&self, system: &LSystem, options: &GpuRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+        let (width, height, data) = instances(&state, options);
+
+        draw(width, height, &data, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    #[test]
+    fn instances_use_image_coordinates() -> Result<(), LSystemError> {
+        let mut builder = TurtleLSystemBuilder::new();
+        builder
+            .token("F", TurtleAction::Forward(10.0))?
+            .token("+", TurtleAction::Rotate(90.0))?
+            .token("T", TurtleAction::SetThickness(0.0))?
+            .axiom("F + F T F")?;
+
+        let (system, renderer) = builder.finish()?;
+        let state = renderer.compute(system.get_state());
+
+        let options = GpuRendererOptionsBuilder::new()
+            .padding(5)
+            .thickness(2.0)
+            .line_color(Rgb([255, 0, 0]))
+            .build();
+        let (width, height, data) = instances(&state, &options);
+
+        // The final line is invisible, so isn't uploaded
+        assert_eq!((width, height), (20, 30));
+        assert_eq!(data.len(), 2 * INSTANCE_SIZE);
+
+        let floats: Vec<f32> = data
+            .chunks(4)
+            .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+
+        // The y axis points down the image
+        assert_eq!(
+            &floats[..INSTANCE_FLOATS],
+            &[5.0, 25.0, 15.0, 25.0, 2.0, 1.0, 0.0, 0.0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_draws_lines_when_a_gpu_is_available() -> Result<(), LSystemError> {
+        let mut builder = TurtleLSystemBuilder::new();
+        builder
+            .token("F", TurtleAction::Forward(10.0))?
+            .token("+", TurtleAction::Rotate(90.0))?
+            .axiom("F + F")?;
+
+        let (system, renderer) = builder.finish()?;
+        let options = GpuRendererOptionsBuilder::new().thickness(2.0).build();
+
+        // Not every machine running the tests has a GPU
+        let buffer = match renderer.render(&system, &options) {
+            Ok(buffer) => buffer,
+            Err(_) => return Ok(()),
+        };
+
+        assert_eq!(buffer.dimensions(), (30, 30));
+        assert_eq!(*buffer.get_pixel(15, 20), Rgb([0, 0, 0]));
+        assert_eq!(*buffer.get_pixel(20, 15), Rgb([0, 0, 0]));
+        assert_eq!(*buffer.get_pixel(5, 5), Rgb([255, 255, 255]));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,400 @@
+//! A small text format for describing an L-system in a single string.
+//!
+//! Each line of a grammar is one of the following:
+//!
+//! * `axiom: <symbols>` sets the axiom of the system.
+//! * `<symbol> => <symbols>` adds a transformation rule.  Each symbol can have at most one rule.
+//! * `<symbol> = <action>` associates a turtle action with a symbol (only supported by
+//!   [`TurtleLSystemBuilder::from_grammar`](crate::turtle::TurtleLSystemBuilder::from_grammar)).
+//!
+//! Symbols are separated by whitespace.  Blank lines, and lines starting with `#`, are ignored.
+//! Tokens are registered automatically the first time they appear.
+//!
+//! When a grammar declares actions, words in the axiom and rules which aren't symbols themselves
+//! are split into symbols which have an action or a rule where possible, taking the longest such
+//! symbol each time.  This allows the usual compact notation, so that `F => F+F--F+F` is the
+//! same as `F => F + F - - F + F` once `F`, `+` and `-` have actions.  Words which can't be
+//! split completely are left as they are.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::LSystemBuilder;
+//!
+//! let grammar = "
+//!     ## Lindenmayer's algae
+//!     axiom: A
+//!     A => A B
+//!     B => A
+//! ";
+//!
+//! let mut system = LSystemBuilder::from_grammar(grammar)?.finish()?;
+//! system.step_by(3);
+//! assert_eq!(system.render(), "ABAAB");
+//! # Ok(())
+//! # }
+//! ```
+use crate::collections::HashSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::LSystemError;
+
+/// A whitespace separated word in a grammar, together with its (1-based) position.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Symbol<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+impl<'a> Symbol<'a> {
+    /// Returns a [`LSystemError::ParseError`] located at this symbol.
+    pub(crate) fn error<S: Into<String>>(&self, message: S) -> LSystemError {
+        LSystemError::ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+/// A single non-empty line of a grammar.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Statement<'a> {
+    Axiom(Vec<Symbol<'a>>),
+    Rule {
+        predecessor: Symbol<'a>,
+        successor: Vec<Symbol<'a>>,
+    },
+    Action {
+        symbol: Symbol<'a>,
+        action: Vec<Symbol<'a>>,
+    },
+}
+
+/// Splits `text` (which starts at `column` on `line`) into whitespace separated symbols.
+pub(crate) fn symbols(text: &str, line: usize, column: usize) -> Vec<Symbol<'_>> {
+    let mut symbols = Vec::new();
+    let mut start = None;
+
+    for (offset, (index, c)) in text.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((index, offset)),
+            (true, Some((begin, begin_offset))) => {
+                symbols.push(Symbol {
+                    name: &text[begin..index],
+                    line,
+                    column: column + begin_offset,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((begin, begin_offset)) = start {
+        symbols.push(Symbol {
+            name: &text[begin..],
+            line,
+            column: column + begin_offset,
+        });
+    }
+
+    symbols
+}
+
+/// Returns the number of characters in `text` before byte offset `index`.
+fn column_of(text: &str, index: usize) -> usize {
+    text[..index].chars().count()
+}
+
+/// Parses a grammar into its statements.
+pub(crate) fn parse(grammar: &str) -> Result<Vec<Statement<'_>>, LSystemError> {
+    let mut statements = Vec::new();
+    let mut seen_axiom = false;
+    let mut predecessors = HashSet::new();
+
+    for (line_index, text) in grammar.lines().enumerate() {
+        let line = line_index + 1;
+        let trimmed = text.trim_start();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("axiom:") {
+            let column = column_of(text, text.len() - rest.len()) + 1;
+            let axiom = symbols(rest, line, column);
+
+            if seen_axiom {
+                return Err(LSystemError::ParseError {
+                    line,
+                    column: column_of(text, text.len() - trimmed.len()) + 1,
+                    message: String::from("the axiom has already been defined"),
+                });
+            }
+
+            if axiom.is_empty() {
+                return Err(LSystemError::ParseError {
+                    line,
+                    column,
+                    message: String::from("the axiom must not be empty"),
+                });
+            }
+
+            seen_axiom = true;
+            statements.push(Statement::Axiom(axiom));
+            continue;
+        }
+
+        let (separator, is_rule) = match text.find("=>") {
+            Some(index) => (index, true),
+            None => match text.find('=') {
+                Some(index) => (index, false),
+                None => {
+                    return Err(LSystemError::ParseError {
+                        line,
+                        column: column_of(text, text.len() - trimmed.len()) + 1,
+                        message: String::from(
+                            "expected an axiom, a rule (`=>`), or an action (`=`)",
+                        ),
+                    })
+                }
+            },
+        };
+
+        let lhs = symbols(&text[..separator], line, 1);
+        let rhs_start = separator + if is_rule { 2 } else { 1 };
+        let rhs = symbols(&text[rhs_start..], line, column_of(text, rhs_start) + 1);
+
+        if lhs.len() != 1 {
+            return Err(LSystemError::ParseError {
+                line,
+                column: lhs.get(1).map_or(1, |s| s.column),
+                message: String::from("expected exactly one symbol on the left hand side"),
+            });
+        }
+
+        if is_rule {
+            if !predecessors.insert(lhs[0].name) {
+                return Err(lhs[0].error("a rule has already been declared for this symbol"));
+            }
+
+            statements.push(Statement::Rule {
+                predecessor: lhs[0],
+                successor: rhs,
+            });
+        } else {
+            if rhs.is_empty() {
+                return Err(LSystemError::ParseError {
+                    line,
+                    column: column_of(text, rhs_start) + 1,
+                    message: String::from("expected an action"),
+                });
+            }
+
+            statements.push(Statement::Action {
+                symbol: lhs[0],
+                action: rhs,
+            });
+        }
+    }
+
+    Ok(statements)
+}
+
+/// Splits `symbol` into symbols named in `names`, taking the longest matching name each time.
+/// Returns `None` if some part of it doesn't match any name.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+fn split_symbol<'a>(symbol: &Symbol<'a>, names: &HashSet<&str>) -> Option<Vec<Symbol<'a>>> {
+    let longest = names.iter().map(|name| name.chars().count()).max()?;
+    let mut pieces = Vec::new();
+    let mut rest = symbol.name;
+    let mut column = symbol.column;
+
+    while !rest.is_empty() {
+        let ends: Vec<usize> = rest
+            .char_indices()
+            .map(|(index, c)| index + c.len_utf8())
+            .take(longest)
+            .collect();
+
+        let end = ends
+            .into_iter()
+            .rev()
+            .find(|end| names.contains(&rest[..*end]))?;
+
+        pieces.push(Symbol {
+            name: &rest[..end],
+            line: symbol.line,
+            column,
+        });
+
+        column += rest[..end].chars().count();
+        rest = &rest[end..];
+    }
+
+    Some(pieces)
+}
+
+/// Splits the symbols of axioms and rules which are neither given an action nor rewritten by a
+/// rule into symbols which are, wherever that's possible.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) fn split_compact(statements: &mut [Statement<'_>]) {
+    let names: HashSet<&str> = statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Action { symbol, .. } => Some(symbol.name),
+            Statement::Rule { predecessor, .. } => Some(predecessor.name),
+            Statement::Axiom(_) => None,
+        })
+        .collect();
+
+    let split = |symbols: &mut Vec<Symbol<'_>>| {
+        *symbols = symbols
+            .iter()
+            .flat_map(|symbol| {
+                if names.contains(symbol.name) {
+                    vec![*symbol]
+                } else {
+                    split_symbol(symbol, &names).unwrap_or_else(|| vec![*symbol])
+                }
+            })
+            .collect();
+    };
+
+    for statement in statements {
+        match statement {
+            Statement::Axiom(axiom) => split(axiom),
+            Statement::Rule { successor, .. } => split(successor),
+            Statement::Action { .. } => {}
+        }
+    }
+}
+
+/// Returns the distinct symbol names used in `statements`, in order of first appearance.
+pub(crate) fn token_names<'a>(statements: &[Statement<'a>]) -> Vec<&'a str> {
+    let mut names: Vec<&str> = Vec::new();
+
+    let mut add = |symbol: &Symbol<'a>| {
+        if !names.contains(&symbol.name) {
+            names.push(symbol.name);
+        }
+    };
+
+    for statement in statements {
+        match statement {
+            Statement::Axiom(axiom) => axiom.iter().for_each(&mut add),
+            Statement::Rule {
+                predecessor,
+                successor,
+            } => {
+                add(predecessor);
+                successor.iter().for_each(&mut add);
+            }
+            Statement::Action { symbol, .. } => add(symbol),
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_statements() -> Result<(), LSystemError> {
+        let statements = parse("axiom: F X\n\n# comment\nX => X + F\nF = forward 30\n")?;
+
+        assert_eq!(statements.len(), 3);
+        assert_eq!(token_names(&statements), vec!["F", "X", "+"]);
+
+        match &statements[1] {
+            Statement::Rule {
+                predecessor,
+                successor,
+            } => {
+                assert_eq!(predecessor.name, "X");
+                assert_eq!(successor.len(), 3);
+                assert_eq!((successor[1].line, successor[1].column), (4, 8));
+            }
+            _ => panic!("expected a rule"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_rules_are_rejected() {
+        match parse("axiom: A\nA => A B\nB => A\n  A => B") {
+            Err(LSystemError::ParseError { line, column, .. }) => {
+                assert_eq!((line, column), (4, 3))
+            }
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn compact_symbols_are_split() -> Result<(), LSystemError> {
+        let mut statements =
+            parse("F = forward 1\nFF = forward 2\n+ = rotate 90\naxiom: FFF+X F+Y\nX => F+FX")?;
+        split_compact(&mut statements);
+
+        let names = |symbols: &[Symbol]| {
+            symbols
+                .iter()
+                .map(|s| s.name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // Y has neither an action nor a rule, so F+Y is left alone
+        match &statements[3] {
+            Statement::Axiom(axiom) => assert_eq!(names(axiom), vec!["FF", "F", "+", "X", "F+Y"]),
+            _ => panic!("expected an axiom"),
+        }
+
+        match &statements[4] {
+            Statement::Rule { successor, .. } => {
+                assert_eq!(names(successor), vec!["F", "+", "F", "X"]);
+            }
+            _ => panic!("expected a rule"),
+        }
+
+        let mut statements = parse("F = forward 1\n+ = rotate 90\naxiom: F\nF => F+FF")?;
+        split_compact(&mut statements);
+
+        match &statements[3] {
+            Statement::Rule { successor, .. } => {
+                assert_eq!(names(successor), vec!["F", "+", "F", "F"]);
+                assert_eq!(successor[3].column, 9);
+            }
+            _ => panic!("expected a rule"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors_have_positions() {
+        match parse("axiom: A\nA B => A") {
+            Err(LSystemError::ParseError { line, column, .. }) => {
+                assert_eq!((line, column), (2, 3))
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        match parse("axiom: A\n  nonsense") {
+            Err(LSystemError::ParseError { line, column, .. }) => {
+                assert_eq!((line, column), (2, 3))
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        assert!(parse("axiom: A\naxiom: B").is_err());
+        assert!(parse("axiom:   ").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Renders how often the turtle visits each pixel, rather than the lines it draws.
+//!
+//! Space-filling curves and other dense systems cover most of the image with lines, so an
+//! ordinary rendering is a solid blob.  Counting the number of lines passing through each pixel
+//! and coloring the pixels by their counts shows where the turtle spends its time instead.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::color::Colormap;
+//! use dcc_lsystem::renderer::{HeatmapRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("X", TurtleAction::Nothing)?
+//!     .token("Y", TurtleAction::Nothing)?
+//!     .token("F", TurtleAction::Forward(4.0))?
+//!     .token("+", TurtleAction::Rotate(-90.0))?
+//!     .token("-", TurtleAction::Rotate(90.0))?
+//!     .axiom("F X")?
+//!     .rule("X => X + Y F +")?
+//!     .rule("Y => - F X - Y")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(10);
+//!
+//! let options = HeatmapRendererOptionsBuilder::new()
+//!     .scale(0.5)
+//!     .colormap(Colormap::Magma)
+//!     .log_scale(true)
+//!     .build();
+//!
+//! let buffer = renderer.render(&system, &options);
+//! # Ok(())
+//! # }
+//! ```
+use image::{ImageBuffer, Rgb};
+
+use crate::color::{interpolate, Colormap};
+use crate::renderer::{bounds, lines, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+pub struct HeatmapRendererOptionsBuilder {
+    options: HeatmapRendererOptions,
+}
+
+impl HeatmapRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: HeatmapRendererOptions {
+                scale: 1.0,
+                padding: 10,
+                colormap: Colormap::Viridis,
+                custom_colormap: None,
+                log_scale: false,
+                fill_color: None,
+            },
+        }
+    }
+
+    /// Set the number of pixels per turtle unit.  Smaller scales put more of the path into
+    /// each pixel.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
+
+    /// Set the colormap that visit counts are mapped through, from the least visited pixels to
+    /// the most visited.
+    pub fn colormap(&mut self, colormap: Colormap) -> &mut Self {
+        self.options.colormap = colormap;
+        self.options.custom_colormap = None;
+        self
+    }
+
+    /// Map visit counts through equally spaced `colors` instead of a standard colormap.
+    /// At least two colors are needed; otherwise the colormap is left unchanged.
+    pub fn custom_colormap(&mut self, colors: &[Rgb<u8>]) -> &mut Self {
+        if colors.len() >= 2 {
+            self.options.custom_colormap = Some(colors.iter().map(|color| color.0).collect());
+        }
+        self
+    }
+
+    /// Scale visit counts logarithmically, so that pixels visited a handful of times remain
+    /// visible next to pixels visited thousands of times.
+    pub fn log_scale(&mut self, log_scale: bool) -> &mut Self {
+        self.options.log_scale = log_scale;
+        self
+    }
+
+    /// Set the color of pixels which are never visited.  By default they're given the first
+    /// color of the colormap.
+    pub fn fill_color(&mut self, fill_color: Rgb<u8>) -> &mut Self {
+        self.options.fill_color = Some(fill_color);
+        self
+    }
+
+    pub fn build(&mut self) -> HeatmapRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for HeatmapRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeatmapRendererOptions {
+    scale: f64,
+    padding: u32,
+    colormap: Colormap,
+    custom_colormap: Option<Vec<[u8; 3]>>,
+    log_scale: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb_option"))]
+    fill_color: Option<Rgb<u8>>,
+}
+
+impl HeatmapRendererOptions {
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn colormap(&self) -> Colormap {
+        self.colormap
+    }
+
+    pub fn custom_colormap(&self) -> Option<Vec<Rgb<u8>>> {
+        self.custom_colormap
+            .as_ref()
+            .map(|colors| colors.iter().map(|color| Rgb(*color)).collect())
+    }
+
+    pub fn log_scale(&self) -> bool {
+        self.log_scale
+    }
+
+    pub fn fill_color(&self) -> Option<Rgb<u8>> {
+        self.fill_color
+    }
+
+    /// Returns the color at position `t` along the chosen colormap.
+    fn sample(&self, t: f64) -> Rgb<u8> {
+        match &self.custom_colormap {
+            Some(colors) => interpolate(colors, t),
+            None => self.colormap.sample(t),
+        }
+    }
+}
+
+/// Counts the number of lines passing through each pixel of a `width` by `height` image.
+/// A line is only counted once for each pixel it passes through.
+fn visits<'a, I>(
+    lines: I,
+    width: u32,
+    height: u32,
+    transform: impl Fn(f64, f64) -> (f64, f64),
+) -> Vec<u32>
+where
+    I: Iterator<Item = &'a (f64, f64, f64, f64)>,
+{
+    let mut counts = vec![0u32; width as usize * height as usize];
+
+    for (x1, y1, x2, y2) in lines {
+        let (x1, y1) = transform(*x1, *y1);
+        let (x2, y2) = transform(*x2, *y2);
+
+        // Sample the line at least once per pixel
+        let steps = (x2 - x1).abs().max((y2 - y1).abs()).ceil().max(1.0) as usize;
+        let mut previous = None;
+
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = (x1 + t * (x2 - x1)).floor();
+            let y = (y1 + t * (y2 - y1)).floor();
+
+            if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+                continue;
+            }
+
+            let index = y as usize * width as usize + x as usize;
+
+            if previous != Some(index) {
+                counts[index] = counts[index].saturating_add(1);
+                previous = Some(index);
+            }
+        }
+    }
+
+    counts
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<HeatmapRendererOptions> for TurtleRenderer<Q> {
+    type Output = ImageBuffer<Rgb<u8>, Vec<u8>>;
+
+    fn render(&self, system: &LSystem, options: &HeatmapRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+
+        let (turtle_width, turtle_height, min_x, min_y) = bounds(&state);
+
+        let padding = options.padding as f64;
+
+        let width = 2.0 * padding + turtle_width * options.scale;
+        let height = 2.0 * padding + turtle_height * options.scale;
+
+        let buffer_width = width.ceil() as u32;
+        let buffer_height = height.ceil() as u32;
+
+        // Convert from turtle coordinates to image coordinates
+        let transform = |x: f64, y: f64| {
+            (
+                (x - min_x) * options.scale + padding,
+                height - ((y - min_y) * options.scale + padding),
+            )
+        };
+
+        let counts = visits(lines(&state), buffer_width, buffer_height, transform);
+        let max_count = counts.iter().copied().max().unwrap_or(0);
+
+        let level = |count: u32| {
+            if options.log_scale {
+                (count as f64).ln_1p() / (max_count as f64).ln_1p()
+            } else {
+                count as f64 / max_count as f64
+            }
+        };
+
+        let fill_color = options.fill_color.unwrap_or_else(|| options.sample(0.0));
+
+        ImageBuffer::from_fn(buffer_width, buffer_height, |x, y| {
+            match counts[y as usize * buffer_width as usize + x as usize] {
+                0 => fill_color,
+                count => options.sample(level(count)),
+            }
+        })
+    }
+}
@@ -0,0 +1,313 @@
+//! Renders turtle output as a self-contained HTML page, which draws the lines on a `<canvas>`.
+//!
+//! The lines are embedded in the page as a JavaScript array, so the page can be opened
+//! directly in a browser or pasted into a blog post.  The lines can optionally be drawn one at
+//! a time, in the order the turtle drew them.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::renderer::{HtmlRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .token("-", TurtleAction::Rotate(-90.0))?
+//!     .axiom("F")?
+//!     .rule("F => F + F - F - F + F")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(2);
+//!
+//! // Draw the curve over five seconds
+//! let options = HtmlRendererOptionsBuilder::new()
+//!     .title("Koch curve")
+//!     .animate(5.0)
+//!     .build();
+//!
+//! let html = renderer.render(&system, &options);
+//!
+//! assert!(html.starts_with("<!DOCTYPE html>"));
+//! assert!(html.contains("<canvas"));
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Write;
+
+use image::Rgb;
+
+use crate::renderer::{bounds, segments, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+pub struct HtmlRendererOptionsBuilder {
+    options: HtmlRendererOptions,
+}
+
+impl HtmlRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: HtmlRendererOptions {
+                title: String::from("L-system"),
+                scale: 1.0,
+                padding: 20.0,
+                thickness: 2.0,
+                fill_color: Some(Rgb([255, 255, 255])),
+                line_color: Rgb([0, 0, 0]),
+                duration: None,
+                repeat: false,
+            },
+        }
+    }
+
+    /// Set the title of the page.
+    pub fn title<T: Into<String>>(&mut self, title: T) -> &mut Self {
+        self.options.title = title.into();
+        self
+    }
+
+    /// Set the number of pixels in a single turtle unit.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Set the space left around the drawing, in turtle units.
+    pub fn padding(&mut self, padding: f64) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
+
+    /// Set the thickness of lines without their own thickness, in turtle units.
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
+
+    /// Fill the background of the canvas with `fill_color`, or leave it transparent if `None`.
+    pub fn fill_color(&mut self, fill_color: Option<Rgb<u8>>) -> &mut Self {
+        self.options.fill_color = fill_color;
+        self
+    }
+
+    /// Set the color of lines without their own color.
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
+
+    /// Draw the lines one at a time, taking `duration` seconds to draw them all.
+    pub fn animate(&mut self, duration: f64) -> &mut Self {
+        self.options.duration = Some(duration);
+        self
+    }
+
+    /// Start the animation again once every line has been drawn.  Only used when the
+    /// drawing is animated.
+    pub fn repeat(&mut self, repeat: bool) -> &mut Self {
+        self.options.repeat = repeat;
+        self
+    }
+
+    pub fn build(&mut self) -> HtmlRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for HtmlRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlRendererOptions {
+    title: String,
+    scale: f64,
+    padding: f64,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb_option"))]
+    fill_color: Option<Rgb<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+    duration: Option<f64>,
+    repeat: bool,
+}
+
+impl HtmlRendererOptions {
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn padding(&self) -> f64 {
+        self.padding
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn fill_color(&self) -> Option<Rgb<u8>> {
+        self.fill_color
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+
+    /// Returns how long the animation takes to draw every line, or `None` if the lines are
+    /// all drawn at once.
+    pub fn duration(&self) -> Option<f64> {
+        self.duration
+    }
+
+    pub fn repeat(&self) -> bool {
+        self.repeat
+    }
+}
+
+/// Returns `color` as a CSS hex color.
+fn css_color(color: Rgb<u8>) -> String {
+    let Rgb([red, green, blue]) = color;
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+/// Escapes the characters of `text` which have a special meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Draws the lines in `LINES` onto the canvas, either all at once or over `DURATION` seconds.
+const SCRIPT: &str = r#"const canvas = document.getElementById("lsystem");
+const context = canvas.getContext("2d");
+context.lineCap = "round";
+
+function draw(count) {
+  context.clearRect(0, 0, canvas.width, canvas.height);
+  if (FILL) {
+    context.fillStyle = FILL;
+    context.fillRect(0, 0, canvas.width, canvas.height);
+  }
+  for (const [x1, y1, x2, y2, color, width] of LINES.slice(0, count)) {
+    context.strokeStyle = color;
+    context.lineWidth = width;
+    context.beginPath();
+    context.moveTo(x1, y1);
+    context.lineTo(x2, y2);
+    context.stroke();
+  }
+}
+
+if (DURATION === null) {
+  draw(LINES.length);
+} else {
+  let start = null;
+  function frame(time) {
+    if (start === null) {
+      start = time;
+    }
+    let t = (time - start) / (1000 * DURATION);
+    if (REPEAT && t > 1) {
+      start = time;
+      t = 0;
+    }
+    draw(Math.ceil(Math.min(t, 1) * LINES.length));
+    if (REPEAT || t < 1) {
+      requestAnimationFrame(frame);
+    }
+  }
+  requestAnimationFrame(frame);
+}
+"#;
+
+impl<Q: TurtleContainer + Clone> Renderer<HtmlRendererOptions> for TurtleRenderer<Q> {
+    type Output = String;
+
+    fn render(&self, system: &LSystem, options: &HtmlRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+        let (width, height, min_x, min_y) = bounds(&state);
+
+        let scale = options.scale;
+        let padding = options.padding;
+        let width = (width + 2.0 * padding) * scale;
+        let height = (height + 2.0 * padding) * scale;
+
+        // Canvases measure y downwards, so the drawing is flipped
+        let xp = |x: f64| (x - min_x + padding) * scale;
+        let yp = |y: f64| height - (y - min_y + padding) * scale;
+
+        let mut output = String::new();
+
+        // unwrap (here and below): writing to a String never fails
+        writeln!(output, "<!DOCTYPE html>").unwrap();
+        writeln!(output, "<html>").unwrap();
+        writeln!(output, "<head>").unwrap();
+        writeln!(output, "<meta charset=\"utf-8\">").unwrap();
+        writeln!(output, "<title>{}</title>", escape(&options.title)).unwrap();
+        writeln!(output, "</head>").unwrap();
+        writeln!(output, "<body>").unwrap();
+        writeln!(
+            output,
+            "<canvas id=\"lsystem\" width=\"{}\" height=\"{}\"></canvas>",
+            width.ceil(),
+            height.ceil()
+        )
+        .unwrap();
+        writeln!(output, "<script>").unwrap();
+
+        // Each line is `[x1, y1, x2, y2, color, width]`, in canvas coordinates
+        writeln!(output, "const LINES = [").unwrap();
+
+        for ((x1, y1, x2, y2), style) in segments(&state) {
+            let thickness = style.resolved_thickness(options.thickness);
+
+            if thickness > 0.0 {
+                writeln!(
+                    output,
+                    "  [{:.2}, {:.2}, {:.2}, {:.2}, \"{}\", {:.2}],",
+                    xp(*x1),
+                    yp(*y1),
+                    xp(*x2),
+                    yp(*y2),
+                    css_color(style.color.unwrap_or(options.line_color)),
+                    thickness * scale
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(output, "];").unwrap();
+
+        let fill = match options.fill_color {
+            Some(color) => format!("\"{}\"", css_color(color)),
+            None => String::from("null"),
+        };
+        let duration = match options.duration {
+            Some(duration) => duration.max(0.0).to_string(),
+            None => String::from("null"),
+        };
+
+        writeln!(output, "const FILL = {};", fill).unwrap();
+        writeln!(output, "const DURATION = {};", duration).unwrap();
+        writeln!(output, "const REPEAT = {};", options.repeat).unwrap();
+        output.push_str(SCRIPT);
+
+        writeln!(output, "</script>").unwrap();
+        writeln!(output, "</body>").unwrap();
+        writeln!(output, "</html>").unwrap();
+
+        output
+    }
+}
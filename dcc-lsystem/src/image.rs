@@ -22,6 +22,89 @@ pub fn fill_mut(buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, color: Rgb<u8>) {
     }
 }
 
+/// Something that a [`TurtleRenderer`](crate::renderer::TurtleRenderer) can draw onto, in
+/// pixel coordinates with the origin at the top left.
+///
+/// This is implemented for [`ImageBuffer`], and can be implemented for other drawing libraries
+/// or frame buffers, which are then drawn using
+/// [`TurtleRenderer::render_canvas`](crate::renderer::TurtleRenderer::render_canvas).
+///
+/// # Example
+/// ```rust
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use dcc_lsystem::image::Canvas;
+/// use dcc_lsystem::renderer::ImageRendererOptionsBuilder;
+/// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+/// use image::Rgb;
+///
+/// /// Counts the lines drawn, rather than drawing them.
+/// struct Counter(usize);
+///
+/// impl Canvas for Counter {
+///     fn new(_width: u32, _height: u32) -> Self {
+///         Counter(0)
+///     }
+///
+///     fn fill(&mut self, _color: Rgb<u8>) {}
+///
+///     fn draw_line(&mut self, _: f64, _: f64, _: f64, _: f64, _: f64, _: Rgb<u8>) {
+///         self.0 += 1;
+///     }
+///
+///     fn draw_polygon(&mut self, _vertices: &[(f64, f64)], _color: Rgb<u8>) {}
+/// }
+///
+/// let mut builder = TurtleLSystemBuilder::new();
+///
+/// builder
+///     .token("F", TurtleAction::Forward(10.0))?
+///     .axiom("F F F")?;
+///
+/// let (system, renderer) = builder.finish()?;
+/// let options = ImageRendererOptionsBuilder::new().build();
+///
+/// let counter: Counter = renderer.render_canvas(&system, &options);
+/// assert_eq!(counter.0, 3);
+/// # Ok(())
+/// # }
+/// ```
+pub trait Canvas {
+    /// Creates a canvas which is `width` pixels wide and `height` pixels high.
+    fn new(width: u32, height: u32) -> Self
+    where
+        Self: Sized;
+
+    /// Sets every pixel of the canvas to `color`.
+    fn fill(&mut self, color: Rgb<u8>);
+
+    /// Draws a line between `(x1, y1)` and `(x2, y2)`, extending `thickness` pixels either
+    /// side of the line.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, thickness: f64, color: Rgb<u8>);
+
+    /// Draws a filled polygon with the given vertices.
+    fn draw_polygon(&mut self, vertices: &[(f64, f64)], color: Rgb<u8>);
+}
+
+impl Canvas for ImageBuffer<Rgb<u8>, Vec<u8>> {
+    fn new(width: u32, height: u32) -> Self {
+        ImageBuffer::new(width, height)
+    }
+
+    fn fill(&mut self, color: Rgb<u8>) {
+        fill_mut(self, color);
+    }
+
+    fn draw_line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, thickness: f64, color: Rgb<u8>) {
+        draw_line_mut(self, x1, y1, x2, y2, thickness, color);
+    }
+
+    fn draw_polygon(&mut self, vertices: &[(f64, f64)], color: Rgb<u8>) {
+        draw_polygon_offset_mut(self, vertices, color, 0);
+    }
+}
+
 #[inline(always)]
 fn _r(x: f64) -> i32 {
     x.round() as i32
@@ -36,6 +119,61 @@ pub fn draw_line_mut(
     y2: f64,
     thickness: f64,
     color: Rgb<u8>,
+) {
+    draw_line_offset_mut(buffer, x1, y1, x2, y2, thickness, color, 0);
+}
+
+/// Draws a filled polygon with the given vertices to `buffer`, with `buffer` holding only the
+/// rows of the image starting at `offset`.
+pub(crate) fn draw_polygon_offset_mut(
+    buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    vertices: &[(f64, f64)],
+    color: Rgb<u8>,
+    offset: i32,
+) {
+    let mut points: Vec<Point<i32>> = Vec::with_capacity(vertices.len());
+
+    for (x, y) in vertices {
+        let point = Point::new(_r(*x), _r(*y) - offset);
+
+        if points.last() != Some(&point) {
+            points.push(point);
+        }
+    }
+
+    // imageproc will panic if the first and last points in the polygon are the same.
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    if points.len() > 2 {
+        draw_polygon_mut(buffer, &points, color);
+    } else if let Some(point) = points.first() {
+        // Shapes smaller than a pixel are still drawn
+        put_pixel(buffer, point.x, point.y, color);
+    }
+}
+
+/// Sets the pixel at `(x, y)` to `color`, if it is inside `buffer`.
+fn put_pixel(buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, x: i32, y: i32, color: Rgb<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < buffer.width() && (y as u32) < buffer.height() {
+        buffer.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+/// Draws a line to `buffer` as [`draw_line_mut`] does, but with `buffer` holding only the rows
+/// of the image starting at `offset`.  The line's pixels are worked out before the offset is
+/// applied, so drawing an image in pieces gives exactly the same result as drawing it at once.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_line_offset_mut(
+    buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+    thickness: f64,
+    color: Rgb<u8>,
+    offset: i32,
 ) {
     assert!(thickness > 0.0);
 
@@ -67,16 +205,26 @@ pub fn draw_line_mut(
 
     // We get the vertices of our rectangle by extending out in the perpendicular
     // direction from our starting point.
-    let p1 = Point::new(_r(x1 + dx), _r(y1 + dy));
-    let p2 = Point::new(_r(x1 - dx), _r(y1 - dy));
-    let p3 = Point::new(_r(x2 + dx), _r(y2 + dy));
-    let p4 = Point::new(_r(x2 - dx), _r(y2 - dy));
+    let p1 = Point::new(_r(x1 + dx), _r(y1 + dy) - offset);
+    let p2 = Point::new(_r(x1 - dx), _r(y1 - dy) - offset);
+    let p3 = Point::new(_r(x2 + dx), _r(y2 + dy) - offset);
+    let p4 = Point::new(_r(x2 - dx), _r(y2 - dy) - offset);
 
     // Now we just draw the line
     if p1 != p2 {
         // imageproc will panic if the first and last points in the polygon are the same.
         draw_polygon_mut(buffer, &[p1, p3, p4, p2], color);
     }
-    draw_filled_circle_mut(buffer, (_r(x1), _r(y1)), _r(thickness / 1.5), color);
-    draw_filled_circle_mut(buffer, (_r(x2), _r(y2)), _r(thickness / 1.5), color);
+    draw_filled_circle_mut(
+        buffer,
+        (_r(x1), _r(y1) - offset),
+        _r(thickness / 1.5),
+        color,
+    );
+    draw_filled_circle_mut(
+        buffer,
+        (_r(x2), _r(y2) - offset),
+        _r(thickness / 1.5),
+        color,
+    );
 }
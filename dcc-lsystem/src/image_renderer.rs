@@ -1,19 +1,35 @@
+use crate::animation::{FrameDelay, Pacing, Playback};
+use crate::color::interpolate;
 use crate::dcc_lsystem::LSystem;
-use crate::image::{draw_line_mut, fill_mut};
-use crate::renderer::{Renderer, TurtleRenderer};
-use crate::turtle::TurtleContainer;
+use crate::image::{draw_line_mut, fill_mut, Canvas};
+use crate::progress::RenderProgress;
+use crate::quantize::write_gif;
+use crate::renderer::{
+    bounds, labels, lines, segments, shapes, Dasher, Renderer, StrokeStyle, TurtleRenderer,
+};
+use crate::turtle::{
+    SegmentStyle, Shape, TurtleContainer, TurtleLSystemBuilder, TurtleLSystemState,
+};
 use crate::LSystemError;
-use gifski::progress::{NoProgress, ProgressReporter};
-use gifski::{CatResult, Collector, Repeat};
-use image::{ImageBuffer, Rgb};
+use gifski::progress::NoProgress;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ImageBuffer, Pixel, Rgb};
+use imageproc::drawing::draw_text_mut;
+use imgref::ImgVec;
 use mtpng::encoder::{Encoder, Options};
-use mtpng::{ColorType, Header};
-use pbr::ProgressBar;
+use mtpng::{ColorType, CompressionLevel, Filter, Header, Mode};
+use rgb::RGBA8;
+use rusttype::{Font, Scale};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::Stdout;
-use std::path::{Path, PathBuf};
-use std::thread;
-use std::time::Duration;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Controls how many times an animation loops.
+pub use gifski::Repeat;
 
 pub struct ImageRendererOptionsBuilder {
     options: ImageRendererOptions,
@@ -27,6 +43,15 @@ impl ImageRendererOptionsBuilder {
                 thickness: 15.0,
                 fill_color: Rgb([255, 255, 255]),
                 line_color: Rgb([0, 0, 0]),
+                supersample: 1,
+                trunk_color: None,
+                tip_color: None,
+                depth_thickness: None,
+                font: None,
+                label_size: 16.0,
+                line_cap: None,
+                line_join: None,
+                stroke_style: None,
             },
         }
     }
@@ -51,6 +76,69 @@ impl ImageRendererOptionsBuilder {
         self
     }
 
+    /// Anti-alias the image by drawing it at `supersample` times the size and then scaling it
+    /// back down.  Drawing takes roughly `supersample²` times as long, so small values such as
+    /// `2` or `4` are usually best.  The default of `1` draws the image directly, with hard edges.
+    pub fn supersample(&mut self, supersample: u8) -> &mut Self {
+        self.options.supersample = supersample.max(1);
+        self
+    }
+
+    /// Color lines without their own color by how deeply they are nested in branches, fading
+    /// from `trunk_color` for lines outside of any branch to `tip_color` for the most deeply
+    /// nested lines.
+    pub fn depth_colors(&mut self, trunk_color: Rgb<u8>, tip_color: Rgb<u8>) -> &mut Self {
+        self.options.trunk_color = Some(trunk_color);
+        self.options.tip_color = Some(tip_color);
+        self
+    }
+
+    /// Multiply the thickness of lines by `factor` for each branch they are nested in, so that
+    /// (for factors below `1`) branches get thinner towards their tips.
+    pub fn depth_thickness(&mut self, factor: f64) -> &mut Self {
+        self.options.depth_thickness = Some(factor);
+        self
+    }
+
+    /// Set the font used to draw labels, from the contents of a TrueType or OpenType font file.
+    /// Labels aren't drawn unless a font is given.
+    pub fn font(&mut self, data: Vec<u8>) -> Result<&mut Self, LSystemError> {
+        let font = Font::try_from_vec(data).ok_or(LSystemError::InvalidFont)?;
+        self.options.font = Some(font);
+        Ok(self)
+    }
+
+    /// Set the height of labels, in pixels.
+    pub fn label_size(&mut self, label_size: f32) -> &mut Self {
+        self.options.label_size = label_size;
+        self
+    }
+
+    /// Set how the ends of lines are drawn.
+    ///
+    /// By default the ends of every line are covered with a small circle, which hides the gaps
+    /// where lines meet but makes joints look blobby for thin lines.  Setting either the cap
+    /// or the [join](ImageRendererOptionsBuilder::line_join) style draws lines exactly instead,
+    /// with [`LineJoin::Miter`] or [`LineCap::Butt`] used for whichever isn't set.
+    pub fn line_cap(&mut self, line_cap: LineCap) -> &mut Self {
+        self.options.line_cap = Some(line_cap);
+        self
+    }
+
+    /// Set how connected lines are joined together.  See
+    /// [`line_cap`](ImageRendererOptionsBuilder::line_cap).
+    pub fn line_join(&mut self, line_join: LineJoin) -> &mut Self {
+        self.options.line_join = Some(line_join);
+        self
+    }
+
+    /// Draw lines with a dash pattern, measured in turtle units.  The lengths are scaled along
+    /// with the rest of the image when supersampling.
+    pub fn stroke_style(&mut self, stroke_style: StrokeStyle) -> &mut Self {
+        self.options.stroke_style = Some(stroke_style);
+        self
+    }
+
     pub fn build(&mut self) -> ImageRendererOptions {
         self.options.clone()
     }
@@ -62,12 +150,50 @@ impl Default for ImageRendererOptionsBuilder {
     }
 }
 
+impl From<ImageRendererOptions> for ImageRendererOptionsBuilder {
+    fn from(options: ImageRendererOptions) -> Self {
+        Self { options }
+    }
+}
+
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImageRendererOptions {
     padding: u32,
     thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
     fill_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
     line_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    supersample: u8,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::color::serde_rgb_option")
+    )]
+    trunk_color: Option<Rgb<u8>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::color::serde_rgb_option")
+    )]
+    tip_color: Option<Rgb<u8>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    depth_thickness: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    font: Option<Font<'static>>,
+    #[cfg_attr(feature = "serde", serde(default = "default_label_size"))]
+    label_size: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    line_cap: Option<LineCap>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    line_join: Option<LineJoin>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    stroke_style: Option<StrokeStyle>,
+}
+
+#[cfg(feature = "serde")]
+fn default_label_size() -> f32 {
+    16.0
 }
 
 impl ImageRendererOptions {
@@ -86,6 +212,58 @@ impl ImageRendererOptions {
     pub fn line_color(&self) -> Rgb<u8> {
         self.line_color
     }
+
+    pub fn supersample(&self) -> u8 {
+        self.supersample.max(1)
+    }
+
+    pub fn depth_colors(&self) -> Option<(Rgb<u8>, Rgb<u8>)> {
+        self.trunk_color.zip(self.tip_color)
+    }
+
+    pub fn depth_thickness(&self) -> Option<f64> {
+        self.depth_thickness
+    }
+
+    pub fn font(&self) -> Option<&Font<'static>> {
+        self.font.as_ref()
+    }
+
+    pub fn label_size(&self) -> f32 {
+        self.label_size
+    }
+
+    pub fn line_cap(&self) -> Option<LineCap> {
+        self.line_cap
+    }
+
+    pub fn line_join(&self) -> Option<LineJoin> {
+        self.line_join
+    }
+
+    pub fn stroke_style(&self) -> Option<&StrokeStyle> {
+        self.stroke_style.as_ref()
+    }
+
+    /// Returns the thickness and color of a line with the given style, where `max_depth` is the
+    /// depth of the most deeply nested line being drawn.
+    fn resolve(&self, style: &SegmentStyle, max_depth: usize) -> (f64, Rgb<u8>) {
+        let thickness = match self.depth_thickness {
+            Some(factor) => self.thickness * factor.powi(style.depth as i32),
+            None => self.thickness,
+        };
+
+        let color = style.color.unwrap_or_else(|| match self.depth_colors() {
+            Some((trunk_color, tip_color)) if max_depth > 0 => interpolate(
+                &[trunk_color.0, tip_color.0],
+                style.depth as f64 / max_depth as f64,
+            ),
+            Some((trunk_color, _)) => trunk_color,
+            None => self.line_color,
+        });
+
+        (style.resolved_thickness(thickness), color)
+    }
 }
 
 pub struct VideoRendererOptionsBuilder {
@@ -98,12 +276,24 @@ impl VideoRendererOptionsBuilder {
             options: VideoRendererOptions {
                 filename: String::from("render.gif"),
                 fps: 20,
-                skip_by: 0,
+                pacing: Pacing::default(),
+                frame_delay: FrameDelay::default(),
+                hold_last: 0.0,
+                playback: Playback::default(),
                 padding: 20,
                 thickness: 15.0,
                 fill_color: Rgb([255, 255, 255]),
                 line_color: Rgb([0, 0, 0]),
-                progress_bar: false,
+                progress: None,
+                palette_mode: PaletteMode::Auto,
+                palette_size: 256,
+                dithering: false,
+                quality: 100,
+                fast: false,
+                repeat: Repeat::Infinite,
+                width: None,
+                height: None,
+                format: AnimationFormat::Gif,
             },
         }
     }
@@ -118,8 +308,36 @@ impl VideoRendererOptionsBuilder {
         self
     }
 
+    /// Emit a frame after every `skip_by` lines.  This is shorthand for
+    /// `pacing(Pacing::SkipBy(skip_by))`.
     pub fn skip_by(&mut self, skip_by: usize) -> &mut Self {
-        self.options.skip_by = skip_by;
+        self.options.pacing = Pacing::SkipBy(skip_by);
+        self
+    }
+
+    /// Set which partial renders become frames of the animation.
+    pub fn pacing(&mut self, pacing: Pacing) -> &mut Self {
+        self.options.pacing = pacing;
+        self
+    }
+
+    /// Set how long each frame is shown for.  Defaults to [`FrameDelay::Fixed`], which shows
+    /// every frame for `1 / fps` seconds.
+    pub fn frame_delay(&mut self, frame_delay: FrameDelay) -> &mut Self {
+        self.options.frame_delay = frame_delay;
+        self
+    }
+
+    /// Show the finished drawing for an extra `seconds` seconds.
+    pub fn hold_last(&mut self, seconds: f64) -> &mut Self {
+        self.options.hold_last = seconds;
+        self
+    }
+
+    /// Set the order the frames are played in.  Playing the animation in any order other
+    /// than [`Playback::Forward`] keeps every frame in memory until the drawing is finished.
+    pub fn playback(&mut self, playback: Playback) -> &mut Self {
+        self.options.playback = playback;
         self
     }
 
@@ -143,8 +361,81 @@ impl VideoRendererOptionsBuilder {
         self
     }
 
+    /// Show the progress of the render in the terminal.  Requires the `terminal_progress`
+    /// feature.
+    #[cfg(feature = "terminal_progress")]
     pub fn progress_bar(&mut self, progress_bar: bool) -> &mut Self {
-        self.options.progress_bar = progress_bar;
+        self.options.progress = if progress_bar {
+            Some(Arc::new(crate::progress::TerminalProgress::new()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Report the progress of the render to `progress`.
+    pub fn progress<P: RenderProgress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.options.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Set how palettes are chosen for the frames of the GIF.
+    pub fn palette_mode(&mut self, palette_mode: PaletteMode) -> &mut Self {
+        self.options.palette_mode = palette_mode;
+        self
+    }
+
+    /// Set the maximum number of colors in each palette (between 2 and 256).  Ignored
+    /// when the palette mode is [`PaletteMode::Auto`].
+    pub fn palette_size(&mut self, palette_size: usize) -> &mut Self {
+        self.options.palette_size = palette_size;
+        self
+    }
+
+    /// Enable or disable Floyd–Steinberg dithering.  Ignored when the palette mode
+    /// is [`PaletteMode::Auto`].
+    pub fn dithering(&mut self, dithering: bool) -> &mut Self {
+        self.options.dithering = dithering;
+        self
+    }
+
+    /// Set the quality gifski encodes with, between 1 and 100.  Lower values give smaller files.
+    /// Ignored unless the palette mode is [`PaletteMode::Auto`].
+    pub fn quality(&mut self, quality: u8) -> &mut Self {
+        self.options.quality = quality;
+        self
+    }
+
+    /// Make gifski encode faster, at the expense of quality.  Ignored unless the palette
+    /// mode is [`PaletteMode::Auto`].
+    pub fn fast(&mut self, fast: bool) -> &mut Self {
+        self.options.fast = fast;
+        self
+    }
+
+    /// Set how many times the animation loops.
+    pub fn repeat(&mut self, repeat: Repeat) -> &mut Self {
+        self.options.repeat = repeat;
+        self
+    }
+
+    /// Resize the animation to the given width.  If only one of the width and height is set,
+    /// the other is chosen to preserve the aspect ratio.  Ignored unless the palette mode is
+    /// [`PaletteMode::Auto`].
+    pub fn width(&mut self, width: u32) -> &mut Self {
+        self.options.width = Some(width);
+        self
+    }
+
+    /// Resize the animation to the given height.  See [`VideoRendererOptionsBuilder::width`].
+    pub fn height(&mut self, height: u32) -> &mut Self {
+        self.options.height = Some(height);
+        self
+    }
+
+    /// Set the file format of the animation.
+    pub fn format(&mut self, format: AnimationFormat) -> &mut Self {
+        self.options.format = format;
         self
     }
 
@@ -159,16 +450,42 @@ impl Default for VideoRendererOptionsBuilder {
     }
 }
 
+impl From<VideoRendererOptions> for VideoRendererOptionsBuilder {
+    fn from(options: VideoRendererOptions) -> Self {
+        Self { options }
+    }
+}
+
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VideoRendererOptions {
     filename: String,
     fps: usize,
-    skip_by: usize,
+    pacing: Pacing,
+    #[cfg_attr(feature = "serde", serde(default))]
+    frame_delay: FrameDelay,
+    #[cfg_attr(feature = "serde", serde(default))]
+    hold_last: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    playback: Playback,
     padding: u32,
     thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
     fill_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
     line_color: Rgb<u8>,
-    progress_bar: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    progress: Option<Arc<dyn RenderProgress>>,
+    palette_mode: PaletteMode,
+    palette_size: usize,
+    dithering: bool,
+    quality: u8,
+    fast: bool,
+    #[cfg_attr(feature = "serde", serde(with = "serde_repeat"))]
+    repeat: Repeat,
+    width: Option<u32>,
+    height: Option<u32>,
+    format: AnimationFormat,
 }
 
 impl VideoRendererOptions {
@@ -180,8 +497,20 @@ impl VideoRendererOptions {
         self.fps
     }
 
-    pub fn skip_by(&self) -> usize {
-        self.skip_by
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    pub fn frame_delay(&self) -> FrameDelay {
+        self.frame_delay
+    }
+
+    pub fn hold_last(&self) -> f64 {
+        self.hold_last
+    }
+
+    pub fn playback(&self) -> Playback {
+        self.playback
     }
 
     pub fn padding(&self) -> u32 {
@@ -200,211 +529,2151 @@ impl VideoRendererOptions {
         self.line_color
     }
 
-    pub fn progress_bar(&self) -> bool {
-        self.progress_bar
+    pub fn progress(&self) -> Option<&dyn RenderProgress> {
+        self.progress.as_deref()
     }
-}
 
-struct Lodecoder {
-    frames: Vec<PathBuf>,
-    fps: usize,
-}
+    pub fn palette_mode(&self) -> PaletteMode {
+        self.palette_mode
+    }
+
+    pub fn palette_size(&self) -> usize {
+        self.palette_size
+    }
+
+    pub fn dithering(&self) -> bool {
+        self.dithering
+    }
 
-impl Lodecoder {
-    pub fn new(frames: Vec<PathBuf>, fps: usize) -> Self {
-        Self { frames, fps }
+    pub fn quality(&self) -> u8 {
+        self.quality
     }
 
-    fn total_frames(&self) -> u64 {
-        self.frames.len() as u64
+    pub fn fast(&self) -> bool {
+        self.fast
     }
 
-    fn collect(&mut self, dest: Collector) -> CatResult<()> {
-        for (i, frame) in self.frames.drain(..).enumerate() {
-            dest.add_frame_png_file(i, frame, i as f64 / self.fps as f64)?;
+    pub fn repeat(&self) -> Repeat {
+        self.repeat
+    }
+
+    pub fn width(&self) -> Option<u32> {
+        self.width
+    }
+
+    pub fn height(&self) -> Option<u32> {
+        self.height
+    }
+
+    pub fn format(&self) -> AnimationFormat {
+        self.format
+    }
+
+    /// Returns the delay in seconds of each of the `frames` frames that are drawn, including
+    /// the time the finished drawing is held for.
+    fn frame_delays(&self, frames: usize) -> Vec<f64> {
+        let mut delays = self.frame_delay.delays(frames, self.fps);
+
+        if let Some(last) = delays.last_mut() {
+            *last += self.hold_last.max(0.0);
+        }
+
+        delays
+    }
+
+    fn gifski_settings(&self) -> gifski::Settings {
+        gifski::Settings {
+            width: self.width,
+            height: self.height,
+            quality: self.quality,
+            fast: self.fast,
+            repeat: self.repeat,
         }
-        Ok(())
     }
 }
 
-impl<Q: TurtleContainer> Renderer<VideoRendererOptions> for TurtleRenderer<Q> {
-    type Output = Result<(), LSystemError>;
+/// (De)serializes a [`Repeat`] as the number of repetitions, or `null` to loop forever.
+#[cfg(feature = "serde")]
+mod serde_repeat {
+    use gifski::Repeat;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-    fn render(mut self, system: &LSystem, options: &VideoRendererOptions) -> Self::Output {
-        // Setup our state machine based on the system state
-        self.compute(system.get_state());
+    pub fn serialize<S: Serializer>(repeat: &Repeat, serializer: S) -> Result<S::Ok, S::Error> {
+        let count = match repeat {
+            Repeat::Finite(count) => Some(*count),
+            Repeat::Infinite => None,
+        };
 
-        let (turtle_width, turtle_height, min_x, min_y) = self.state.inner().inner().bounds();
+        count.serialize(serializer)
+    }
 
-        let padding = options.padding as f64;
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Repeat, D::Error> {
+        Ok(match Option::<u16>::deserialize(deserializer)? {
+            Some(count) => Repeat::Finite(count),
+            None => Repeat::Infinite,
+        })
+    }
+}
+
+/// The file format of an animation.
+///
+/// GIFs are limited to 256 colors per frame, so renders with many colors (for example
+/// gradients) are better served by one of the true color formats.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnimationFormat {
+    /// An animated GIF.
+    Gif,
+    /// An animated PNG.  The palette, quality and resizing options are ignored.
+    Apng,
+    /// A lossless animated WebP.  The palette, quality and resizing options are ignored.
+    /// Requires the `webp` feature.
+    #[cfg(feature = "webp")]
+    WebP,
+    /// An H.264 video, encoded by running `ffmpeg`, which must be installed.  The palette,
+    /// quality, resizing and repeat options are ignored.  Requires the `mp4` feature.
+    #[cfg(feature = "mp4")]
+    Mp4,
+    /// A VP9 video in a WebM container, encoded by running `ffmpeg`, which must be installed.
+    /// The palette, quality, resizing and repeat options are ignored.  Requires the `mp4`
+    /// feature.
+    #[cfg(feature = "mp4")]
+    WebM,
+}
 
-        // We add some padding to the width reported by our turtle to make
-        // our final image look a little nicer.
-        let width = (2.0 * padding) + turtle_width;
-        let height = (2.0 * padding) + turtle_height;
+/// How the ends of lines are drawn by the image renderer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineCap {
+    /// Lines stop exactly at their endpoints.
+    Butt,
+    /// Lines end in a semicircle centred on their endpoints.
+    Round,
+    /// Lines extend past their endpoints by their thickness.
+    Square,
+}
 
-        let mut buffer = ImageBuffer::new(width.ceil() as u32, height.ceil() as u32);
-        fill_mut(&mut buffer, options.fill_color);
+/// How connected lines are joined together by the image renderer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineJoin {
+    /// The outer edges of the lines are extended until they meet.  Very sharp corners, which
+    /// would produce long spikes, are beveled instead.
+    Miter,
+    /// Joins are rounded off with a circle.
+    Round,
+    /// The outer corners of the lines are joined with a straight edge.
+    Bevel,
+}
 
-        let mut files = Vec::new();
+/// The longest a miter can be, relative to the thickness of the lines it joins, before it is
+/// beveled instead.
+const MITER_LIMIT: f64 = 4.0;
 
-        // Helper functions for converting between the coordinate system used
-        // by the image crate and our coordinate system.  These functions also
-        // take care of the padding for us.
-        let xp = |x: f64| -> f64 { x - min_x + padding };
+/// Converts `lines` into the polygons covered by them, with the given cap and join styles.
+/// Consecutive lines are joined if the first ends where the second starts.
+fn stroke(lines: &[PixelLine], cap: LineCap, join: LineJoin) -> Vec<PixelPolygon> {
+    let connected = |a: &PixelLine, b: &PixelLine| (a.2, a.3) == (b.0, b.1);
+    let circle = |x: f64, y: f64, radius: f64| Shape::Dot { x, y, radius }.outline();
 
-        let yp = |y: f64| -> f64 { height - (y - min_y + padding) };
+    // The unit vector along a line
+    let direction = |&(x1, y1, x2, y2, ..): &PixelLine| {
+        let length = (x2 - x1).hypot(y2 - y1);
+        (length > 0.0).then(|| ((x2 - x1) / length, (y2 - y1) / length))
+    };
 
-        let mut absolute_frame_counter = 0;
-        let total_frame_counter = self.state.inner().inner().lines().len();
+    let mut polygons = Vec::new();
 
-        let mut pb = if options.progress_bar {
-            Some(ProgressBar::new(total_frame_counter as u64))
-        } else {
-            None
+    for (index, line) in lines.iter().enumerate() {
+        let &(x1, y1, x2, y2, thickness, color) = line;
+
+        let Some((ux, uy)) = direction(line) else {
+            if cap == LineCap::Round {
+                polygons.push((circle(x1, y1, thickness), color));
+            }
+            continue;
         };
 
-        let dir = tempfile::tempdir()?;
-        let mut workers = Vec::new();
+        let before = index > 0 && connected(&lines[index - 1], line);
+        let after = lines
+            .get(index + 1)
+            .filter(|next| connected(line, next))
+            .and_then(direction);
 
-        for (frame_counter, (x1, y1, x2, y2)) in
-            self.state.inner().inner().lines().iter().enumerate()
-        {
-            draw_line_mut(
-                &mut buffer,
-                xp(*x1),
-                yp(*y1),
-                xp(*x2),
-                yp(*y2),
-                options.thickness,
-                options.line_color,
-            );
+        // Square caps extend the line past its unconnected ends
+        let extend = if cap == LineCap::Square {
+            thickness
+        } else {
+            0.0
+        };
+        let (sx, sy) = if before {
+            (x1, y1)
+        } else {
+            (x1 - ux * extend, y1 - uy * extend)
+        };
+        let (ex, ey) = if after.is_some() {
+            (x2, y2)
+        } else {
+            (x2 + ux * extend, y2 + uy * extend)
+        };
 
-            if let Some(pb) = pb.as_mut() {
-                pb.inc();
+        let (nx, ny) = (-uy * thickness, ux * thickness);
+        polygons.push((
+            vec![
+                (sx + nx, sy + ny),
+                (ex + nx, ey + ny),
+                (ex - nx, ey - ny),
+                (sx - nx, sy - ny),
+            ],
+            color,
+        ));
+
+        if cap == LineCap::Round {
+            if !before {
+                polygons.push((circle(x1, y1, thickness), color));
             }
-
-            if options.skip_by == 0 || frame_counter % options.skip_by == 0 {
-                // TODO: estimate number of digits we need (for correct padding of filenames)
-                // for the moment we just use 8.
-                let filename = dir
-                    .path()
-                    .join(format!("frame-{:08}.png", absolute_frame_counter));
-                absolute_frame_counter += 1;
-                files.push(filename.clone());
-
-                let local_buffer = buffer.clone();
-
-                // spawn a thread to do this work
-                workers.push(std::thread::spawn(move || -> Result<(), LSystemError> {
-                    save_png(&local_buffer, filename.as_path())
-                }));
+            if after.is_none() {
+                polygons.push((circle(x2, y2, thickness), color));
             }
         }
 
-        for child in workers {
-            child.join().map_err(|_| LSystemError::ThreadError)??;
+        let Some((vx, vy)) = after else {
+            continue;
+        };
 
-            if let Some(pb) = pb.as_mut() {
-                pb.inc();
-            }
+        if join == LineJoin::Round {
+            polygons.push((circle(x2, y2, thickness), color));
+            continue;
         }
 
-        if let Some(pb) = pb.as_mut() {
-            pb.finish();
+        // Fill the gap on each side of the corner
+        let (mx, my) = (-vy * thickness, vx * thickness);
+        let cosine = ux * vx + uy * vy;
+
+        for side in [1.0, -1.0] {
+            let first = (x2 + side * nx, y2 + side * ny);
+            let second = (x2 + side * mx, y2 + side * my);
+
+            // The miter point is where the outer edges of the two lines meet
+            if join == LineJoin::Miter && 2.0 / (1.0 + cosine) <= MITER_LIMIT * MITER_LIMIT {
+                let miter = (
+                    x2 + side * (nx + mx) / (1.0 + cosine),
+                    y2 + side * (ny + my) / (1.0 + cosine),
+                );
+                polygons.push((vec![(x2, y2), first, miter, second], color));
+            } else {
+                polygons.push((vec![(x2, y2), first, second], color));
+            }
         }
+    }
 
-        let settings = gifski::Settings {
-            width: None,
-            height: None,
-            quality: 100,
-            fast: false,
-            repeat: Repeat::Infinite,
-        };
-
-        let mut decoder = Box::new(Lodecoder::new(files, options.fps));
+    polygons
+}
 
-        let mut progress: Box<dyn ProgressReporter> = if !options.progress_bar {
-            Box::new(NoProgress {})
-        } else {
-            let mut pb: ProgressBar<Stdout> = ProgressBar::new(decoder.total_frames());
-            pb.set_max_refresh_rate(Some(Duration::from_millis(250)));
-            Box::new(pb)
-        };
+/// Determines how the colors of each GIF frame are quantized.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PaletteMode {
+    /// Leave quantization to gifski, which picks a palette for each frame itself.
+    Auto,
+    /// Use a single palette, computed from the final frame, for the entire animation.
+    Global,
+    /// Compute a separate palette for each frame.
+    PerFrame,
+}
 
-        let (collector, writer) = gifski::new(settings)?;
-        let decode_thread = thread::spawn(move || decoder.collect(collector));
+/// Builds [`FrameSequenceRendererOptions`].
+///
+/// # Example
+/// ```rust,no_run
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use dcc_lsystem::presets;
+/// use dcc_lsystem::renderer::{FrameSequenceRendererOptionsBuilder, Renderer};
+///
+/// let (mut system, renderer) = presets::koch_curve().finish()?;
+/// system.step_by(3);
+///
+/// // Writes frames/frame00000.png, frames/frame00001.png, ... ready for
+/// // `ffmpeg -framerate 30 -i frames/frame%05d.png koch.mp4`
+/// let options = FrameSequenceRendererOptionsBuilder::new()
+///     .directory("frames")
+///     .skip_by(4)
+///     .build();
+///
+/// renderer.render(&system, &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FrameSequenceRendererOptionsBuilder {
+    options: FrameSequenceRendererOptions,
+}
 
-        let file = File::create(&options.filename)?;
-        writer.write(file, &mut *progress)?;
-        let _ = decode_thread
-            .join()
-            .map_err(|_| LSystemError::ThreadError)?;
-        progress.done(&format!("Output written to {}", options.filename));
+impl FrameSequenceRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: FrameSequenceRendererOptions {
+                directory: None,
+                prefix: String::from("frame"),
+                pacing: Pacing::default(),
+                padding: 20,
+                thickness: 15.0,
+                fill_color: Rgb([255, 255, 255]),
+                line_color: Rgb([0, 0, 0]),
+                progress: None,
+            },
+        }
+    }
 
-        // Now delete the temporary files
-        drop(dir);
+    /// Write each frame to `directory` as a numbered PNG, creating the directory if needed,
+    /// rather than returning the frames.
+    pub fn directory<T: Into<String>>(&mut self, directory: T) -> &mut Self {
+        self.options.directory = Some(directory.into());
+        self
+    }
 
-        Ok(())
+    /// Set the start of the name of each frame written to the directory.  The name is followed
+    /// by the index of the frame, padded to five digits, and `.png`.
+    pub fn prefix<T: Into<String>>(&mut self, prefix: T) -> &mut Self {
+        self.options.prefix = prefix.into();
+        self
     }
-}
 
-impl<Q: TurtleContainer> Renderer<ImageRendererOptions> for TurtleRenderer<Q> {
-    type Output = ImageBuffer<Rgb<u8>, Vec<u8>>;
+    /// Emit a frame after every `skip_by` lines.  This is shorthand for
+    /// `pacing(Pacing::SkipBy(skip_by))`.
+    pub fn skip_by(&mut self, skip_by: usize) -> &mut Self {
+        self.options.pacing = Pacing::SkipBy(skip_by);
+        self
+    }
 
-    fn render(mut self, system: &LSystem, options: &ImageRendererOptions) -> Self::Output {
-        // Setup our state machine based on the LSystem state
-        self.compute(system.get_state());
+    /// Set which partial renders become frames.
+    pub fn pacing(&mut self, pacing: Pacing) -> &mut Self {
+        self.options.pacing = pacing;
+        self
+    }
 
-        let (turtle_width, turtle_height, min_x, min_y) = self.state.inner().inner().bounds();
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
 
-        let padding = options.padding as f64;
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
 
-        let width = 2.0 * padding + turtle_width;
-        let height = 2.0 * padding + turtle_height;
+    pub fn fill_color(&mut self, fill_color: Rgb<u8>) -> &mut Self {
+        self.options.fill_color = fill_color;
+        self
+    }
 
-        let buffer_width = width.ceil() as u32;
-        let buffer_height = height.ceil() as u32;
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
 
-        let mut buffer = ImageBuffer::new(buffer_width, buffer_height);
-        fill_mut(&mut buffer, options.fill_color);
+    /// Show the progress of the render in the terminal.  Requires the `terminal_progress`
+    /// feature.
+    #[cfg(feature = "terminal_progress")]
+    pub fn progress_bar(&mut self, progress_bar: bool) -> &mut Self {
+        self.options.progress = if progress_bar {
+            Some(Arc::new(crate::progress::TerminalProgress::new()))
+        } else {
+            None
+        };
+        self
+    }
 
-        // Helper functions for converting between the coordinate system used
-        // by the image crate and our coordinate system.  These functions also
-        // take care of the padding for us.
-        let xp = |x: f64| -> f64 { x - min_x + padding };
-        let yp = |y: f64| -> f64 { height - (y - min_y + padding) };
+    /// Report the progress of the render to `progress`.
+    pub fn progress<P: RenderProgress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.options.progress = Some(Arc::new(progress));
+        self
+    }
 
-        // Determine the pixels we want to draw
-        for (x1, y1, x2, y2) in self.state.inner().inner().lines() {
-            draw_line_mut(
-                &mut buffer,
-                xp(*x1),
-                yp(*y1),
-                xp(*x2),
-                yp(*y2),
-                options.thickness,
-                options.line_color,
-            );
-        }
+    pub fn build(&mut self) -> FrameSequenceRendererOptions {
+        self.options.clone()
+    }
+}
 
-        buffer
+impl Default for FrameSequenceRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-/// Convenience function for saving image renderer output.  This uses the [`mtpng`] crate which
-/// is significantly faster than calling [`image::ImageBuffer::save`] directly.
-pub fn save_png(buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>, path: &Path) -> Result<(), LSystemError> {
-    let file = File::create(path)?;
+/// Options for rendering the frames of a line-by-line animation as separate images, for
+/// encoding with other tools.  Rendering returns the frames, or writes them to a directory
+/// (in which case the returned list is empty, so long animations needn't fit in memory).
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameSequenceRendererOptions {
+    directory: Option<String>,
+    prefix: String,
+    pacing: Pacing,
+    padding: u32,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    fill_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    progress: Option<Arc<dyn RenderProgress>>,
+}
 
-    let options = Options::new();
-    let mut encoder = Encoder::new(file, &options);
+impl FrameSequenceRendererOptions {
+    pub fn directory(&self) -> Option<&str> {
+        self.directory.as_deref()
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn fill_color(&self) -> Rgb<u8> {
+        self.fill_color
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+
+    pub fn progress(&self) -> Option<&dyn RenderProgress> {
+        self.progress.as_deref()
+    }
+
+    /// Returns where frame `index` is written within `directory`.
+    fn frame_path(&self, directory: &str, index: usize) -> std::path::PathBuf {
+        Path::new(directory).join(format!("{}{:05}.png", self.prefix, index))
+    }
+}
+
+pub struct StepAnimationRendererOptionsBuilder {
+    options: StepAnimationRendererOptions,
+}
+
+impl StepAnimationRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: StepAnimationRendererOptions {
+                filename: String::from("growth.gif"),
+                fps: 2,
+                steps: 5,
+                interpolation_frames: 0,
+                padding: 20,
+                thickness: 15.0,
+                fill_color: Rgb([255, 255, 255]),
+                line_color: Rgb([0, 0, 0]),
+            },
+        }
+    }
+
+    pub fn filename<T: Into<String>>(&mut self, filename: T) -> &mut Self {
+        self.options.filename = filename.into();
+        self
+    }
+
+    pub fn fps(&mut self, fps: usize) -> &mut Self {
+        self.options.fps = fps;
+        self
+    }
+
+    /// Set the number of times the system is stepped.  The animation has one frame for the
+    /// initial state of the system, and one for each step.
+    pub fn steps(&mut self, steps: usize) -> &mut Self {
+        self.options.steps = steps;
+        self
+    }
+
+    /// Set the number of extra frames inserted between consecutive steps.  In these frames
+    /// every line of the next step grows out from its starting point.
+    pub fn interpolation_frames(&mut self, interpolation_frames: usize) -> &mut Self {
+        self.options.interpolation_frames = interpolation_frames;
+        self
+    }
+
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
+
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
+
+    pub fn fill_color(&mut self, fill_color: Rgb<u8>) -> &mut Self {
+        self.options.fill_color = fill_color;
+        self
+    }
+
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
+
+    pub fn build(&mut self) -> StepAnimationRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for StepAnimationRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Options for animating the growth of a system across iterations, rather than the
+/// drawing of a single state.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StepAnimationRendererOptions {
+    filename: String,
+    fps: usize,
+    steps: usize,
+    interpolation_frames: usize,
+    padding: u32,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    fill_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+}
+
+impl StepAnimationRendererOptions {
+    pub fn filename(&self) -> &String {
+        &self.filename
+    }
+
+    pub fn fps(&self) -> usize {
+        self.fps
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn interpolation_frames(&self) -> usize {
+        self.interpolation_frames
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn fill_color(&self) -> Rgb<u8> {
+        self.fill_color
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+}
+
+/// The maximum number of rendered frames waiting to be handed to gifski.  Drawing blocks
+/// once this many frames are queued, which caps the memory used by long animations.
+const BUFFERED_FRAMES: usize = 8;
+
+/// Converts a frame to the format expected by gifski.
+fn to_rgba(buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> ImgVec<RGBA8> {
+    let pixels = buffer
+        .pixels()
+        .map(|Rgb([r, g, b])| RGBA8::new(*r, *g, *b, 255))
+        .collect();
+
+    ImgVec::new(pixels, buffer.width() as usize, buffer.height() as usize)
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<VideoRendererOptions> for TurtleRenderer<Q> {
+    type Output = Result<(), LSystemError>;
+
+    fn render(&self, system: &LSystem, options: &VideoRendererOptions) -> Self::Output {
+        // Setup our state machine based on the system state
+        let state = self.compute(system.get_state());
+
+        let style = FrameStyle {
+            pacing: options.pacing,
+            padding: options.padding,
+            thickness: options.thickness,
+            fill_color: options.fill_color,
+            line_color: options.line_color,
+            progress: options.progress(),
+        };
+
+        let playback = options.playback;
+
+        let (mut sink, frames) = draw_frames(
+            &state,
+            &style,
+            |width, height, frames| {
+                // Each frame keeps its delay wherever it's played
+                let delays = options.frame_delays(frames);
+                let delays = playback.order(frames).iter().map(|&i| delays[i]).collect();
+
+                Ok((FrameSink::new(options, width, height, delays)?, Vec::new()))
+            },
+            |(sink, frames), buffer| {
+                if playback == Playback::Forward {
+                    sink.add_frame(buffer)
+                } else {
+                    frames.push(buffer.clone());
+                    Ok(true)
+                }
+            },
+        )?;
+
+        // Frames can only be played out of order once every frame has been drawn
+        for index in playback.order(frames.len()) {
+            if !sink.add_frame(&frames[index])? {
+                break;
+            }
+        }
+
+        sink.finish(options)?;
+
+        if let Some(progress) = options.progress() {
+            progress.finished();
+        }
+
+        Ok(())
+    }
+}
+
+/// How the frames of a line-by-line animation are drawn.
+struct FrameStyle<'a> {
+    pacing: Pacing,
+    padding: u32,
+    thickness: f64,
+    fill_color: Rgb<u8>,
+    line_color: Rgb<u8>,
+    progress: Option<&'a dyn RenderProgress>,
+}
+
+/// Draws the lines of `state` one at a time, passing a frame to `add_frame` whenever the
+/// pacing asks for one.  The sink receiving the frames is created by `start`, which is told
+/// the size of each frame and how many frames there will be.  `add_frame` returns `false` to
+/// stop drawing early.
+///
+/// Returns the sink, so the caller can finish it.  The progress reporter is told about every
+/// line drawn, but it's up to the caller to tell it when the render has finished.
+fn draw_frames<Q, S, N, A>(
+    state: &Q,
+    style: &FrameStyle,
+    start: N,
+    mut add_frame: A,
+) -> Result<S, LSystemError>
+where
+    Q: TurtleContainer,
+    N: FnOnce(u32, u32, usize) -> Result<S, LSystemError>,
+    A: FnMut(&mut S, &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Result<bool, LSystemError>,
+{
+    let (turtle_width, turtle_height, min_x, min_y) = bounds(state);
+
+    let padding = style.padding as f64;
+
+    // We add some padding to the width reported by our turtle to make
+    // our final image look a little nicer.
+    let (width, height) = image_size(turtle_width, turtle_height, padding);
+
+    let mut buffer = ImageBuffer::new(width.ceil() as u32, height.ceil() as u32);
+    fill_mut(&mut buffer, style.fill_color);
+
+    // Helper functions for converting between the coordinate system used
+    // by the image crate and our coordinate system.  These functions also
+    // take care of the padding for us.
+    let xp = |x: f64| -> f64 { x - min_x + padding };
+
+    let yp = |y: f64| -> f64 { height - (y - min_y + padding) };
+
+    let total_frame_counter = lines(state).count();
+
+    // The lines after which we emit a frame, in order
+    let frame_indices = style.pacing.frame_indices(total_frame_counter);
+    let mut next_frame = frame_indices.iter().peekable();
+
+    if let Some(progress) = style.progress {
+        progress.on_progress(0, total_frame_counter);
+    }
+
+    let mut sink = start(buffer.width(), buffer.height(), frame_indices.len())?;
+
+    for (frame_counter, ((x1, y1, x2, y2), segment_style)) in segments(state).enumerate() {
+        let thickness = segment_style.resolved_thickness(style.thickness);
+
+        if thickness > 0.0 {
+            draw_line_mut(
+                &mut buffer,
+                xp(*x1),
+                yp(*y1),
+                xp(*x2),
+                yp(*y2),
+                thickness,
+                segment_style.color.unwrap_or(style.line_color),
+            );
+        }
+
+        if let Some(progress) = style.progress {
+            progress.on_progress(frame_counter + 1, total_frame_counter);
+        }
+
+        if next_frame.next_if_eq(&&frame_counter).is_some() && !add_frame(&mut sink, &buffer)? {
+            // the sink has stopped accepting frames, so stop drawing;
+            // any error is reported when the sink is finished.
+            break;
+        }
+    }
+
+    Ok(sink)
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<FrameSequenceRendererOptions> for TurtleRenderer<Q> {
+    type Output = Result<Vec<ImageBuffer<Rgb<u8>, Vec<u8>>>, LSystemError>;
+
+    fn render(&self, system: &LSystem, options: &FrameSequenceRendererOptions) -> Self::Output {
+        if let Some(directory) = &options.directory {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        let mut frames = Vec::new();
+
+        self.render_frames(system, options, |index, buffer| {
+            match &options.directory {
+                Some(directory) => save_png(buffer, &options.frame_path(directory, index))?,
+                None => frames.push(buffer.clone()),
+            }
+
+            Ok(())
+        })?;
+
+        Ok(frames)
+    }
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<StepAnimationRendererOptions> for TurtleRenderer<Q> {
+    type Output = Result<(), LSystemError>;
+
+    fn render(&self, system: &LSystem, options: &StepAnimationRendererOptions) -> Self::Output {
+        let mut system = system.clone();
+
+        // Work out what every step looks like before drawing anything, so that every
+        // frame can share the same dimensions.
+        let mut steps = Vec::with_capacity(options.steps + 1);
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+
+        for step in 0..=options.steps {
+            if step > 0 {
+                system.step();
+            }
+
+            let state = self.compute(system.get_state());
+
+            let (width, height, x, y) = bounds(&state);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x + width);
+            max_y = max_y.max(y + height);
+
+            steps.push(
+                segments(&state)
+                    .map(|(line, style)| (*line, *style))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        let padding = options.padding as f64;
+        let (width, height) = image_size(max_x - min_x, max_y - min_y, padding);
+
+        // Helper functions for converting between the coordinate system used
+        // by the image crate and our coordinate system.  These functions also
+        // take care of the padding for us.
+        let xp = |x: f64| -> f64 { x - min_x + padding };
+        let yp = |y: f64| -> f64 { height - (y - min_y + padding) };
+
+        let settings = gifski::Settings {
+            width: None,
+            height: None,
+            quality: 100,
+            fast: false,
+            repeat: Repeat::Infinite,
+        };
+        // Step 0 is a single frame, and every later step interpolates its lines in
+        let frames = 1 + steps.len().saturating_sub(1) * (options.interpolation_frames + 1);
+        let delays = FrameDelay::Fixed.delays(frames, options.fps);
+        let mut encoder = GifskiEncoder::new(&options.filename, delays, settings)?;
+
+        for (step, segments) in steps.iter().enumerate() {
+            // Step 0 has nothing to interpolate from
+            let frames = if step == 0 {
+                1
+            } else {
+                options.interpolation_frames + 1
+            };
+
+            for frame in 1..=frames {
+                // The proportion of each line which is drawn in this frame
+                let t = frame as f64 / frames as f64;
+
+                let mut buffer = ImageBuffer::new(width.ceil() as u32, height.ceil() as u32);
+                fill_mut(&mut buffer, options.fill_color);
+
+                for ((x1, y1, x2, y2), style) in segments.iter() {
+                    let thickness = style.resolved_thickness(options.thickness);
+
+                    if thickness > 0.0 {
+                        draw_line_mut(
+                            &mut buffer,
+                            xp(*x1),
+                            yp(*y1),
+                            xp(x1 + t * (x2 - x1)),
+                            yp(y1 + t * (y2 - y1)),
+                            thickness,
+                            style.color.unwrap_or(options.line_color),
+                        );
+                    }
+                }
+
+                if !encoder.add_frame(to_rgba(&buffer)) {
+                    // the error is reported by `finish`
+                    break;
+                }
+            }
+        }
+
+        encoder.finish()
+    }
+}
+
+/// Where the frames of a video render end up.
+enum FrameSink {
+    /// Streamed to gifski as they're drawn.
+    Gifski(GifskiEncoder),
+    /// Written to an APNG as they're drawn, each with the next of the delays.
+    Apng(png::Writer<File>, std::vec::IntoIter<f64>),
+    /// Piped to `ffmpeg` as they're drawn.
+    #[cfg(feature = "mp4")]
+    Ffmpeg(FfmpegEncoder),
+    /// Kept in memory and encoded once every frame has been drawn, with the delays.
+    Collect(Vec<ImageBuffer<Rgb<u8>, Vec<u8>>>, Vec<f64>),
+}
+
+impl FrameSink {
+    fn new(
+        options: &VideoRendererOptions,
+        width: u32,
+        height: u32,
+        delays: Vec<f64>,
+    ) -> Result<Self, LSystemError> {
+        Ok(match options.format {
+            AnimationFormat::Gif if options.palette_mode == PaletteMode::Auto => FrameSink::Gifski(
+                GifskiEncoder::new(&options.filename, delays, options.gifski_settings())?,
+            ),
+            #[cfg(feature = "mp4")]
+            AnimationFormat::Mp4 | AnimationFormat::WebM => FrameSink::Ffmpeg(FfmpegEncoder::new(
+                &options.filename,
+                options.format,
+                width,
+                height,
+                options.fps,
+                &delays,
+            )?),
+            AnimationFormat::Apng => {
+                let file = File::create(&options.filename)?;
+
+                let mut encoder = png::Encoder::new(file, width, height);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+
+                // APNG counts the total number of plays, with 0 meaning forever
+                let plays = match options.repeat {
+                    Repeat::Finite(count) => u32::from(count) + 1,
+                    Repeat::Infinite => 0,
+                };
+
+                encoder.set_animated(delays.len().max(1) as u32, plays)?;
+
+                FrameSink::Apng(encoder.write_header()?, delays.into_iter())
+            }
+            _ => FrameSink::Collect(Vec::new(), delays),
+        })
+    }
+
+    /// Adds a frame to the animation, returning `false` if the encoder has stopped accepting frames.
+    fn add_frame(&mut self, buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Result<bool, LSystemError> {
+        match self {
+            FrameSink::Gifski(encoder) => Ok(encoder.add_frame(to_rgba(buffer))),
+            FrameSink::Apng(writer, delays) => {
+                if let Some(delay) = delays.next() {
+                    let (numerator, denominator) = apng_delay(delay);
+                    writer.set_frame_delay(numerator, denominator)?;
+                }
+
+                writer.write_image_data(buffer.as_raw())?;
+                Ok(true)
+            }
+            #[cfg(feature = "mp4")]
+            FrameSink::Ffmpeg(encoder) => Ok(encoder.add_frame(buffer)),
+            FrameSink::Collect(frames, _) => {
+                frames.push(buffer.clone());
+                Ok(true)
+            }
+        }
+    }
+
+    fn finish(self, options: &VideoRendererOptions) -> Result<(), LSystemError> {
+        match self {
+            FrameSink::Gifski(encoder) => encoder.finish(),
+            FrameSink::Apng(writer, _) => Ok(writer.finish()?),
+            #[cfg(feature = "mp4")]
+            FrameSink::Ffmpeg(encoder) => encoder.finish(),
+            FrameSink::Collect(frames, delays) => match options.format {
+                #[cfg(feature = "webp")]
+                AnimationFormat::WebP => write_webp(&options.filename, &frames, &delays, options),
+                _ => write_gif(
+                    File::create(&options.filename)?,
+                    &frames,
+                    &delays,
+                    options.palette_size,
+                    options.dithering,
+                    options.palette_mode,
+                    options.repeat,
+                ),
+            },
+        }
+    }
+}
+
+/// Converts a delay in seconds to the fraction of a second APNG stores it as, using
+/// milliseconds unless the delay is too long for them.
+fn apng_delay(delay: f64) -> (u16, u16) {
+    let milliseconds = (delay * 1000.0).round();
+
+    if milliseconds <= f64::from(u16::MAX) {
+        (milliseconds as u16, 1000)
+    } else {
+        ((delay * 10.0).round().min(f64::from(u16::MAX)) as u16, 10)
+    }
+}
+
+/// Writes `frames` to `filename` as a lossless animated WebP, showing each for its delay.
+#[cfg(feature = "webp")]
+fn write_webp(
+    filename: &str,
+    frames: &[ImageBuffer<Rgb<u8>, Vec<u8>>],
+    delays: &[f64],
+    options: &VideoRendererOptions,
+) -> Result<(), LSystemError> {
+    let error = |message: String| LSystemError::Other {
+        source: message.into(),
+    };
+
+    let last = match frames.last() {
+        Some(last) => last,
+        None => return Ok(()),
+    };
+
+    let mut config =
+        webp::WebPConfig::new().map_err(|_| error(String::from("invalid WebP configuration")))?;
+    config.lossless = 1;
+
+    let mut encoder = webp::AnimEncoder::new(last.width(), last.height(), &config);
+    encoder.set_loop_count(match options.repeat {
+        Repeat::Finite(count) => i32::from(count) + 1,
+        Repeat::Infinite => 0,
+    });
+
+    // WebP timestamps are measured in milliseconds from the start of the animation
+    let mut elapsed = 0.0_f64;
+
+    for (frame, delay) in frames.iter().zip(delays) {
+        encoder.add_frame(webp::AnimFrame::from_rgb(
+            frame.as_raw(),
+            frame.width(),
+            frame.height(),
+            (elapsed * 1000.0).round() as i32,
+        ));
+
+        elapsed += delay;
+    }
+
+    let data = encoder
+        .try_encode()
+        .map_err(|e| error(format!("failed to encode WebP: {:?}", e)))?;
+    std::fs::write(filename, &*data)?;
+
+    Ok(())
+}
+
+/// Encodes a video by piping raw frames into an `ffmpeg` process.
+#[cfg(feature = "mp4")]
+struct FfmpegEncoder {
+    child: std::process::Child,
+    stdin: Option<std::process::ChildStdin>,
+    /// Collects whatever `ffmpeg` reports, so it can be included in any error.
+    stderr: JoinHandle<String>,
+    /// How many times each frame is written, so that it's shown for its delay.
+    copies: std::vec::IntoIter<usize>,
+}
+
+#[cfg(feature = "mp4")]
+impl FfmpegEncoder {
+    fn new(
+        filename: &str,
+        format: AnimationFormat,
+        width: u32,
+        height: u32,
+        fps: usize,
+        delays: &[f64],
+    ) -> Result<Self, LSystemError> {
+        use std::io::Read;
+        use std::process::{Command, Stdio};
+
+        let codec: &[&str] = match format {
+            AnimationFormat::WebM => &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "30"],
+            _ => &["-c:v", "libx264", "-crf", "20"],
+        };
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-nostats"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &fps.max(1).to_string()])
+            .args(["-i", "-"])
+            // yuv420p is the most widely supported pixel format, but needs an even size
+            .args([
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                "-pix_fmt",
+                "yuv420p",
+            ])
+            .args(codec)
+            .arg(filename)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take();
+        let mut stderr = child.stderr.take();
+
+        // Drain stderr as we go, so that ffmpeg never blocks writing to it
+        let stderr = thread::spawn(move || {
+            let mut output = String::new();
+
+            if let Some(stderr) = stderr.as_mut() {
+                let _ = stderr.read_to_string(&mut output);
+            }
+
+            output
+        });
+
+        // Videos play at a constant frame rate, so longer delays repeat the frame
+        let fps = fps.max(1) as f64;
+        let copies = delays
+            .iter()
+            .map(|delay| ((delay * fps).round() as usize).max(1))
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            child,
+            stdin,
+            stderr,
+            copies: copies.into_iter(),
+        })
+    }
+
+    /// Writes a frame to `ffmpeg`.  Returns `false` if `ffmpeg` has stopped accepting frames.
+    fn add_frame(&mut self, buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> bool {
+        let copies = self.copies.next().unwrap_or(1);
+
+        match self.stdin.as_mut() {
+            Some(stdin) => (0..copies).all(|_| stdin.write_all(buffer.as_raw()).is_ok()),
+            None => false,
+        }
+    }
+
+    /// Waits for `ffmpeg` to finish writing the video.
+    fn finish(mut self) -> Result<(), LSystemError> {
+        // Closing stdin tells ffmpeg that there are no more frames
+        drop(self.stdin.take());
+
+        let status = self.child.wait()?;
+        let stderr = self.stderr.join().map_err(|_| LSystemError::ThreadError)?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(LSystemError::Other {
+                source: format!("ffmpeg failed ({}): {}", status, stderr.trim()).into(),
+            })
+        }
+    }
+}
+
+/// Streams frames to gifski without touching the disk.
+///
+/// Frames are passed over a bounded channel to a thread which feeds them to the gifski
+/// [`Collector`](gifski::Collector), while a second thread writes the encoded GIF to the output file.
+struct GifskiEncoder {
+    sender: SyncSender<ImgVec<RGBA8>>,
+    collector: JoinHandle<Result<(), LSystemError>>,
+    writer: JoinHandle<Result<(), LSystemError>>,
+}
+
+impl GifskiEncoder {
+    /// Creates an encoder writing to `filename`, which shows each frame for the corresponding
+    /// number of seconds in `delays`.
+    fn new(
+        filename: &str,
+        delays: Vec<f64>,
+        settings: gifski::Settings,
+    ) -> Result<Self, LSystemError> {
+        let (collector, writer) = gifski::new(settings)?;
+        let file = File::create(filename)?;
+
+        let writer = thread::spawn(move || -> Result<(), LSystemError> {
+            writer.write(file, &mut NoProgress {})?;
+            Ok(())
+        });
+
+        let (sender, receiver) = mpsc::sync_channel::<ImgVec<RGBA8>>(BUFFERED_FRAMES);
+
+        // gifski uses the timestamp of the first frame as the delay of the last frame, and
+        // shifts every other timestamp back by it
+        let mut timestamp = delays.last().copied().unwrap_or_default();
+
+        let collector = thread::spawn(move || -> Result<(), LSystemError> {
+            for (index, frame) in receiver.into_iter().enumerate() {
+                collector.add_frame_rgba(index, frame, timestamp)?;
+                timestamp += delays.get(index).copied().unwrap_or_default();
+            }
+
+            // Dropping the collector tells gifski that there are no more frames
+            Ok(())
+        });
+
+        Ok(Self {
+            sender,
+            collector,
+            writer,
+        })
+    }
+
+    /// Queues a frame, blocking if too many frames are already queued.  Returns `false` if
+    /// the frame could not be queued because encoding has failed.
+    fn add_frame(&mut self, frame: ImgVec<RGBA8>) -> bool {
+        self.sender.send(frame).is_ok()
+    }
+
+    /// Waits for all queued frames to be encoded and the GIF to be written.
+    fn finish(self) -> Result<(), LSystemError> {
+        drop(self.sender);
+
+        let collected = self
+            .collector
+            .join()
+            .map_err(|_| LSystemError::ThreadError)?;
+        let written = self.writer.join().map_err(|_| LSystemError::ThreadError)?;
+
+        collected.and(written)
+    }
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<ImageRendererOptions> for TurtleRenderer<Q> {
+    type Output = ImageBuffer<Rgb<u8>, Vec<u8>>;
+
+    fn render(&self, system: &LSystem, options: &ImageRendererOptions) -> Self::Output {
+        // Setup our state machine based on the LSystem state
+        let state = self.compute(system.get_state());
+
+        // When supersampling, everything is drawn `supersample` times larger
+        let supersample = options.supersample();
+        let scene = Scene::new(&state, options, supersample as f64);
+
+        #[cfg(feature = "parallel")]
+        let buffer = draw_bands(
+            scene.width,
+            scene.height,
+            &scene.lines,
+            &scene.polygons,
+            options.fill_color,
+        );
+
+        #[cfg(not(feature = "parallel"))]
+        let buffer = {
+            let mut buffer: ImageBuffer<Rgb<u8>, Vec<u8>> = Canvas::new(scene.width, scene.height);
+            scene.draw(&mut buffer, options.fill_color);
+            buffer
+        };
+
+        let mut buffer = if supersample > 1 {
+            downsample(&buffer, supersample as u32)
+        } else {
+            buffer
+        };
+
+        // Text is already smoothed, so labels are drawn after supersampling
+        if let Some(font) = &options.font {
+            let (turtle_width, turtle_height, min_x, min_y) = bounds(&state);
+            let padding = options.padding as f64;
+            let (_, height) = image_size(turtle_width, turtle_height, padding);
+            let max_depth = max_depth(&state);
+            let scale = Scale::uniform(options.label_size);
+
+            for (label, style) in labels(&state) {
+                let (_, color) = options.resolve(style, max_depth);
+
+                // The text starts at the anchor, and is centred on it vertically
+                let x = label.x - min_x + padding;
+                let y = height - (label.y - min_y + padding) - options.label_size as f64 / 2.0;
+
+                draw_text_mut(
+                    &mut buffer,
+                    color,
+                    x.round() as i32,
+                    y.round() as i32,
+                    scale,
+                    font,
+                    &label.text,
+                );
+            }
+        }
+
+        buffer
+    }
+}
+
+impl<Q: TurtleContainer + Clone> TurtleRenderer<Q> {
+    /// Draws the system onto a new [`Canvas`], using the same layout, colors and thicknesses
+    /// as the [`ImageRendererOptions`] renderer.  This makes it possible to draw with other
+    /// graphics libraries, or into your own frame buffers.
+    ///
+    /// Supersampling and labels are specific to the [`ImageBuffer`] renderer, and are ignored.
+    /// See [`Canvas`] for an example.
+    pub fn render_canvas<C: Canvas>(&self, system: &LSystem, options: &ImageRendererOptions) -> C {
+        let state = self.compute(system.get_state());
+        let scene = Scene::new(&state, options, 1.0);
+
+        let mut canvas = C::new(scene.width, scene.height);
+        scene.draw(&mut canvas, options.fill_color);
+        canvas
+    }
+
+    /// Draws the frames of a line-by-line animation of the system, passing each one to `frame`
+    /// together with its index as soon as it's drawn.  This allows frames to be streamed to a
+    /// window, over the network or to your own encoder, without ever being written to disk.
+    ///
+    /// The frames are drawn as the [`FrameSequenceRendererOptions`] renderer would draw them,
+    /// except that the directory and prefix are ignored.  Drawing stops at the first error
+    /// returned by `frame`, which is then returned.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::renderer::FrameSequenceRendererOptionsBuilder;
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .token("+", TurtleAction::Rotate(90.0))?
+    ///     .axiom("F + F + F + F")?;
+    ///
+    /// let (system, renderer) = builder.finish()?;
+    /// let options = FrameSequenceRendererOptionsBuilder::new().build();
+    ///
+    /// let mut sizes = Vec::new();
+    ///
+    /// renderer.render_frames(&system, &options, |index, frame| {
+    ///     sizes.push((index, frame.width()));
+    ///     Ok(())
+    /// })?;
+    ///
+    /// assert_eq!(sizes.len(), 4);
+    /// assert_eq!(sizes[3].0, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_frames<F>(
+        &self,
+        system: &LSystem,
+        options: &FrameSequenceRendererOptions,
+        mut frame: F,
+    ) -> Result<(), LSystemError>
+    where
+        F: FnMut(usize, &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Result<(), LSystemError>,
+    {
+        let state = self.compute(system.get_state());
+
+        let style = FrameStyle {
+            pacing: options.pacing,
+            padding: options.padding,
+            thickness: options.thickness,
+            fill_color: options.fill_color,
+            line_color: options.line_color,
+            progress: options.progress(),
+        };
+
+        // The number of frames drawn so far
+        draw_frames(
+            &state,
+            &style,
+            |_, _, _| Ok(0),
+            |count, buffer| {
+                frame(*count, buffer)?;
+                *count += 1;
+                Ok(true)
+            },
+        )?;
+
+        if let Some(progress) = options.progress() {
+            progress.finished();
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the depth of the most deeply nested line drawn by the turtles in `state`.
+fn max_depth<Q: TurtleContainer>(state: &Q) -> usize {
+    segments(state)
+        .map(|(_, style)| style.depth)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the width and height of the image drawn for turtles with the given bounds.  Images
+/// are at least a pixel in each direction, even when the turtles only move along one axis and
+/// there's no padding.
+fn image_size(turtle_width: f64, turtle_height: f64, padding: f64) -> (f64, f64) {
+    (
+        (2.0 * padding + turtle_width).max(1.0),
+        (2.0 * padding + turtle_height).max(1.0),
+    )
+}
+
+/// The lines and shapes drawn by the turtles, converted to pixel coordinates.
+struct Scene {
+    width: u32,
+    height: u32,
+    lines: Vec<PixelLine>,
+    polygons: Vec<PixelPolygon>,
+}
+
+impl Scene {
+    /// Lays out the turtles in `state` as `options` describes, with everything drawn `factor`
+    /// times larger.
+    fn new<Q: TurtleContainer>(state: &Q, options: &ImageRendererOptions, factor: f64) -> Self {
+        let (turtle_width, turtle_height, min_x, min_y) = bounds(state);
+
+        let padding = options.padding as f64;
+
+        let (width, height) = image_size(turtle_width, turtle_height, padding);
+
+        // Helper functions for converting between the coordinate system used
+        // by the image crate and our coordinate system.  These functions also
+        // take care of the padding for us.
+        let xp = |x: f64| -> f64 { (x - min_x + padding) * factor };
+        let yp = |y: f64| -> f64 { (height - (y - min_y + padding)) * factor };
+
+        let max_depth = max_depth(state);
+
+        // Determine the pixels we want to draw
+        let lines: Vec<PixelLine> = segments(state)
+            .filter_map(|((x1, y1, x2, y2), style)| {
+                let (thickness, color) = options.resolve(style, max_depth);
+
+                if thickness > 0.0 {
+                    Some((
+                        xp(*x1),
+                        yp(*y1),
+                        xp(*x2),
+                        yp(*y2),
+                        thickness * factor,
+                        color,
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        // Split the lines into dashes, which are then drawn like any other line
+        let dasher = options
+            .stroke_style
+            .as_ref()
+            .and_then(|style| Dasher::new(style, factor));
+
+        let lines = match dasher {
+            Some(mut dasher) => lines
+                .into_iter()
+                .flat_map(|(x1, y1, x2, y2, thickness, color)| {
+                    dasher
+                        .split(x1, y1, x2, y2)
+                        .into_iter()
+                        .map(move |(x1, y1, x2, y2)| (x1, y1, x2, y2, thickness, color))
+                })
+                .collect(),
+            None => lines,
+        };
+
+        let mut polygons: Vec<PixelPolygon> = Vec::new();
+
+        // Lines with a cap or join style are drawn as polygons
+        let lines = match (options.line_cap, options.line_join) {
+            (None, None) => lines,
+            (cap, join) => {
+                polygons = stroke(
+                    &lines,
+                    cap.unwrap_or(LineCap::Butt),
+                    join.unwrap_or(LineJoin::Miter),
+                );
+                Vec::new()
+            }
+        };
+
+        polygons.extend(shapes(state).map(|(shape, style)| {
+            let (_, color) = options.resolve(style, max_depth);
+            let outline: Vec<_> = shape
+                .outline()
+                .into_iter()
+                .map(|(x, y)| (xp(x), yp(y)))
+                .collect();
+
+            (outline, color)
+        }));
+
+        Self {
+            width: width.ceil() as u32 * factor as u32,
+            height: height.ceil() as u32 * factor as u32,
+            lines,
+            polygons,
+        }
+    }
+
+    /// Fills `canvas` with `fill_color`, then draws the lines, and then the shapes on top of
+    /// them.
+    fn draw<C: Canvas>(&self, canvas: &mut C, fill_color: Rgb<u8>) {
+        canvas.fill(fill_color);
+
+        for &(x1, y1, x2, y2, thickness, color) in &self.lines {
+            canvas.draw_line(x1, y1, x2, y2, thickness, color);
+        }
+
+        for (outline, color) in &self.polygons {
+            canvas.draw_polygon(outline, *color);
+        }
+    }
+}
+
+/// Shrinks `buffer` by `factor` in each direction, averaging each `factor` by `factor` block of
+/// pixels into a single pixel.
+fn downsample(
+    buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    factor: u32,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let samples = factor * factor;
+
+    ImageBuffer::from_fn(buffer.width() / factor, buffer.height() / factor, |x, y| {
+        let mut totals = [0u32; 3];
+
+        for dy in 0..factor {
+            for dx in 0..factor {
+                let Rgb(channels) = buffer.get_pixel(x * factor + dx, y * factor + dy);
+
+                for (total, channel) in totals.iter_mut().zip(channels.iter()) {
+                    *total += *channel as u32;
+                }
+            }
+        }
+
+        // Round to the nearest value, rather than always rounding down
+        Rgb(totals.map(|total| ((total + samples / 2) / samples) as u8))
+    })
+}
+
+/// A line in image coordinates, together with its thickness and color.
+type PixelLine = (f64, f64, f64, f64, f64, Rgb<u8>);
+
+/// The outline of a shape in image coordinates, together with its color.
+type PixelPolygon = (Vec<(f64, f64)>, Rgb<u8>);
+
+/// The fewest rows drawn by a single thread.  Thinner bands spend more time checking which
+/// lines they contain than drawing them.
+#[cfg(feature = "parallel")]
+const MIN_BAND_HEIGHT: usize = 16;
+
+/// Draws `lines` and then `polygons` onto a new image, splitting the image into horizontal bands
+/// which are drawn in parallel.  Every band draws the lines and polygons which cross it in order,
+/// so the result is exactly the same as drawing the whole image on a single thread.
+#[cfg(feature = "parallel")]
+fn draw_bands(
+    width: u32,
+    height: u32,
+    lines: &[PixelLine],
+    polygons: &[PixelPolygon],
+    fill_color: Rgb<u8>,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    use crate::image::{draw_line_offset_mut, draw_polygon_offset_mut};
+    use rayon::prelude::*;
+
+    // Use several bands per thread, so that threads which finish sparse parts of the
+    // image early can help out with the dense parts.
+    let band_height = (height as usize)
+        .div_ceil(4 * rayon::current_num_threads())
+        .max(MIN_BAND_HEIGHT);
+
+    let bands: Vec<Vec<u8>> = (0..height)
+        .step_by(band_height)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|top| {
+            let bottom = (top + band_height as u32).min(height);
+
+            let mut band = ImageBuffer::new(width, bottom - top);
+            fill_mut(&mut band, fill_color);
+
+            for &(x1, y1, x2, y2, thickness, color) in lines {
+                // Lines (and the circles covering their ends) reach a little past their endpoints
+                let reach = thickness + 1.0;
+
+                if y1.max(y2) + reach >= top as f64 && y1.min(y2) - reach <= bottom as f64 {
+                    draw_line_offset_mut(&mut band, x1, y1, x2, y2, thickness, color, top as i32);
+                }
+            }
+
+            for (outline, color) in polygons {
+                let (low, high) = outline
+                    .iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), (_, y)| {
+                        (low.min(*y), high.max(*y))
+                    });
+
+                if high + 1.0 >= top as f64 && low - 1.0 <= bottom as f64 {
+                    draw_polygon_offset_mut(&mut band, outline, *color, top as i32);
+                }
+            }
+
+            band.into_raw()
+        })
+        .collect();
+
+    // unwrap: the bands hold exactly three bytes for each pixel of the image
+    ImageBuffer::from_raw(width, height, bands.concat()).unwrap()
+}
+
+/// Convenience function for saving image renderer output.  This uses the [`mtpng`] crate which
+/// is significantly faster than calling [`image::ImageBuffer::save`] directly.
+pub fn save_png(buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>, path: &Path) -> Result<(), LSystemError> {
+    save_png_with(buffer, path, &PngOptions::default())
+}
+
+/// Saves image renderer output as a JPEG.  The `quality` ranges from 1 (the smallest files)
+/// to 100 (the best looking), and values outside that range are clamped.
+///
+/// Lossy formats are best suited to renders with gradients or many colors, which compress
+/// poorly as PNGs.  Thin lines in a few flat colors are usually smaller, and sharper, as PNGs.
+pub fn save_jpeg(
+    buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    path: &Path,
+    quality: u8,
+) -> Result<(), LSystemError> {
+    let mut file = BufWriter::new(File::create(path)?);
+
+    JpegEncoder::new_with_quality(&mut file, quality.clamp(1, 100)).encode_image(buffer)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Saves image renderer output as a lossy WebP.  The `quality` ranges from 0 (the smallest
+/// files) to 100 (the best looking), and values outside that range are clamped.  Requires the
+/// `webp` feature.
+#[cfg(feature = "webp")]
+pub fn save_webp(
+    buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    path: &Path,
+    quality: f32,
+) -> Result<(), LSystemError> {
+    let data = webp::Encoder::from_rgb(buffer.as_raw(), buffer.width(), buffer.height())
+        .encode_simple(false, quality.clamp(0.0, 100.0))
+        .map_err(|e| LSystemError::Other {
+            source: format!("failed to encode WebP: {:?}", e).into(),
+        })?;
+    std::fs::write(path, &*data)?;
+
+    Ok(())
+}
+
+/// The keywords of the text chunks holding a [`PngRecipe`].
+const GRAMMAR_KEYWORD: &str = "dcc-lsystem:grammar";
+const STEPS_KEYWORD: &str = "dcc-lsystem:steps";
+const SEED_KEYWORD: &str = "dcc-lsystem:seed";
+const RENDERER_OPTIONS_KEYWORD: &str = "dcc-lsystem:renderer-options";
+
+/// How hard the PNG encoder works to make the file smaller.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PngCompression {
+    /// Compress quickly, at the cost of a larger file.
+    Fast,
+    /// A balance between speed and size.
+    Default,
+    /// Make the file as small as possible, at the cost of speed.
+    High,
+}
+
+/// The filter applied to each row of the image before it is compressed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PngFilter {
+    /// Pick the filter for each row which is likely to compress best.
+    Adaptive,
+    /// Store each row as it is.
+    None,
+    /// Store each pixel as the difference from the pixel to its left.
+    Sub,
+    /// Store each pixel as the difference from the pixel above it.
+    Up,
+    /// Store each pixel as the difference from the average of the pixels to its left and above.
+    Average,
+    /// Store each pixel as the difference from a prediction made from its neighbours.
+    Paeth,
+}
+
+/// The color type of a saved PNG.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PngColorType {
+    /// Red, green and blue channels.
+    Truecolor,
+    /// Red, green, blue and alpha channels.
+    TruecolorAlpha,
+    /// A single gray channel.  Colors are converted to their luminance.
+    Grayscale,
+    /// Gray and alpha channels.
+    GrayscaleAlpha,
+}
+
+impl PngColorType {
+    fn has_alpha(self) -> bool {
+        matches!(
+            self,
+            PngColorType::TruecolorAlpha | PngColorType::GrayscaleAlpha
+        )
+    }
+}
+
+/// Options for [`save_png_with`].
+///
+/// # Example
+/// ```rust,no_run
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use std::path::Path;
+///
+/// use dcc_lsystem::image_renderer::{save_png_with, PngOptionsBuilder};
+/// use dcc_lsystem::renderer::{ImageRendererOptionsBuilder, Renderer};
+/// use dcc_lsystem::turtle::TurtleLSystemBuilder;
+///
+/// let grammar = "
+///     F = forward 30
+///     + = rotate 90
+///     - = rotate -90
+///     axiom: F
+///     F => F+F-F-F+F
+/// ";
+///
+/// let (mut system, renderer) = TurtleLSystemBuilder::from_grammar(grammar)?.finish()?;
+/// system.step_by(4);
+///
+/// let image = renderer.render(&system, &ImageRendererOptionsBuilder::new().build());
+///
+/// // Anyone with the image can draw it again using `load_png_recipe`
+/// let options = PngOptionsBuilder::new().grammar(grammar).steps(4).build();
+/// save_png_with(&image, Path::new("koch_curve.png"), &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PngOptionsBuilder {
+    options: PngOptions,
+}
+
+impl PngOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: PngOptions {
+                grammar: None,
+                steps: None,
+                seed: None,
+                renderer_options: None,
+                compression: PngCompression::Default,
+                filter: PngFilter::Adaptive,
+                threads: None,
+                color_type: PngColorType::Truecolor,
+                transparent_color: None,
+            },
+        }
+    }
+
+    /// Set how hard the encoder works to make the file smaller.
+    pub fn compression(&mut self, compression: PngCompression) -> &mut Self {
+        self.options.compression = compression;
+        self
+    }
+
+    /// Set the filter applied to each row before it is compressed.  By default the filter
+    /// is chosen for each row.
+    pub fn filter(&mut self, filter: PngFilter) -> &mut Self {
+        self.options.filter = filter;
+        self
+    }
+
+    /// Set the number of threads used to compress the image.  By default every core is used.
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.options.threads = Some(threads.max(1));
+        self
+    }
+
+    /// Set the color type of the saved image.  Grayscale images are smaller, but lose the
+    /// color of the lines.
+    pub fn color_type(&mut self, color_type: PngColorType) -> &mut Self {
+        self.options.color_type = color_type;
+        self
+    }
+
+    /// Make every pixel of `transparent_color` (usually the fill color) fully transparent.
+    /// This only has an effect for color types with an alpha channel.
+    pub fn transparent_color(&mut self, transparent_color: Rgb<u8>) -> &mut Self {
+        self.options.transparent_color = Some(transparent_color);
+        self
+    }
+
+    /// Embed the grammar of the system, written in the format described in the
+    /// [`grammar`](crate::grammar) module.
+    pub fn grammar(&mut self, grammar: &str) -> &mut Self {
+        self.options.grammar = Some(grammar.to_string());
+        self
+    }
+
+    /// Embed the number of times the system was stepped.
+    pub fn steps(&mut self, steps: usize) -> &mut Self {
+        self.options.steps = Some(steps);
+        self
+    }
+
+    /// Embed the seed used for stochastic rules and actions.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    /// Embed a description of the options the image was rendered with.  With the `serde`
+    /// feature enabled, the options can be serialized to (and later restored from) a string.
+    pub fn renderer_options(&mut self, renderer_options: &str) -> &mut Self {
+        self.options.renderer_options = Some(renderer_options.to_string());
+        self
+    }
+
+    pub fn build(&mut self) -> PngOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for PngOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PngOptions {
+    grammar: Option<String>,
+    steps: Option<usize>,
+    seed: Option<u64>,
+    renderer_options: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default = "PngOptions::default_compression"))]
+    compression: PngCompression,
+    #[cfg_attr(feature = "serde", serde(default = "PngOptions::default_filter"))]
+    filter: PngFilter,
+    #[cfg_attr(feature = "serde", serde(default))]
+    threads: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default = "PngOptions::default_color_type"))]
+    color_type: PngColorType,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::color::serde_rgb_option")
+    )]
+    transparent_color: Option<Rgb<u8>>,
+}
+
+#[cfg(feature = "serde")]
+impl PngOptions {
+    fn default_compression() -> PngCompression {
+        PngCompression::Default
+    }
+
+    fn default_filter() -> PngFilter {
+        PngFilter::Adaptive
+    }
+
+    fn default_color_type() -> PngColorType {
+        PngColorType::Truecolor
+    }
+}
+
+impl PngOptions {
+    pub fn grammar(&self) -> Option<&str> {
+        self.grammar.as_deref()
+    }
+
+    pub fn steps(&self) -> Option<usize> {
+        self.steps
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn renderer_options(&self) -> Option<&str> {
+        self.renderer_options.as_deref()
+    }
+
+    pub fn compression(&self) -> PngCompression {
+        self.compression
+    }
+
+    pub fn filter(&self) -> PngFilter {
+        self.filter
+    }
+
+    pub fn threads(&self) -> Option<usize> {
+        self.threads
+    }
+
+    pub fn color_type(&self) -> PngColorType {
+        self.color_type
+    }
+
+    pub fn transparent_color(&self) -> Option<Rgb<u8>> {
+        self.transparent_color
+    }
+
+    /// Returns the rows of `buffer` in the chosen color type.
+    pub(crate) fn pixels(&self, buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Vec<u8> {
+        if self.color_type == PngColorType::Truecolor {
+            return buffer.as_raw().clone();
+        }
+
+        let channels = match self.color_type {
+            PngColorType::Truecolor => 3,
+            PngColorType::TruecolorAlpha => 4,
+            PngColorType::Grayscale => 1,
+            PngColorType::GrayscaleAlpha => 2,
+        };
+
+        let mut data = Vec::with_capacity(buffer.len() / 3 * channels);
+
+        for pixel in buffer.pixels() {
+            match self.color_type {
+                PngColorType::Grayscale | PngColorType::GrayscaleAlpha => {
+                    data.push(pixel.to_luma()[0])
+                }
+                _ => data.extend_from_slice(&pixel.0),
+            }
+
+            if self.color_type.has_alpha() {
+                let transparent = self.transparent_color == Some(*pixel);
+                data.push(if transparent { 0 } else { 255 });
+            }
+        }
+
+        data
+    }
+
+    /// Returns the keyword and text of each text chunk to embed.
+    fn text(&self) -> Vec<(&'static str, String)> {
+        let mut text = Vec::new();
+
+        if let Some(grammar) = &self.grammar {
+            text.push((GRAMMAR_KEYWORD, grammar.clone()));
+        }
+
+        if let Some(steps) = self.steps {
+            text.push((STEPS_KEYWORD, steps.to_string()));
+        }
+
+        if let Some(seed) = self.seed {
+            text.push((SEED_KEYWORD, seed.to_string()));
+        }
+
+        if let Some(renderer_options) = &self.renderer_options {
+            text.push((RENDERER_OPTIONS_KEYWORD, renderer_options.clone()));
+        }
+
+        text
+    }
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptionsBuilder::new().build()
+    }
+}
+
+/// Returns the contents of an uncompressed `iTXt` chunk, which holds UTF-8 text.
+fn itxt_chunk(keyword: &str, text: &str) -> Vec<u8> {
+    let mut data = Vec::with_capacity(keyword.len() + text.len() + 5);
+
+    data.extend_from_slice(keyword.as_bytes());
+    // No compression, and an empty language tag and translated keyword
+    data.extend_from_slice(&[0, 0, 0, 0, 0]);
+    data.extend_from_slice(text.as_bytes());
+
+    data
+}
+
+/// Saves image renderer output like [`save_png`], embedding whatever is set in `options` as
+/// text chunks.  The embedded recipe can be read back using [`load_png_recipe`].
+pub fn save_png_with(
+    buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    path: &Path,
+    options: &PngOptions,
+) -> Result<(), LSystemError> {
+    let file = File::create(path)?;
+
+    let pool = match options.threads {
+        Some(threads) => Some(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(|e| LSystemError::Other {
+                    source: Box::new(e),
+                })?,
+        ),
+        None => None,
+    };
+
+    let mut encoder_options = Options::new();
+
+    if let Some(pool) = &pool {
+        encoder_options.set_thread_pool(pool)?;
+    }
+
+    encoder_options.set_compression_level(match options.compression {
+        PngCompression::Fast => CompressionLevel::Fast,
+        PngCompression::Default => CompressionLevel::Default,
+        PngCompression::High => CompressionLevel::High,
+    })?;
+
+    encoder_options.set_filter_mode(match options.filter {
+        PngFilter::Adaptive => Mode::Adaptive,
+        PngFilter::None => Mode::Fixed(Filter::None),
+        PngFilter::Sub => Mode::Fixed(Filter::Sub),
+        PngFilter::Up => Mode::Fixed(Filter::Up),
+        PngFilter::Average => Mode::Fixed(Filter::Average),
+        PngFilter::Paeth => Mode::Fixed(Filter::Paeth),
+    })?;
+
+    let color_type = match options.color_type {
+        PngColorType::Truecolor => ColorType::Truecolor,
+        PngColorType::TruecolorAlpha => ColorType::TruecolorAlpha,
+        PngColorType::Grayscale => ColorType::Greyscale,
+        PngColorType::GrayscaleAlpha => ColorType::GreyscaleAlpha,
+    };
+
+    let mut encoder = Encoder::new(file, &encoder_options);
     let mut header = Header::new();
     header.set_size(buffer.width(), buffer.height())?;
-    header.set_color(ColorType::Truecolor, 8)?;
+    header.set_color(color_type, 8)?;
     encoder.write_header(&header)?;
-    encoder.write_image_rows(buffer.as_raw())?;
+
+    // Text chunks go before the image data, so they can be read without decoding the image
+    for (keyword, text) in options.text() {
+        encoder.write_chunk(b"iTXt", &itxt_chunk(keyword, &text))?;
+    }
+
+    encoder.write_image_rows(&options.pixels(buffer))?;
     encoder.finish()?;
 
     Ok(())
 }
+
+/// Everything needed to draw an image saved by [`save_png_with`] again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PngRecipe {
+    grammar: String,
+    steps: usize,
+    seed: Option<u64>,
+    renderer_options: Option<String>,
+}
+
+impl PngRecipe {
+    /// Returns the grammar of the system.
+    pub fn grammar(&self) -> &str {
+        &self.grammar
+    }
+
+    /// Returns the number of times the system was stepped, which is `0` if it wasn't recorded.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn renderer_options(&self) -> Option<&str> {
+        self.renderer_options.as_deref()
+    }
+
+    /// Returns a builder for the system, seeded with the recorded seed.
+    pub fn builder(&self) -> Result<TurtleLSystemBuilder, LSystemError> {
+        let mut builder = TurtleLSystemBuilder::from_grammar(&self.grammar)?;
+
+        if let Some(seed) = self.seed {
+            builder.seed(seed);
+        }
+
+        Ok(builder)
+    }
+
+    /// Builds the system and steps it as many times as it was stepped before it was saved.
+    pub fn system(&self) -> Result<(LSystem, TurtleRenderer<TurtleLSystemState>), LSystemError> {
+        let (mut system, renderer) = self.builder()?.finish()?;
+        system.step_by(self.steps);
+
+        Ok((system, renderer))
+    }
+}
+
+/// Reads the recipe embedded in a PNG saved by [`save_png_with`].
+///
+/// # Example
+/// ```rust,no_run
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use std::path::Path;
+///
+/// use dcc_lsystem::image_renderer::load_png_recipe;
+/// use dcc_lsystem::renderer::{ImageRendererOptionsBuilder, Renderer};
+///
+/// let recipe = load_png_recipe(Path::new("koch_curve.png"))?;
+/// let (system, renderer) = recipe.system()?;
+///
+/// let image = renderer.render(&system, &ImageRendererOptionsBuilder::new().build());
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_png_recipe(path: &Path) -> Result<PngRecipe, LSystemError> {
+    let reader = png::Decoder::new(File::open(path)?).read_info()?;
+    let info = reader.info();
+
+    let mut text = HashMap::new();
+
+    for chunk in &info.uncompressed_latin1_text {
+        text.insert(chunk.keyword.as_str(), chunk.text.clone());
+    }
+
+    for chunk in &info.utf8_text {
+        text.insert(chunk.keyword.as_str(), chunk.get_text()?);
+    }
+
+    let number = |keyword: &str| -> Result<Option<u64>, LSystemError> {
+        text.get(keyword)
+            .map(|value| {
+                value.trim().parse().map_err(|_| {
+                    LSystemError::InvalidRecipe(format!("`{}` is not a valid {}", value, keyword))
+                })
+            })
+            .transpose()
+    };
+
+    let steps = number(STEPS_KEYWORD)?.unwrap_or(0);
+    let seed = number(SEED_KEYWORD)?;
+
+    let grammar = text
+        .get(GRAMMAR_KEYWORD)
+        .cloned()
+        .ok_or_else(|| LSystemError::InvalidRecipe(String::from("the image has no grammar")))?;
+
+    Ok(PngRecipe {
+        grammar,
+        steps: steps as usize,
+        seed,
+        renderer_options: text.get(RENDERER_OPTIONS_KEYWORD).cloned(),
+    })
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use super::*;
+    use crate::image::draw_polygon_offset_mut;
+
+    #[test]
+    fn bands_match_drawing_on_a_single_thread() {
+        let black = Rgb([0u8, 0u8, 0u8]);
+        let white = Rgb([255u8, 255u8, 255u8]);
+
+        // A fan of lines of various thicknesses, crossing plenty of band boundaries
+        let lines: Vec<PixelLine> = (0..40)
+            .map(|i| {
+                let angle = i as f64 * 0.15;
+                let thickness = 1.0 + (i % 5) as f64;
+                let color = if i % 2 == 0 { black } else { Rgb([200, 0, 0]) };
+
+                (
+                    100.0,
+                    100.0,
+                    100.0 + 90.0 * angle.cos(),
+                    100.0 + 95.5 * angle.sin(),
+                    thickness,
+                    color,
+                )
+            })
+            .collect();
+
+        // And some dots spread over the image
+        let polygons: Vec<PixelPolygon> = (0..10)
+            .map(|i| {
+                let x = 20.0 * i as f64;
+                let dot = Shape::Dot {
+                    x,
+                    y: x,
+                    radius: 1.0 + 3.0 * (i % 3) as f64,
+                };
+                (dot.outline(), Rgb([0, 0, 200]))
+            })
+            .collect();
+
+        let mut expected = ImageBuffer::new(200, 201);
+        fill_mut(&mut expected, white);
+
+        for &(x1, y1, x2, y2, thickness, color) in lines.iter() {
+            draw_line_mut(&mut expected, x1, y1, x2, y2, thickness, color);
+        }
+
+        for (outline, color) in polygons.iter() {
+            draw_polygon_offset_mut(&mut expected, outline, *color, 0);
+        }
+
+        assert_eq!(draw_bands(200, 201, &lines, &polygons, white), expected);
+    }
+}
@@ -92,9 +92,9 @@ use dcc_lsystem::renderer::{ImageRendererOptionsBuilder, Renderer};
 let mut builder = TurtleLSystemBuilder::new();
 
 builder
-    .token("F", TurtleAction::Forward(30))? // F => go forward 30 units
-    .token("+", TurtleAction::Rotate(90))?  // + => rotate left 90°
-    .token("-", TurtleAction::Rotate(-90))? // - => rotate right 90°
+    .token("F", TurtleAction::Forward(30.0))? // F => go forward 30 units
+    .token("+", TurtleAction::Rotate(90.0))?  // + => rotate left 90°
+    .token("-", TurtleAction::Rotate(-90.0))? // - => rotate right 90°
     .axiom("F")?
     .rule("F => F + F - F - F + F")?;
 
@@ -118,6 +118,9 @@ renderer
 
 The resulting image is shown in the Examples section below.
 
+Lines are drawn with hard edges by default.  For smoother output, use
+`.supersample(4)` to draw the image at four times the size and scale it back down.
+
 ## GIFs
 
 It is also possible to render a GIF using an L-system.  The individual frames
@@ -134,9 +137,9 @@ use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
 let mut builder = TurtleLSystemBuilder::new();
 
 builder
-    .token("F", TurtleAction::Forward(30))?
-    .token("+", TurtleAction::Rotate(90))?
-    .token("-", TurtleAction::Rotate(-90))?
+    .token("F", TurtleAction::Forward(30.0))?
+    .token("+", TurtleAction::Rotate(90.0))?
+    .token("-", TurtleAction::Rotate(-90.0))?
     .axiom("F")?
     .rule("F => F + F - F - F + F")?;
 
@@ -160,6 +163,86 @@ renderer
 # }
 ```
 
+## TikZ
+
+For LaTeX documents, an L-system can also be rendered as a `tikzpicture`, with a `\draw`
+command for each line.
+
+```rust,no_run
+# use dcc_lsystem::LSystemError;
+# fn main() -> Result<(), LSystemError> {
+use dcc_lsystem::renderer::{Renderer, TikzRendererOptionsBuilder};
+use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+let mut builder = TurtleLSystemBuilder::new();
+
+builder
+    .token("F", TurtleAction::Forward(30.0))?
+    .token("+", TurtleAction::Rotate(90.0))?
+    .token("-", TurtleAction::Rotate(-90.0))?
+    .axiom("F")?
+    .rule("F => F + F - F - F + F")?;
+
+let (mut system, renderer) = builder.finish()?;
+system.step_by(3);
+
+let options = TikzRendererOptionsBuilder::new()
+    .scale(0.01)
+    .standalone(true)
+    .build();
+
+std::fs::write("koch_curve.tex", renderer.render(&system, &options))?;
+# Ok(())
+# }
+```
+
+## Preview
+
+With the `preview` feature enabled, `TurtleRenderer::preview` opens a window showing the
+system, which can be panned and zoomed, and stepped by pressing space.  This makes it much
+quicker to experiment with a grammar than repeatedly saving and opening images.  See
+`examples/preview.rs`.
+
+## Music
+
+The `music` module plays systems as music: a `MusicRenderer` associates tokens with musical
+actions, such as playing a note or moving up the scale, and writes the result as a standard
+MIDI file.
+
+## Command line
+
+With the `cli` feature enabled, the `dcc-lsystem` binary renders a grammar file, in the format
+accepted by `TurtleLSystemBuilder::from_grammar`, to a PNG, SVG, GIF or JSON file.  Files
+ending in `.toml` or `.json` are read as configs, as described in the `config` module, which
+requires the `config` feature.  The options in a config's `renderer` entry are used in place of
+the defaults for the chosen format.
+
+```text
+cargo install dcc-lsystem --features cli
+dcc-lsystem koch_curve.txt --steps 4 --output koch_curve.svg
+```
+
+Run `dcc-lsystem --help` for the other options.
+
+## Python
+
+With the `python` feature enabled, the `python` module exposes `LSystemBuilder`, `LSystem`,
+`TurtleLSystemBuilder` and a turtle renderer to Python with `pyo3`.  Lines are returned as
+tuples which `numpy.array` accepts, and images as PNG bytes which PIL can open.  See the module
+for how to build the extension module.
+
+## `no_std`
+
+With the default `std` feature disabled the crate builds for `no_std` targets which have an
+allocator.  Only the core system is available: `LSystemBuilder`, `LSystem`, and the analysis
+of systems and their states.  Without a source of entropy, stochastic rules are seeded with
+a fixed seed unless `LSystemBuilder::seed` is called.
+
+```toml
+[dependencies]
+dcc-lsystem = { version = "0.7", default-features = false }
+```
+
 ## Turtle actions
 
 The following actions are currently available:
@@ -167,44 +250,87 @@ The following actions are currently available:
 | [`TurtleAction`](dcc_lsystem::turtle::TurtleAction) | Description                                                                             |
 |--------------------------------------------|-----------------------------------------------------------------------------------------|
 | `Nothing`                                  | The turtle does nothing.                                                                |
-| `Rotate(i32)`                              | Rotate the turtle through an angle.                                                     |
-| `Forward(i32)`                             | Move the turtle forwards.                                                               |
-| `Push`                                     | Push the turtle's current heading and location onto the stack.                          |
-| `Pop`                                      | Pop the turtle's heading and location off the stack.                                    |
+| `Rotate(f64)`                              | Rotate the turtle through an angle.                                                     |
+| `Forward(f64)`                             | Move the turtle forwards.                                                               |
+| `Push`                                     | Push the turtle's current heading, location and thickness onto the stack.               |
+| `Pop`                                      | Pop the turtle's heading, location and thickness off the stack.                         |
 | `StochasticRotate(Box<dyn Distribution>)`  | Rotate the turtle through an angle specified by some probability distribution.          |
 | `StochasticForward(Box<dyn Distribution>)` | Move the turtle forwards through a distance specified by some probability distribution. |
+| `SwitchTurtle(String)`                     | Make the named turtle active, creating it if needed.                                    |
+| `SetColor(Rgb<u8>)`                        | Set the color of the lines drawn by the turtle.                                         |
+| `PushColor`                                | Push the turtle's current color onto the color stack.                                   |
+| `PopColor`                                 | Pop the turtle's color off the color stack.                                             |
+| `SetThickness(f64)`                        | Set the thickness of the lines drawn by the turtle.                                     |
+| `IncrementThickness(f64)`                  | Increase the thickness of the lines drawn by the turtle.                                |
+| `DecrementThickness(f64)`                  | Decrease the thickness of the lines drawn by the turtle.                                |
+| `PenUp`                                    | Lift the pen, so the turtle moves without drawing.                                      |
+| `PenDown`                                  | Put the pen down, so the turtle draws as it moves.                                      |
+| `Skip(f64)`                                | Move the turtle forwards without drawing.                                               |
+| `Goto(f64, f64)`                           | Move the turtle to a position without drawing.                                          |
+| `Home`                                     | Move the turtle back to the origin and reset its heading.                               |
+| `SetHeading(f64)`                          | Turn the turtle to face a given heading.                                                |
+| `Arc { radius: f64, angle: f64 }`          | Move the turtle along a circular arc, turning as it goes.                               |
+| `MultiplyStep(f64)`                        | Scale the distances moved by the turtle from now on.                                    |
+| `Dot(f64)`                                 | Draw a filled circle centred on the turtle.                                             |
+| `Leaf(f64)`                                | Draw a filled leaf pointing the way the turtle is facing.                               |
+| `Label(String)`                            | Write some text next to the turtle.                                                     |
+| `Sequence(Vec<TurtleAction>)`              | Perform several actions in turn.                                                        |
+| `Custom(Rc<dyn Fn>)`                       | Modify the turtle's state using an arbitrary function.                                  |
+| `Script(String)`                           | Run a rhai script which can read the turtle's state (requires the `scripting` feature). |
+
+Distances and angles are `f64`s, so fractional angles such as the golden angle can be used.  Code
+written for earlier versions, which used `i32`s, can build actions from integers with
+[`TurtleAction::forward`](dcc_lsystem::turtle::TurtleAction::forward) and
+[`TurtleAction::rotate`](dcc_lsystem::turtle::TurtleAction::rotate), e.g. `TurtleAction::forward(30)`
+in place of `TurtleAction::Forward(30)`.
 
 The [`Distribution`](dcc_lsystem::turtle::Distribution) trait is given by:
 
 ```rust
+# use rand::RngCore;
 pub trait Distribution: dyn_clone:: DynClone {
-    fn sample(&self) -> i32;
+    fn sample(&self) -> f64;
+
+    fn sample_with(&self, rng: &mut dyn RngCore) -> f64 {
+        self.sample()
+    }
 }
 ```
 
+Distributions which implement `sample_with` can be seeded using
+[`TurtleLSystemBuilder::seed`](dcc_lsystem::turtle::TurtleLSystemBuilder::seed), so that the same seed always
+produces the same render.
+
 The [`Uniform`](dcc_lsystem::turtle::Uniform) distribution (using the `rand` crate) is implemented as follows:
 
 ```rust
 # pub trait Distribution: dyn_clone::DynClone {
-#     fn sample(&self) -> i32;
+#     fn sample(&self) -> f64;
+#     fn sample_with(&self, rng: &mut dyn RngCore) -> f64;
 # }
-use rand::Rng;
+use rand::{Rng, RngCore};
 
 #[derive(Clone)]
 pub struct Uniform {
-    lower: i32,
-    upper: i32,
+    lower: f64,
+    upper: f64,
 }
 
 impl Uniform {
-    pub fn new(lower: i32, upper: i32) -> Self {
-        Self { lower, upper }
+    pub fn new<T: Into<f64>>(lower: T, upper: T) -> Self {
+        Self {
+            lower: lower.into(),
+            upper: upper.into(),
+        }
     }
 }
 
 impl Distribution for Uniform {
-    fn sample(&self) -> i32 {
-        let mut rng = rand::thread_rng();
+    fn sample(&self) -> f64 {
+        self.sample_with(&mut rand::thread_rng())
+    }
+
+    fn sample_with(&self, rng: &mut dyn RngCore) -> f64 {
         rng.gen_range(self.lower..=self.upper)
     }
 }
@@ -255,6 +381,9 @@ for inclusion in the work by you, as defined in the Apache-2.0 license, shall be
 dual licensed as above, without any additional terms or conditions.
 */
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 extern crate self as dcc_lsystem;
 
 pub use arena::{Arena, ArenaId};
@@ -262,17 +391,83 @@ pub use builder::LSystemBuilder;
 pub use errors::LSystemError;
 pub use system::LSystem;
 
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod animation;
 pub mod arena;
 pub mod builder;
+#[cfg(feature = "std")]
+pub mod color;
+pub mod compressed;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod derivation;
+#[cfg(feature = "std")]
+pub mod dxf;
 pub mod errors;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod generate;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod grammar;
+#[cfg(feature = "std")]
+pub mod heatmap;
+#[cfg(feature = "std")]
+pub mod html;
 #[cfg(feature = "image_renderer")]
 pub mod image;
 #[cfg(feature = "image_renderer")]
 pub mod image_renderer;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod music;
+#[cfg(feature = "std")]
+pub mod palette;
+#[cfg(all(feature = "std", feature = "kurbo"))]
+pub mod path;
+#[cfg(feature = "std")]
+pub mod plotter;
+#[cfg(feature = "std")]
+pub mod presets;
+#[cfg(feature = "preview")]
+pub mod preview;
+#[cfg(feature = "image_renderer")]
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "image_renderer")]
+mod quantize;
+#[cfg(feature = "std")]
 pub mod renderer;
+#[cfg(feature = "std")]
+pub mod scene;
+#[cfg(feature = "scripting")]
+mod scripting;
+#[cfg(feature = "std")]
+pub mod svg;
 pub mod system;
+#[cfg(feature = "std")]
+pub mod tikz;
 pub mod token;
+#[cfg(feature = "std")]
 pub mod turtle;
+#[cfg(feature = "std")]
+pub mod vector;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// The hash maps used by the core of the crate, which come from `hashbrown` without `std`.
+/// Their type depends on the features enabled, so they're kept out of public signatures.
+mod collections {
+    #[cfg(feature = "std")]
+    pub(crate) use std::collections::{HashMap, HashSet};
+
+    #[cfg(not(feature = "std"))]
+    pub(crate) use hashbrown::{HashMap, HashSet};
+}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests;
@@ -0,0 +1,392 @@
+//! Measures the drawing produced by a system without rendering it.
+//!
+//! Besides plain measurements ([`TurtleRenderer::metrics`]), a drawing can be given a rough
+//! score of how interesting it looks ([`TurtleRenderer::score`]), which is useful for filtering
+//! randomly generated systems (see the [`generate`](crate::generate) module).
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .axiom("F + F + F + F")?;
+//!
+//! let (system, renderer) = builder.finish()?;
+//! let metrics = renderer.metrics(&system);
+//!
+//! assert_eq!((metrics.width.round(), metrics.height.round()), (10.0, 10.0));
+//! assert_eq!(metrics.segments, 4);
+//! assert!((metrics.length - 40.0).abs() < 1e-9);
+//! assert!((metrics.hull_area - 100.0).abs() < 1e-9);
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::{HashMap, HashSet};
+
+use crate::renderer::{bounds, lines, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+/// Measurements of the lines drawn by every turtle in a system.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// The width of the region visited by the turtles.
+    pub width: f64,
+    /// The height of the region visited by the turtles.
+    pub height: f64,
+    /// The smallest x coordinate visited by the turtles.
+    pub min_x: f64,
+    /// The smallest y coordinate visited by the turtles.
+    pub min_y: f64,
+    /// The total length of every line drawn.
+    pub length: f64,
+    /// The number of lines drawn.
+    pub segments: usize,
+    /// The number of times a turtle started a line somewhere other than where its previous
+    /// line ended, for example after moving with the pen up or popping its position.
+    pub jumps: usize,
+    /// The area of the convex hull of every line drawn.
+    pub hull_area: f64,
+}
+
+/// Returns the z component of the cross product of `a - o` and `b - o`, which is positive when
+/// `o`, `a` and `b` turn anticlockwise.
+fn cross(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Returns half of the convex hull of the sorted `points`, turning anticlockwise throughout.
+fn half_hull<'a>(points: impl Iterator<Item = &'a (f64, f64)>) -> Vec<(f64, f64)> {
+    let mut hull: Vec<(f64, f64)> = Vec::new();
+
+    for &point in points {
+        while let [.., a, b] = hull[..] {
+            if cross(a, b, point) > 0.0 {
+                break;
+            }
+
+            hull.pop();
+        }
+
+        hull.push(point);
+    }
+
+    hull
+}
+
+/// Returns the area of the convex hull of `points`, using Andrew's monotone chain algorithm.
+fn hull_area(mut points: Vec<(f64, f64)>) -> f64 {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+
+    let lower = half_hull(points.iter());
+    let upper = half_hull(points.iter().rev());
+
+    // The ends of each half are shared with the other half
+    let hull: Vec<(f64, f64)> = lower[..lower.len().saturating_sub(1)]
+        .iter()
+        .chain(&upper[..upper.len().saturating_sub(1)])
+        .copied()
+        .collect();
+
+    // The shoelace formula
+    let twice_area: f64 = hull
+        .iter()
+        .zip(hull.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum();
+
+    twice_area.abs() / 2.0
+}
+
+/// Heuristics describing how interesting a drawing looks, returned by
+/// [`TurtleRenderer::score`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Score {
+    /// The ratio of the shorter side of the drawing to the longer side, between `0` (a
+    /// straight line) and `1` (a square).
+    pub aspect: f64,
+    /// The coefficient of variation of the length of line drawn in each cell of a grid laid
+    /// over the drawing.  Drawings which fill their bounds evenly score close to `0`, while
+    /// drawings bunched into a few cells score highly.
+    pub density_variation: f64,
+    /// The proportion of lines with a mirror image in the drawing, reflecting in either the
+    /// vertical or the horizontal axis through the middle of the drawing.
+    pub symmetry: f64,
+    /// The number of pairs of lines which cross each other.
+    pub intersections: usize,
+    /// The number of lines drawn.
+    pub segments: usize,
+}
+
+impl Score {
+    /// Combines the heuristics into a single score between `0` and `1`.
+    ///
+    /// The score rewards drawings which are roughly as wide as they are tall, neither
+    /// evenly filled nor bunched up (a coefficient of variation around `1` scores best), and
+    /// symmetric.  It penalises drawings with many crossing lines, or only a handful of lines.
+    pub fn total(&self) -> f64 {
+        if self.segments == 0 {
+            return 0.0;
+        }
+
+        let segments = self.segments as f64;
+        let spread = self.density_variation / (1.0 + self.density_variation);
+        let crossings = self.intersections as f64 / segments;
+
+        self.aspect * 4.0 * spread * (1.0 - spread) * (0.5 + 0.5 * self.symmetry)
+            / (1.0 + crossings)
+            * segments
+            / (segments + 16.0)
+    }
+}
+
+/// The number of cells along each side of the grid used to measure density.
+const DENSITY_GRID: usize = 16;
+
+/// Returns true if the line segments `a` and `b` cross at a point which isn't an endpoint of
+/// either of them.
+fn crosses(a: &(f64, f64, f64, f64), b: &(f64, f64, f64, f64)) -> bool {
+    let (p, q) = ((a.0, a.1), (a.2, a.3));
+    let (r, s) = ((b.0, b.1), (b.2, b.3));
+
+    let d1 = cross(p, q, r);
+    let d2 = cross(p, q, s);
+    let d3 = cross(r, s, p);
+    let d4 = cross(r, s, q);
+
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Counts the pairs of `lines` which cross each other.  Lines are bucketed into a grid so that
+/// only nearby lines are compared.
+fn intersections(lines: &[(f64, f64, f64, f64)], bounds: (f64, f64, f64, f64)) -> usize {
+    let (width, height, min_x, min_y) = bounds;
+
+    let cells = ((lines.len() as f64).sqrt().ceil() as usize).max(1);
+    let cell = |value: f64, min: f64, size: f64| {
+        if size > 0.0 {
+            (((value - min) / size * cells as f64) as usize).min(cells - 1)
+        } else {
+            0
+        }
+    };
+
+    let mut grid: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+
+    for (index, (x1, y1, x2, y2)) in lines.iter().enumerate() {
+        let (cx1, cx2) = (
+            cell(x1.min(*x2), min_x, width),
+            cell(x1.max(*x2), min_x, width),
+        );
+        let (cy1, cy2) = (
+            cell(y1.min(*y2), min_y, height),
+            cell(y1.max(*y2), min_y, height),
+        );
+
+        for cx in cx1..=cx2 {
+            for cy in cy1..=cy2 {
+                grid.entry((cx, cy)).or_default().push(index);
+            }
+        }
+    }
+
+    let mut count = 0;
+
+    for (&(cx, cy), members) in grid.iter() {
+        for (i, a) in members.iter().enumerate() {
+            for b in &members[i + 1..] {
+                let (a, b) = (&lines[*a], &lines[*b]);
+
+                if !crosses(a, b) {
+                    continue;
+                }
+
+                // A pair of lines can share several cells, so only count the crossing in
+                // the lowest, leftmost cell they share
+                let shared = (
+                    cell(a.0.min(a.2).max(b.0.min(b.2)), min_x, width),
+                    cell(a.1.min(a.3).max(b.1.min(b.3)), min_y, height),
+                );
+
+                if shared == (cx, cy) {
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    count
+}
+
+/// Returns the coefficient of variation of the length of line in each cell of a grid over the
+/// drawing.  Each line is assigned to the cell containing its midpoint.
+fn density_variation(lines: &[(f64, f64, f64, f64)], bounds: (f64, f64, f64, f64)) -> f64 {
+    let (width, height, min_x, min_y) = bounds;
+    let mut density = vec![0.0; DENSITY_GRID * DENSITY_GRID];
+
+    let cell = |value: f64, min: f64, size: f64| {
+        if size > 0.0 {
+            (((value - min) / size * DENSITY_GRID as f64) as usize).min(DENSITY_GRID - 1)
+        } else {
+            0
+        }
+    };
+
+    for (x1, y1, x2, y2) in lines {
+        let cx = cell((x1 + x2) / 2.0, min_x, width);
+        let cy = cell((y1 + y2) / 2.0, min_y, height);
+
+        density[cy * DENSITY_GRID + cx] += (x2 - x1).hypot(y2 - y1);
+    }
+
+    let mean = density.iter().sum::<f64>() / density.len() as f64;
+
+    if mean <= 0.0 {
+        return 0.0;
+    }
+
+    let variance =
+        density.iter().map(|d| (d - mean) * (d - mean)).sum::<f64>() / density.len() as f64;
+
+    variance.sqrt() / mean
+}
+
+/// Returns the proportion of `lines` whose reflection in the vertical or horizontal axis
+/// through the middle of `bounds` is (approximately) also in `lines`, whichever is greater.
+fn symmetry(lines: &[(f64, f64, f64, f64)], bounds: (f64, f64, f64, f64)) -> f64 {
+    if lines.is_empty() {
+        return 0.0;
+    }
+
+    let (width, height, min_x, min_y) = bounds;
+    let (centre_x, centre_y) = (min_x + width / 2.0, min_y + height / 2.0);
+
+    // Lines are matched by their (unordered) endpoints, snapped to a grid
+    let tolerance = (width.max(height) / 200.0).max(1e-9);
+    let snap = |x: f64, y: f64| {
+        (
+            (x / tolerance).round() as i64,
+            (y / tolerance).round() as i64,
+        )
+    };
+    let key = |x1: f64, y1: f64, x2: f64, y2: f64| {
+        let (a, b) = (snap(x1, y1), snap(x2, y2));
+        if a <= b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    };
+
+    let keys: HashSet<_> = lines
+        .iter()
+        .map(|(x1, y1, x2, y2)| key(*x1, *y1, *x2, *y2))
+        .collect();
+
+    let matches = |reflect: &dyn Fn(f64, f64) -> (f64, f64)| {
+        lines
+            .iter()
+            .filter(|(x1, y1, x2, y2)| {
+                let (rx1, ry1) = reflect(*x1, *y1);
+                let (rx2, ry2) = reflect(*x2, *y2);
+                keys.contains(&key(rx1, ry1, rx2, ry2))
+            })
+            .count()
+    };
+
+    let vertical = matches(&|x, y| (2.0 * centre_x - x, y));
+    let horizontal = matches(&|x, y| (x, 2.0 * centre_y - y));
+
+    vertical.max(horizontal) as f64 / lines.len() as f64
+}
+
+impl<Q: TurtleContainer + Clone> TurtleRenderer<Q> {
+    /// Scores how interesting the drawing of `system` looks, using some simple heuristics.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .token("+", TurtleAction::Rotate(90.0))?
+    ///     .axiom("F F F F")?;
+    ///
+    /// let (system, renderer) = builder.finish()?;
+    ///
+    /// // A straight line isn't very interesting
+    /// assert_eq!(renderer.score(&system).total(), 0.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn score(&self, system: &LSystem) -> Score {
+        let state = self.compute(system.get_state());
+        let bounds = bounds(&state);
+        let (width, height, _, _) = bounds;
+        let lines: Vec<_> = lines(&state).copied().collect();
+
+        Score {
+            aspect: if width > 0.0 && height > 0.0 {
+                width.min(height) / width.max(height)
+            } else {
+                0.0
+            },
+            density_variation: density_variation(&lines, bounds),
+            symmetry: symmetry(&lines, bounds),
+            intersections: intersections(&lines, bounds),
+            segments: lines.len(),
+        }
+    }
+
+    /// Returns measurements of the drawing of `system`, such as its size, without rendering it.
+    /// See the [`metrics`](crate::metrics) module for an example.
+    pub fn metrics(&self, system: &LSystem) -> Metrics {
+        let state = self.compute(system.get_state());
+        let (width, height, min_x, min_y) = bounds(&state);
+
+        let mut length = 0.0;
+        let mut segments = 0;
+        let mut jumps = 0;
+        let mut points = Vec::new();
+
+        for turtle in state.turtles() {
+            let mut previous = None;
+
+            for &(x1, y1, x2, y2) in turtle.inner().lines() {
+                if previous.is_some() && previous != Some((x1, y1)) {
+                    jumps += 1;
+                }
+
+                length += (x2 - x1).hypot(y2 - y1);
+                segments += 1;
+                points.push((x1, y1));
+                points.push((x2, y2));
+                previous = Some((x2, y2));
+            }
+        }
+
+        Metrics {
+            width,
+            height,
+            min_x,
+            min_y,
+            length,
+            segments,
+            jumps,
+            hull_area: hull_area(points),
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! Plays L-systems as music, by interpreting their tokens as musical actions.
+//!
+//! A [`MusicRenderer`] reads the state of a system from left to right, keeping track of the
+//! current pitch and note length, much like a turtle keeps track of its position and heading.
+//! Pitches are measured in degrees of a [`Scale`], so any system stays in key, and the key
+//! itself can be changed with [`MusicAction::Transpose`].  The result is written as a standard
+//! MIDI file, which can be played or imported by most music software.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::music::{MidiRendererOptionsBuilder, MusicAction, MusicRenderer, Scale};
+//! use dcc_lsystem::renderer::Renderer;
+//! use dcc_lsystem::LSystemBuilder;
+//!
+//! let mut builder = LSystemBuilder::new();
+//! let a = builder.token("A")?;
+//! let b = builder.token("B")?;
+//! let up = builder.token("+")?;
+//! builder.axiom(vec![a])?;
+//! builder.transformation_rule(a, vec![a, up, b])?;
+//! builder.transformation_rule(b, vec![a, a])?;
+//!
+//! let mut system = builder.finish()?;
+//! system.step_by(4);
+//!
+//! let mut renderer = MusicRenderer::new();
+//! renderer
+//!     .token("A", MusicAction::Note)
+//!     .token("B", MusicAction::Rest)
+//!     .token("+", MusicAction::Up(1));
+//!
+//! let options = MidiRendererOptionsBuilder::new()
+//!     .tempo(90.0)
+//!     .scale(Scale::Pentatonic)
+//!     .duration(0.5)
+//!     .build();
+//!
+//! let midi = renderer.render(&system, &options);
+//! assert_eq!(&midi[..4], b"MThd");
+//!
+//! // std::fs::write("music.mid", midi)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+
+use crate::renderer::Renderer;
+use crate::LSystem;
+
+/// The number of MIDI ticks in a beat.
+const TICKS_PER_BEAT: u16 = 480;
+
+/// The actions which can be associated to tokens by a [`MusicRenderer`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MusicAction {
+    Nothing,
+    /// Play the current note for the current length, then move on to the end of the note.
+    Note,
+    /// Wait for the current length without playing anything.
+    Rest,
+    /// Raise the pitch by the given number of degrees of the scale.
+    Up(i32),
+    /// Lower the pitch by the given number of degrees of the scale.
+    Down(i32),
+    /// Multiply the length of notes and rests from now on by the given factor.
+    MultiplyDuration(f64),
+    /// Change key by shifting the scale by the given number of semitones, e.g. `7` moves
+    /// from C major to G major.
+    Transpose(i32),
+    /// Push the current pitch, note length and key onto the stack.
+    Push,
+    /// Pop the pitch, note length and key off the stack.  Popping an empty stack does nothing.
+    Pop,
+}
+
+/// The scales which pitches are measured in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Scale {
+    /// Every semitone.
+    Chromatic,
+    #[default]
+    Major,
+    /// The natural minor scale.
+    Minor,
+    /// The major pentatonic scale, which has no semitones so always sounds pleasant.
+    Pentatonic,
+}
+
+impl Scale {
+    /// Returns the number of semitones each degree of the scale is above the root.
+    pub fn intervals(self) -> &'static [i32] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::Pentatonic => &[0, 2, 4, 7, 9],
+        }
+    }
+
+    /// Returns the number of semitones `degree` is above the root, where degree `0` is the
+    /// root and negative degrees are below it.
+    pub fn semitones(self, degree: i32) -> i32 {
+        let intervals = self.intervals();
+        let length = intervals.len() as i32;
+
+        12 * degree.div_euclid(length) + intervals[degree.rem_euclid(length) as usize]
+    }
+}
+
+pub struct MidiRendererOptionsBuilder {
+    options: MidiRendererOptions,
+}
+
+impl MidiRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: MidiRendererOptions {
+                tempo: 120.0,
+                root: 60,
+                scale: Scale::Major,
+                duration: 1.0,
+                velocity: 100,
+                program: 0,
+            },
+        }
+    }
+
+    /// Set the tempo, in beats per minute.  MIDI files can't hold tempos slower than about
+    /// 3.6 beats per minute, so slower tempos are played at that tempo instead.
+    pub fn tempo(&mut self, tempo: f64) -> &mut Self {
+        self.options.tempo = tempo;
+        self
+    }
+
+    /// Set the MIDI note number of the first pitch, which is also the root of the scale.  The
+    /// default of `60` is middle C.
+    pub fn root(&mut self, root: u8) -> &mut Self {
+        self.options.root = root.min(127);
+        self
+    }
+
+    pub fn scale(&mut self, scale: Scale) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Set the length of the first notes and rests, in beats.
+    pub fn duration(&mut self, duration: f64) -> &mut Self {
+        self.options.duration = duration;
+        self
+    }
+
+    /// Set how hard each note is played, from `1` to `127`.
+    pub fn velocity(&mut self, velocity: u8) -> &mut Self {
+        self.options.velocity = velocity.clamp(1, 127);
+        self
+    }
+
+    /// Set the General MIDI instrument the notes are played on, from `0` (acoustic grand
+    /// piano) to `127`.
+    pub fn program(&mut self, program: u8) -> &mut Self {
+        self.options.program = program.min(127);
+        self
+    }
+
+    pub fn build(&mut self) -> MidiRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for MidiRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MidiRendererOptions {
+    tempo: f64,
+    root: u8,
+    scale: Scale,
+    duration: f64,
+    velocity: u8,
+    program: u8,
+}
+
+impl MidiRendererOptions {
+    pub fn tempo(&self) -> f64 {
+        self.tempo
+    }
+
+    pub fn root(&self) -> u8 {
+        self.root
+    }
+
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    pub fn velocity(&self) -> u8 {
+        self.velocity
+    }
+
+    pub fn program(&self) -> u8 {
+        self.program
+    }
+}
+
+/// A note played by a [`MusicRenderer`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Note {
+    /// The degree of the scale the note is pitched at.
+    pub degree: i32,
+    /// The number of semitones the scale is shifted by, as set by [`MusicAction::Transpose`].
+    pub transpose: i32,
+    /// The beat the note starts on, counting from zero.
+    pub start: f64,
+    /// The length of the note, in beats.
+    pub duration: f64,
+}
+
+/// Plays a system by associating a [`MusicAction`] to each of its tokens.
+///
+/// Tokens without an action do nothing, and actions for tokens which the system doesn't have
+/// are ignored, so the same renderer can play any number of systems.
+#[derive(Debug, Clone, Default)]
+pub struct MusicRenderer {
+    actions: HashMap<String, MusicAction>,
+}
+
+impl MusicRenderer {
+    /// Create a renderer without any actions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Associate the token called `name` with `action`.
+    pub fn token<S: Into<String>>(&mut self, name: S, action: MusicAction) -> &mut Self {
+        self.actions.insert(name.into(), action);
+        self
+    }
+
+    /// Returns the notes played by the current state of `system`, in the order they start,
+    /// with notes and rests which start `duration` beats long.
+    pub fn notes(&self, system: &LSystem, duration: f64) -> Vec<Note> {
+        let actions: HashMap<_, _> = self
+            .actions
+            .iter()
+            .filter_map(|(name, action)| Some((system.token_id(name)?, *action)))
+            .collect();
+
+        let mut notes = Vec::new();
+        let mut stack = Vec::new();
+        let mut degree = 0;
+        let mut transpose = 0;
+        let mut duration = duration;
+        let mut time = 0.0;
+
+        for token in system.get_state() {
+            match actions.get(token) {
+                Some(MusicAction::Note) => {
+                    notes.push(Note {
+                        degree,
+                        transpose,
+                        start: time,
+                        duration,
+                    });
+                    time += duration;
+                }
+                Some(MusicAction::Rest) => time += duration,
+                Some(MusicAction::Up(degrees)) => degree += degrees,
+                Some(MusicAction::Down(degrees)) => degree -= degrees,
+                Some(MusicAction::MultiplyDuration(factor)) => duration *= factor,
+                Some(MusicAction::Transpose(semitones)) => transpose += semitones,
+                Some(MusicAction::Push) => stack.push((degree, duration, transpose)),
+                Some(MusicAction::Pop) => {
+                    if let Some((d, length, t)) = stack.pop() {
+                        degree = d;
+                        duration = length;
+                        transpose = t;
+                    }
+                }
+                Some(MusicAction::Nothing) | None => {}
+            }
+        }
+
+        notes
+    }
+}
+
+/// Appends `value` to `bytes` as a MIDI variable length quantity.
+fn write_varint(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut value = value >> 7;
+
+    while value > 0 {
+        groups.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.extend(groups.iter().rev());
+}
+
+impl Renderer<MidiRendererOptions> for MusicRenderer {
+    /// A standard MIDI file with a single track.
+    type Output = Vec<u8>;
+
+    fn render(&self, system: &LSystem, options: &MidiRendererOptions) -> Self::Output {
+        let ticks = |beats: f64| (beats.max(0.0) * f64::from(TICKS_PER_BEAT)).round() as u32;
+
+        // Each event is `(tick, status, key)`, and note offs sort before note ons at the
+        // same tick so that repeated notes aren't cut short
+        let mut events = Vec::new();
+
+        for note in self.notes(system, options.duration) {
+            let key =
+                i32::from(options.root) + note.transpose + options.scale.semitones(note.degree);
+            let start = ticks(note.start);
+            let end = ticks(note.start + note.duration).max(start + 1);
+
+            // Notes outside the range of MIDI can't be played
+            if (0..=127).contains(&key) {
+                events.push((start, 0x90, key as u8));
+                events.push((end, 0x80, key as u8));
+            }
+        }
+
+        events.sort_by_key(|&(tick, status, _)| (tick, status));
+
+        let mut track = Vec::new();
+
+        // The tempo, in microseconds per beat, which has to fit in three bytes
+        let tempo = (60_000_000.0 / options.tempo)
+            .round()
+            .clamp(1.0, f64::from(0x00ff_ffff)) as u32;
+        track.extend([0x00, 0xff, 0x51, 0x03]);
+        track.extend(&tempo.to_be_bytes()[1..]);
+
+        track.extend([0x00, 0xc0, options.program]);
+
+        let mut now = 0;
+
+        for (tick, status, key) in events {
+            write_varint(&mut track, tick - now);
+            track.extend([status, key, options.velocity]);
+            now = tick;
+        }
+
+        // End of track
+        track.extend([0x00, 0xff, 0x2f, 0x00]);
+
+        let mut bytes = Vec::new();
+
+        // A format 0 file, with a single track
+        bytes.extend(b"MThd");
+        bytes.extend(6u32.to_be_bytes());
+        bytes.extend(0u16.to_be_bytes());
+        bytes.extend(1u16.to_be_bytes());
+        bytes.extend(TICKS_PER_BEAT.to_be_bytes());
+
+        bytes.extend(b"MTrk");
+        bytes.extend((track.len() as u32).to_be_bytes());
+        bytes.extend(track);
+
+        bytes
+    }
+}
@@ -0,0 +1,106 @@
+//! Colormaps as plain functions from a value `t` in `[0, 1]` to a color.
+//!
+//! Each function samples the matching [`Colormap`], so they can be passed anywhere a
+//! `fn(f64) -> Rgb<u8>` is wanted.  Together with [`SymbolContext`] they make it easy to color
+//! a rendering along its length or by how deeply nested each branch is.
+//!
+//! [`SymbolContext`]: crate::renderer::SymbolContext
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::palette;
+//! use dcc_lsystem::renderer::TurtleRenderer;
+//! use dcc_lsystem::turtle::{MovingTurtle, TurtleLSystemState};
+//! use dcc_lsystem::LSystemBuilder;
+//!
+//! let mut builder = LSystemBuilder::new();
+//! let f = builder.token("F")?;
+//! builder.axiom(vec![f, f, f])?;
+//! let system = builder.finish()?;
+//!
+//! let mut renderer = TurtleRenderer::new(TurtleLSystemState::new());
+//!
+//! // Color the path from the start of inferno to the end
+//! renderer.register_with_context(f, |state, context| {
+//!     let turtle = state.turtle_mut();
+//!
+//!     turtle.inner_mut().style_mut().color = Some(palette::inferno(context.progress()));
+//!     turtle.forward(10.0);
+//! });
+//! # Ok(())
+//! # }
+//! ```
+use image::Rgb;
+
+use crate::color::{interpolate, Colormap};
+
+/// The perceptually uniform blue-green-yellow map from matplotlib.
+pub fn viridis(t: f64) -> Rgb<u8> {
+    Colormap::Viridis.sample(t)
+}
+
+/// The perceptually uniform black-purple-cream map from matplotlib.
+pub fn magma(t: f64) -> Rgb<u8> {
+    Colormap::Magma.sample(t)
+}
+
+/// The perceptually uniform black-purple-yellow map from matplotlib.
+pub fn inferno(t: f64) -> Rgb<u8> {
+    Colormap::Inferno.sample(t)
+}
+
+/// Google's improved rainbow map.
+pub fn turbo(t: f64) -> Rgb<u8> {
+    Colormap::Turbo.sample(t)
+}
+
+/// Every fully saturated hue in turn, from red through green and blue to magenta.
+pub fn rainbow(t: f64) -> Rgb<u8> {
+    Colormap::Rainbow.sample(t)
+}
+
+/// A linear ramp from black to white.
+pub fn grayscale(t: f64) -> Rgb<u8> {
+    Colormap::Grayscale.sample(t)
+}
+
+/// Returns a colormap which passes through each of `stops`, given as a position in `[0, 1]`
+/// and the color at that position.  Values before the first stop or after the last take the
+/// color of the nearest stop.  With no stops every value is black.
+///
+/// # Example
+/// ```rust
+/// use dcc_lsystem::palette;
+/// use image::Rgb;
+///
+/// let sunset = palette::custom(&[
+///     (0.0, Rgb([40, 0, 80])),
+///     (0.8, Rgb([240, 80, 0])),
+///     (1.0, Rgb([255, 220, 0])),
+/// ]);
+///
+/// assert_eq!(sunset(0.4), Rgb([140, 40, 40]));
+/// assert_eq!(sunset(0.9), Rgb([248, 150, 0]));
+/// ```
+pub fn custom(stops: &[(f64, Rgb<u8>)]) -> impl Fn(f64) -> Rgb<u8> {
+    let mut stops = stops.to_vec();
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    move |t| {
+        let upper = stops.iter().position(|&(position, _)| position > t);
+
+        match upper {
+            _ if stops.is_empty() => Rgb([0, 0, 0]),
+            Some(0) => stops[0].1,
+            None => stops[stops.len() - 1].1,
+            Some(upper) => {
+                let (start, lower) = stops[upper - 1];
+                let (end, upper) = stops[upper];
+
+                interpolate(&[lower.0, upper.0], (t - start) / (end - start))
+            }
+        }
+    }
+}
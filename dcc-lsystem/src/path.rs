@@ -0,0 +1,116 @@
+//! Converts turtle output into a [`kurbo::BezPath`], for use with other 2D graphics libraries.
+//!
+//! Each run of connected visible lines becomes a subpath.  Consecutive lines pointing in the
+//! same direction are merged into a single path element, so that (for example) `F F F` produces
+//! one `LineTo` rather than three.
+//!
+//! This module requires the `kurbo` feature.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::renderer::{PathRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .axiom("F F + F F")?;
+//!
+//! let (system, renderer) = builder.finish()?;
+//!
+//! let path = renderer.render(&system, &PathRendererOptionsBuilder::new().build());
+//!
+//! // One move, followed by a line for each side
+//! assert_eq!(path.elements().len(), 3);
+//! # Ok(())
+//! # }
+//! ```
+use kurbo::{BezPath, Point};
+
+use crate::renderer::{merge_collinear, polylines, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+pub struct PathRendererOptionsBuilder {
+    options: PathRendererOptions,
+}
+
+impl PathRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: PathRendererOptions {
+                scale: 1.0,
+                merge_collinear: true,
+            },
+        }
+    }
+
+    /// Set the length of a single turtle unit in the path's coordinate space.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Merge consecutive lines pointing in the same direction into a single line.
+    pub fn merge_collinear(&mut self, merge_collinear: bool) -> &mut Self {
+        self.options.merge_collinear = merge_collinear;
+        self
+    }
+
+    pub fn build(&mut self) -> PathRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for PathRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathRendererOptions {
+    scale: f64,
+    merge_collinear: bool,
+}
+
+impl PathRendererOptions {
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn merge_collinear(&self) -> bool {
+        self.merge_collinear
+    }
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<PathRendererOptions> for TurtleRenderer<Q> {
+    type Output = BezPath;
+
+    fn render(&self, system: &LSystem, options: &PathRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+        let mut bez_path = BezPath::new();
+
+        for (_, path) in polylines(&state) {
+            let path = if options.merge_collinear {
+                merge_collinear(path)
+            } else {
+                path
+            };
+
+            let point = |(x, y): (f64, f64)| Point::new(x * options.scale, y * options.scale);
+
+            bez_path.move_to(point(path[0]));
+            for &vertex in &path[1..] {
+                bez_path.line_to(point(vertex));
+            }
+        }
+
+        bez_path
+    }
+}
@@ -0,0 +1,228 @@
+//! Renders turtle output as HPGL or G-code toolpaths for pen plotters.
+//!
+//! Connected lines are drawn without lifting the pen, and the pen is raised to travel between
+//! lines which don't meet (or which have different colors, so that the pen can be changed).
+//! The drawing is moved so that its lower left corner is at the origin.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::plotter::PlotterFormat;
+//! use dcc_lsystem::renderer::{PlotterRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .token("-", TurtleAction::Rotate(-90.0))?
+//!     .axiom("F")?
+//!     .rule("F => F + F - F - F + F")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(3);
+//!
+//! let options = PlotterRendererOptionsBuilder::new()
+//!     .format(PlotterFormat::GCode)
+//!     .scale(0.5)
+//!     .feed_rate(1500.0)
+//!     .build();
+//!
+//! let gcode = renderer.render(&system, &options);
+//!
+//! // The Koch curve is a single connected line, so the pen only goes down once
+//! assert_eq!(gcode.matches("Z0.000").count(), 1);
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Write;
+
+use crate::renderer::{bounds, polylines, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+/// The number of HPGL plotter units in a millimetre.
+const HPGL_UNITS_PER_MM: f64 = 40.0;
+
+/// The toolpath formats supported by the plotter renderer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlotterFormat {
+    /// Hewlett-Packard Graphics Language.
+    Hpgl,
+    /// G-code, raising and lowering the pen along the Z axis.
+    GCode,
+}
+
+pub struct PlotterRendererOptionsBuilder {
+    options: PlotterRendererOptions,
+}
+
+impl PlotterRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: PlotterRendererOptions {
+                format: PlotterFormat::Hpgl,
+                scale: 1.0,
+                feed_rate: 1000.0,
+                travel_rate: 3000.0,
+                pen_up: 5.0,
+                pen_down: 0.0,
+            },
+        }
+    }
+
+    pub fn format(&mut self, format: PlotterFormat) -> &mut Self {
+        self.options.format = format;
+        self
+    }
+
+    /// Set the length, in millimetres, of a single turtle unit.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Set the speed the pen draws at, in millimetres per minute.  Only used by G-code.
+    pub fn feed_rate(&mut self, feed_rate: f64) -> &mut Self {
+        self.options.feed_rate = feed_rate;
+        self
+    }
+
+    /// Set the speed the pen travels at while raised, in millimetres per minute.  Only used
+    /// by G-code.
+    pub fn travel_rate(&mut self, travel_rate: f64) -> &mut Self {
+        self.options.travel_rate = travel_rate;
+        self
+    }
+
+    /// Set the heights, in millimetres, of the raised and lowered pen.  Only used by G-code.
+    pub fn pen_heights(&mut self, pen_up: f64, pen_down: f64) -> &mut Self {
+        self.options.pen_up = pen_up;
+        self.options.pen_down = pen_down;
+        self
+    }
+
+    pub fn build(&mut self) -> PlotterRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for PlotterRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlotterRendererOptions {
+    format: PlotterFormat,
+    scale: f64,
+    feed_rate: f64,
+    travel_rate: f64,
+    pen_up: f64,
+    pen_down: f64,
+}
+
+impl PlotterRendererOptions {
+    pub fn format(&self) -> PlotterFormat {
+        self.format
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn feed_rate(&self) -> f64 {
+        self.feed_rate
+    }
+
+    pub fn travel_rate(&self) -> f64 {
+        self.travel_rate
+    }
+
+    pub fn pen_up(&self) -> f64 {
+        self.pen_up
+    }
+
+    pub fn pen_down(&self) -> f64 {
+        self.pen_down
+    }
+}
+
+/// Writes `paths`, in millimetres, as HPGL.
+fn hpgl(paths: &[Vec<(f64, f64)>]) -> String {
+    let unit = |value: f64| (value * HPGL_UNITS_PER_MM).round() as i64;
+    let mut output = String::from("IN;SP1;\n");
+
+    // unwrap (here and below): writing to a String never fails
+    for path in paths {
+        let (x, y) = path[0];
+        writeln!(output, "PU{},{};", unit(x), unit(y)).unwrap();
+
+        let points: Vec<_> = path[1..]
+            .iter()
+            .map(|(x, y)| format!("{},{}", unit(*x), unit(*y)))
+            .collect();
+        writeln!(output, "PD{};", points.join(",")).unwrap();
+    }
+
+    output.push_str("PU0,0;SP0;\n");
+    output
+}
+
+/// Writes `paths`, in millimetres, as G-code.
+fn gcode(paths: &[Vec<(f64, f64)>], options: &PlotterRendererOptions) -> String {
+    let mut output = String::new();
+
+    writeln!(output, "G21 ; millimetres").unwrap();
+    writeln!(output, "G90 ; absolute coordinates").unwrap();
+    writeln!(output, "G0 Z{:.3}", options.pen_up).unwrap();
+
+    for path in paths {
+        let (x, y) = path[0];
+        writeln!(output, "G0 X{:.3} Y{:.3} F{:.0}", x, y, options.travel_rate).unwrap();
+        writeln!(
+            output,
+            "G1 Z{:.3} F{:.0}",
+            options.pen_down, options.feed_rate
+        )
+        .unwrap();
+
+        for (x, y) in &path[1..] {
+            writeln!(output, "G1 X{:.3} Y{:.3}", x, y).unwrap();
+        }
+
+        writeln!(output, "G0 Z{:.3}", options.pen_up).unwrap();
+    }
+
+    writeln!(output, "G0 X0 Y0 F{:.0}", options.travel_rate).unwrap();
+    output
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<PlotterRendererOptions> for TurtleRenderer<Q> {
+    type Output = String;
+
+    fn render(&self, system: &LSystem, options: &PlotterRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+        let (_, _, min_x, min_y) = bounds(&state);
+
+        // Move the drawing to the origin, and convert it to millimetres
+        let paths: Vec<Vec<_>> = polylines(&state)
+            .into_iter()
+            .map(|(_, path)| {
+                path.into_iter()
+                    .map(|(x, y)| ((x - min_x) * options.scale, (y - min_y) * options.scale))
+                    .collect()
+            })
+            .collect();
+
+        match options.format {
+            PlotterFormat::Hpgl => hpgl(&paths),
+            PlotterFormat::GCode => gcode(&paths, options),
+        }
+    }
+}
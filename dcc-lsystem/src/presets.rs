@@ -0,0 +1,206 @@
+//! Ready-made turtle systems for some well known fractals.
+//!
+//! Each function returns a [`TurtleLSystemBuilder`] with the tokens, axiom and rules of the
+//! system already registered, so it can be adjusted further before being finished.  Every
+//! system moves the turtle forwards 10 units at a time; use
+//! [`TurtleLSystemBuilder::scale`] to draw it larger or smaller.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::presets;
+//!
+//! let mut builder = presets::koch_curve();
+//! builder.scale(3.0);
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(2);
+//!
+//! assert_eq!(renderer.metrics(&system).segments, 25);
+//! # Ok(())
+//! # }
+//! ```
+use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+use crate::LSystemError;
+
+/// The distance the turtle moves forwards for each line of a preset.
+const DISTANCE: f64 = 10.0;
+
+/// Creates a builder and sets it up with `setup`.
+fn preset<F>(setup: F) -> TurtleLSystemBuilder
+where
+    F: FnOnce(&mut TurtleLSystemBuilder) -> Result<(), LSystemError>,
+{
+    let mut builder = TurtleLSystemBuilder::new();
+
+    // The presets only use symbols which the builder accepts
+    setup(&mut builder).expect("preset systems are valid");
+
+    builder
+}
+
+/// The quadratic Koch curve, with axiom `F` and rule `F => F + F - F - F + F`.
+pub fn koch_curve() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("F", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(90.0))?
+            .token("-", TurtleAction::Rotate(-90.0))?
+            .axiom("F")?
+            .rule("F => F + F - F - F + F")?;
+        Ok(())
+    })
+}
+
+/// The Koch snowflake, which puts three triangular Koch curves around a triangle.
+pub fn koch_snowflake() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("F", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(60.0))?
+            .token("-", TurtleAction::Rotate(-60.0))?
+            .axiom("F - - F - - F")?
+            .rule("F => F + F - - F + F")?;
+        Ok(())
+    })
+}
+
+/// The Heighway dragon curve.
+pub fn dragon_curve() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("X", TurtleAction::Nothing)?
+            .token("Y", TurtleAction::Nothing)?
+            .token("F", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(-90.0))?
+            .token("-", TurtleAction::Rotate(90.0))?
+            .axiom("F X")?
+            .rule("X => X + Y F +")?
+            .rule("Y => - F X - Y")?;
+        Ok(())
+    })
+}
+
+/// The Sierpinski triangle, drawn as a triangle of smaller triangles.
+pub fn sierpinski_triangle() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("F", TurtleAction::Forward(DISTANCE))?
+            .token("G", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(120.0))?
+            .token("-", TurtleAction::Rotate(-120.0))?
+            .axiom("F - G - G")?
+            .rule("F => F - G + F + G - F")?
+            .rule("G => G G")?;
+        Ok(())
+    })
+}
+
+/// The Sierpinski arrowhead curve, which approximates the Sierpinski triangle with a single
+/// line.
+pub fn sierpinski_arrowhead() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("A", TurtleAction::Forward(DISTANCE))?
+            .token("B", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(60.0))?
+            .token("-", TurtleAction::Rotate(-60.0))?
+            .axiom("A")?
+            .rule("A => B - A - B")?
+            .rule("B => A + B + A")?;
+        Ok(())
+    })
+}
+
+/// A branching plant, leaning to one side.
+pub fn fractal_plant() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("X", TurtleAction::Nothing)?
+            .token("F", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(25.0))?
+            .token("-", TurtleAction::Rotate(-25.0))?
+            .token("[", TurtleAction::Push)?
+            .token("]", TurtleAction::Pop)?
+            .axiom("X")?
+            .rule("X => F + [ [ X ] - X ] - F [ - F X ] + X")?
+            .rule("F => F F")?
+            .rotate(70);
+        Ok(())
+    })
+}
+
+/// A binary tree, which splits in two at the end of every branch.
+pub fn fractal_binary_tree() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("0", TurtleAction::Forward(DISTANCE))?
+            .token("1", TurtleAction::Forward(DISTANCE))?
+            .token("L", TurtleAction::Rotate(45.0))?
+            .token("R", TurtleAction::Rotate(-45.0))?
+            .token("[", TurtleAction::Push)?
+            .token("]", TurtleAction::Pop)?
+            .axiom("0")?
+            .rule("1 => 1 1")?
+            .rule("0 => 1 [ L 0 ] R 0")?
+            .rotate(90);
+        Ok(())
+    })
+}
+
+/// The Hilbert curve, which fills a square.
+pub fn hilbert_curve() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("A", TurtleAction::Nothing)?
+            .token("B", TurtleAction::Nothing)?
+            .token("F", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(90.0))?
+            .token("-", TurtleAction::Rotate(-90.0))?
+            .axiom("A")?
+            .rule("A => + B F - A F A - F B +")?
+            .rule("B => - A F + B F B + F A -")?;
+        Ok(())
+    })
+}
+
+/// The Gosper curve, which fills a hexagonal region.
+pub fn gosper_curve() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("A", TurtleAction::Forward(DISTANCE))?
+            .token("B", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(60.0))?
+            .token("-", TurtleAction::Rotate(-60.0))?
+            .axiom("A")?
+            .rule("A => A - B - - B + A + + A A + B -")?
+            .rule("B => + A - B B - - B - A + + A + B")?;
+        Ok(())
+    })
+}
+
+/// Penrose's P3 tiling of thick and thin rhombuses, drawn outwards from a star of five
+/// rhombuses.  The edges are only drawn by `F`, which is removed each step, so the tiling is
+/// empty until the system has been stepped at least once.
+pub fn penrose_p3() -> TurtleLSystemBuilder {
+    preset(|builder| {
+        builder
+            .token("M", TurtleAction::Nothing)?
+            .token("N", TurtleAction::Nothing)?
+            .token("O", TurtleAction::Nothing)?
+            .token("P", TurtleAction::Nothing)?
+            .token("F", TurtleAction::Forward(DISTANCE))?
+            .token("+", TurtleAction::Rotate(36.0))?
+            .token("-", TurtleAction::Rotate(-36.0))?
+            .token("[", TurtleAction::Push)?
+            .token("]", TurtleAction::Pop)?
+            .axiom("[ N ] + + [ N ] + + [ N ] + + [ N ] + + [ N ]")?
+            .rule("M => O F + + P F - - - - N F [ - O F - - - - M F ] + +")?
+            .rule("N => + O F - - P F [ - - - M F - - N F ] +")?
+            .rule("O => - M F + + N F [ + + + O F + + P F ] -")?
+            .rule("P => - - O F + + + + M F [ + P F + + + + N F ] - - N F")?
+            .rule("F =>")?;
+        Ok(())
+    })
+}
@@ -0,0 +1,383 @@
+//! An interactive window for previewing a system while tuning its grammar.
+//!
+//! The window starts out showing the whole drawing, and can be navigated with the following
+//! controls:
+//!
+//! | Input                       | Action                        |
+//! |-----------------------------|-------------------------------|
+//! | `Space` or `N`              | Step the system               |
+//! | `R`                         | Reset the system to its axiom |
+//! | Arrow keys or mouse drag    | Pan                           |
+//! | `+`/`-` or the scroll wheel | Zoom                          |
+//! | `0`                         | Fit the drawing to the window |
+//! | `Escape`                    | Close the window              |
+//!
+//! This module requires the `preview` feature.
+//!
+//! # Example
+//! ```rust,no_run
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::renderer::PreviewOptionsBuilder;
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .token("-", TurtleAction::Rotate(-90.0))?
+//!     .axiom("F")?
+//!     .rule("F => F + F - F - F + F")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//!
+//! let options = PreviewOptionsBuilder::new().title("Koch curve").build();
+//! renderer.preview(&mut system, &options)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::time::Duration;
+
+use image::{ImageBuffer, Rgb};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+
+use crate::image::{draw_line_mut, fill_mut};
+use crate::renderer::{bounds, segments, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::{LSystem, LSystemError};
+
+/// How far, in pixels, a single press of an arrow key moves the view.
+const PAN_STEP: f64 = 40.0;
+
+/// How much a single press of `+` or `-` zooms the view.
+const ZOOM_STEP: f64 = 1.25;
+
+pub struct PreviewOptionsBuilder {
+    options: PreviewOptions,
+}
+
+impl PreviewOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: PreviewOptions {
+                title: String::from("dcc-lsystem"),
+                width: 800,
+                height: 600,
+                padding: 20,
+                thickness: 2.0,
+                fill_color: Rgb([255u8, 255u8, 255u8]),
+                line_color: Rgb([0u8, 0u8, 0u8]),
+            },
+        }
+    }
+
+    pub fn title<T: Into<String>>(&mut self, title: T) -> &mut Self {
+        self.options.title = title.into();
+        self
+    }
+
+    /// Set the initial size of the window, in pixels.
+    pub fn size(&mut self, width: usize, height: usize) -> &mut Self {
+        self.options.width = width;
+        self.options.height = height;
+        self
+    }
+
+    /// Set the space left around the drawing when it is fitted to the window.
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
+
+    /// Set the thickness of lines without their own thickness.  Lines keep the same thickness
+    /// on screen however far the view is zoomed.
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
+
+    pub fn fill_color(&mut self, fill_color: Rgb<u8>) -> &mut Self {
+        self.options.fill_color = fill_color;
+        self
+    }
+
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
+
+    pub fn build(&mut self) -> PreviewOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for PreviewOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PreviewOptions {
+    title: String,
+    width: usize,
+    height: usize,
+    padding: u32,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    fill_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+}
+
+impl PreviewOptions {
+    pub fn title(&self) -> &String {
+        &self.title
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn fill_color(&self) -> Rgb<u8> {
+        self.fill_color
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+}
+
+/// The part of the drawing shown in the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct View {
+    /// The number of pixels per turtle unit.
+    scale: f64,
+    /// The turtle coordinates shown in the middle of the window.
+    centre: (f64, f64),
+}
+
+impl View {
+    /// Returns a view showing the whole of the region given by `bounds`.
+    fn fit(
+        (turtle_width, turtle_height, min_x, min_y): (f64, f64, f64, f64),
+        width: usize,
+        height: usize,
+        padding: u32,
+    ) -> Self {
+        let padding = 2.0 * padding as f64;
+        let available_width = (width as f64 - padding).max(1.0);
+        let available_height = (height as f64 - padding).max(1.0);
+
+        let scale = (available_width / turtle_width).min(available_height / turtle_height);
+
+        Self {
+            // A drawing with no size (such as a single point) is shown unscaled
+            scale: if scale.is_finite() { scale } else { 1.0 },
+            centre: (min_x + turtle_width / 2.0, min_y + turtle_height / 2.0),
+        }
+    }
+
+    /// Converts turtle coordinates into window coordinates.
+    fn project(&self, (x, y): (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        (
+            width as f64 / 2.0 + (x - self.centre.0) * self.scale,
+            height as f64 / 2.0 - (y - self.centre.1) * self.scale,
+        )
+    }
+
+    /// Moves the drawing by the given number of pixels.
+    fn pan(&mut self, dx: f64, dy: f64) {
+        self.centre.0 -= dx / self.scale;
+        self.centre.1 += dy / self.scale;
+    }
+
+    /// Zooms in (for factors above `1`) or out, keeping the middle of the window fixed.
+    fn zoom(&mut self, factor: f64) {
+        self.scale *= factor;
+    }
+}
+
+/// Draws `state`, as seen through `view`, in the pixel format used by `minifb`.
+fn draw<Q: TurtleContainer>(
+    state: &Q,
+    view: &View,
+    width: usize,
+    height: usize,
+    options: &PreviewOptions,
+) -> Vec<u32> {
+    let mut buffer = ImageBuffer::new(width as u32, height as u32);
+    fill_mut(&mut buffer, options.fill_color);
+
+    for ((x1, y1, x2, y2), style) in segments(state) {
+        let thickness = style.resolved_thickness(options.thickness);
+
+        if thickness <= 0.0 {
+            continue;
+        }
+
+        let (sx1, sy1) = view.project((*x1, *y1), width, height);
+        let (sx2, sy2) = view.project((*x2, *y2), width, height);
+
+        // Skip lines which are entirely outside the window
+        if sx1.max(sx2) < -thickness
+            || sy1.max(sy2) < -thickness
+            || sx1.min(sx2) > width as f64 + thickness
+            || sy1.min(sy2) > height as f64 + thickness
+        {
+            continue;
+        }
+
+        draw_line_mut(
+            &mut buffer,
+            sx1,
+            sy1,
+            sx2,
+            sy2,
+            thickness,
+            style.color.unwrap_or(options.line_color),
+        );
+    }
+
+    buffer
+        .pixels()
+        .map(|Rgb([r, g, b])| u32::from(*r) << 16 | u32::from(*g) << 8 | u32::from(*b))
+        .collect()
+}
+
+impl<Q: TurtleContainer + Clone> TurtleRenderer<Q> {
+    /// Opens a window showing `system`, which can be stepped, panned and zoomed interactively.
+    /// See the [`preview`](crate::preview) module for the controls.
+    ///
+    /// This blocks until the window is closed, leaving `system` at the last step shown.
+    pub fn preview(
+        &self,
+        system: &mut LSystem,
+        options: &PreviewOptions,
+    ) -> Result<(), LSystemError> {
+        let mut window = Window::new(
+            &options.title,
+            options.width,
+            options.height,
+            WindowOptions {
+                resize: true,
+                ..WindowOptions::default()
+            },
+        )?;
+        window.limit_update_rate(Some(Duration::from_micros(16600)));
+
+        let mut state = self.compute(system.get_state());
+        let mut size = window.get_size();
+        let mut view = View::fit(bounds(&state), size.0, size.1, options.padding);
+
+        let mut dirty = true;
+        let mut drag: Option<(f32, f32)> = None;
+
+        while window.is_open() && !window.is_key_down(Key::Escape) {
+            let mut stepped = false;
+
+            for key in window.get_keys_pressed(KeyRepeat::Yes) {
+                match key {
+                    Key::Left => view.pan(PAN_STEP, 0.0),
+                    Key::Right => view.pan(-PAN_STEP, 0.0),
+                    Key::Up => view.pan(0.0, PAN_STEP),
+                    Key::Down => view.pan(0.0, -PAN_STEP),
+                    Key::Equal | Key::NumPadPlus => view.zoom(ZOOM_STEP),
+                    Key::Minus | Key::NumPadMinus => view.zoom(1.0 / ZOOM_STEP),
+                    Key::Key0 | Key::NumPad0 => {
+                        view = View::fit(bounds(&state), size.0, size.1, options.padding)
+                    }
+                    _ => continue,
+                }
+
+                dirty = true;
+            }
+
+            // Stepping can be expensive, so holding a key down doesn't repeat it
+            for key in window.get_keys_pressed(KeyRepeat::No) {
+                match key {
+                    Key::Space | Key::N => system.step(),
+                    Key::R => system.reset(),
+                    _ => continue,
+                }
+
+                stepped = true;
+            }
+
+            if let Some((_, scroll)) = window.get_scroll_wheel() {
+                view.zoom(1.1f64.powf(scroll as f64));
+                dirty = true;
+            }
+
+            if window.get_mouse_down(MouseButton::Left) {
+                let position = window.get_mouse_pos(MouseMode::Pass);
+
+                if let (Some((x, y)), Some((previous_x, previous_y))) = (position, drag) {
+                    view.pan((x - previous_x) as f64, (y - previous_y) as f64);
+                    dirty = true;
+                }
+
+                drag = position;
+            } else {
+                drag = None;
+            }
+
+            if stepped {
+                state = self.compute(system.get_state());
+                window.set_title(&format!("{} (step {})", options.title, system.steps()));
+                dirty = true;
+            }
+
+            if window.get_size() != size {
+                size = window.get_size();
+                dirty = true;
+            }
+
+            // A minimised window has no pixels to draw
+            if dirty && size.0 > 0 && size.1 > 0 {
+                let pixels = draw(&state, &view, size.0, size.1, options);
+                window.update_with_buffer(&pixels, size.0, size.1)?;
+                dirty = false;
+            } else {
+                window.update();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_fits_and_pans_the_drawing() {
+        // A 100 x 50 drawing in a 240 x 240 window with 20 pixels of padding
+        let mut view = View::fit((100.0, 50.0, -50.0, 0.0), 240, 240, 20);
+
+        assert_eq!(view.scale, 2.0);
+        assert_eq!(view.project((-50.0, 0.0), 240, 240), (20.0, 170.0));
+        assert_eq!(view.project((50.0, 50.0), 240, 240), (220.0, 70.0));
+
+        view.pan(10.0, -10.0);
+        assert_eq!(view.project((0.0, 25.0), 240, 240), (130.0, 110.0));
+
+        view.zoom(2.0);
+        assert_eq!(view.project((0.0, 25.0), 240, 240), (140.0, 100.0));
+    }
+}
@@ -0,0 +1,87 @@
+//! Reports the progress of long renders, such as animations.
+//!
+//! Renders which draw a system one line at a time tell a [`RenderProgress`] how far they've
+//! got, so that progress can be shown however suits the application.  A progress bar for the
+//! terminal is provided by [`TerminalProgress`], which requires the `terminal_progress` feature.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//!
+//! use dcc_lsystem::progress::RenderProgress;
+//! use dcc_lsystem::renderer::{FrameSequenceRendererOptionsBuilder, Renderer};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! // Remembers how many lines have been drawn, for another thread to display
+//! struct Lines(Arc<AtomicUsize>);
+//!
+//! impl RenderProgress for Lines {
+//!     fn on_progress(&self, done: usize, _total: usize) {
+//!         self.0.store(done, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .axiom("F + F + F")?;
+//!
+//! let (system, renderer) = builder.finish()?;
+//!
+//! let lines = Arc::new(AtomicUsize::new(0));
+//! let options = FrameSequenceRendererOptionsBuilder::new()
+//!     .progress(Lines(lines.clone()))
+//!     .build();
+//!
+//! renderer.render(&system, &options)?;
+//! assert_eq!(lines.load(Ordering::Relaxed), 3);
+//! # Ok(())
+//! # }
+//! ```
+
+/// Receives updates on the progress of a render.
+///
+/// Reporters are shared by every render made with the same options, and may be used from
+/// other threads, so they're updated through shared references.
+pub trait RenderProgress: Send + Sync {
+    /// Called as the render progresses, with the amount of work `done` out of `total`.
+    fn on_progress(&self, done: usize, total: usize);
+
+    /// Called once the render has finished.
+    fn finished(&self) {}
+}
+
+/// Shows the progress of renders as a progress bar in the terminal.
+#[cfg(feature = "terminal_progress")]
+#[derive(Default)]
+pub struct TerminalProgress {
+    bar: std::sync::Mutex<Option<pbr::ProgressBar<std::io::Stdout>>>,
+}
+
+#[cfg(feature = "terminal_progress")]
+impl TerminalProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "terminal_progress")]
+impl RenderProgress for TerminalProgress {
+    fn on_progress(&self, done: usize, total: usize) {
+        if let Ok(mut bar) = self.bar.lock() {
+            // The bar is created by the first update of each render, once the total is known
+            bar.get_or_insert_with(|| pbr::ProgressBar::new(total as u64))
+                .set(done as u64);
+        }
+    }
+
+    fn finished(&self) {
+        if let Some(mut bar) = self.bar.lock().ok().and_then(|mut bar| bar.take()) {
+            bar.finish();
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Bindings for driving L-systems from Python, with `pyo3`.
+//!
+//! Requires the `python` feature.  The bindings are built into an extension module by building
+//! the crate as a `cdylib`, and renaming the library to `dcc_lsystem.so` (or `dcc_lsystem.pyd`
+//! on Windows) somewhere Python can import it from:
+//!
+//! ```text
+//! cargo rustc --release --lib --crate-type cdylib --features python,pyo3/extension-module
+//! cp target/release/libdcc_lsystem.so dcc_lsystem.so
+//! ```
+//!
+//! Segments are returned as lists of `(x1, y1, x2, y2)` tuples, which `numpy.array` turns into
+//! an array with a row for each line, and images are returned as PNG bytes, which PIL can open:
+//!
+//! ```text
+//! import io
+//!
+//! import numpy
+//! from PIL import Image
+//! from dcc_lsystem import TurtleLSystemBuilder
+//!
+//! builder = TurtleLSystemBuilder.from_grammar("""
+//!     F = forward 10
+//!     + = rotate 90
+//!     - = rotate -90
+//!     axiom: F
+//!     F => F+F-F-F+F
+//! """)
+//! system, renderer = builder.finish()
+//! system.step_by(3)
+//!
+//! lines = numpy.array(renderer.segments(system))
+//! image = Image.open(io.BytesIO(renderer.render_png(system, thickness=4.0)))
+//! ```
+//!
+//! Errors returned by the crate are raised as `ValueError`s.
+
+// The methods generated by `pyo3` convert every error into a `PyErr`, even ones which already are
+#![allow(clippy::useless_conversion)]
+
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder, Rgb};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::renderer::{DataRendererOptions, ImageRendererOptionsBuilder, Renderer, TurtleRenderer};
+use crate::turtle::{self, TurtleLSystemBuilder, TurtleLSystemState};
+use crate::{ArenaId, LSystem, LSystemBuilder, LSystemError};
+
+impl From<LSystemError> for PyErr {
+    fn from(e: LSystemError) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+/// Builds an [`LSystem`] from tokens, which Python refers to by their integer ids.
+#[pyclass(name = "LSystemBuilder", module = "dcc_lsystem")]
+#[derive(Default)]
+pub struct PyLSystemBuilder {
+    builder: LSystemBuilder,
+}
+
+#[pymethods]
+impl PyLSystemBuilder {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a system from a grammar in the format accepted by
+    /// [`LSystemBuilder::from_grammar`].
+    #[staticmethod]
+    fn from_grammar(grammar: &str) -> PyResult<Self> {
+        Ok(Self {
+            builder: LSystemBuilder::from_grammar(grammar)?,
+        })
+    }
+
+    /// Registers a token, returning its id.
+    fn token(&mut self, name: String) -> PyResult<usize> {
+        Ok(self.builder.token(name)?.0)
+    }
+
+    fn axiom(&mut self, axiom: Vec<usize>) -> PyResult<()> {
+        Ok(self.builder.axiom(ids(axiom))?)
+    }
+
+    fn transformation_rule(&mut self, predecessor: usize, successor: Vec<usize>) -> PyResult<()> {
+        Ok(self
+            .builder
+            .transformation_rule(ArenaId(predecessor), ids(successor))?)
+    }
+
+    /// Registers a stochastic rule, given as a list of `(weight, successor)` pairs.
+    fn stochastic_rule(
+        &mut self,
+        predecessor: usize,
+        successors: Vec<(f64, Vec<usize>)>,
+    ) -> PyResult<()> {
+        let successors = successors
+            .into_iter()
+            .map(|(weight, successor)| (weight, ids(successor)))
+            .collect();
+
+        Ok(self
+            .builder
+            .stochastic_rule(ArenaId(predecessor), successors)?)
+    }
+
+    fn seed(&mut self, seed: u64) {
+        self.builder.seed(seed);
+    }
+
+    /// Returns the system, leaving the builder untouched so that it can be built again.
+    fn finish(&self) -> PyResult<PyLSystem> {
+        Ok(PyLSystem {
+            system: self.builder.clone().finish()?,
+        })
+    }
+}
+
+/// Converts the token ids passed in from Python.
+fn ids(ids: Vec<usize>) -> Vec<ArenaId> {
+    ids.into_iter().map(ArenaId).collect()
+}
+
+#[pyclass(name = "LSystem", module = "dcc_lsystem")]
+pub struct PyLSystem {
+    system: LSystem,
+}
+
+#[pymethods]
+impl PyLSystem {
+    fn step(&mut self) {
+        self.system.step();
+    }
+
+    fn step_by(&mut self, n: usize) {
+        self.system.step_by(n);
+    }
+
+    fn reset(&mut self) {
+        self.system.reset();
+    }
+
+    /// Returns the number of steps the system has taken.
+    fn steps(&self) -> usize {
+        self.system.steps()
+    }
+
+    /// Returns the current state of the system as a string of tokens.
+    fn render(&self) -> String {
+        self.system.render()
+    }
+
+    /// Returns the ids of the tokens in the current state.
+    fn state(&self) -> Vec<usize> {
+        self.system.get_state().iter().map(|id| id.0).collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.system.state_len()
+    }
+}
+
+/// Builds a turtle system and the renderer that draws it, with actions written as they are in a
+/// [grammar](crate::grammar), e.g. `forward 10`.
+#[pyclass(name = "TurtleLSystemBuilder", module = "dcc_lsystem", unsendable)]
+pub struct PyTurtleLSystemBuilder {
+    builder: TurtleLSystemBuilder,
+}
+
+#[pymethods]
+impl PyTurtleLSystemBuilder {
+    #[new]
+    fn new() -> Self {
+        Self {
+            builder: TurtleLSystemBuilder::new(),
+        }
+    }
+
+    /// Builds a system from a grammar in the format accepted by
+    /// [`TurtleLSystemBuilder::from_grammar`].
+    #[staticmethod]
+    fn from_grammar(grammar: &str) -> PyResult<Self> {
+        Ok(Self {
+            builder: TurtleLSystemBuilder::from_grammar(grammar)?,
+        })
+    }
+
+    fn token(&mut self, name: String, action: &str) -> PyResult<()> {
+        self.builder
+            .token(name, turtle::parse_action_text(action)?)?;
+        Ok(())
+    }
+
+    fn axiom(&mut self, axiom: &str) -> PyResult<()> {
+        self.builder.axiom(axiom)?;
+        Ok(())
+    }
+
+    /// Registers a rule written as in a grammar, e.g. `F => F + F`.
+    fn rule(&mut self, rule: &str) -> PyResult<()> {
+        self.builder.rule(rule)?;
+        Ok(())
+    }
+
+    fn seed(&mut self, seed: u64) {
+        self.builder.seed(seed);
+    }
+
+    fn rotate(&mut self, angle: f64) {
+        self.builder.rotate(angle);
+    }
+
+    fn scale(&mut self, scale: f64) {
+        self.builder.scale(scale);
+    }
+
+    fn initial_heading(&mut self, heading: f64) {
+        self.builder.initial_heading(heading);
+    }
+
+    /// Returns the system and its renderer, leaving the builder untouched so that it can be
+    /// built again.
+    fn finish(&self) -> PyResult<(PyLSystem, PyTurtleRenderer)> {
+        let (system, renderer) = self.builder.build()?;
+
+        Ok((PyLSystem { system }, PyTurtleRenderer { renderer }))
+    }
+}
+
+/// Draws the systems built by a `TurtleLSystemBuilder`.
+#[pyclass(name = "TurtleRenderer", module = "dcc_lsystem", unsendable)]
+pub struct PyTurtleRenderer {
+    renderer: TurtleRenderer<TurtleLSystemState>,
+}
+
+#[pymethods]
+impl PyTurtleRenderer {
+    /// Returns the lines drawn by the current state of `system`, as `(x1, y1, x2, y2)` tuples.
+    fn segments(&self, system: PyRef<'_, PyLSystem>) -> Vec<(f64, f64, f64, f64)> {
+        self.renderer
+            .render(&system.system, &DataRendererOptions::default())
+    }
+
+    /// Draws the current state of `system`, returning the image as PNG bytes.  Colors are
+    /// given as `(red, green, blue)` tuples.
+    #[pyo3(signature = (system, *, padding=None, thickness=None, fill_color=None, line_color=None))]
+    fn render_png<'py>(
+        &self,
+        py: Python<'py>,
+        system: PyRef<'_, PyLSystem>,
+        padding: Option<u32>,
+        thickness: Option<f64>,
+        fill_color: Option<(u8, u8, u8)>,
+        line_color: Option<(u8, u8, u8)>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let mut options = ImageRendererOptionsBuilder::new();
+
+        if let Some(padding) = padding {
+            options.padding(padding);
+        }
+
+        if let Some(thickness) = thickness {
+            options.thickness(thickness);
+        }
+
+        if let Some((red, green, blue)) = fill_color {
+            options.fill_color(Rgb([red, green, blue]));
+        }
+
+        if let Some((red, green, blue)) = line_color {
+            options.line_color(Rgb([red, green, blue]));
+        }
+
+        let buffer = self.renderer.render(&system.system, &options.build());
+        let mut png = Vec::new();
+
+        PngEncoder::new(&mut png)
+            .write_image(
+                buffer.as_raw(),
+                buffer.width(),
+                buffer.height(),
+                ColorType::Rgb8,
+            )
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        Ok(PyBytes::new_bound(py, &png))
+    }
+}
+
+/// The `dcc_lsystem` Python module.
+#[pymodule]
+#[pyo3(name = "dcc_lsystem")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyLSystemBuilder>()?;
+    m.add_class::<PyLSystem>()?;
+    m.add_class::<PyTurtleLSystemBuilder>()?;
+    m.add_class::<PyTurtleRenderer>()?;
+
+    Ok(())
+}
@@ -0,0 +1,285 @@
+//! Color quantization for the GIF pipeline.
+//!
+//! GIF frames are limited to palettes of at most 256 colors.  gifski takes care of this for us
+//! by default, but when the user wants control over the palette we quantize the frames ourselves
+//! (using median cut) and write them out with the `gif` crate.
+use std::collections::HashMap;
+use std::io::Write;
+
+use gif::{Encoder, Frame, Repeat};
+use image::{ImageBuffer, Rgb};
+
+use crate::image_renderer::PaletteMode;
+use crate::LSystemError;
+
+/// A palette of at most 256 colors.
+#[derive(Debug, Clone)]
+pub(crate) struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    /// Computes a palette with at most `size` colors for the given buffer using median cut.
+    pub(crate) fn median_cut(buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>, size: usize) -> Self {
+        let mut histogram: HashMap<[u8; 3], u64> = HashMap::new();
+
+        for pixel in buffer.pixels() {
+            *histogram.entry(pixel.0).or_insert(0) += 1;
+        }
+
+        let mut boxes = vec![histogram.into_iter().collect::<Vec<_>>()];
+
+        while boxes.len() < size {
+            // Split the box whose colors are the most spread out
+            let candidate = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| b.len() > 1)
+                .max_by_key(|(_, b)| widest_channel(b).1)
+                .map(|(index, _)| index);
+
+            let index = match candidate {
+                Some(index) => index,
+                None => break,
+            };
+
+            let mut colors = boxes.swap_remove(index);
+            let (channel, _) = widest_channel(&colors);
+            colors.sort_by_key(|(color, _)| color[channel]);
+
+            // Split at the weighted median, making sure both halves are non-empty
+            let total: u64 = colors.iter().map(|(_, count)| count).sum();
+            let mut running = 0;
+            let mut split = colors.len() - 1;
+
+            for (position, (_, count)) in colors.iter().enumerate() {
+                running += count;
+                if 2 * running >= total {
+                    split = position + 1;
+                    break;
+                }
+            }
+
+            let split = split.clamp(1, colors.len() - 1);
+            let upper = colors.split_off(split);
+
+            boxes.push(colors);
+            boxes.push(upper);
+        }
+
+        let colors = boxes
+            .iter()
+            .filter(|b| !b.is_empty())
+            .map(|b| {
+                let total: u64 = b.iter().map(|(_, count)| count).sum();
+                let mut average = [0u8; 3];
+
+                for (channel, value) in average.iter_mut().enumerate() {
+                    let sum: u64 = b.iter().map(|(c, count)| c[channel] as u64 * count).sum();
+                    *value = ((sum as f64) / (total as f64)).round() as u8;
+                }
+
+                average
+            })
+            .collect();
+
+        Self { colors }
+    }
+
+    /// Returns the palette as a flat `RGBRGB...` byte vector, as expected by the `gif` crate.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.colors.iter().flat_map(|c| c.iter().cloned()).collect()
+    }
+
+    fn nearest(&self, color: [f64; 3]) -> usize {
+        let distance = |c: &[u8; 3]| -> f64 {
+            (0..3)
+                .map(|i| (c[i] as f64 - color[i]).powi(2))
+                .sum::<f64>()
+        };
+
+        let mut best = 0;
+        let mut best_distance = f64::INFINITY;
+
+        for (index, candidate) in self.colors.iter().enumerate() {
+            let d = distance(candidate);
+            if d < best_distance {
+                best = index;
+                best_distance = d;
+            }
+        }
+
+        best
+    }
+
+    /// Maps every pixel of `buffer` to an index into this palette, optionally using
+    /// Floyd–Steinberg dithering to spread out the quantization error.
+    pub(crate) fn index(&self, buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>, dithering: bool) -> Vec<u8> {
+        let width = buffer.width() as usize;
+        let height = buffer.height() as usize;
+        let mut indices = Vec::with_capacity(width * height);
+
+        if !dithering {
+            let mut cache = HashMap::new();
+
+            for pixel in buffer.pixels() {
+                let index = *cache.entry(pixel.0).or_insert_with(|| {
+                    let [r, g, b] = pixel.0;
+                    self.nearest([r as f64, g as f64, b as f64])
+                });
+                indices.push(index as u8);
+            }
+
+            return indices;
+        }
+
+        let mut error = vec![[0.0f64; 3]; width * height];
+
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = buffer.get_pixel(x as u32, y as u32).0;
+                let mut wanted = [0.0; 3];
+
+                for channel in 0..3 {
+                    wanted[channel] =
+                        (pixel[channel] as f64 + error[y * width + x][channel]).clamp(0.0, 255.0);
+                }
+
+                let index = self.nearest(wanted);
+                indices.push(index as u8);
+
+                let chosen = self.colors[index];
+
+                for channel in 0..3 {
+                    let e = wanted[channel] - chosen[channel] as f64;
+
+                    let mut spread = |dx: isize, dy: usize, weight: f64| {
+                        let nx = x as isize + dx;
+                        let ny = y + dy;
+
+                        if nx >= 0 && (nx as usize) < width && ny < height {
+                            error[ny * width + nx as usize][channel] += e * weight;
+                        }
+                    };
+
+                    spread(1, 0, 7.0 / 16.0);
+                    spread(-1, 1, 3.0 / 16.0);
+                    spread(0, 1, 5.0 / 16.0);
+                    spread(1, 1, 1.0 / 16.0);
+                }
+            }
+        }
+
+        indices
+    }
+}
+
+/// Returns the channel with the largest range of values, together with that range.
+fn widest_channel(colors: &[([u8; 3], u64)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = colors.iter().map(|(c, _)| c[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|(c, _)| c[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
+
+fn dimension(value: u32) -> Result<u16, LSystemError> {
+    use std::convert::TryFrom;
+
+    u16::try_from(value).map_err(|e| LSystemError::Other {
+        source: Box::new(e),
+    })
+}
+
+/// Writes `frames` to `writer` as a GIF, quantizing each frame ourselves.
+///
+/// With [`PaletteMode::Global`] the palette is computed from the last frame, which for our
+/// line-by-line animations contains every color that appears in the animation.
+pub(crate) fn write_gif<W: Write>(
+    writer: W,
+    frames: &[ImageBuffer<Rgb<u8>, Vec<u8>>],
+    delays: &[f64],
+    palette_size: usize,
+    dithering: bool,
+    mode: PaletteMode,
+    repeat: Repeat,
+) -> Result<(), LSystemError> {
+    let last = match frames.last() {
+        Some(last) => last,
+        None => return Ok(()),
+    };
+
+    let width = dimension(last.width())?;
+    let height = dimension(last.height())?;
+    let palette_size = palette_size.clamp(2, 256);
+
+    let global = match mode {
+        PaletteMode::Global => Some(Palette::median_cut(last, palette_size)),
+        _ => None,
+    };
+
+    let global_bytes = global.as_ref().map(Palette::to_bytes).unwrap_or_default();
+    let mut encoder = Encoder::new(writer, width, height, &global_bytes)?;
+    encoder.set_repeat(repeat)?;
+
+    for (index, buffer) in frames.iter().enumerate() {
+        // GIF delays are measured in hundredths of a second
+        let delay = delays.get(index).copied().unwrap_or_default() * 100.0;
+
+        let (indices, local) = match &global {
+            Some(palette) => (palette.index(buffer, dithering), None),
+            None => {
+                let palette = Palette::median_cut(buffer, palette_size);
+                (palette.index(buffer, dithering), Some(palette.to_bytes()))
+            }
+        };
+
+        let frame = Frame {
+            delay: delay.round().min(f64::from(u16::MAX)) as u16,
+            width,
+            height,
+            palette: local,
+            buffer: indices.into(),
+            ..Frame::default()
+        };
+
+        encoder.write_frame(&frame)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_cut_keeps_distinct_colors() {
+        let mut buffer = ImageBuffer::new(4, 1);
+        buffer.put_pixel(0, 0, Rgb([0, 0, 0]));
+        buffer.put_pixel(1, 0, Rgb([255, 0, 0]));
+        buffer.put_pixel(2, 0, Rgb([0, 255, 0]));
+        buffer.put_pixel(3, 0, Rgb([0, 0, 255]));
+
+        let palette = Palette::median_cut(&buffer, 16);
+        assert_eq!(palette.colors.len(), 4);
+
+        // Each pixel should map to an exact palette entry
+        let indices = palette.index(&buffer, false);
+        for (pixel, index) in buffer.pixels().zip(indices) {
+            assert_eq!(palette.colors[index as usize], pixel.0);
+        }
+    }
+
+    #[test]
+    fn median_cut_respects_size() {
+        let buffer = ImageBuffer::from_fn(16, 16, |x, y| Rgb([(x * 16) as u8, (y * 16) as u8, 0]));
+
+        let palette = Palette::median_cut(&buffer, 8);
+        assert_eq!(palette.colors.len(), 8);
+        assert_eq!(palette.index(&buffer, true).len(), 256);
+    }
+}
@@ -1,6 +1,10 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
-use crate::turtle::TurtleContainer;
+use image::Rgb;
+
+use crate::scene::Symmetry;
+use crate::turtle::{Simplify, Smooth, TurtleContainer, TurtleEvent};
 use crate::{ArenaId, LSystem};
 
 #[cfg(feature = "image_renderer")]
@@ -9,36 +13,234 @@ pub use crate::image_renderer::ImageRendererOptionsBuilder;
 #[cfg(feature = "image_renderer")]
 pub use crate::image_renderer::VideoRendererOptionsBuilder;
 
+#[cfg(feature = "image_renderer")]
+pub use crate::image_renderer::StepAnimationRendererOptionsBuilder;
+
+#[cfg(feature = "image_renderer")]
+pub use crate::image_renderer::FrameSequenceRendererOptionsBuilder;
+
+pub use crate::dxf::DxfRendererOptionsBuilder;
+
+pub use crate::export::ExportRendererOptionsBuilder;
+
+pub use crate::heatmap::HeatmapRendererOptionsBuilder;
+
+pub use crate::html::HtmlRendererOptionsBuilder;
+
+pub use crate::music::MidiRendererOptionsBuilder;
+
+#[cfg(feature = "gpu")]
+pub use crate::gpu::GpuRendererOptionsBuilder;
+
+#[cfg(feature = "kurbo")]
+pub use crate::path::PathRendererOptionsBuilder;
+
+pub use crate::plotter::PlotterRendererOptionsBuilder;
+
+#[cfg(feature = "preview")]
+pub use crate::preview::PreviewOptionsBuilder;
+
+pub use crate::svg::SvgAnimationRendererOptionsBuilder;
+
+pub use crate::tikz::TikzRendererOptionsBuilder;
+
+pub use crate::vector::VectorRendererOptionsBuilder;
+
 pub trait Renderer<S> {
     /// The output of the rendering operation
     type Output;
 
-    /// Renders the system, consuming the renderer.
-    fn render(self, system: &LSystem, options: &S) -> Self::Output;
+    /// Renders the system.  The renderer is left unchanged, so it can be used to render
+    /// any number of systems (or the same system with different options).
+    fn render(&self, system: &LSystem, options: &S) -> Self::Output;
+}
+
+/// Describes where a symbol is in the state being rendered.  Passed to the actions
+/// registered using [`TurtleRenderer::register_with_context`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct SymbolContext {
+    /// The position of the symbol in the state.
+    pub index: usize,
+    /// The total number of symbols in the state.
+    pub length: usize,
+    /// The number of branches enclosing the symbol.  Branches are opened and closed by the
+    /// tokens registered using [`TurtleRenderer::register_branch_open`] and
+    /// [`TurtleRenderer::register_branch_close`].
+    pub depth: usize,
+}
+
+impl SymbolContext {
+    /// Returns the proportion of the state that comes before this symbol, between `0` and `1`.
+    pub fn progress(&self) -> f64 {
+        if self.length <= 1 {
+            0.0
+        } else {
+            self.index as f64 / (self.length - 1) as f64
+        }
+    }
 }
 
+/// Interprets the state of an [`LSystem`] by applying an action to a state `Q` for each token.
+///
+/// Every render starts from a fresh copy of the state the renderer was created with, so the
+/// same renderer can draw any number of systems.  This is why the renderers need `Q: Clone`,
+/// which custom containers have to derive.
+///
+/// The actions are run while a render holds on to them, so rendering must not be started
+/// again from inside an action of the same renderer.  Doing so panics.
 pub struct TurtleRenderer<Q: TurtleContainer> {
-    pub(crate) state: Q,
+    state: Q,
     #[allow(clippy::type_complexity)]
-    state_actions: HashMap<ArenaId, Box<dyn Fn(&mut Q)>>,
+    state_actions: RefCell<HashMap<ArenaId, Box<dyn FnMut(&mut Q, SymbolContext)>>>,
     aliases: HashMap<ArenaId, ArenaId>,
+    branch_open: HashSet<ArenaId>,
+    branch_close: HashSet<ArenaId>,
+    simplify: Simplify,
+    smooth: Smooth,
+    symmetry: Symmetry,
 }
 
 impl<Q: TurtleContainer> TurtleRenderer<Q> {
     pub fn new(state: Q) -> Self {
         Self {
             state,
-            state_actions: HashMap::new(),
+            state_actions: RefCell::new(HashMap::new()),
             aliases: HashMap::new(),
+            branch_open: HashSet::new(),
+            branch_close: HashSet::new(),
+            simplify: Simplify::Off,
+            smooth: Smooth::Off,
+            symmetry: Symmetry::new(),
         }
     }
 
-    pub fn register<F: 'static + Fn(&mut Q)>(&mut self, arena_id: ArenaId, modifier: F) {
+    pub fn register<F: 'static + FnMut(&mut Q)>(&mut self, arena_id: ArenaId, mut modifier: F) {
+        self.register_with_context(arena_id, move |state, _| modifier(state));
+    }
+
+    /// Registers an action which is also told where the symbol is in the state.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::renderer::{DataRendererOptions, Renderer, TurtleRenderer};
+    /// use dcc_lsystem::turtle::{MovingTurtle, TurtleLSystemState};
+    /// use dcc_lsystem::LSystemBuilder;
+    /// use image::Rgb;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let f = builder.token("F")?;
+    /// builder.axiom(vec![f, f, f])?;
+    /// let system = builder.finish()?;
+    ///
+    /// let mut renderer = TurtleRenderer::new(TurtleLSystemState::new());
+    ///
+    /// // Fade from black to red along the length of the state
+    /// renderer.register_with_context(f, |state, context| {
+    ///     let turtle = state.turtle_mut();
+    ///     let red = (255.0 * context.progress()) as u8;
+    ///
+    ///     turtle.inner_mut().style_mut().color = Some(Rgb([red, 0, 0]));
+    ///     turtle.forward(10.0);
+    /// });
+    ///
+    /// assert_eq!(renderer.render(&system, &DataRendererOptions::default()).len(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_with_context<F: 'static + FnMut(&mut Q, SymbolContext)>(
+        &mut self,
+        arena_id: ArenaId,
+        modifier: F,
+    ) {
         self.aliases.insert(arena_id, arena_id);
-        self.state_actions.insert(arena_id, Box::from(modifier));
+        self.state_actions
+            .get_mut()
+            .insert(arena_id, Box::from(modifier));
+    }
+
+    /// Marks `arena_id` as a token which opens a branch, which is used to compute
+    /// [`SymbolContext::depth`].
+    pub fn register_branch_open(&mut self, arena_id: ArenaId) {
+        self.branch_open.insert(arena_id);
+    }
+
+    /// Marks `arena_id` as a token which closes a branch, which is used to compute
+    /// [`SymbolContext::depth`].
+    pub fn register_branch_close(&mut self, arena_id: ArenaId) {
+        self.branch_close.insert(arena_id);
     }
 
-    pub fn register_multiple<F: 'static + Fn(&mut Q)>(
+    /// Simplifies the lines drawn by the turtles before they are rendered, which shrinks the
+    /// output of the vector renderers and speeds up drawing images.  Only containers which
+    /// implement [`TurtleContainer::turtles_mut`] are simplified.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::renderer::{DataRendererOptions, Renderer};
+    /// use dcc_lsystem::turtle::{Simplify, TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .token("+", TurtleAction::Rotate(90.0))?
+    ///     .axiom("F F F + F F")?;
+    ///
+    /// let (system, mut renderer) = builder.finish()?;
+    /// assert_eq!(renderer.render(&system, &DataRendererOptions::default()).len(), 5);
+    ///
+    /// renderer.simplify(Simplify::Collinear);
+    /// assert_eq!(renderer.render(&system, &DataRendererOptions::default()).len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn simplify(&mut self, simplify: Simplify) {
+        self.simplify = simplify;
+    }
+
+    /// Smooths the lines drawn by the turtles into curves before they are rendered, which gives
+    /// branching systems a more organic look.  The curves are made up of short lines, so they
+    /// are drawn by every renderer, including the [`DataRendererOptions`] renderer.  Smoothing
+    /// happens after any [simplification](TurtleRenderer::simplify).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::renderer::{DataRendererOptions, Renderer};
+    /// use dcc_lsystem::turtle::{Smooth, TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .token("+", TurtleAction::Rotate(90.0))?
+    ///     .axiom("F + F + F")?;
+    ///
+    /// let (system, mut renderer) = builder.finish()?;
+    ///
+    /// renderer.smooth(Smooth::CatmullRom(8));
+    /// assert_eq!(renderer.render(&system, &DataRendererOptions::default()).len(), 24);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn smooth(&mut self, smooth: Smooth) {
+        self.smooth = smooth;
+    }
+
+    /// Repeats the drawing as rotated copies and tiles, to make patterns.  This happens after
+    /// any [smoothing](TurtleRenderer::smooth).  Only containers which implement
+    /// [`TurtleContainer::turtles_mut`] are repeated.  See [`Symmetry`].
+    pub fn symmetry(&mut self, symmetry: Symmetry) {
+        self.symmetry = symmetry;
+    }
+
+    pub fn register_multiple<F: 'static + FnMut(&mut Q)>(
         &mut self,
         arena_ids: &[ArenaId],
         modifier: F,
@@ -53,36 +255,473 @@ impl<Q: TurtleContainer> TurtleRenderer<Q> {
             self.register(*id, modifier);
         }
     }
+}
+
+impl<Q: TurtleContainer + Clone> TurtleRenderer<Q> {
+    /// Runs the state machine over `system_state`, starting from a copy of the initial state,
+    /// and returns the final state.
+    pub(crate) fn compute(&self, system_state: &[ArenaId]) -> Q {
+        self.run(self.state.clone(), system_state)
+    }
+
+    /// Like [`TurtleRenderer::compute`], but the turtles record the events described in
+    /// [`BaseTurtle::record_events`](crate::turtle::BaseTurtle::record_events).
+    pub(crate) fn compute_events(&self, system_state: &[ArenaId]) -> Q {
+        let mut state = self.state.clone();
+
+        for turtle in state.turtles_mut() {
+            turtle.inner_mut().record_events(true);
+        }
+
+        self.run(state, system_state)
+    }
+
+    fn run(&self, mut state: Q, system_state: &[ArenaId]) -> Q {
+        let mut state_actions = self
+            .state_actions
+            .try_borrow_mut()
+            .expect("a TurtleRenderer can't render from inside one of its own actions");
+        let mut depth = 0;
+
+        for (index, arena_id) in system_state.iter().enumerate() {
+            // A closing token sits at the same depth as the token that opened its branch
+            if self.branch_close.contains(arena_id) {
+                depth = usize::saturating_sub(depth, 1);
+            }
 
-    pub(crate) fn compute(&mut self, system_state: &[ArenaId]) {
-        for arena_id in system_state {
             if self.aliases.contains_key(arena_id) {
                 // Find the arena id that the provided one points to
                 let alias = self.aliases[arena_id];
 
                 // If there is a function corresponding to the alias,
                 // apply it
-                if self.state_actions.contains_key(&alias) {
-                    self.state_actions[&alias](&mut self.state);
+                if let Some(action) = state_actions.get_mut(&alias) {
+                    let context = SymbolContext {
+                        index,
+                        length: system_state.len(),
+                        depth,
+                    };
+
+                    action(&mut state, context);
                 }
             }
+
+            if self.branch_open.contains(arena_id) {
+                depth += 1;
+            }
         }
+
+        if self.simplify != Simplify::Off || self.smooth != Smooth::Off {
+            for turtle in state.turtles_mut() {
+                turtle.inner_mut().simplify(self.simplify);
+                turtle.inner_mut().smooth(self.smooth);
+            }
+        }
+
+        if !self.symmetry.is_identity() {
+            let rotations = self.symmetry.rotation_transforms();
+
+            for turtle in state.turtles_mut() {
+                turtle.inner_mut().replicate(&rotations);
+            }
+
+            // Every turtle is tiled by the same amount, so the tiles line up
+            let (width, height, _, _) = bounds(&state);
+            let tiles = self.symmetry.tile_transforms(width, height);
+
+            for turtle in state.turtles_mut() {
+                turtle.inner_mut().replicate(&tiles);
+            }
+        }
+
+        state
+    }
+}
+
+/// Returns `(total_width, total_height, min_x, min_y)` for the region visited by every
+/// turtle in the state.  See [`BaseTurtle::bounds`](crate::turtle::BaseTurtle::bounds).
+pub(crate) fn bounds<Q: TurtleContainer>(state: &Q) -> (f64, f64, f64, f64) {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+
+    for turtle in state.turtles() {
+        let (width, height, x, y) = turtle.inner().bounds();
+
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x + width);
+        max_y = max_y.max(y + height);
+    }
+
+    (max_x - min_x, max_y - min_y, min_x, min_y)
+}
+
+/// Returns the lines drawn by every turtle in the state, one turtle after another.
+pub(crate) fn lines<Q: TurtleContainer>(
+    state: &Q,
+) -> impl Iterator<Item = &(f64, f64, f64, f64)> + '_ {
+    state
+        .turtles()
+        .into_iter()
+        .flat_map(|turtle| turtle.inner().lines().iter())
+}
+
+/// Returns the lines drawn by every turtle in the state together with their styles.
+pub(crate) fn segments<Q: TurtleContainer>(
+    state: &Q,
+) -> impl Iterator<Item = (&(f64, f64, f64, f64), &crate::turtle::SegmentStyle)> + '_ {
+    state.turtles().into_iter().flat_map(|turtle| {
+        let inner = turtle.inner();
+        inner.lines().iter().zip(inner.styles().iter())
+    })
+}
+
+/// Returns the shapes drawn by every turtle in the state together with their styles.
+pub(crate) fn shapes<Q: TurtleContainer>(
+    state: &Q,
+) -> impl Iterator<Item = &(crate::turtle::Shape, crate::turtle::SegmentStyle)> + '_ {
+    state
+        .turtles()
+        .into_iter()
+        .flat_map(|turtle| turtle.inner().shapes().iter())
+}
+
+/// Returns the labels written by every turtle in the state together with their styles.
+#[cfg(feature = "image_renderer")]
+pub(crate) fn labels<Q: TurtleContainer>(
+    state: &Q,
+) -> impl Iterator<Item = &(crate::turtle::Label, crate::turtle::SegmentStyle)> + '_ {
+    state
+        .turtles()
+        .into_iter()
+        .flat_map(|turtle| turtle.inner().labels().iter())
+}
+
+/// Returns true if the line from `b` to `c` continues the line from `a` to `b` in the same
+/// direction.
+fn continues(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> bool {
+    let (ux, uy) = (b.0 - a.0, b.1 - a.1);
+    let (vx, vy) = (c.0 - b.0, c.1 - b.1);
+
+    // The turtle's coordinates pick up some rounding error as it rotates, so allow
+    // a little slack relative to the length of the lines
+    let cross = ux * vy - uy * vx;
+    let dot = ux * vx + uy * vy;
+
+    cross.abs() <= 1e-9 * (ux.hypot(uy) * vx.hypot(vy)).max(1.0) && dot > 0.0
+}
+
+/// Removes every point of `path` which lies in the middle of a straight run.
+pub(crate) fn merge_collinear(path: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(path.len());
+
+    for point in path {
+        if let [.., a, b] = merged[..] {
+            if continues(a, b, point) {
+                merged.pop();
+            }
+        }
+
+        merged.push(point);
+    }
+
+    merged
+}
+
+/// A sequence of connected points, together with the color they are drawn in.
+pub(crate) type Polyline = (Option<Rgb<u8>>, Vec<(f64, f64)>);
+
+/// Joins the visible lines drawn by every turtle in the state into polylines, together with their
+/// colors.  A new polyline is started whenever a line doesn't begin where the previous one ended,
+/// or has a different color.
+pub(crate) fn polylines<Q: TurtleContainer>(state: &Q) -> Vec<Polyline> {
+    let mut polylines: Vec<Polyline> = Vec::new();
+
+    for ((x1, y1, x2, y2), style) in segments(state) {
+        // Lines with no thickness are invisible
+        if style.resolved_thickness(1.0) <= 0.0 {
+            continue;
+        }
+
+        match polylines.last_mut() {
+            Some((color, polyline))
+                if *color == style.color && polyline.last() == Some(&(*x1, *y1)) =>
+            {
+                polyline.push((*x2, *y2))
+            }
+            _ => polylines.push((style.color, vec![(*x1, *y1), (*x2, *y2)])),
+        }
+    }
+
+    polylines
+}
+
+/// A dash pattern for the lines drawn by a renderer.
+///
+/// The pattern alternates between the lengths of dashes and the gaps between them, in turtle
+/// units, and repeats along each run of connected lines.  A pattern with an odd number of
+/// lengths is repeated twice, so `[4.0]` is the same as `[4.0, 4.0]`.  A dash of length zero
+/// draws a dot, which is only visible with round line caps.
+///
+/// # Example
+/// ```rust
+/// use dcc_lsystem::renderer::StrokeStyle;
+///
+/// // Dashes 6 units long, separated by gaps of 2 units
+/// let dashed = StrokeStyle::new(vec![6.0, 2.0], 0.0);
+/// assert!(!dashed.is_solid());
+/// assert!(StrokeStyle::solid().is_solid());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StrokeStyle {
+    dash_pattern: Vec<f64>,
+    offset: f64,
+}
+
+impl StrokeStyle {
+    /// Creates a stroke style with the given dash pattern, starting `offset` units into the
+    /// pattern.  Negative lengths are treated as zero.
+    pub fn new(dash_pattern: Vec<f64>, offset: f64) -> Self {
+        Self {
+            dash_pattern: dash_pattern
+                .into_iter()
+                .map(|length| length.max(0.0))
+                .collect(),
+            offset,
+        }
+    }
+
+    /// Creates a stroke style that draws lines without any gaps.
+    pub fn solid() -> Self {
+        Self::default()
+    }
+
+    /// Creates a stroke style with dashes of length `dash`, separated by gaps of length `gap`.
+    pub fn dashed(dash: f64, gap: f64) -> Self {
+        Self::new(vec![dash, gap], 0.0)
+    }
+
+    /// Creates a stroke style with dots separated by gaps of length `gap`.
+    pub fn dotted(gap: f64) -> Self {
+        Self::new(vec![0.0, gap], 0.0)
+    }
+
+    pub fn dash_pattern(&self) -> &[f64] {
+        &self.dash_pattern
+    }
+
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Returns true if lines are drawn without any gaps, either because the dash pattern is
+    /// empty or because it has no length.
+    pub fn is_solid(&self) -> bool {
+        self.dash_pattern.iter().sum::<f64>() <= 0.0
+    }
+
+    /// Returns the dash pattern with an even number of lengths, multiplied by `scale`.
+    pub(crate) fn scaled_pattern(&self, scale: f64) -> Vec<f64> {
+        let mut pattern: Vec<f64> = self.dash_pattern.iter().map(|l| l * scale).collect();
+
+        if pattern.len() % 2 == 1 {
+            pattern.extend_from_within(..);
+        }
+
+        pattern
+    }
+}
+
+/// Splits lines into dashes, carrying the position in the dash pattern from each line to the
+/// next while they stay connected.
+pub(crate) struct Dasher {
+    pattern: Vec<f64>,
+    offset: f64,
+    index: usize,
+    remaining: f64,
+    distance: f64,
+    previous: Option<(f64, f64)>,
+}
+
+impl Dasher {
+    /// Creates a dasher for `style`, with lengths multiplied by `scale`.  Returns `None` if the
+    /// style is solid.
+    pub(crate) fn new(style: &StrokeStyle, scale: f64) -> Option<Self> {
+        if style.is_solid() {
+            return None;
+        }
+
+        let pattern = style.scaled_pattern(scale);
+
+        Some(Self {
+            index: 0,
+            remaining: pattern[0],
+            pattern,
+            offset: style.offset * scale,
+            distance: 0.0,
+            previous: None,
+        })
+    }
+
+    /// Returns the distance into the dash pattern at which the line from `(x1, y1)` to
+    /// `(x2, y2)` starts, counting the offset, and moves past the line.
+    pub(crate) fn phase(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
+        self.start(x1, y1);
+        let phase = self.offset + self.distance;
+        self.advance(x1, y1, x2, y2, |_, _| {});
+        phase
+    }
+
+    /// Returns the dashes of the line from `(x1, y1)` to `(x2, y2)`.
+    #[cfg(feature = "image_renderer")]
+    pub(crate) fn split(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+    ) -> Vec<(f64, f64, f64, f64)> {
+        self.start(x1, y1);
+
+        let length = (x2 - x1).hypot(y2 - y1);
+        let point = |t: f64| {
+            if length > 0.0 {
+                (x1 + (x2 - x1) * t / length, y1 + (y2 - y1) * t / length)
+            } else {
+                (x1, y1)
+            }
+        };
+
+        let mut dashes = Vec::new();
+        self.advance(x1, y1, x2, y2, |from, to| {
+            let ((ax, ay), (bx, by)) = (point(from), point(to));
+            dashes.push((ax, ay, bx, by));
+        });
+        dashes
+    }
+
+    /// Starts the pattern again, skipping the offset, unless `(x, y)` is where the previous line
+    /// ended.
+    fn start(&mut self, x: f64, y: f64) {
+        if self.previous == Some((x, y)) {
+            return;
+        }
+
+        let total: f64 = self.pattern.iter().sum();
+
+        self.index = 0;
+        self.remaining = self.pattern[0];
+        self.distance = 0.0;
+        self.walk(self.offset.rem_euclid(total), |_, _| {});
+        self.distance = 0.0;
+    }
+
+    /// Moves along the line from `(x1, y1)` to `(x2, y2)`, calling `dash` with the start and end
+    /// distances of each dash along it.
+    fn advance(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, dash: impl FnMut(f64, f64)) {
+        self.walk((x2 - x1).hypot(y2 - y1), dash);
+        self.previous = Some((x2, y2));
+    }
+
+    /// Moves `length` units through the pattern, calling `dash` with the start and end distances
+    /// of each dash passed through.
+    fn walk(&mut self, length: f64, mut dash: impl FnMut(f64, f64)) {
+        let mut travelled = 0.0;
+
+        loop {
+            let step = self.remaining.min(length - travelled);
+
+            // Dashes have even indices, and gaps odd ones
+            if self.index.is_multiple_of(2) && (step > 0.0 || self.remaining == 0.0) {
+                dash(travelled, travelled + step);
+            }
+
+            travelled += step;
+            self.remaining -= step;
+
+            if self.remaining > 0.0 {
+                break;
+            }
+
+            self.index = (self.index + 1) % self.pattern.len();
+            self.remaining = self.pattern[self.index];
+
+            if travelled >= length {
+                break;
+            }
+        }
+
+        self.distance += length;
     }
 }
 
 /// A version of ImageRendererOptions but intended for data only rendering (no image).
 /// For symmetry reasons and future proofing, it is implemented as an empty struct.
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataRendererOptions {}
 
-impl<Q: TurtleContainer> Renderer<DataRendererOptions> for TurtleRenderer<Q> {
+impl<Q: TurtleContainer + Clone> Renderer<DataRendererOptions> for TurtleRenderer<Q> {
     type Output = Vec<(f64, f64, f64, f64)>;
 
-    fn render(mut self, system: &LSystem, _options: &DataRendererOptions) -> Self::Output {
+    fn render(&self, system: &LSystem, _options: &DataRendererOptions) -> Self::Output {
         // Setup our state machine based on the LSystem state
-        self.compute(system.get_state());
+        let state = self.compute(system.get_state());
+
+        lines(&state).cloned().collect()
+    }
+}
+
+/// Options for rendering the events recorded by each turtle, for driving custom drawing
+/// backends.  The output has the events of each turtle in turn, in the order the turtles
+/// were created.  See [`TurtleEvent`].
+///
+/// Only containers which implement [`TurtleContainer::turtles_mut`] record events.
+///
+/// # Example
+/// ```rust
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use dcc_lsystem::renderer::{EventRendererOptions, Renderer};
+/// use dcc_lsystem::turtle::{TurtleAction, TurtleEvent, TurtleLSystemBuilder};
+///
+/// let mut builder = TurtleLSystemBuilder::new();
+///
+/// builder
+///     .token("F", TurtleAction::Forward(10.0))?
+///     .token("[", TurtleAction::Push)?
+///     .token("]", TurtleAction::Pop)?
+///     .axiom("[ F ]")?;
+///
+/// let (system, renderer) = builder.finish()?;
+/// let events = renderer.render(&system, &EventRendererOptions::default());
+///
+/// assert_eq!(
+///     events[0],
+///     vec![
+///         TurtleEvent::Push,
+///         TurtleEvent::LineTo { x: 10.0, y: 0.0 },
+///         TurtleEvent::Pop,
+///         TurtleEvent::MoveTo { x: 0.0, y: 0.0 },
+///     ]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventRendererOptions {}
+
+impl<Q: TurtleContainer + Clone> Renderer<EventRendererOptions> for TurtleRenderer<Q> {
+    type Output = Vec<Vec<TurtleEvent>>;
+
+    fn render(&self, system: &LSystem, _options: &EventRendererOptions) -> Self::Output {
+        let state = self.compute_events(system.get_state());
 
-        // TODO: find a way to move lines() instead of cloning it with to_vec()
-        self.state.inner().inner().lines().to_vec()
+        state
+            .turtles()
+            .iter()
+            .map(|turtle| turtle.inner().events().to_vec())
+            .collect()
     }
 }
@@ -0,0 +1,326 @@
+//! Draws several systems together, each moved, rotated and scaled into place.
+//!
+//! A [`SceneRenderer`] collects the lines drawn by any number of systems, and can then be
+//! rendered with any of the options accepted by a [`TurtleRenderer`], such as
+//! [`ImageRendererOptions`](crate::image_renderer::ImageRendererOptions) or
+//! [`VectorRendererOptions`](crate::vector::VectorRendererOptions).
+//!
+//! A single system can also be repeated into a pattern with a [`Symmetry`].
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::presets;
+//! use dcc_lsystem::renderer::{DataRendererOptions, VectorRendererOptionsBuilder};
+//! use dcc_lsystem::scene::{SceneRenderer, Transform};
+//!
+//! let mut scene = SceneRenderer::new();
+//!
+//! // A forest of three plants, each a little smaller than the last
+//! for (index, &x) in [0.0, 400.0, 700.0].iter().enumerate() {
+//!     let (mut system, renderer) = presets::fractal_plant().finish()?;
+//!     system.step_by(3);
+//!
+//!     let scale = 1.0 - 0.25 * index as f64;
+//!     scene.add(&system, &renderer, Transform::new().scale(scale).translate(x, 0.0));
+//! }
+//!
+//! assert_eq!(scene.render(&DataRendererOptions::default()).len(), 3 * 84);
+//!
+//! let eps = scene.render(&VectorRendererOptionsBuilder::new().build());
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+
+use crate::arena::Arena;
+use crate::renderer::{Renderer, TurtleRenderer};
+use crate::turtle::{BaseTurtle, MovingTurtle, SimpleTurtle, TurtleContainer};
+use crate::LSystem;
+
+/// A combination of a scale, a rotation and a translation, which are applied in that order
+/// whatever order they're set in.  The scale and rotation are about the origin.
+///
+/// # Example
+/// ```rust
+/// use dcc_lsystem::scene::Transform;
+///
+/// let transform = Transform::new().translate(10.0, 0.0).rotate(90.0).scale(2.0);
+/// let (x, y) = transform.apply(1.0, 0.0);
+///
+/// assert!((x - 10.0).abs() < 1e-9 && (y - 2.0).abs() < 1e-9);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transform {
+    dx: f64,
+    dy: f64,
+    rotation: f64,
+    scale: f64,
+}
+
+impl Transform {
+    /// Returns the transform which leaves everything where it is.
+    pub fn new() -> Self {
+        Self {
+            dx: 0.0,
+            dy: 0.0,
+            rotation: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    /// Move everything by `(dx, dy)`.
+    pub fn translate(mut self, dx: f64, dy: f64) -> Self {
+        self.dx = dx;
+        self.dy = dy;
+        self
+    }
+
+    /// Rotate everything anticlockwise through `angle` degrees.
+    pub fn rotate(mut self, angle: f64) -> Self {
+        self.rotation = angle;
+        self
+    }
+
+    /// Scale everything by `scale`, which should be positive.
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns the angle of the rotation, in radians.
+    pub fn rotation(&self) -> f64 {
+        self.rotation.to_radians()
+    }
+
+    /// Returns the scale factor.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale
+    }
+
+    /// Returns where the point `(x, y)` is moved to.
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotation().sin_cos();
+        let (x, y) = (x * self.scale, y * self.scale);
+
+        (x * cos - y * sin + self.dx, x * sin + y * cos + self.dy)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Repeats the drawing of a system to make patterns: first as rotated copies about a point,
+/// like a kaleidoscope, and then as a grid of tiles.
+///
+/// See [`TurtleRenderer::symmetry`].
+///
+/// # Example
+/// ```rust
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use dcc_lsystem::renderer::{DataRendererOptions, Renderer};
+/// use dcc_lsystem::scene::Symmetry;
+/// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+///
+/// let mut builder = TurtleLSystemBuilder::new();
+/// builder.token("F", TurtleAction::Forward(10.0))?.axiom("F")?;
+///
+/// let (system, mut renderer) = builder.finish()?;
+///
+/// // Six spokes, tiled three across and twice down
+/// renderer.symmetry(Symmetry::new().rotations(6).tile(3, 2).spacing(5.0, 5.0));
+/// assert_eq!(renderer.render(&system, &DataRendererOptions::default()).len(), 36);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symmetry {
+    rotations: usize,
+    center: (f64, f64),
+    columns: usize,
+    rows: usize,
+    spacing: (f64, f64),
+}
+
+impl Symmetry {
+    /// Returns the symmetry which draws the system once.
+    pub fn new() -> Self {
+        Self {
+            rotations: 1,
+            center: (0.0, 0.0),
+            columns: 1,
+            rows: 1,
+            spacing: (0.0, 0.0),
+        }
+    }
+
+    /// Draw `rotations` copies of the system, evenly spaced around the center.
+    pub fn rotations(mut self, rotations: usize) -> Self {
+        self.rotations = rotations.max(1);
+        self
+    }
+
+    /// Set the point that rotated copies are drawn around.  Defaults to the origin, which is
+    /// where the turtle starts.
+    pub fn center(mut self, x: f64, y: f64) -> Self {
+        self.center = (x, y);
+        self
+    }
+
+    /// Repeat the bounding box of the drawing in a grid of `columns` by `rows` tiles.
+    pub fn tile(mut self, columns: usize, rows: usize) -> Self {
+        self.columns = columns.max(1);
+        self.rows = rows.max(1);
+        self
+    }
+
+    /// Set the horizontal and vertical gap between tiles.
+    pub fn spacing(mut self, x: f64, y: f64) -> Self {
+        self.spacing = (x, y);
+        self
+    }
+
+    /// Returns `true` if the system is only drawn once.
+    pub fn is_identity(&self) -> bool {
+        self.rotations == 1 && self.columns == 1 && self.rows == 1
+    }
+
+    /// Returns the transforms that make the rotated copies.
+    pub(crate) fn rotation_transforms(&self) -> Vec<Transform> {
+        let (cx, cy) = self.center;
+
+        (0..self.rotations)
+            .map(|copy| {
+                let transform =
+                    Transform::new().rotate(360.0 * copy as f64 / self.rotations as f64);
+
+                // Rotating about the center is rotating about the origin, then moving the
+                // center back to where it was
+                let (x, y) = transform.apply(cx, cy);
+                transform.translate(cx - x, cy - y)
+            })
+            .collect()
+    }
+
+    /// Returns the transforms that make the tiles, given the size of a single tile.
+    pub(crate) fn tile_transforms(&self, width: f64, height: f64) -> Vec<Transform> {
+        let (dx, dy) = (width + self.spacing.0, height + self.spacing.1);
+
+        (0..self.rows)
+            .flat_map(|row| {
+                (0..self.columns).map(move |column| {
+                    Transform::new().translate(column as f64 * dx, row as f64 * dy)
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The turtles of every system added to a [`SceneRenderer`], after they have been moved into
+/// place.
+#[derive(Clone)]
+pub struct SceneState {
+    turtles: Vec<SimpleTurtle>,
+    blank: SimpleTurtle,
+}
+
+impl TurtleContainer for SceneState {
+    type Item = f64;
+
+    fn inner(&self) -> &dyn MovingTurtle<Item = Self::Item> {
+        self.turtles.first().unwrap_or(&self.blank)
+    }
+
+    fn turtles(&self) -> Vec<&dyn MovingTurtle<Item = Self::Item>> {
+        self.turtles
+            .iter()
+            .map(|turtle| turtle as &dyn MovingTurtle<Item = Self::Item>)
+            .collect()
+    }
+
+    fn turtles_mut(&mut self) -> Vec<&mut dyn MovingTurtle<Item = Self::Item>> {
+        self.turtles
+            .iter_mut()
+            .map(|turtle| turtle as &mut dyn MovingTurtle<Item = Self::Item>)
+            .collect()
+    }
+}
+
+/// Composes the output of several systems into a single rendering.  See the
+/// [`scene`](crate::scene) module for an example.
+#[derive(Clone)]
+pub struct SceneRenderer {
+    state: SceneState,
+}
+
+impl SceneRenderer {
+    /// Create an empty scene.
+    pub fn new() -> Self {
+        Self {
+            state: SceneState {
+                turtles: Vec::new(),
+                blank: SimpleTurtle::new(),
+            },
+        }
+    }
+
+    /// Add the lines drawn by `renderer` for the current state of `system` to the scene,
+    /// after applying `transform` to them.  Systems are drawn in the order they're added.
+    pub fn add<Q: TurtleContainer + Clone>(
+        &mut self,
+        system: &LSystem,
+        renderer: &TurtleRenderer<Q>,
+        transform: Transform,
+    ) -> &mut Self {
+        let state = renderer.compute(system.get_state());
+
+        for turtle in state.turtles() {
+            let mut base: BaseTurtle = turtle.inner().clone();
+            base.transform(&transform);
+
+            let mut turtle = SimpleTurtle::new();
+            *turtle.inner_mut() = base;
+            self.state.turtles.push(turtle);
+        }
+
+        self
+    }
+
+    /// Renders every system in the scene with `options`, exactly as a [`TurtleRenderer`]
+    /// would render a single system.
+    pub fn render<S>(&self, options: &S) -> <TurtleRenderer<SceneState> as Renderer<S>>::Output
+    where
+        TurtleRenderer<SceneState>: Renderer<S>,
+    {
+        // The scene has already been drawn, so it's rendered for a system with no tokens
+        let empty = LSystem::new(
+            Arena::new(),
+            Vec::new(),
+            HashMap::new(),
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        TurtleRenderer::new(self.state.clone()).render(&empty, options)
+    }
+}
+
+impl Default for SceneRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
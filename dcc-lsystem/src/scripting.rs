@@ -0,0 +1,98 @@
+//! Runs the scripts of [`TurtleAction::Script`] actions with rhai.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use image::Rgb;
+use rhai::{Engine, Scope};
+
+use crate::turtle::TurtleAction;
+use crate::LSystemError;
+
+/// The actions called by a script, which are performed once it has finished running.
+pub(crate) type Queue = Rc<RefCell<Vec<TurtleAction>>>;
+
+/// Returns an engine whose turtle functions add their actions to `queue`.
+pub(crate) fn engine(queue: &Queue) -> Engine {
+    let mut engine = Engine::new();
+
+    // rhai treats integers and floats as different types, so numeric functions are
+    // registered for both, to allow both `forward(10)` and `forward(2.5)`
+    macro_rules! register {
+        ($name:expr, $action:expr) => {{
+            let sink = queue.clone();
+            engine.register_fn($name, move |value: f64| {
+                sink.borrow_mut().push($action(value))
+            });
+
+            let sink = queue.clone();
+            engine.register_fn($name, move |value: i64| {
+                sink.borrow_mut().push($action(value as f64))
+            });
+        }};
+    }
+
+    register!("forward", TurtleAction::Forward);
+    register!("rotate", TurtleAction::Rotate);
+    register!("skip", TurtleAction::Skip);
+    register!("heading", TurtleAction::SetHeading);
+    register!("thickness", TurtleAction::SetThickness);
+    register!("multiply_step", TurtleAction::MultiplyStep);
+    register!("dot", TurtleAction::Dot);
+    register!("leaf", TurtleAction::Leaf);
+
+    for (name, action) in [
+        ("push", TurtleAction::Push),
+        ("pop", TurtleAction::Pop),
+        ("pen_up", TurtleAction::PenUp),
+        ("pen_down", TurtleAction::PenDown),
+    ] {
+        let queue = queue.clone();
+        engine.register_fn(name, move || queue.borrow_mut().push(action.clone()));
+    }
+
+    let goto = queue.clone();
+    engine.register_fn("goto", move |x: f64, y: f64| {
+        goto.borrow_mut().push(TurtleAction::Goto(x, y))
+    });
+
+    let goto = queue.clone();
+    engine.register_fn("goto", move |x: i64, y: i64| {
+        goto.borrow_mut()
+            .push(TurtleAction::Goto(x as f64, y as f64))
+    });
+
+    let color = queue.clone();
+    engine.register_fn("color", move |red: i64, green: i64, blue: i64| {
+        let channel = |value: i64| value.clamp(0, 255) as u8;
+
+        color.borrow_mut().push(TurtleAction::SetColor(Rgb([
+            channel(red),
+            channel(green),
+            channel(blue),
+        ])))
+    });
+
+    engine
+}
+
+/// Returns a scope holding the state of the active turtle, which scripts can read.
+pub(crate) fn scope(x: f64, y: f64, heading: f64, step: f64, depth: usize) -> Scope<'static> {
+    let mut scope = Scope::new();
+
+    scope
+        .push_constant("x", x)
+        .push_constant("y", y)
+        .push_constant("heading", heading)
+        .push_constant("step", step)
+        .push_constant("depth", depth as i64);
+
+    scope
+}
+
+/// Checks that `script` is a valid script.
+pub(crate) fn check(script: &str) -> Result<(), LSystemError> {
+    Engine::new()
+        .compile(script)
+        .map(|_| ())
+        .map_err(|e| LSystemError::InvalidScript(e.to_string()))
+}
@@ -0,0 +1,307 @@
+//! Renders turtle output as an animated SVG, which draws the lines in the order the turtle
+//! drew them.
+//!
+//! Each run of connected lines becomes a single path, which is revealed by animating its
+//! `stroke-dashoffset` with CSS.  The result is a single small file which plays in any
+//! browser and can be scaled to any size.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::renderer::{Renderer, SvgAnimationRendererOptionsBuilder};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .token("-", TurtleAction::Rotate(-90.0))?
+//!     .axiom("F")?
+//!     .rule("F => F + F - F - F + F")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(3);
+//!
+//! // Draw the curve over three seconds, pause for a second, and start again
+//! let options = SvgAnimationRendererOptionsBuilder::new()
+//!     .duration(3.0)
+//!     .hold(1.0)
+//!     .repeat(true)
+//!     .build();
+//!
+//! let svg = renderer.render(&system, &options);
+//!
+//! assert!(svg.starts_with("<svg"));
+//! assert_eq!(svg.matches("<path").count(), 1);
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Write;
+
+use image::Rgb;
+
+use crate::renderer::{bounds, polylines, Renderer, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+pub struct SvgAnimationRendererOptionsBuilder {
+    options: SvgAnimationRendererOptions,
+}
+
+impl SvgAnimationRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: SvgAnimationRendererOptions {
+                duration: 5.0,
+                hold: 0.0,
+                repeat: false,
+                padding: 20.0,
+                thickness: 2.0,
+                fill_color: None,
+                line_color: Rgb([0, 0, 0]),
+            },
+        }
+    }
+
+    /// Set how long it takes to draw every line, in seconds.  A duration of zero draws every
+    /// line at once, giving a static SVG which doesn't rely on CSS animations.
+    pub fn duration(&mut self, duration: f64) -> &mut Self {
+        self.options.duration = duration;
+        self
+    }
+
+    /// Show the finished drawing for `hold` seconds before the animation starts again.  Only
+    /// used when the animation repeats.
+    pub fn hold(&mut self, hold: f64) -> &mut Self {
+        self.options.hold = hold;
+        self
+    }
+
+    /// Play the animation forever, rather than stopping once everything has been drawn.
+    pub fn repeat(&mut self, repeat: bool) -> &mut Self {
+        self.options.repeat = repeat;
+        self
+    }
+
+    /// Set the space left around the drawing, in turtle units.
+    pub fn padding(&mut self, padding: f64) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
+
+    /// Set the thickness of every line, in turtle units.
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
+
+    /// Fill the background with `fill_color`.  By default the background is transparent.
+    pub fn fill_color(&mut self, fill_color: Rgb<u8>) -> &mut Self {
+        self.options.fill_color = Some(fill_color);
+        self
+    }
+
+    /// Set the color of lines without their own color.
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
+
+    pub fn build(&mut self) -> SvgAnimationRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for SvgAnimationRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<SvgAnimationRendererOptions> for SvgAnimationRendererOptionsBuilder {
+    fn from(options: SvgAnimationRendererOptions) -> Self {
+        Self { options }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SvgAnimationRendererOptions {
+    duration: f64,
+    hold: f64,
+    repeat: bool,
+    padding: f64,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb_option"))]
+    fill_color: Option<Rgb<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+}
+
+impl SvgAnimationRendererOptions {
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    pub fn hold(&self) -> f64 {
+        self.hold
+    }
+
+    pub fn repeat(&self) -> bool {
+        self.repeat
+    }
+
+    pub fn padding(&self) -> f64 {
+        self.padding
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn fill_color(&self) -> Option<Rgb<u8>> {
+        self.fill_color
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+}
+
+/// Returns `color` as a CSS hex color.
+fn svg_color(color: Rgb<u8>) -> String {
+    let Rgb([red, green, blue]) = color;
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+/// Returns the length of the path through `points`.
+fn length(points: &[(f64, f64)]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+        .sum()
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<SvgAnimationRendererOptions> for TurtleRenderer<Q> {
+    type Output = String;
+
+    fn render(&self, system: &LSystem, options: &SvgAnimationRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+        let (width, height, min_x, min_y) = bounds(&state);
+
+        let padding = options.padding;
+        let width = width + 2.0 * padding;
+        let height = height + 2.0 * padding;
+
+        // SVG measures y downwards, so the drawing is flipped
+        let xp = |x: f64| x - min_x + padding;
+        let yp = |y: f64| height - (y - min_y + padding);
+
+        let paths = polylines(&state);
+        let total = paths.iter().map(|(_, points)| length(points)).sum::<f64>();
+
+        let duration = options.duration.max(0.0);
+        let animated = duration > 0.0;
+        let period = if options.repeat {
+            duration + options.hold.max(0.0)
+        } else {
+            duration
+        };
+
+        // The percentage of the animation at which `distance` has been drawn
+        let percent = |distance: f64| {
+            if total > 0.0 && period > 0.0 {
+                100.0 * duration * distance / (total * period)
+            } else {
+                0.0
+            }
+        };
+
+        let mut output = String::new();
+
+        // unwrap (here and below): writing to a String never fails
+        writeln!(
+            output,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.3}\" height=\"{:.3}\" viewBox=\"0 0 {:.3} {:.3}\">",
+            width, height, width, height
+        )
+        .unwrap();
+
+        writeln!(output, "<style>").unwrap();
+
+        if animated {
+            // Every path has a `pathLength` of 1, so a single dash and gap of length 1 can hide
+            // it and then reveal it as the dash is moved along
+            writeln!(
+                output,
+                "path {{ fill: none; stroke-linejoin: round; stroke-dasharray: 1; stroke-dashoffset: 1; }}"
+            )
+            .unwrap();
+
+            let mut drawn = 0.0;
+
+            for (index, (_, points)) in paths.iter().enumerate() {
+                let start = percent(drawn);
+                drawn += length(points);
+                let end = percent(drawn);
+
+                writeln!(
+                    output,
+                    "@keyframes draw{} {{ 0%, {:.3}% {{ stroke-dashoffset: 1; }} {:.3}%, 100% {{ stroke-dashoffset: 0; }} }}",
+                    index, start, end
+                )
+                .unwrap();
+            }
+        } else {
+            writeln!(output, "path {{ fill: none; stroke-linejoin: round; }}").unwrap();
+        }
+
+        writeln!(output, "</style>").unwrap();
+
+        if let Some(fill_color) = options.fill_color {
+            writeln!(
+                output,
+                "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+                svg_color(fill_color)
+            )
+            .unwrap();
+        }
+
+        let iterations = if options.repeat { "infinite" } else { "1" };
+
+        for (index, (color, points)) in paths.iter().enumerate() {
+            let data = points
+                .iter()
+                .enumerate()
+                .map(|(i, (x, y))| {
+                    let command = if i == 0 { 'M' } else { 'L' };
+                    format!("{}{:.3} {:.3}", command, xp(*x), yp(*y))
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let color = svg_color(color.unwrap_or(options.line_color));
+
+            if animated {
+                writeln!(
+                    output,
+                    "<path d=\"{}\" pathLength=\"1\" stroke=\"{}\" stroke-width=\"{:.3}\" style=\"animation: draw{} {:.3}s linear {} both\"/>",
+                    data, color, options.thickness, index, period, iterations
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    output,
+                    "<path d=\"{}\" stroke=\"{}\" stroke-width=\"{:.3}\"/>",
+                    data, color, options.thickness
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(output, "</svg>").unwrap();
+        output
+    }
+}
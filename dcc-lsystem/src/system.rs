@@ -61,19 +61,208 @@
 //! # Ok(())
 //! # }
 //! ```
-use std::collections::HashMap;
+use crate::collections::HashMap;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
 
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::analysis;
 use crate::arena::{Arena, ArenaId};
-use crate::token::Token;
+use crate::compressed::CompressedState;
+use crate::derivation::Derivation;
+use crate::errors::LSystemError;
+use crate::token::{self, Token};
+
+/// The successor of every token in a system, for a single table of production rules.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Productions {
+    /// The successors of every token, stored back to back in a single buffer.
+    successors: Vec<ArenaId>,
+    /// The successor of the token `id` is `successors[ranges[id.0].0..ranges[id.0].1]`.
+    ranges: Vec<(usize, usize)>,
+}
+
+impl Productions {
+    /// Flattens `rules_map`, which must contain a rule for each of the first `token_count` tokens.
+    pub(crate) fn new(token_count: usize, rules_map: &HashMap<ArenaId, Vec<ArenaId>>) -> Self {
+        let mut successors = Vec::new();
+        let mut ranges = Vec::with_capacity(token_count);
+
+        for index in 0..token_count {
+            let start = successors.len();
+            successors.extend_from_slice(&rules_map[&ArenaId(index)]);
+            ranges.push((start, successors.len()));
+        }
+
+        Self { successors, ranges }
+    }
+
+    /// Returns the successor of the token `id`.
+    pub(crate) fn successor(&self, id: ArenaId) -> &[ArenaId] {
+        let (start, end) = self.ranges[id.0];
+        &self.successors[start..end]
+    }
+
+    /// Changes the successor of the token `id` to `successor`.
+    fn set_successor(&mut self, id: ArenaId, successor: &[ArenaId]) {
+        let (start, end) = self.ranges[id.0];
+        let new_end = start + successor.len();
+
+        self.successors
+            .splice(start..end, successor.iter().copied());
+        self.ranges[id.0].1 = new_end;
+
+        // The successors of the later tokens follow on in the buffer
+        for range in self.ranges[id.0 + 1..].iter_mut() {
+            range.0 = range.0 - end + new_end;
+            range.1 = range.1 - end + new_end;
+        }
+    }
+}
+
+/// A table of production rules which replaces the default rules for the steps in `start..end`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct RuleTable {
+    pub(crate) start: usize,
+    pub(crate) end: Option<usize>,
+    pub(crate) productions: Productions,
+    /// Whether the table has its own rule for each token, indexed by token.  The productions
+    /// of the other tokens are copied from the usual rules.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) has_rule: Vec<bool>,
+}
+
+impl RuleTable {
+    fn contains(&self, step: usize) -> bool {
+        step >= self.start && !matches!(self.end, Some(end) if step >= end)
+    }
+
+    /// Returns whether the table has its own rule for the token `id`.
+    fn has_rule(&self, id: ArenaId) -> bool {
+        self.has_rule.get(id.0).copied().unwrap_or(false)
+    }
+}
+
+/// Describes a single symbol being expanded by [`LSystem::step_with()`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct StepContext<'a> {
+    /// The symbol being expanded.
+    pub token: ArenaId,
+    /// The position of the symbol in the current state.
+    pub index: usize,
+    /// The number of steps the system has taken before this one.
+    pub step: usize,
+    /// The symbols the system's production rules would expand this symbol to.
+    pub successor: &'a [ArenaId],
+    /// The current state of the system.
+    pub state: &'a [ArenaId],
+}
+
+/// Limits on how large the state of an [`LSystem`] may grow, used by [`LSystem::try_step_by()`].
+///
+/// # Example
+/// ```rust
+/// use dcc_lsystem::system::StepLimits;
+///
+/// // At most a million tokens, using at most 4MiB
+/// let limits = StepLimits::new().max_len(1_000_000).max_bytes(4 << 20);
+/// ```
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct StepLimits {
+    max_len: Option<usize>,
+    max_bytes: Option<usize>,
+}
+
+impl StepLimits {
+    /// Returns a set of limits which doesn't limit anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of tokens in the state.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Limit the memory used to store the state, in bytes.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Returns `true` if a state with `length` tokens is within the limits.
+    fn allows(&self, length: usize) -> bool {
+        let bytes = length.saturating_mul(core::mem::size_of::<ArenaId>());
+
+        !matches!(self.max_len, Some(max_len) if length > max_len)
+            && !matches!(self.max_bytes, Some(max_bytes) if bytes > max_bytes)
+    }
+}
 
 /// Main struct for working with Lindenmayer systems.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LSystem {
     arena: Arena<Token>,
     axiom: Vec<ArenaId>,
-    rules_map: HashMap<ArenaId, Vec<ArenaId>>,
+    productions: Productions,
+    #[cfg_attr(feature = "serde", serde(default))]
+    tables: Vec<RuleTable>,
     state: Vec<ArenaId>,
     steps: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    derivation: Option<Derivation>,
+    /// The previous states of the system, if history is enabled.
+    #[cfg_attr(feature = "serde", serde(default))]
+    history: Option<Vec<Vec<ArenaId>>>,
+    /// The weighted successors of every token, indexed by token.  Tokens without a stochastic
+    /// rule have no successors.
+    #[cfg_attr(feature = "serde", serde(default))]
+    stochastic: Vec<Vec<(f64, Vec<ArenaId>)>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    seed: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "entropy_rng"))]
+    rng: StdRng,
+}
+
+/// Returns the common value of `values`, or `None` if they aren't all equal.
+fn common<T: PartialEq, I: Iterator<Item = T>>(mut values: I) -> Option<T> {
+    let first = values.next()?;
+
+    if values.all(|value| value == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// Returns a random number generator seeded with `seed`, or from entropy if there is no seed.
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => entropy_rng(),
+    }
+}
+
+/// Returns a random number generator seeded from entropy.
+#[cfg(feature = "std")]
+fn entropy_rng() -> StdRng {
+    StdRng::from_entropy()
+}
+
+/// Without `std` there is no source of entropy, so unseeded systems always use the same seed.
+/// Use [`LSystemBuilder::seed`](crate::LSystemBuilder::seed) to vary the output.
+#[cfg(not(feature = "std"))]
+fn entropy_rng() -> StdRng {
+    StdRng::seed_from_u64(0)
 }
 
 impl LSystem {
@@ -83,13 +272,22 @@ impl LSystem {
         arena: Arena<Token>,
         axiom: Vec<ArenaId>,
         rules_map: HashMap<ArenaId, Vec<ArenaId>>,
+        tables: Vec<RuleTable>,
+        stochastic: Vec<Vec<(f64, Vec<ArenaId>)>>,
+        seed: Option<u64>,
     ) -> Self {
         Self {
+            productions: Productions::new(arena.len(), &rules_map),
             arena,
             axiom: axiom.clone(),
-            rules_map,
+            tables,
             state: axiom,
             steps: 0,
+            derivation: None,
+            history: None,
+            stochastic,
+            seed,
+            rng: seeded_rng(seed),
         }
     }
 
@@ -122,6 +320,293 @@ impl LSystem {
     pub fn reset(&mut self) {
         self.state = self.axiom.clone();
         self.steps = 0;
+        self.rng = seeded_rng(self.seed);
+
+        if self.derivation.is_some() {
+            self.derivation = Some(Derivation::new(&self.state));
+        }
+
+        if let Some(history) = self.history.as_mut() {
+            history.clear();
+        }
+    }
+
+    /// Change the transformation rule for `predecessor`, replacing any stochastic rule for it.
+    /// The current state is left alone, so the new rule applies from the next step, and
+    /// [`LSystem::reset`] still returns to the axiom.  Rule tables which have their own rule for
+    /// `predecessor` keep it, and the rest use the new rule like they use the usual rules.
+    ///
+    /// Returns an [`LSystemError::InvalidArenaId`] error, leaving the system unchanged, if any
+    /// of the provided ids don't belong to this system.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("A")?;
+    /// let b = builder.token("B")?;
+    /// builder.axiom(vec![a])?;
+    /// builder.transformation_rule(a, vec![a, b])?;
+    /// let mut system = builder.finish()?;
+    ///
+    /// system.step();
+    /// assert_eq!(system.render(), "AB");
+    ///
+    /// // From now on, B grows as well
+    /// system.set_rule(b, vec![b, b])?;
+    /// system.step();
+    /// assert_eq!(system.render(), "ABBB");
+    ///
+    /// system.reset();
+    /// system.step_by(2);
+    /// assert_eq!(system.render(), "ABBB");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_rule(
+        &mut self,
+        predecessor: ArenaId,
+        successor: Vec<ArenaId>,
+    ) -> Result<(), LSystemError> {
+        self.validate_ids(&[predecessor])?;
+        self.validate_ids(&successor)?;
+
+        self.productions.set_successor(predecessor, &successor);
+
+        for table in self.tables.iter_mut() {
+            if !table.has_rule(predecessor) {
+                table.productions.set_successor(predecessor, &successor);
+            }
+        }
+
+        if let Some(alternatives) = self.stochastic.get_mut(predecessor.0) {
+            alternatives.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Change the axiom of the system, and [reset](LSystem::reset) the system to it.
+    ///
+    /// Returns an [`LSystemError::InvalidArenaId`] error, leaving the system unchanged, if any
+    /// of the provided ids don't belong to this system.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("A")?;
+    /// let b = builder.token("B")?;
+    /// builder.axiom(vec![a])?;
+    /// builder.transformation_rule(a, vec![a, b])?;
+    /// builder.transformation_rule(b, vec![a])?;
+    /// let mut system = builder.finish()?;
+    ///
+    /// system.set_axiom(vec![b])?;
+    /// assert_eq!(system.render(), "B");
+    ///
+    /// system.step_by(2);
+    /// system.reset();
+    /// assert_eq!(system.render(), "B");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_axiom(&mut self, axiom: Vec<ArenaId>) -> Result<(), LSystemError> {
+        self.validate_ids(&axiom)?;
+
+        self.axiom = axiom;
+        self.reset();
+
+        Ok(())
+    }
+
+    /// Replace the current state of the system, for example to resume from a previously
+    /// exported state.  The number of steps taken is unchanged, so [rule tables] continue to
+    /// apply as before, while [`LSystem::reset`] still returns to the axiom.  Any history is
+    /// discarded, and a tracked derivation restarts from the new state.
+    ///
+    /// Returns an [`LSystemError::InvalidArenaId`] error, leaving the system unchanged, if any
+    /// of the provided ids don't belong to this system.
+    ///
+    /// [rule tables]: crate::LSystemBuilder::rule_table
+    pub fn set_state(&mut self, state: Vec<ArenaId>) -> Result<(), LSystemError> {
+        self.validate_ids(&state)?;
+
+        self.state = state;
+
+        if let Some(history) = self.history.as_mut() {
+            history.clear();
+        }
+
+        if self.derivation.is_some() {
+            self.derivation = Some(Derivation::new(&self.state));
+        }
+
+        Ok(())
+    }
+
+    /// Replace the current state of the system with the tokens named in `state`, as described
+    /// in [`LSystem::set_state`].  Tokens needn't be separated by whitespace, so the output of
+    /// [`LSystem::render`] can be read back in: each word is split by repeatedly taking the
+    /// longest token name it starts with.
+    ///
+    /// Returns an [`LSystemError::UnknownToken`] error, leaving the system unchanged, if part
+    /// of `state` isn't the name of a token.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::LSystemBuilder;
+    ///
+    /// let mut builder = LSystemBuilder::new();
+    /// let a = builder.token("A")?;
+    /// let b = builder.token("B")?;
+    /// builder.axiom(vec![a])?;
+    /// builder.transformation_rule(a, vec![a, b])?;
+    /// builder.transformation_rule(b, vec![a])?;
+    /// let mut system = builder.finish()?;
+    ///
+    /// // What happens if we start from BB?
+    /// system.set_state_from_str("BB")?;
+    /// system.step_by(2);
+    /// assert_eq!(system.render(), "ABAB");
+    ///
+    /// assert!(system.set_state_from_str("ABC").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_state_from_str(&mut self, state: &str) -> Result<(), LSystemError> {
+        let names = self
+            .arena
+            .enumerate()
+            .map(|(id, token)| (token.name().to_string(), id))
+            .collect();
+
+        self.set_state(token::tokenize(state, &names)?)
+    }
+
+    /// Returns an [`LSystemError::InvalidArenaId`] error if any of `ids` don't belong to this
+    /// system.
+    fn validate_ids(&self, ids: &[ArenaId]) -> Result<(), LSystemError> {
+        match ids.iter().find(|id| !self.arena.is_valid(**id)) {
+            Some(invalid) => Err(LSystemError::InvalidArenaId(*invalid)),
+            None => Ok(()),
+        }
+    }
+
+    /// Start keeping a history of the states the system passes through, so that
+    /// [`LSystem::step_back()`] and [`LSystem::rewind_to()`] can return to them.  The history
+    /// starts from the current state.
+    ///
+    /// Every previous state is kept in memory, which roughly doubles the memory used by
+    /// a system whose state grows exponentially.
+    pub fn enable_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(Vec::new());
+        }
+    }
+
+    /// Stop keeping a history, discarding any previous states.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Return to the state before the most recent step.  Returns `false`, leaving the
+    /// system unchanged, if there is no previous state in the history.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> ab`.
+    /// system.enable_history();
+    /// system.step_by(3);
+    /// assert_eq!(system.render(), "abbb");
+    ///
+    /// assert!(system.step_back());
+    /// assert_eq!(system.render(), "abb");
+    ///
+    /// assert!(system.rewind_to(0));
+    /// assert_eq!(system.render(), "a");
+    /// assert!(!system.step_back());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn step_back(&mut self) -> bool {
+        self.steps > 0 && self.rewind_to(self.steps - 1)
+    }
+
+    /// Return to the state the system had after `steps` steps.  Returns `false`, leaving
+    /// the system unchanged, if that state isn't in the history.
+    pub fn rewind_to(&mut self, steps: usize) -> bool {
+        let history = match self.history.as_mut() {
+            Some(history) => history,
+            None => return false,
+        };
+
+        if steps > self.steps || self.steps - steps > history.len() {
+            return false;
+        }
+
+        if steps == self.steps {
+            return true;
+        }
+
+        let index = history.len() - (self.steps - steps);
+        self.state = history.swap_remove(index);
+        history.truncate(index);
+
+        if let Some(derivation) = self.derivation.as_mut() {
+            let start = self.steps - derivation.steps();
+
+            if steps >= start {
+                derivation.truncate(steps - start);
+            } else {
+                *derivation = Derivation::new(&self.state);
+            }
+        }
+
+        self.steps = steps;
+        true
+    }
+
+    /// Move the system to `next_state`, which follows on from the current state.
+    fn advance(&mut self, next_state: Vec<ArenaId>) {
+        let previous = core::mem::replace(&mut self.state, next_state);
+
+        if let Some(history) = self.history.as_mut() {
+            history.push(previous);
+        }
+
+        self.steps += 1;
+    }
+
+    /// Start recording the derivation of the system, which can then be obtained using
+    /// [`LSystem::derivation()`].  The derivation starts from the current state, and is
+    /// restarted from the axiom whenever the system is [`reset`](LSystem::reset).
+    ///
+    /// Recording a derivation keeps every state the system passes through in memory.
+    pub fn track_derivation(&mut self) {
+        self.derivation = Some(Derivation::new(&self.state));
+    }
+
+    /// Returns the derivation of the system, or `None` if [`LSystem::track_derivation()`]
+    /// hasn't been called.  See the [`derivation`](crate::derivation) module for an example.
+    pub fn derivation(&self) -> Option<&Derivation> {
+        self.derivation.as_ref()
     }
 
     /// Iterate the system a single step.
@@ -150,14 +635,286 @@ impl LSystem {
     /// # }
     /// ```
     pub fn step(&mut self) {
-        let mut next_state = Vec::new();
+        let choices = self.choose();
+        self.step_using(&choices);
+    }
 
-        for id in self.state.iter() {
-            next_state.extend(self.rules_map[id].clone());
+    /// Iterate the system a single step, using the successors picked by [`LSystem::choose`].
+    fn step_using(&mut self, choices: &[Option<usize>]) {
+        let mut derivation = self.derivation.take();
+
+        // Work out the length of the next state up front, so that we only allocate once
+        let length = self.next_len(choices);
+        let mut next_state = Vec::with_capacity(length);
+
+        for (index, id) in self.state.iter().enumerate() {
+            next_state.extend_from_slice(self.successor(choices, index, *id));
         }
 
-        self.state = next_state;
-        self.steps += 1;
+        if let Some(derivation) = derivation.as_mut() {
+            let lengths = self
+                .state
+                .iter()
+                .enumerate()
+                .map(|(index, id)| self.successor(choices, index, *id).len());
+            derivation.grow(&next_state, lengths);
+        }
+
+        self.derivation = derivation;
+
+        self.advance(next_state);
+    }
+
+    /// Picks a successor for each symbol in the state with a stochastic rule, returning the
+    /// index of the chosen successor for each symbol.  Symbols with a rule in the rule table
+    /// for the next step use that rule instead.  Returns an empty list if the system has no
+    /// stochastic rules.
+    fn choose(&mut self) -> Vec<Option<usize>> {
+        if self.stochastic.iter().all(Vec::is_empty) {
+            return Vec::new();
+        }
+
+        let step = self.steps;
+        let table = self.tables.iter().find(|table| table.contains(step));
+        let rng = &mut self.rng;
+        let stochastic = &self.stochastic;
+
+        self.state
+            .iter()
+            .map(|id| {
+                let alternatives = &stochastic[id.0];
+
+                if alternatives.is_empty() || table.is_some_and(|table| table.has_rule(*id)) {
+                    return None;
+                }
+
+                let total: f64 = alternatives.iter().map(|(weight, _)| weight).sum();
+                let mut target = rng.gen::<f64>() * total;
+
+                // Rounding can leave a little of the target behind, so fall back to the last one
+                let choice = alternatives.iter().position(|(weight, _)| {
+                    target -= weight;
+                    target < 0.0
+                });
+
+                Some(choice.unwrap_or(alternatives.len() - 1))
+            })
+            .collect()
+    }
+
+    /// Returns the successor of the symbol `id` at position `index` of the state, given the
+    /// successors picked by [`LSystem::choose`].
+    fn successor(&self, choices: &[Option<usize>], index: usize, id: ArenaId) -> &[ArenaId] {
+        match choices.get(index) {
+            Some(Some(choice)) => &self.stochastic[id.0][*choice].1,
+            _ => self.productions_at(self.steps).successor(id),
+        }
+    }
+
+    /// Returns the length of the next state, saturating at `usize::MAX`.
+    fn next_len(&self, choices: &[Option<usize>]) -> usize {
+        self.state
+            .iter()
+            .enumerate()
+            .fold(0usize, |length, (index, id)| {
+                length.saturating_add(self.successor(choices, index, *id).len())
+            })
+    }
+
+    /// Iterate the system a single step, letting `f` decide how each symbol is expanded.
+    ///
+    /// `f` is called once for every symbol in the current state.  Returning `None` expands
+    /// the symbol using the system's production rules, while returning `Some(successor)`
+    /// expands the symbol to `successor` instead.  This makes it possible to build
+    /// environmentally-sensitive systems, whose growth depends on some external state.
+    ///
+    /// Returns an [`LSystemError::InvalidArenaId`] error, leaving the system unchanged, if
+    /// `f` returns a token that doesn't belong to this system.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # builder.axiom(vec![a, a, a])?;
+    /// # builder.transformation_rule(a, vec![a, b])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `aaa` and transformation rule `a -> ab`.
+    /// // Stop the middle symbol from growing
+    /// system.step_with(|context| {
+    ///     if context.index == 1 {
+    ///         Some(vec![context.token])
+    ///     } else {
+    ///         None
+    ///     }
+    /// })?;
+    ///
+    /// assert_eq!(system.render(), "abaab");
+    /// assert_eq!(system.steps(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn step_with<F>(&mut self, mut f: F) -> Result<(), LSystemError>
+    where
+        F: FnMut(StepContext<'_>) -> Option<Vec<ArenaId>>,
+    {
+        // Picking the stochastic successors advances the random number generator, so it's
+        // rewound if the step fails
+        let rng = self.rng.clone();
+        let choices = self.choose();
+        let mut next_state = Vec::with_capacity(self.state.len());
+        let mut lengths = Vec::with_capacity(self.state.len());
+
+        for (index, id) in self.state.iter().enumerate() {
+            let successor = self.successor(&choices, index, *id);
+
+            let context = StepContext {
+                token: *id,
+                index,
+                step: self.steps,
+                successor,
+                state: &self.state,
+            };
+
+            let start = next_state.len();
+
+            match f(context) {
+                Some(replacement) => {
+                    if let Err(e) = self.validate_ids(&replacement) {
+                        self.rng = rng;
+                        return Err(e);
+                    }
+
+                    next_state.extend_from_slice(&replacement);
+                }
+                None => next_state.extend_from_slice(successor),
+            }
+
+            lengths.push(next_state.len() - start);
+        }
+
+        if let Some(derivation) = self.derivation.as_mut() {
+            derivation.grow(&next_state, lengths);
+        }
+
+        self.advance(next_state);
+
+        Ok(())
+    }
+
+    /// Returns a compressed representation of the state of the system after `n` further steps,
+    /// without modifying the system.
+    ///
+    /// The compressed state can be iterated over lazily, which makes it possible to work with
+    /// states far too long to fit in memory.  See the [`compressed`](crate::compressed) module
+    /// for more details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b, a])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> aba`.
+    /// let state = system.step_compressed(3);
+    /// assert_eq!(state.len(), 15);
+    ///
+    /// let tokens: Vec<_> = state.iter().collect();
+    /// system.step_by(3);
+    /// assert_eq!(tokens, system.get_state());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn step_compressed(&self, n: usize) -> CompressedState<'_> {
+        let mut state = CompressedState::new(self);
+        state.step_by(n);
+        state
+    }
+
+    /// Returns the number of distinct tokens in the system.
+    pub(crate) fn token_count(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns the id of the token named `name`, if the system has one.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn token_id(&self, name: &str) -> Option<ArenaId> {
+        self.arena
+            .enumerate()
+            .find_map(|(id, token)| (token.name() == name).then_some(id))
+    }
+
+    /// Returns the first step after `step` at which a rule table starts or stops applying.
+    pub(crate) fn next_table_boundary(&self, step: usize) -> Option<usize> {
+        self.tables
+            .iter()
+            .flat_map(|table| core::iter::once(table.start).chain(table.end))
+            .filter(|boundary| *boundary > step)
+            .min()
+    }
+
+    /// Returns the length the state will have after `n` further steps, saturating at
+    /// `usize::MAX`, without iterating the system.  See the [`analysis`](crate::analysis)
+    /// module for more details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b, a])?;
+    /// # let system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> aba`.
+    /// assert_eq!(system.predicted_len(3), 15);
+    /// assert_eq!(system.predicted_len(40), 2_199_023_255_551);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predicted_len(&self, n: usize) -> usize {
+        self.predicted_counts(n)
+            .into_iter()
+            .fold(0usize, |length, count| length.saturating_add(count))
+    }
+
+    /// Returns how many of each token the state will contain after `n` further steps,
+    /// indexed by [`ArenaId`], without iterating the system.  Counts saturate at `usize::MAX`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b, a])?;
+    /// # let system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> aba`.
+    /// let counts = system.predicted_counts(3);
+    /// assert_eq!(counts[a.0], 8);
+    /// assert_eq!(counts[b.0], 7);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predicted_counts(&self, n: usize) -> Vec<usize> {
+        analysis::growth(self, n).apply(&analysis::counts(self))
+    }
+
+    /// Returns the production rules used to go from step `step` to step `step + 1`.
+    pub(crate) fn productions_at(&self, step: usize) -> &Productions {
+        self.tables
+            .iter()
+            .find(|table| table.contains(step))
+            .map_or(&self.productions, |table| &table.productions)
     }
 
     /// Iterate the system by `n` steps.
@@ -187,6 +944,55 @@ impl LSystem {
         }
     }
 
+    /// Iterate the system by `n` steps, stopping with an [`LSystemError::LimitExceeded`] error
+    /// if a step would grow the state beyond `limits`.  The length of each state is checked
+    /// before it is built, and the system is left at the last state within the limits.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, a])?;
+    /// # let mut system = builder.finish()?;
+    /// use dcc_lsystem::system::StepLimits;
+    ///
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> aa`.
+    /// let limits = StepLimits::new().max_len(100);
+    ///
+    /// // The state doubles in length every step, so this would need 2^64 tokens
+    /// assert!(system.try_step_by(64, limits).is_err());
+    ///
+    /// // We stopped at the largest state within the limit
+    /// assert_eq!(system.steps(), 6);
+    /// assert_eq!(system.state_len(), 64);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_step_by(&mut self, n: usize, limits: StepLimits) -> Result<(), LSystemError> {
+        for _ in 0..n {
+            // Picking the stochastic successors advances the random number generator, so it's
+            // rewound if the step is over the limits
+            let rng = self.rng.clone();
+            let choices = self.choose();
+            let length = self.next_len(&choices);
+
+            if !limits.allows(length) {
+                self.rng = rng;
+                return Err(LSystemError::LimitExceeded {
+                    step: self.steps + 1,
+                    length,
+                });
+            }
+
+            self.step_using(&choices);
+        }
+
+        Ok(())
+    }
+
     /// Returns the number of iterations the system has undergone so far
     ///
     /// # Example
@@ -238,13 +1044,368 @@ impl LSystem {
     /// # }
     /// ```
     pub fn render(&self) -> String {
-        self.state
+        let mut rendered = String::new();
+        self.render_into(&mut rendered);
+        rendered
+    }
+
+    /// Returns the name of the token `id`.
+    fn name(&self, id: ArenaId) -> &str {
+        // unwrap: the only way to obtain an LSystem is through one of the builders,
+        //         which verify that all indexes are valid.
+        self.arena.get(id).unwrap().name()
+    }
+
+    /// Appends the current state of the system to `buffer`, so that a buffer can be reused
+    /// between renders.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, a])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> aa`.
+    /// let mut buffer = String::new();
+    ///
+    /// for expected in &["a", "aa", "aaaa"] {
+    ///     buffer.clear();
+    ///     system.render_into(&mut buffer);
+    ///     assert_eq!(&buffer, expected);
+    ///
+    ///     system.step();
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_into(&self, buffer: &mut String) {
+        let length: usize = self.state.iter().map(|id| self.name(*id).len()).sum();
+        buffer.reserve(length);
+
+        for id in self.state.iter() {
+            buffer.push_str(self.name(*id));
+        }
+    }
+
+    /// Returns a hash of the current state of the system, which is cheap to compare when
+    /// looking for duplicate states.  The hash only depends on the names of the tokens in the
+    /// state, so systems built separately with the same tokens give the same hash for the same
+    /// state, whatever order their tokens were registered in.  The hash is also the same from
+    /// one run of a program to the next, so it can be stored.
+    ///
+    /// Different states can have the same hash, so use [`LSystem::same_state`] to check that
+    /// two states are really equal.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// let mut first = LSystemBuilder::from_grammar("axiom: A\nA => A B\nB => A")?.finish()?;
+    ///
+    /// // The same system, with the tokens registered in the other order
+    /// let mut second = LSystemBuilder::from_grammar("axiom: B\nB => A\nA => A B")?.finish()?;
+    /// second.step();
+    ///
+    /// assert_eq!(first.render(), second.render());
+    /// assert_ne!(first.get_state(), second.get_state());
+    /// assert_eq!(first.state_hash(), second.state_hash());
+    /// assert!(first.same_state(&second));
+    ///
+    /// first.step();
+    /// assert_ne!(first.state_hash(), second.state_hash());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn state_hash(&self) -> u64 {
+        // An FNV-1a hash of each token's name, combined with a polynomial rolling hash
+        let token_hashes: Vec<u64> = self
+            .arena
             .iter()
+            .map(|token| {
+                token
+                    .name()
+                    .bytes()
+                    .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+                    })
+            })
+            .collect();
+
+        self.state.iter().fold(0, |hash: u64, id| {
+            hash.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                .wrapping_add(token_hashes[id.0])
+        })
+    }
+
+    /// Returns whether the current states of `self` and `other` are made up of the same
+    /// tokens, comparing tokens by name so that the systems needn't have been built by the
+    /// same builder.  See [`LSystem::state_hash`] for an example.
+    pub fn same_state(&self, other: &LSystem) -> bool {
+        self.state.len() == other.state.len()
+            && self
+                .state
+                .iter()
+                .zip(other.state.iter())
+                .all(|(id, other_id)| self.name(*id) == other.name(*other_id))
+    }
+
+    /// Returns a copy of the system with its tokens renamed according to `mapping`, which pairs
+    /// the names of tokens with new names (e.g. an array of pairs, or a `&HashMap<&str, &str>`).
+    /// Tokens which `mapping` doesn't mention keep their
+    /// names, and tokens mapped to the same name are merged into one token.  This is the
+    /// homomorphism often used to separate the symbols which make a system grow from the
+    /// symbols which are drawn.
+    ///
+    /// The state, axiom and number of steps of the new system are the renamed state, axiom and
+    /// number of steps of this system.  The rules of merged tokens are kept if their renamed
+    /// successors agree, and otherwise the merged token is left unchanged by each step.  The
+    /// history and derivation aren't copied.
+    ///
+    /// Returns an [`LSystemError::InvalidToken`] error if one of the new names isn't a valid
+    /// token name.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// let mut system = LSystemBuilder::from_grammar("axiom: A\nA => A B\nB => A")?.finish()?;
+    /// system.step_by(3);
+    /// assert_eq!(system.render(), "ABAAB");
+    ///
+    /// // Draw both kinds of cell the same way
+    /// let mapped = system.map_tokens([("A", "F"), ("B", "F")])?;
+    ///
+    /// assert_eq!(mapped.render(), "FFFFF");
+    /// assert_eq!(mapped.tokens().count(), 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map_tokens<I, S, T>(&self, mapping: I) -> Result<LSystem, LSystemError>
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: AsRef<str>,
+        T: AsRef<str>,
+    {
+        let mapping: HashMap<String, String> = mapping
+            .into_iter()
+            .map(|(name, new_name)| (name.as_ref().to_string(), new_name.as_ref().to_string()))
+            .collect();
+
+        let mut arena = Arena::new();
+        let mut ids: HashMap<&str, ArenaId> = HashMap::new();
+
+        // The id of each token of this system in the new system
+        let mut translation = Vec::with_capacity(self.arena.len());
+
+        for token in self.arena.iter() {
+            let name = mapping
+                .get(token.name())
+                .map(String::as_str)
+                .unwrap_or(token.name());
+
+            let id = match ids.get(name) {
+                Some(id) => *id,
+                None => {
+                    let id = arena.push(Token::new(name)?);
+                    ids.insert(name, id);
+                    id
+                }
+            };
+
+            translation.push(id);
+        }
+
+        let translate =
+            |ids: &[ArenaId]| -> Vec<ArenaId> { ids.iter().map(|id| translation[id.0]).collect() };
+
+        // The tokens of this system which are merged into each token of the new system
+        let mut preimages = vec![Vec::new(); arena.len()];
+
+        for (index, id) in translation.iter().enumerate() {
+            preimages[id.0].push(ArenaId(index));
+        }
+
+        let merge_rules = |productions: &Productions| -> HashMap<ArenaId, Vec<ArenaId>> {
+            preimages
+                .iter()
+                .enumerate()
+                .map(|(index, preimage)| {
+                    let successors = preimage
+                        .iter()
+                        .map(|id| translate(productions.successor(*id)));
+
+                    let id = ArenaId(index);
+                    (id, common(successors).unwrap_or_else(|| vec![id]))
+                })
+                .collect()
+        };
+
+        let tables = self
+            .tables
+            .iter()
+            .map(|table| RuleTable {
+                start: table.start,
+                end: table.end,
+                productions: Productions::new(arena.len(), &merge_rules(&table.productions)),
+                has_rule: preimages
+                    .iter()
+                    .map(|preimage| preimage.iter().any(|id| table.has_rule(*id)))
+                    .collect(),
+            })
+            .collect();
+
+        let stochastic = preimages
+            .iter()
+            .map(|preimage| {
+                let alternatives = preimage.iter().map(|id| {
+                    self.stochastic
+                        .get(id.0)
+                        .map(|alternatives| {
+                            alternatives
+                                .iter()
+                                .map(|(weight, successor)| (*weight, translate(successor)))
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                });
+
+                common(alternatives).unwrap_or_default()
+            })
+            .collect();
+
+        let rules_map = merge_rules(&self.productions);
+        let axiom = translate(&self.axiom);
+
+        let mut system = LSystem::new(arena, axiom, rules_map, tables, stochastic, self.seed);
+        system.state = translate(&self.state);
+        system.steps = self.steps;
+
+        Ok(system)
+    }
+
+    /// Writes the current state of the system to `writer`, without building the whole
+    /// state as a string first.  Each token is written separately, so `writer` should
+    /// usually be buffered.  Requires the `std` feature.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> ab`.
+    /// system.step_by(2);
+    ///
+    /// let mut output = Vec::new();
+    /// system.render_to(&mut output)?;
+    /// assert_eq!(output, b"abb");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn render_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for id in self.state.iter() {
+            writer.write_all(self.name(*id).as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of tokens in the current state of the system.
+    ///
+    /// Unlike `render().len()`, this doesn't build a string, and counts tokens rather than bytes.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("bee")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> a bee`.
+    /// system.step_by(3);
+    /// assert_eq!(system.state_len(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn state_len(&self) -> usize {
+        self.state.len()
+    }
+
+    /// Returns the number of times each token appears in the current state of the system.
+    /// Tokens which don't appear in the state are omitted.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # let c = builder.token("c")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b, b])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> abb`.
+    /// system.step_by(3);
+    ///
+    /// let counts = system.token_counts();
+    /// assert_eq!(counts[&a], 1);
+    /// assert_eq!(counts[&b], 6);
+    /// assert!(!counts.contains_key(&c));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn token_counts(&self) -> BTreeMap<ArenaId, usize> {
+        let mut counts = BTreeMap::new();
+
+        for id in self.state.iter() {
+            *counts.entry(*id).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    /// Returns an iterator over the tokens currently in the system, together with their
+    /// [`ArenaId`]'s.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::{LSystemError, LSystemBuilder};
+    /// # fn main() -> Result<(), LSystemError> {
+    /// # let mut builder = LSystemBuilder::new();
+    /// # let a = builder.token("a")?;
+    /// # let b = builder.token("b")?;
+    /// # builder.axiom(vec![a])?;
+    /// # builder.transformation_rule(a,vec![a, b])?;
+    /// # let mut system = builder.finish()?;
+    /// // `system` is an LSystem with axiom `a` and transformation rule `a -> ab`.
+    /// system.step();
+    ///
+    /// let names: Vec<_> = system.tokens().map(|(_, token)| token.name()).collect();
+    /// assert_eq!(names, vec!["a", "b"]);
+    ///
+    /// let ids: Vec<_> = system.tokens().map(|(id, _)| *id).collect();
+    /// assert_eq!(ids, system.get_state());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tokens(&self) -> impl Iterator<Item = (&ArenaId, &Token)> + '_ {
+        self.state.iter().map(move |id| {
             // unwrap: the only way to obtain an LSystem is through one of the builders,
             //         which verify that all indexes are valid.
-            .map(|id| self.arena.get(*id).unwrap().name())
-            .collect::<Vec<_>>()
-            .join("")
+            (id, self.arena.get(*id).unwrap())
+        })
     }
 
     /// Returns a slice consisting of the [`ArenaId`]'s of the tokens currently in the system.
@@ -50,3 +50,2767 @@ fn fractal_binary_tree() -> Result<(), LSystemError> {
 
     Ok(())
 }
+
+#[test]
+fn multiple_turtles() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .token("A", TurtleAction::SwitchTurtle(String::from("a")))?
+        .token("M", TurtleAction::SwitchTurtle(String::from("main")))?
+        .axiom("F A + F F M F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    // Each turtle keeps its own position and heading, and the main turtle's lines come first
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], (0.0, 0.0, 10.0, 0.0));
+    assert_eq!(lines[1], (10.0, 0.0, 20.0, 0.0));
+    assert!((lines[2].3 - 10.0).abs() < 1e-9);
+    assert!((lines[3].3 - 20.0).abs() < 1e-9);
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+
+    let a = builder.token("A")?;
+    let b = builder.token("B")?;
+
+    builder.axiom(vec![a])?;
+    builder.transformation_rule(a, vec![a, b])?;
+    builder.transformation_rule(b, vec![a])?;
+
+    // A builder can be persisted before it is finished
+    let json = serde_json::to_string(&builder).unwrap();
+    let builder: LSystemBuilder = serde_json::from_str(&json).unwrap();
+
+    let mut system = builder.finish()?;
+    system.step_by(3);
+
+    // A partially stepped system resumes where it left off
+    let json = serde_json::to_string(&system).unwrap();
+    let mut resumed: LSystem = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(resumed.steps(), 3);
+    assert_eq!(resumed.render(), "ABAAB");
+
+    resumed.step();
+    assert_eq!(resumed.render(), "ABAABABA");
+
+    Ok(())
+}
+
+#[test]
+fn turtle_grammar_errors() {
+    use crate::turtle::TurtleLSystemBuilder;
+
+    let error = |grammar: &str| match TurtleLSystemBuilder::from_grammar(grammar) {
+        Err(LSystemError::ParseError { line, column, .. }) => (line, column),
+        _ => panic!("expected a parse error"),
+    };
+
+    assert_eq!(error("axiom: F\nF = fly 30"), (2, 5));
+    assert_eq!(error("F = forward 3O"), (1, 13));
+    assert_eq!(error("F = rotate 5..1"), (1, 12));
+    assert_eq!(error("F = push\nF = pop"), (2, 1));
+    assert!(TurtleLSystemBuilder::from_grammar("F = rotate -5..5\naxiom: F").is_ok());
+}
+
+#[test]
+fn fractional_distances_and_angles() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::TurtleLSystemBuilder;
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        F = forward 2.5
+        G = forward 0.25..0.5
+        + = rotate 137.5
+        axiom: F + F G
+        ",
+    )?;
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    assert_eq!(lines[0], (0.0, 0.0, 2.5, 0.0));
+
+    let (x1, y1, x2, y2) = lines[1];
+    let angle = (y2 - y1).atan2(x2 - x1).to_degrees();
+    assert!((angle - 137.5).abs() < 1e-9);
+    assert!(((x2 - x1).hypot(y2 - y1) - 2.5).abs() < 1e-9);
+
+    // Ranges can have fractional bounds
+    let (x1, y1, x2, y2) = lines[2];
+    let length = (x2 - x1).hypot(y2 - y1);
+    assert!(length > 0.25 - 1e-9 && length < 0.5 + 1e-9);
+
+    Ok(())
+}
+
+#[test]
+fn step_length_is_scaled_and_restored_by_pop() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("S", TurtleAction::MultiplyStep(0.5))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F [ S F [ S F ] ] F")?
+        .scale(2.0);
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    let lengths: Vec<f64> = lines
+        .iter()
+        .map(|(x1, y1, x2, y2)| (x2 - x1).hypot(y2 - y1))
+        .collect();
+    assert_eq!(lengths, vec![20.0, 10.0, 5.0, 20.0]);
+
+    Ok(())
+}
+
+#[test]
+fn set_heading_and_initial_heading() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(45.0))?
+        .token("E", TurtleAction::SetHeading(0.0))?
+        .token("H", TurtleAction::Home)?
+        .axiom("F + E F H F")?
+        .initial_heading(90.0);
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    let direction = |(x1, y1, x2, y2): (f64, f64, f64, f64)| {
+        ((x2 - x1).round() as i32, (y2 - y1).round() as i32)
+    };
+
+    // North to start with, then east after setting the heading, then north again from home
+    assert_eq!(direction(lines[0]), (0, 10));
+    assert_eq!(direction(lines[1]), (10, 0));
+    assert_eq!(direction(lines[2]), (0, 10));
+    assert_eq!(lines[2].0, 0.0);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn canvas_rendering_matches_the_image_renderer() -> Result<(), LSystemError> {
+    use crate::renderer::{ImageRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use ::image::{ImageBuffer, Rgb};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(20.0))?
+        .token("+", TurtleAction::Rotate(60.0))?
+        .token("D", TurtleAction::Dot(3.0))?
+        .axiom("F + F D + F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let options = ImageRendererOptionsBuilder::new()
+        .padding(5)
+        .thickness(2.0)
+        .build();
+
+    let canvas: ImageBuffer<Rgb<u8>, Vec<u8>> = renderer.render_canvas(&system, &options);
+    assert_eq!(canvas, renderer.render(&system, &options));
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn dashes_continue_around_corners() -> Result<(), LSystemError> {
+    use crate::image_renderer::LineCap;
+    use crate::renderer::{
+        ImageRendererOptionsBuilder, Renderer, StrokeStyle, TikzRendererOptionsBuilder,
+    };
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use ::image::Rgb;
+
+    let white = Rgb([255u8, 255u8, 255u8]);
+    let black = Rgb([0u8, 0u8, 0u8]);
+
+    // An L shape, from (10, 30) to the corner at (30, 30) and then up to (30, 10)
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(20.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let options = ImageRendererOptionsBuilder::new()
+        .padding(10)
+        .thickness(2.0)
+        .line_cap(LineCap::Butt)
+        .stroke_style(StrokeStyle::dashed(6.0, 4.0))
+        .build();
+
+    let buffer = renderer.render(&system, &options);
+
+    for (x, y, color) in [
+        (13, 30, black),
+        (18, 30, white),
+        (23, 30, black),
+        (27, 30, white),
+        (30, 27, black),
+        (30, 22, white),
+        (30, 17, black),
+    ] {
+        assert_eq!(*buffer.get_pixel(x, y), color, "pixel ({}, {})", x, y);
+    }
+
+    // An odd pattern is repeated, and the second line starts 20 units into it
+    let options = TikzRendererOptionsBuilder::new()
+        .scale(0.1)
+        .stroke_style(StrokeStyle::new(vec![3.0], 1.0))
+        .build();
+
+    let picture = renderer.render(&system, &options);
+    assert!(picture.contains("dash pattern=on 0.3000cm off 0.3000cm, dash phase=0.1000cm]"));
+    assert!(picture.contains("dash phase=2.1000cm]"));
+
+    assert!(StrokeStyle::new(vec![0.0, -1.0], 0.0).is_solid());
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn line_caps_and_joins() -> Result<(), LSystemError> {
+    use crate::image_renderer::{LineCap, LineJoin};
+    use crate::renderer::{ImageRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use ::image::Rgb;
+
+    let white = Rgb([255u8, 255u8, 255u8]);
+    let black = Rgb([0u8, 0u8, 0u8]);
+
+    // An L shape, with its corner at (30, 30) in the image
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(20.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let render = |cap: LineCap, join: LineJoin| {
+        let options = ImageRendererOptionsBuilder::new()
+            .padding(10)
+            .thickness(4.0)
+            .line_cap(cap)
+            .line_join(join)
+            .build();
+
+        renderer.render(&system, &options)
+    };
+
+    // Caps are drawn at the start of the first line, at (10, 30)
+    assert_eq!(
+        *render(LineCap::Butt, LineJoin::Miter).get_pixel(8, 30),
+        white
+    );
+    assert_eq!(
+        *render(LineCap::Square, LineJoin::Miter).get_pixel(8, 30),
+        black
+    );
+    assert_eq!(
+        *render(LineCap::Round, LineJoin::Miter).get_pixel(8, 30),
+        black
+    );
+    assert_eq!(
+        *render(LineCap::Round, LineJoin::Miter).get_pixel(7, 34),
+        white
+    );
+
+    // Only a miter fills the outside of the corner
+    assert_eq!(
+        *render(LineCap::Butt, LineJoin::Miter).get_pixel(34, 34),
+        black
+    );
+    assert_eq!(
+        *render(LineCap::Butt, LineJoin::Bevel).get_pixel(33, 33),
+        white
+    );
+    assert_eq!(
+        *render(LineCap::Butt, LineJoin::Round).get_pixel(34, 34),
+        white
+    );
+    assert_eq!(
+        *render(LineCap::Butt, LineJoin::Round).get_pixel(32, 32),
+        black
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn per_segment_colors() -> Result<(), LSystemError> {
+    use crate::renderer::{ImageRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use ::image::Rgb;
+
+    let red = Rgb([255u8, 0u8, 0u8]);
+    let black = Rgb([0u8, 0u8, 0u8]);
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(20.0))?
+        .token("R", TurtleAction::SetColor(red))?
+        .token("(", TurtleAction::PushColor)?
+        .token(")", TurtleAction::PopColor)?
+        .axiom("F ( R F ) F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let options = ImageRendererOptionsBuilder::new()
+        .padding(5)
+        .thickness(1.0)
+        .fill_color(Rgb([255u8, 255u8, 255u8]))
+        .line_color(black)
+        .build();
+
+    let buffer = renderer.render(&system, &options);
+
+    // The three segments run left to right along y = 5
+    assert_eq!(*buffer.get_pixel(15, 5), black);
+    assert_eq!(*buffer.get_pixel(35, 5), red);
+    assert_eq!(*buffer.get_pixel(55, 5), black);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn supersampling_smooths_edges() -> Result<(), LSystemError> {
+    use crate::renderer::{ImageRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use ::image::{ImageBuffer, Rgb};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(40.0))?
+        .token("+", TurtleAction::Rotate(30.0))?
+        .axiom("+ F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let mut options = ImageRendererOptionsBuilder::new();
+    options
+        .padding(5)
+        .thickness(2.0)
+        .fill_color(Rgb([255u8, 255u8, 255u8]))
+        .line_color(Rgb([0u8, 0u8, 0u8]));
+
+    let sharp = renderer.render(&system, &options.build());
+    let smooth = renderer.render(&system, &options.supersample(4).build());
+
+    let greys = |buffer: &ImageBuffer<Rgb<u8>, Vec<u8>>| {
+        buffer
+            .pixels()
+            .filter(|Rgb([red, _, _])| *red != 0 && *red != 255)
+            .count()
+    };
+
+    assert_eq!(sharp.dimensions(), smooth.dimensions());
+    assert_eq!(greys(&sharp), 0);
+    assert!(greys(&smooth) > 0);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn depth_styling() -> Result<(), LSystemError> {
+    use crate::renderer::{segments, ImageRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use ::image::Rgb;
+
+    let white = Rgb([255u8, 255u8, 255u8]);
+    let brown = Rgb([100u8, 50u8, 0u8]);
+    let green = Rgb([0u8, 200u8, 0u8]);
+
+    // A single line, nested one branch deeper every 20 units
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(20.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F [ F [ F ] ]")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let state = renderer.compute(system.get_state());
+    let depths: Vec<_> = segments(&state).map(|(_, style)| style.depth).collect();
+    assert_eq!(depths, vec![0, 1, 2]);
+
+    let options = ImageRendererOptionsBuilder::new()
+        .padding(5)
+        .thickness(4.0)
+        .fill_color(white)
+        .depth_colors(brown, green)
+        .depth_thickness(0.5)
+        .build();
+
+    let buffer = renderer.render(&system, &options);
+
+    // The line runs left to right along y = 5
+    assert_eq!(*buffer.get_pixel(15, 5), brown);
+    assert_eq!(*buffer.get_pixel(35, 5), Rgb([50, 125, 0]));
+    assert_eq!(*buffer.get_pixel(55, 5), green);
+
+    // The tip is a quarter as thick as the trunk
+    assert_eq!(*buffer.get_pixel(15, 8), brown);
+    assert_eq!(*buffer.get_pixel(55, 8), white);
+
+    Ok(())
+}
+
+#[test]
+fn unbalanced_pops_are_counted() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleContainer, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F ] F [ F ] ]")?;
+
+    let (system, renderer) = builder.finish()?;
+    let state = renderer.compute(system.get_state());
+
+    // The unbalanced pops leave the turtle where it was
+    assert_eq!(state.unbalanced_pops(), 2);
+    assert_eq!(state.inner().inner().x(), 20.0);
+
+    Ok(())
+}
+
+#[test]
+fn events_record_color_changes_and_new_turtles() -> Result<(), LSystemError> {
+    use crate::renderer::{EventRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleEvent, TurtleLSystemBuilder};
+    use ::image::Rgb;
+
+    let red = Rgb([255u8, 0u8, 0u8]);
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("R", TurtleAction::SetColor(red))?
+        .token("U", TurtleAction::PenUp)?
+        .token("A", TurtleAction::SwitchTurtle(String::from("a")))?
+        .axiom("R R F U F A F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let events = renderer.render(&system, &EventRendererOptions::default());
+
+    // The color change is only recorded once, just before it's used
+    assert_eq!(
+        events,
+        vec![
+            vec![
+                TurtleEvent::ColorChange(Some(red)),
+                TurtleEvent::LineTo { x: 10.0, y: 0.0 },
+                TurtleEvent::PenChange(false),
+                TurtleEvent::MoveTo { x: 20.0, y: 0.0 },
+            ],
+            vec![TurtleEvent::LineTo { x: 10.0, y: 0.0 }],
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn thickness_tokens() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleContainer, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("T", TurtleAction::SetThickness(8.0))?
+        .token("!", TurtleAction::DecrementThickness(2.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F T F [ ! F ! F ] F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let state = renderer.compute(system.get_state());
+
+    let turtles = state.turtles();
+    let thicknesses: Vec<f64> = turtles[0]
+        .inner()
+        .styles()
+        .iter()
+        .map(|style| style.resolved_thickness(1.0))
+        .collect();
+
+    // The thickness tapers inside the branch and is restored when the branch ends
+    assert_eq!(thicknesses, vec![1.0, 8.0, 6.0, 4.0, 8.0]);
+
+    Ok(())
+}
+
+#[test]
+fn seeded_stochastic_actions() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder, Uniform};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token(
+            "F",
+            TurtleAction::StochasticForward(Box::new(Uniform::new(1, 1000))),
+        )?
+        .token(
+            "+",
+            TurtleAction::StochasticRotate(Box::new(Uniform::new(-90, 90))),
+        )?
+        .axiom("F + F + F + F + F")?;
+
+    let render = |seed: u64| -> Result<Vec<(f64, f64, f64, f64)>, LSystemError> {
+        let mut builder = builder.clone();
+        builder.seed(seed);
+
+        let (system, renderer) = builder.finish()?;
+        Ok(renderer.render(&system, &DataRendererOptions::default()))
+    };
+
+    assert_eq!(render(7)?, render(7)?);
+    assert_ne!(render(7)?, render(8)?);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn apng_output() -> Result<(), LSystemError> {
+    use crate::image_renderer::AnimationFormat;
+    use crate::renderer::{Renderer, VideoRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let path = std::env::temp_dir().join(format!("dcc-lsystem-{}.png", std::process::id()));
+    let options = VideoRendererOptionsBuilder::new()
+        .filename(path.to_string_lossy())
+        .format(AnimationFormat::Apng)
+        .padding(2)
+        .thickness(1.0)
+        .build();
+
+    renderer.render(&system, &options)?;
+
+    let decoder = png::Decoder::new(std::fs::File::open(&path)?);
+    let reader = decoder.read_info().unwrap();
+    let frames = reader.info().animation_control().map(|a| a.num_frames);
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(frames, Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn renderer_can_be_reused() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(1.0))?
+        .axiom("F")?
+        .rule("F => F F")?;
+
+    let (mut system, renderer) = builder.finish()?;
+    let options = DataRendererOptions::default();
+
+    assert_eq!(renderer.render(&system, &options).len(), 1);
+
+    system.step_by(3);
+    assert_eq!(renderer.render(&system, &options).len(), 8);
+
+    // Rendering doesn't leave any state behind in the renderer
+    assert_eq!(
+        renderer.render(&system, &options),
+        renderer.render(&system, &options)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn symbol_context_tracks_depth() -> Result<(), LSystemError> {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(1.0))?
+        .token("X", TurtleAction::Nothing)?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("X [ X [ X ] X ] X")?;
+
+    let (system, mut renderer) = builder.finish()?;
+    let x = system.get_state()[0];
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let recorded = Rc::clone(&seen);
+    let mut calls = 0;
+
+    renderer.register_with_context(x, move |_, context| {
+        calls += 1;
+        recorded
+            .borrow_mut()
+            .push((calls, context.index, context.length, context.depth));
+    });
+
+    renderer.render(&system, &DataRendererOptions::default());
+
+    assert_eq!(
+        *seen.borrow(),
+        vec![
+            (1, 0, 9, 0),
+            (2, 2, 9, 1),
+            (3, 4, 9, 2),
+            (4, 6, 9, 1),
+            (5, 8, 9, 0)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rule_tables_follow_the_step_count() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+
+    let a = builder.token("A")?;
+    let b = builder.token("B")?;
+
+    builder.axiom(vec![a])?;
+    builder.transformation_rule(a, vec![a, b])?;
+    builder.transformation_rule(b, vec![a])?;
+    builder.rule_table(1..3, vec![(a, vec![b, b])])?;
+    builder.rule_table(2..=5, vec![(b, vec![a, a])])?;
+
+    let initial = builder.finish()?;
+    let mut system = initial.clone();
+
+    let mut renders = Vec::new();
+    for _ in 0..5 {
+        system.step();
+        renders.push(system.render());
+    }
+
+    assert_eq!(
+        renders,
+        vec!["AB", "BBA", "AABB", "ABABAAAA", "ABAAABAAABABABAB"]
+    );
+
+    // Compressed states agree with the flat state, wherever they start from
+    system.reset();
+    system.step();
+    for steps in 0..5 {
+        let compressed = system.step_compressed(steps);
+        let tokens: Vec<_> = compressed.iter().collect();
+
+        let mut flat = system.clone();
+        flat.step_by(steps);
+
+        assert_eq!(compressed.len(), flat.get_state().len());
+        assert_eq!(tokens, flat.get_state());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn step_with_overrides_expansions() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+
+    let a = builder.token("A")?;
+    let b = builder.token("B")?;
+
+    builder.axiom(vec![a, b])?;
+    builder.transformation_rule(a, vec![a, b])?;
+    builder.transformation_rule(b, vec![a])?;
+
+    let mut system = builder.finish()?;
+
+    // Delete every `B`, and record what the rules would have done instead
+    let mut skipped = Vec::new();
+    system.step_with(|context| {
+        assert_eq!(context.step, 0);
+        assert_eq!(context.state, &[a, b]);
+
+        if context.token == b {
+            skipped.push((context.index, context.successor.to_vec()));
+            Some(Vec::new())
+        } else {
+            None
+        }
+    })?;
+
+    assert_eq!(system.render(), "AB");
+    assert_eq!(skipped, vec![(1, vec![a])]);
+
+    // Invalid tokens are rejected without touching the state
+    assert!(system.step_with(|_| Some(vec![crate::ArenaId(7)])).is_err());
+    assert_eq!(system.render(), "AB");
+    assert_eq!(system.steps(), 1);
+
+    // ...or the choices made by stochastic rules later on
+    let seeded = || -> Result<LSystem, LSystemError> {
+        let mut builder = LSystemBuilder::new();
+        let a = builder.token("A")?;
+        let b = builder.token("B")?;
+        builder.axiom(vec![a])?;
+        builder.stochastic_rule(a, vec![(1.0, vec![a, b]), (1.0, vec![b, a])])?;
+        builder.seed(5);
+        builder.finish()
+    };
+
+    let mut expected = seeded()?;
+    expected.step_by(10);
+
+    let mut system = seeded()?;
+    assert!(system.step_with(|_| Some(vec![crate::ArenaId(7)])).is_err());
+    system.step_by(10);
+    assert_eq!(system.render(), expected.render());
+
+    Ok(())
+}
+
+#[test]
+fn try_step_by_stops_within_the_limits() -> Result<(), LSystemError> {
+    use crate::system::StepLimits;
+
+    let seeded = || -> Result<LSystem, LSystemError> {
+        let mut builder = LSystemBuilder::new();
+        let a = builder.token("A")?;
+        let b = builder.token("B")?;
+        let c = builder.token("C")?;
+        builder.axiom(vec![a])?;
+        builder.stochastic_rule(a, vec![(1.0, vec![a, b]), (1.0, vec![a, c])])?;
+        builder.seed(5);
+        builder.finish()
+    };
+
+    let mut expected = seeded()?;
+    expected.step_by(10);
+
+    // Each step adds a token, so only four steps fit
+    let mut system = seeded()?;
+    assert!(system
+        .try_step_by(10, StepLimits::new().max_len(5))
+        .is_err());
+    assert_eq!(system.steps(), 4);
+
+    // The step which went over the limits doesn't change the choices made afterwards
+    system.step_by(6);
+    assert_eq!(system.render(), expected.render());
+
+    Ok(())
+}
+
+#[test]
+fn derivation_links_parents_and_children() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+
+    let a = builder.token("A")?;
+    let b = builder.token("B")?;
+
+    builder.axiom(vec![a])?;
+    builder.transformation_rule(a, vec![a, b])?;
+    builder.transformation_rule(b, vec![a])?;
+
+    let mut system = builder.finish()?;
+    assert!(system.derivation().is_none());
+
+    system.track_derivation();
+    system.step();
+    system.step_with(|context| {
+        if context.token == a {
+            Some(vec![b, b, b])
+        } else {
+            None
+        }
+    })?;
+    assert_eq!(system.render(), "BBBA");
+
+    let derivation = system.derivation().unwrap();
+    assert_eq!(derivation.steps(), 2);
+    assert_eq!(derivation.nodes().len(), 1 + 2 + 4);
+
+    // Every node in the latest generation matches the state, and points back to its parent
+    let current = derivation.generation(2).unwrap();
+    let tokens: Vec<_> = current.iter().map(|node| node.token()).collect();
+    assert_eq!(tokens, system.get_state());
+
+    for (position, node) in current.iter().enumerate() {
+        let parent = derivation.node(node.parent().unwrap()).unwrap();
+        assert!(parent
+            .children()
+            .contains(&derivation.current_index(position)));
+        assert_eq!(node.step(), 2);
+    }
+
+    let root = &derivation.generation(0).unwrap()[0];
+    assert_eq!(root.children(), 1..3);
+
+    // Resetting restarts the derivation from the axiom
+    system.reset();
+    assert_eq!(system.derivation().unwrap().nodes().len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn history_rewinds_state_and_derivation() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+
+    let a = builder.token("A")?;
+    let b = builder.token("B")?;
+
+    builder.axiom(vec![a])?;
+    builder.transformation_rule(a, vec![a, b])?;
+    builder.transformation_rule(b, vec![a])?;
+
+    let mut system = builder.finish()?;
+
+    // Without history there's nothing to go back to
+    system.step();
+    assert!(!system.step_back());
+
+    system.enable_history();
+    system.step();
+    system.track_derivation();
+    system.step_by(3);
+    assert_eq!(system.steps(), 5);
+    assert_eq!(system.derivation().unwrap().steps(), 3);
+
+    let mut expected = system.clone();
+    expected.reset();
+    expected.step_by(3);
+
+    assert!(system.rewind_to(3));
+    assert_eq!(system.get_state(), expected.get_state());
+    assert_eq!(system.derivation().unwrap().steps(), 1);
+    assert!(system
+        .derivation()
+        .unwrap()
+        .nodes()
+        .iter()
+        .all(|node| node.step() <= 1));
+
+    // Stepping forwards again extends the derivation from the rewound state
+    system.step();
+    expected.step();
+    assert_eq!(system.get_state(), expected.get_state());
+    assert_eq!(system.derivation().unwrap().steps(), 2);
+
+    // We can't rewind past the start of the history, or into the future
+    assert!(!system.rewind_to(0));
+    assert!(!system.rewind_to(10));
+    assert!(system.rewind_to(1));
+    assert_eq!(system.render(), "AB");
+    assert_eq!(system.derivation().unwrap().steps(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn tikz_output_uses_segment_styles() -> Result<(), LSystemError> {
+    use crate::renderer::{Renderer, TikzRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("R", TurtleAction::SetColor(::image::Rgb([255, 0, 0])))?
+        .token("T", TurtleAction::SetThickness(5.0))?
+        .axiom("F R T F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let options = TikzRendererOptionsBuilder::new()
+        .scale(0.5)
+        .standalone(true)
+        .build();
+
+    let picture = renderer.render(&system, &options);
+    let lines: Vec<_> = picture.lines().collect();
+
+    assert_eq!(lines[0], "\\documentclass[tikz]{standalone}");
+    assert_eq!(
+        lines[3],
+        "  \\draw[line width=1.0000cm, color={rgb,255:red,0;green,0;blue,0}] (0.000, 0.000) -- (10.000, 0.000);"
+    );
+    assert_eq!(
+        lines[4],
+        "  \\draw[line width=2.5000cm, color={rgb,255:red,255;green,0;blue,0}] (10.000, 0.000) -- (20.000, 0.000);"
+    );
+    assert_eq!(lines.last(), Some(&"\\end{document}"));
+
+    Ok(())
+}
+
+#[test]
+fn vector_output_is_well_formed() -> Result<(), LSystemError> {
+    use crate::renderer::{Renderer, VectorRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use crate::vector::VectorFormat;
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let mut options = VectorRendererOptionsBuilder::new();
+    options.scale(2.0).padding(5.0);
+
+    let eps =
+        String::from_utf8(renderer.render(&system, &options.format(VectorFormat::Eps).build()))
+            .unwrap();
+    assert!(eps.contains("%%BoundingBox: 0 0 30 30\n"));
+    assert_eq!(eps.matches(" lineto").count(), 2);
+
+    let pdf =
+        String::from_utf8(renderer.render(&system, &options.format(VectorFormat::Pdf).build()))
+            .unwrap();
+    assert!(pdf.contains("/MediaBox [0 0 30.000 30.000]"));
+    assert_eq!(pdf.matches(" l\n").count(), 2);
+
+    // Every cross reference entry points at the object it describes
+    let xref = pdf.find("xref\n").unwrap();
+    for (index, entry) in pdf[xref..].lines().skip(3).take(4).enumerate() {
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn plotter_output_lifts_pen_between_branches() -> Result<(), LSystemError> {
+    use crate::plotter::PlotterFormat;
+    use crate::renderer::{PlotterRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F [ + F ] F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let mut options = PlotterRendererOptionsBuilder::new();
+    options.scale(2.0);
+
+    let hpgl = renderer.render(&system, &options.format(PlotterFormat::Hpgl).build());
+    assert_eq!(
+        hpgl,
+        "IN;SP1;\nPU0,0;\nPD800,0,800,800;\nPU800,0;\nPD1600,0;\nPU0,0;SP0;\n"
+    );
+
+    let gcode = renderer.render(&system, &options.format(PlotterFormat::GCode).build());
+    assert_eq!(gcode.matches("G1 Z0.000").count(), 2);
+    assert!(gcode.contains("G0 X20.000 Y0.000 F3000\n"));
+
+    Ok(())
+}
+
+#[test]
+fn svg_animation_draws_paths_in_order() -> Result<(), LSystemError> {
+    use crate::renderer::{Renderer, SvgAnimationRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F [ + F ] F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let mut options = SvgAnimationRendererOptionsBuilder::new();
+    options.duration(3.0).hold(1.0).padding(0.0);
+
+    let svg = renderer.render(&system, &options.build());
+    assert!(svg.contains("viewBox=\"0 0 20.000 10.000\""));
+    assert!(svg.contains("d=\"M0.000 10.000 L10.000 10.000 L10.000 0.000\""));
+
+    // The first path is twice as long, so takes twice as long to draw
+    assert!(svg.contains("@keyframes draw0 { 0%, 0.000% { stroke-dashoffset: 1; } 66.667%, 100%"));
+    assert!(svg.contains("@keyframes draw1 { 0%, 66.667% { stroke-dashoffset: 1; } 100.000%, 100%"));
+    assert!(svg.contains("animation: draw1 3.000s linear 1 both"));
+
+    // Repeating animations hold the finished drawing before starting again
+    let svg = renderer.render(&system, &options.repeat(true).build());
+    assert!(svg.contains("@keyframes draw1 { 0%, 50.000% { stroke-dashoffset: 1; } 75.000%, 100%"));
+    assert!(svg.contains("animation: draw1 4.000s linear infinite both"));
+
+    // Without a duration every line is drawn straight away
+    let svg = renderer.render(&system, &options.duration(0.0).build());
+    assert!(!svg.contains("@keyframes"));
+    assert!(
+        svg.contains("<path d=\"M0.000 10.000 L10.000 10.000 L10.000 0.000\" stroke=\"#000000\"")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn html_output_embeds_the_lines() -> Result<(), LSystemError> {
+    use crate::renderer::{HtmlRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .token("R", TurtleAction::SetColor(::image::Rgb([255, 0, 0])))?
+        .axiom("F + R F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let mut options = HtmlRendererOptionsBuilder::new();
+    options
+        .title("<Koch & co>")
+        .scale(2.0)
+        .padding(0.0)
+        .fill_color(None);
+
+    let html = renderer.render(&system, &options.build());
+    assert!(html.contains("<title>&lt;Koch &amp; co&gt;</title>"));
+    assert!(html.contains("<canvas id=\"lsystem\" width=\"20\" height=\"20\">"));
+    assert!(html.contains("  [0.00, 20.00, 20.00, 20.00, \"#000000\", 4.00],\n"));
+    assert!(html.contains("  [20.00, 20.00, 20.00, 0.00, \"#ff0000\", 4.00],\n"));
+    assert!(html.contains("const FILL = null;\nconst DURATION = null;"));
+
+    let html = renderer.render(&system, &options.animate(2.5).repeat(true).build());
+    assert!(html.contains("const DURATION = 2.5;\nconst REPEAT = true;"));
+
+    Ok(())
+}
+
+#[test]
+fn dxf_output_has_a_layer_per_color() -> Result<(), LSystemError> {
+    use crate::renderer::{DxfRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("R", TurtleAction::SetColor(::image::Rgb([255, 0, 0])))?
+        .axiom("F F R F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let mut options = DxfRendererOptionsBuilder::new();
+
+    let dxf = renderer.render(&system, &options.build());
+    assert!(dxf.contains("\n2\nCOLOR_FF0000\n"));
+    assert!(dxf.contains("\n420\n16711680\n"));
+    assert_eq!(dxf.matches("\nLWPOLYLINE\n").count(), 2);
+    assert_eq!(dxf.matches("\n8\nCOLOR_FF0000\n").count(), 1);
+    assert!(dxf.ends_with("0\nENDSEC\n0\nEOF\n"));
+
+    let dxf = renderer.render(&system, &options.polylines(false).build());
+    assert_eq!(dxf.matches("\nLINE\n").count(), 3);
+    assert_eq!(dxf.matches("\nLWPOLYLINE\n").count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn geojson_export_is_valid_json() -> Result<(), LSystemError> {
+    use crate::export::ExportFormat;
+    use crate::renderer::{ExportRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(20.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .token("R", TurtleAction::SetColor(::image::Rgb([255, 0, 0])))?
+        .axiom("F + F R F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let mut options = ExportRendererOptionsBuilder::new();
+    options.precision(2).normalize(true);
+
+    let json: serde_json::Value = serde_json::from_str(
+        &renderer.render(&system, &options.format(ExportFormat::Json).build()),
+    )
+    .unwrap();
+    assert_eq!(json[1], serde_json::json!([0.5, 0.0, 0.5, 0.5]));
+
+    let geojson: serde_json::Value = serde_json::from_str(
+        &renderer.render(&system, &options.format(ExportFormat::GeoJson).build()),
+    )
+    .unwrap();
+
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(features.len(), 2);
+    assert_eq!(features[0]["properties"]["color"], serde_json::Value::Null);
+    assert_eq!(
+        features[0]["geometry"]["coordinates"],
+        serde_json::json!([[0.0, 0.0], [0.5, 0.0], [0.5, 0.5]])
+    );
+    assert_eq!(features[1]["properties"]["color"], "#ff0000");
+
+    Ok(())
+}
+
+#[test]
+fn heatmap_counts_visits() -> Result<(), LSystemError> {
+    use crate::color::Colormap;
+    use crate::renderer::{HeatmapRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use ::image::Rgb;
+
+    // Walk 20 units, then turn around and walk back over the second half
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F F + + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let options = HeatmapRendererOptionsBuilder::new()
+        .padding(2)
+        .colormap(Colormap::Grayscale)
+        .build();
+
+    let buffer = renderer.render(&system, &options);
+
+    // The line runs left to right along y = 2
+    assert_eq!(*buffer.get_pixel(5, 2), Rgb([128, 128, 128]));
+    assert_eq!(*buffer.get_pixel(17, 2), Rgb([255, 255, 255]));
+    assert_eq!(*buffer.get_pixel(5, 0), Rgb([0, 0, 0]));
+
+    let options = HeatmapRendererOptionsBuilder::new()
+        .padding(2)
+        .custom_colormap(&[Rgb([0, 0, 255]), Rgb([255, 0, 0])])
+        .fill_color(Rgb([255, 255, 255]))
+        .build();
+
+    let buffer = renderer.render(&system, &options);
+
+    assert_eq!(*buffer.get_pixel(17, 2), Rgb([255, 0, 0]));
+    assert_eq!(*buffer.get_pixel(5, 0), Rgb([255, 255, 255]));
+
+    Ok(())
+}
+
+#[test]
+fn merge_collinear_keeps_corners_and_reversals() {
+    use crate::renderer::merge_collinear;
+
+    let path = vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (2.0, 0.5)];
+
+    assert_eq!(
+        merge_collinear(path),
+        vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (2.0, 0.5)]
+    );
+}
+
+#[test]
+fn simplify_merges_lines_within_tolerance() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{Simplify, TurtleAction, TurtleLSystemBuilder};
+
+    // A shallow zigzag, which strays less than 2 units from a straight line
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(100.0))?
+        .token("+", TurtleAction::Rotate(1.0))?
+        .token("-", TurtleAction::Rotate(-1.0))?
+        .axiom("+ F - - F + + F - - F")?;
+
+    let (system, mut renderer) = builder.finish()?;
+    let options = DataRendererOptions::default();
+
+    renderer.simplify(Simplify::Collinear);
+    assert_eq!(renderer.render(&system, &options).len(), 4);
+
+    renderer.simplify(Simplify::Tolerance(5.0));
+    let lines = renderer.render(&system, &options);
+    assert_eq!(lines.len(), 1);
+
+    let (x1, y1, x2, y2) = lines[0];
+    assert!((x2 - x1 - 400.0).abs() < 1.0 && (y2 - y1).abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn smoothing_rounds_corners() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{Smooth, TurtleAction, TurtleLSystemBuilder};
+
+    // A closed square
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F + F + F")?;
+
+    let (system, mut renderer) = builder.finish()?;
+    let options = DataRendererOptions::default();
+
+    // The spline passes through every corner, and bulges out of the square
+    renderer.smooth(Smooth::CatmullRom(4));
+    let lines = renderer.render(&system, &options);
+    assert_eq!(lines.len(), 16);
+    assert_eq!((lines[3].2, lines[3].3), (10.0, 0.0));
+    assert!(lines.iter().any(|line| line.1 < -1.0));
+    assert!(lines[15].2.abs() < 1e-9 && lines[15].3.abs() < 1e-9);
+
+    // The curves cut each corner, staying inside the square
+    renderer.smooth(Smooth::Bezier(4));
+    let lines = renderer.render(&system, &options);
+    assert_eq!(lines.len(), 14);
+    assert!(!lines.iter().any(|line| line.2 == 10.0 && line.3 == 0.0));
+    assert!(lines
+        .iter()
+        .all(|line| line.3 >= 0.0 && line.2 <= 10.0 + 1e-9));
+
+    Ok(())
+}
+
+#[test]
+fn tropism_bends_branches_and_is_restored_by_pop() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F [ F ] F")?
+        .rotate(90)
+        .tropism(1.0, 0.0, 0.5);
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+    assert_eq!(lines.len(), 3);
+
+    // The first line goes straight up, and the turtle is bent towards the right afterwards
+    let (x1, _, x2, y2) = lines[0];
+    assert!((x2 - x1).abs() < 1e-9 && (y2 - 10.0).abs() < 1e-9);
+
+    let direction = |(x1, y1, x2, y2): (f64, f64, f64, f64)| (x2 - x1, y2 - y1);
+    let (dx, dy) = direction(lines[1]);
+    assert!((dx - 10.0 * 0.5f64.sin()).abs() < 1e-9 && (dy - 10.0 * 0.5f64.cos()).abs() < 1e-9);
+
+    // Popping restores the bend from before the branch
+    let (other_dx, other_dy) = direction(lines[2]);
+    assert!((dx - other_dx).abs() < 1e-9 && (dy - other_dy).abs() < 1e-9);
+
+    Ok(())
+}
+
+#[test]
+fn metrics_count_jumps_and_measure_the_hull() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F [ + F ] F")?;
+
+    let (system, renderer) = builder.finish()?;
+    let metrics = renderer.metrics(&system);
+
+    assert_eq!(metrics.segments, 3);
+    assert_eq!(metrics.jumps, 1);
+    assert!((metrics.length - 30.0).abs() < 1e-9);
+    assert!((metrics.hull_area - 100.0).abs() < 1e-9);
+    assert_eq!(metrics.min_x, 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn score_measures_symmetry_and_crossings() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let square = || -> Result<TurtleLSystemBuilder, LSystemError> {
+        let mut builder = TurtleLSystemBuilder::new();
+        builder
+            .token("F", TurtleAction::Forward(10.0))?
+            .token("G", TurtleAction::Forward(20.0))?
+            .token("H", TurtleAction::Forward(5.0))?
+            .token("+", TurtleAction::Rotate(90.0))?
+            .axiom("F + F + F + F")?;
+        Ok(builder)
+    };
+
+    let (system, renderer) = square()?.finish()?;
+    let score = renderer.score(&system);
+
+    assert!((score.aspect - 1.0).abs() < 1e-9);
+    assert_eq!(score.symmetry, 1.0);
+    assert_eq!(score.intersections, 0);
+    assert!(score.total() > 0.0);
+
+    // The last line crosses the first one
+    let mut builder = square()?;
+    builder.axiom("G + F + H + G")?;
+
+    let (system, renderer) = builder.finish()?;
+    let score = renderer.score(&system);
+
+    assert_eq!(score.segments, 4);
+    assert_eq!(score.intersections, 1);
+
+    Ok(())
+}
+
+#[test]
+fn pen_up_moves_without_drawing() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::TurtleLSystemBuilder;
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        F = forward 10
+        u = pen_up
+        d = pen_down
+        axiom: F u F d F
+        ",
+    )?;
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    assert_eq!(lines.len(), 2);
+    assert!((lines[1].0 - 20.0).abs() < 1e-9);
+    assert_eq!(renderer.metrics(&system).jumps, 1);
+
+    Ok(())
+}
+
+#[test]
+fn skip_goto_and_home_move_without_drawing() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::TurtleLSystemBuilder;
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        F = forward 10
+        f = skip 10
+        + = rotate 90
+        g = goto -5 30
+        h = home
+        axiom: F f F g + F h F
+        ",
+    )?;
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    let rounded: Vec<_> = lines
+        .iter()
+        .map(|(x1, y1, x2, y2)| (x1.round(), y1.round(), x2.round(), y2.round()))
+        .collect();
+
+    assert_eq!(
+        rounded,
+        vec![
+            (0.0, 0.0, 10.0, 0.0),
+            (20.0, 0.0, 30.0, 0.0),
+            (-5.0, 30.0, -5.0, 40.0),
+            (0.0, 0.0, 10.0, 0.0),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sequence_and_custom_actions() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use std::rc::Rc;
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token(
+            "A",
+            TurtleAction::Sequence(vec![
+                TurtleAction::Forward(10.0),
+                TurtleAction::Rotate(90.0),
+            ]),
+        )?
+        .token(
+            "B",
+            TurtleAction::Custom(Rc::new(|state| state.switch_turtle("other"))),
+        )?
+        .axiom("A A A A B A")?;
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    let rounded: Vec<_> = lines
+        .iter()
+        .map(|(x1, y1, x2, y2)| (x1.round(), y1.round(), x2.round(), y2.round()))
+        .collect();
+
+    assert_eq!(rounded.len(), 5);
+    assert_eq!(rounded[1], (10.0, 0.0, 10.0, 10.0));
+    assert_eq!(rounded[4], (0.0, 0.0, 10.0, 0.0));
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn dots_and_leaves_are_drawn() -> Result<(), LSystemError> {
+    use crate::renderer::{ImageRendererOptionsBuilder, Renderer, VectorRendererOptionsBuilder};
+    use crate::turtle::TurtleLSystemBuilder;
+    use crate::vector::VectorFormat;
+    use ::image::Rgb;
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        F = forward 20
+        L = leaf 20
+        O = dot 5
+        axiom: F O L
+        ",
+    )?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let options = ImageRendererOptionsBuilder::new()
+        .padding(0)
+        .thickness(1.0)
+        .fill_color(Rgb([255, 255, 255]))
+        .line_color(Rgb([0, 0, 0]))
+        .build();
+
+    let buffer = renderer.render(&system, &options);
+
+    // The dot reaches 5 units either side of the line, and the leaf 20 units past its end
+    assert_eq!(buffer.dimensions(), (40, 10));
+    assert_eq!(*buffer.get_pixel(20, 8), Rgb([0, 0, 0]));
+    assert_eq!(*buffer.get_pixel(35, 5), Rgb([0, 0, 0]));
+    assert_eq!(*buffer.get_pixel(37, 1), Rgb([255, 255, 255]));
+
+    let options = VectorRendererOptionsBuilder::new()
+        .format(VectorFormat::Eps)
+        .build();
+    let eps = String::from_utf8(renderer.render(&system, &options)).unwrap();
+
+    assert_eq!(eps.matches("closepath fill").count(), 2);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn labels_are_drawn_with_a_font() -> Result<(), LSystemError> {
+    use crate::renderer::{ImageRendererOptionsBuilder, Renderer};
+    use crate::turtle::{TurtleContainer, TurtleLSystemBuilder};
+    use ::image::Rgb;
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        F = forward 40
+        A = label end of the line
+        axiom: F A
+        ",
+    )?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let state = renderer.compute(system.get_state());
+    let (label, _) = &state.inner().inner().labels()[0];
+    assert_eq!(label.text, "end of the line");
+    assert!((label.x - 40.0).abs() < 1e-9);
+
+    // Only draw the label if there's a font to draw it with
+    let font = match std::fs::read("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf") {
+        Ok(font) => font,
+        Err(_) => return Ok(()),
+    };
+
+    let white = Rgb([255, 255, 255]);
+    let mut builder = ImageRendererOptionsBuilder::new();
+    builder.padding(20).thickness(1.0).fill_color(white);
+
+    let plain = renderer.render(&system, &builder.build());
+    let labelled = renderer.render(&system, &builder.font(font)?.build());
+
+    // The label is drawn to the right of the line, which stops at x = 60
+    let changed =
+        |x: u32| (0..plain.height()).any(|y| plain.get_pixel(x, y) != labelled.get_pixel(x, y));
+    assert!(!(0..55).any(changed));
+    assert!((60..80).any(changed));
+
+    assert!(matches!(
+        ImageRendererOptionsBuilder::new().font(vec![1, 2, 3]),
+        Err(LSystemError::InvalidFont)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn arcs_turn_the_turtle() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::TurtleLSystemBuilder;
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        A = arc 10 90
+        F = forward 10
+        axiom: A F
+        ",
+    )?;
+
+    let (system, renderer) = builder.finish()?;
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+
+    // A quarter circle in lines of at most 5 degrees, followed by a line heading up
+    assert_eq!(lines.len(), 19);
+
+    let (x1, y1, x2, y2) = lines[18];
+    assert!((x1 - 10.0).abs() < 1e-9 && (y1 - 10.0).abs() < 1e-9);
+    assert!((x2 - 10.0).abs() < 1e-9 && (y2 - 20.0).abs() < 1e-9);
+
+    Ok(())
+}
+
+#[test]
+fn presets_draw_something() -> Result<(), LSystemError> {
+    use crate::presets;
+
+    let builders = [
+        presets::koch_curve(),
+        presets::koch_snowflake(),
+        presets::dragon_curve(),
+        presets::sierpinski_triangle(),
+        presets::sierpinski_arrowhead(),
+        presets::fractal_plant(),
+        presets::fractal_binary_tree(),
+        presets::hilbert_curve(),
+        presets::gosper_curve(),
+        presets::penrose_p3(),
+    ];
+
+    for builder in builders {
+        assert!(builder.analyze().is_empty());
+
+        let (mut system, renderer) = builder.finish()?;
+        system.step_by(3);
+
+        let metrics = renderer.metrics(&system);
+        assert!(metrics.segments > 0);
+        assert_eq!(renderer.compute(system.get_state()).unbalanced_pops(), 0);
+    }
+
+    // A Hilbert curve visits each cell of a 2^n by 2^n grid once
+    let (mut system, renderer) = presets::hilbert_curve().finish()?;
+    system.step_by(3);
+    assert_eq!(renderer.metrics(&system).segments, 63);
+
+    Ok(())
+}
+
+#[test]
+fn lsystem_macro_matches_the_builder() -> Result<(), LSystemError> {
+    use crate::presets;
+    use crate::turtle::TurtleAction;
+    use dcc_lsystem_derive::lsystem;
+
+    let turn = TurtleAction::Rotate(-45.0);
+
+    let builder = lsystem! {
+        tokens {
+            0 => Forward(10.0),
+            1 => Forward(10.0),
+            L => Rotate(45.0),
+            R => turn,
+            "[" => Push,
+            "]" => Pop,
+        }
+        axiom: 0;
+        rules {
+            1 => 1 1;
+            0 => 1 [L 0] R 0;
+        }
+    }?;
+
+    let mut preset = presets::fractal_binary_tree();
+    preset.rotate(0);
+
+    let (mut system, renderer) = builder.finish()?;
+    let (mut expected, expected_renderer) = preset.finish()?;
+
+    system.step_by(4);
+    expected.step_by(4);
+
+    assert_eq!(system.render(), expected.render());
+    assert_eq!(
+        renderer.metrics(&system).segments,
+        expected_renderer.metrics(&expected).segments
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tokens_macro_registers_every_token() -> Result<(), LSystemError> {
+    use crate::{tokens, LSystemBuilder};
+
+    let mut builder = LSystemBuilder::new();
+
+    let (f, plus) = tokens!(builder => "F", "+")?;
+    let brackets = tokens!(builder, "[", "]",)?;
+    assert_eq!(brackets.len(), 2);
+
+    builder.axiom(vec![f])?;
+    builder.transformation_rule(f, vec![f, brackets[0], plus, f, brackets[1]])?;
+
+    let mut system = builder.finish()?;
+    system.step();
+    assert_eq!(system.render(), "F[+F]");
+
+    let mut builder = LSystemBuilder::new();
+    assert!(tokens!(builder, "A", "B C").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rules_use_the_longest_registered_tokens() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("FF", TurtleAction::Forward(20.0))?
+        .token("X", TurtleAction::Nothing)?
+        .token("→", TurtleAction::Rotate(90.0))?
+        .axiom("FX")?
+        .rule("X => FFF→X")?
+        .rule("FF=>F")?;
+
+    let (mut system, _) = builder.finish()?;
+    system.step_by(2);
+
+    // FFF is split into FF followed by F, and only FF is rewritten
+    assert_eq!(system.render(), "FFF→FFF→X");
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder.token("F", TurtleAction::Forward(10.0))?;
+
+    assert!(builder.rule("F F => F").is_err());
+    assert!(builder.rule("F -> F").is_err());
+    assert!(builder.rule("F => G").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn stochastic_rules_are_seeded() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("A", TurtleAction::Nothing)?
+        .token("B", TurtleAction::Nothing)?
+        .token("C", TurtleAction::Nothing)?
+        .axiom("A")?
+        .rule("A => 0.6: A B | 0.4: A C")?
+        .seed(11);
+
+    let (mut system, _) = builder.build()?;
+    system.step_by(200);
+    let first = system.render();
+
+    // Both successors are chosen, and the same seed makes the same choices
+    assert!(first.contains('B') && first.contains('C'));
+    assert_eq!(first.len(), 201);
+
+    system.reset();
+    system.step_by(200);
+    assert_eq!(system.render(), first);
+
+    let (mut system, _) = builder.finish()?;
+    system.step_by(200);
+    assert_eq!(system.render(), first);
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder.token("A", TurtleAction::Nothing)?;
+
+    assert!(builder.rule("A => 0: A").is_err());
+    assert!(builder.rule("A => A |").is_ok());
+    assert!(builder.rule("A => -1: A | A").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn interpretations_are_independent_of_the_grammar() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer, TurtleRenderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .token("[", TurtleAction::Push)?
+        .token("]", TurtleAction::Pop)?
+        .axiom("F [ + F ] F")?;
+
+    let (system, renderer) = builder.build()?;
+    let options = DataRendererOptions::default();
+
+    // The builder's own interpretation draws the same lines as its renderer
+    let interpretation = builder.interpretation();
+    let same = TurtleRenderer::with_interpretation(&system, &interpretation);
+    assert_eq!(
+        same.render(&system, &options),
+        renderer.render(&system, &options)
+    );
+
+    // Rotating every turn the other way, and ignoring the branches
+    let mut interpretation = builder.interpretation();
+    interpretation
+        .token("+", TurtleAction::Rotate(-90.0))
+        .token("[", TurtleAction::Nothing)
+        .token("]", TurtleAction::Nothing)
+        .token("G", TurtleAction::Forward(5.0));
+
+    let lines =
+        TurtleRenderer::with_interpretation(&system, &interpretation).render(&system, &options);
+    assert_eq!(lines.len(), 3);
+    assert!((lines[2].2 - 10.0).abs() < 1e-9 && (lines[2].3 + 20.0).abs() < 1e-9);
+
+    Ok(())
+}
+
+#[test]
+fn duplicate_rules_are_errors() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = LSystemBuilder::new();
+    let a = builder.token("a")?;
+    let b = builder.token("b")?;
+
+    builder.transformation_rule(a, vec![a, b])?;
+    assert!(matches!(
+        builder.transformation_rule(a, vec![b]),
+        Err(LSystemError::DuplicateRule(name)) if name == "a"
+    ));
+    assert!(builder.stochastic_rule(a, vec![(1.0, vec![b])]).is_err());
+    assert!(builder
+        .rule_table(1.., vec![(a, vec![b]), (a, vec![a])])
+        .is_err());
+    builder.rule_table(1.., vec![(a, vec![b])])?;
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .rule("F => F F")?;
+
+    assert!(builder.rule("F => F").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn rules_can_be_changed_after_building() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+    let a = builder.token("a")?;
+    let b = builder.token("b")?;
+    let c = builder.token("c")?;
+
+    builder.axiom(vec![a, b, c])?;
+    builder.transformation_rule(a, vec![a, a])?;
+    builder.transformation_rule(c, vec![c, b])?;
+
+    let mut system = builder.finish()?;
+
+    // Lengthen and shorten the successor of a token between two other rules
+    system.set_rule(b, vec![b, c, a])?;
+    system.step();
+    assert_eq!(system.render(), "aabcacb");
+
+    system.set_rule(b, vec![])?;
+    system.set_rule(a, vec![c])?;
+    system.step();
+    assert_eq!(system.render(), "cccbccb");
+
+    assert!(system.set_rule(ArenaId(3), vec![a]).is_err());
+    assert!(system.set_axiom(vec![a, ArenaId(5)]).is_err());
+    assert_eq!(system.steps(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn state_can_be_replaced() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+    let a = builder.token("a")?;
+    let ab = builder.token("ab")?;
+    let b = builder.token("b")?;
+
+    builder.axiom(vec![a])?;
+    builder.transformation_rule(a, vec![a, b])?;
+    builder.transformation_rule(b, vec![ab])?;
+
+    let mut system = builder.finish()?;
+    system.enable_history();
+    system.step_by(2);
+
+    // `a b ab` renders as `abab`, which reads back as the longest tokens
+    let rendered = system.render();
+    system.set_state_from_str(&rendered)?;
+    assert_eq!(system.get_state(), &[ab, ab]);
+
+    system.set_state_from_str("a b ab")?;
+    assert_eq!(system.get_state(), &[a, b, ab]);
+    assert_eq!(system.steps(), 2);
+    assert!(!system.step_back());
+
+    system.set_state(vec![b, a])?;
+    system.step();
+    assert_eq!(system.render(), "abab");
+
+    assert!(system.set_state(vec![ArenaId(3)]).is_err());
+    assert!(system.set_state_from_str("a c").is_err());
+    assert_eq!(system.get_state(), &[ab, a, b]);
+
+    Ok(())
+}
+
+#[test]
+fn analysis_finds_unproductive_rules() -> Result<(), LSystemError> {
+    use crate::analysis::GrammarWarning;
+
+    let mut builder = LSystemBuilder::new();
+    let a = builder.token("A")?;
+    let b = builder.token("B")?;
+    let plus = builder.token("+")?;
+    let c = builder.token("C")?;
+
+    builder.transformation_rule(a, vec![b, b])?;
+    builder.stochastic_rule(c, vec![(1.0, vec![b]), (1.0, vec![a])])?;
+
+    // Without an axiom, reachability isn't checked
+    assert_eq!(
+        builder.analyze(),
+        vec![
+            GrammarWarning::UnproductiveRule(String::from("A")),
+            GrammarWarning::MissingRule(String::from("B")),
+        ]
+    );
+
+    builder.axiom(vec![a])?;
+    builder.replace_rule(b, vec![plus])?;
+
+    assert_eq!(
+        builder.analyze(),
+        vec![GrammarWarning::Unreachable(String::from("C"))]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn state_hashes_are_stable() -> Result<(), LSystemError> {
+    let mut system = LSystemBuilder::from_grammar("axiom: A\nA => A B\nB => A")?.finish()?;
+    system.step_by(3);
+
+    // Stored hashes must keep matching between releases
+    assert_eq!(system.render(), "ABAAB");
+    assert_eq!(system.state_hash(), 0xb065_3e92_fe80_97fa);
+
+    let other = LSystemBuilder::from_grammar("axiom: B A\nB => A")?.finish()?;
+    assert!(!system.same_state(&other));
+
+    Ok(())
+}
+
+#[test]
+fn mapped_systems_keep_agreeing_rules() -> Result<(), LSystemError> {
+    use std::collections::HashMap;
+
+    let grammar = "axiom: X\nX => X Y F\nY => X Y F\nF => F F";
+    let mut system = LSystemBuilder::from_grammar(grammar)?.finish()?;
+    system.step();
+
+    // X and Y rewrite to the same thing once merged, unlike X and F
+    let mapping = HashMap::from([("X", "A"), ("Y", "A")]);
+    let mut mapped = system.map_tokens(&mapping)?;
+    assert_eq!(mapped.render(), "AAF");
+
+    mapped.step();
+    system.step();
+    assert_eq!(mapped.render(), "AAFAAFFF");
+    assert_eq!(mapped.steps(), system.steps());
+
+    let mapping = HashMap::from([("X", "G"), ("F", "G")]);
+    let mut mapped = system.map_tokens(&mapping)?;
+    mapped.reset();
+    mapped.step();
+    assert_eq!(mapped.render(), "G");
+
+    assert!(system.map_tokens(&HashMap::from([("X", "a b")])).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn scenes_transform_each_system() -> Result<(), LSystemError> {
+    use crate::renderer::DataRendererOptions;
+    use crate::scene::{SceneRenderer, Transform};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .axiom("F")?;
+    let (system, renderer) = builder.finish()?;
+
+    let mut scene = SceneRenderer::new();
+    assert!(scene.render(&DataRendererOptions::default()).is_empty());
+
+    scene.add(&system, &renderer, Transform::new()).add(
+        &system,
+        &renderer,
+        Transform::new().rotate(90.0).scale(2.0).translate(5.0, 5.0),
+    );
+
+    let lines = scene.render(&DataRendererOptions::default());
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], (0.0, 0.0, 10.0, 0.0));
+
+    let (x1, y1, x2, y2) = lines[1];
+    for (actual, expected) in [(x1, 5.0), (y1, 5.0), (x2, 5.0), (y2, 25.0)] {
+        assert!((actual - expected).abs() < 1e-9);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn scenes_render_every_system() -> Result<(), LSystemError> {
+    use crate::renderer::{ImageRendererOptionsBuilder, Renderer};
+    use crate::scene::{SceneRenderer, Transform};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .axiom("F")?;
+    let (system, renderer) = builder.finish()?;
+
+    let mut scene = SceneRenderer::new();
+    scene.add(&system, &renderer, Transform::new()).add(
+        &system,
+        &renderer,
+        Transform::new().translate(0.0, 30.0),
+    );
+
+    // The image covers both systems
+    let options = ImageRendererOptionsBuilder::new().padding(0).build();
+    let image = scene.render(&options);
+    let single = renderer.render(&system, &options);
+    assert_eq!(image.width(), single.width());
+    assert!(image.height() > single.height() + 20);
+
+    // A single horizontal line without padding is still drawn a pixel tall
+    assert_eq!(single.height(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn symmetry_repeats_the_drawing() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::scene::Symmetry;
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .axiom("F")?;
+    let (system, mut renderer) = builder.finish()?;
+
+    // Two copies about (5, 5) draw a square's diagonal corner
+    renderer.symmetry(Symmetry::new().rotations(2).center(5.0, 5.0));
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+    assert_eq!(lines.len(), 2);
+
+    let (x1, y1, x2, y2) = lines[1];
+    for (actual, expected) in [(x1, 10.0), (y1, 10.0), (x2, 0.0), (y2, 10.0)] {
+        assert!((actual - expected).abs() < 1e-9);
+    }
+
+    // Each tile is 10 wide, plus 2 of spacing
+    renderer.symmetry(Symmetry::new().tile(3, 1).spacing(2.0, 0.0));
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+    assert_eq!(
+        lines,
+        vec![
+            (0.0, 0.0, 10.0, 0.0),
+            (12.0, 0.0, 22.0, 0.0),
+            (24.0, 0.0, 34.0, 0.0)
+        ]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn png_recipes_can_be_loaded() -> Result<(), LSystemError> {
+    use crate::image_renderer::load_png_recipe;
+
+    let path = std::env::temp_dir().join(format!("dcc-lsystem-recipe-{}.png", std::process::id()));
+    let grammar = "F = forward 10\n+ = rotate 90\naxiom: F\nF => F+F";
+
+    // The same chunks written by `save_png_with`
+    let mut encoder = png::Encoder::new(std::fs::File::create(&path)?, 1, 1);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.add_itxt_chunk("dcc-lsystem:grammar".into(), grammar.into())?;
+    encoder.add_itxt_chunk("dcc-lsystem:steps".into(), "3".into())?;
+    encoder.add_text_chunk("dcc-lsystem:seed".into(), "42".into())?;
+    encoder.write_header()?.write_image_data(&[0, 0, 0])?;
+
+    let recipe = load_png_recipe(&path);
+    std::fs::remove_file(&path)?;
+    let recipe = recipe?;
+
+    assert_eq!(recipe.grammar(), grammar);
+    assert_eq!(recipe.seed(), Some(42));
+    assert_eq!(recipe.renderer_options(), None);
+
+    let (system, _) = recipe.system()?;
+    assert_eq!(system.steps(), 3);
+    assert_eq!(system.render(), "F+F+F+F+F+F+F+F");
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn png_color_types() {
+    use crate::image_renderer::{PngColorType, PngOptionsBuilder};
+    use ::image::{ImageBuffer, Rgb};
+
+    let white = Rgb([255, 255, 255]);
+    let mut buffer = ImageBuffer::from_pixel(2, 1, white);
+    buffer.put_pixel(1, 0, Rgb([255, 0, 0]));
+
+    let pixels = |builder: &mut PngOptionsBuilder| builder.build().pixels(&buffer);
+
+    assert_eq!(
+        pixels(&mut PngOptionsBuilder::new()),
+        buffer.as_raw().clone()
+    );
+    assert_eq!(
+        pixels(PngOptionsBuilder::new().color_type(PngColorType::Grayscale)),
+        vec![255, 54]
+    );
+    assert_eq!(
+        pixels(
+            PngOptionsBuilder::new()
+                .color_type(PngColorType::TruecolorAlpha)
+                .transparent_color(white)
+        ),
+        vec![255, 255, 255, 0, 255, 0, 0, 255]
+    );
+    assert_eq!(
+        pixels(PngOptionsBuilder::new().color_type(PngColorType::GrayscaleAlpha)),
+        vec![255, 255, 54, 255]
+    );
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn lossy_formats_can_be_saved() -> Result<(), LSystemError> {
+    use crate::image_renderer::save_jpeg;
+    use ::image::{ImageBuffer, Rgb};
+
+    let buffer = ImageBuffer::from_fn(16, 8, |x, _| Rgb([(x * 16) as u8, 0, 100]));
+    let path = std::env::temp_dir().join(format!("dcc-lsystem-{}.jpg", std::process::id()));
+
+    save_jpeg(&buffer, &path, 90)?;
+    let saved = ::image::open(&path);
+    std::fs::remove_file(&path)?;
+    assert_eq!(saved?.into_rgb8().dimensions(), (16, 8));
+
+    #[cfg(feature = "webp")]
+    {
+        use crate::image_renderer::save_webp;
+
+        let path = path.with_extension("webp");
+        save_webp(&buffer, &path, 75.0)?;
+        let bytes = std::fs::read(&path)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(&bytes[8..12], b"WEBP");
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn frame_sequences() -> Result<(), LSystemError> {
+    use crate::renderer::{
+        FrameSequenceRendererOptionsBuilder, ImageRendererOptionsBuilder, Renderer,
+    };
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F + F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let options = FrameSequenceRendererOptionsBuilder::new()
+        .skip_by(2)
+        .padding(2)
+        .thickness(1.0)
+        .build();
+    let frames = renderer.render(&system, &options)?;
+
+    // A frame after the first and third lines, each the size of the whole drawing
+    assert_eq!(frames.len(), 2);
+    let image = renderer.render(
+        &system,
+        &ImageRendererOptionsBuilder::new().padding(2).build(),
+    );
+    assert!(frames
+        .iter()
+        .all(|frame| frame.dimensions() == image.dimensions()));
+    assert_ne!(frames[0], frames[1]);
+
+    let directory = std::env::temp_dir().join(format!("dcc-lsystem-frames-{}", std::process::id()));
+    let options = FrameSequenceRendererOptionsBuilder::new()
+        .directory(directory.to_string_lossy())
+        .prefix("koch")
+        .build();
+
+    let frames = renderer.render(&system, &options)?;
+    assert!(frames.is_empty());
+
+    let mut written: Vec<_> = std::fs::read_dir(&directory)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<Result<_, _>>()?;
+    written.sort();
+    std::fs::remove_dir_all(&directory)?;
+
+    let expected: Vec<_> = (0..4)
+        .map(|i| std::ffi::OsString::from(format!("koch{:05}.png", i)))
+        .collect();
+    assert_eq!(written, expected);
+
+    Ok(())
+}
+
+#[cfg(feature = "mp4")]
+#[test]
+fn mp4_output() -> Result<(), LSystemError> {
+    use crate::image_renderer::AnimationFormat;
+    use crate::renderer::{Renderer, VideoRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let path = std::env::temp_dir().join(format!("dcc-lsystem-{}.mp4", std::process::id()));
+    let options = VideoRendererOptionsBuilder::new()
+        .filename(path.to_string_lossy())
+        .format(AnimationFormat::Mp4)
+        .padding(3)
+        .thickness(1.0)
+        .build();
+
+    let result = renderer.render(&system, &options);
+
+    // ffmpeg mightn't be installed, in which case rendering fails
+    if std::process::Command::new("ffmpeg")
+        .arg("-version")
+        .output()
+        .is_ok()
+    {
+        result?;
+        let size = std::fs::metadata(&path)?.len();
+        std::fs::remove_file(&path)?;
+        assert!(size > 0);
+    } else {
+        assert!(result.is_err());
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn frame_callbacks_can_stop_rendering() -> Result<(), LSystemError> {
+    use crate::renderer::FrameSequenceRendererOptionsBuilder;
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .axiom("F F F F F")?;
+    let (system, renderer) = builder.finish()?;
+
+    let options = FrameSequenceRendererOptionsBuilder::new().build();
+    let mut indices = Vec::new();
+
+    let result = renderer.render_frames(&system, &options, |index, _| {
+        indices.push(index);
+
+        if index == 2 {
+            Err(LSystemError::Other {
+                source: "the viewer was closed".into(),
+            })
+        } else {
+            Ok(())
+        }
+    });
+
+    assert!(matches!(result, Err(LSystemError::Other { .. })));
+    assert_eq!(indices, vec![0, 1, 2]);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn animations_have_per_frame_delays() -> Result<(), LSystemError> {
+    use crate::animation::{Easing, FrameDelay};
+    use crate::image_renderer::AnimationFormat;
+    use crate::renderer::{Renderer, VideoRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let path = std::env::temp_dir().join(format!("dcc-lsystem-delays-{}.png", std::process::id()));
+    let options = VideoRendererOptionsBuilder::new()
+        .filename(path.to_string_lossy())
+        .format(AnimationFormat::Apng)
+        .frame_delay(FrameDelay::Eased {
+            start: 0.5,
+            end: 0.1,
+            easing: Easing::Linear,
+        })
+        .hold_last(2.0)
+        .padding(2)
+        .thickness(1.0)
+        .build();
+
+    renderer.render(&system, &options)?;
+
+    let decoder = png::Decoder::new(std::fs::File::open(&path)?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let mut delays = Vec::new();
+
+    for _ in 0..3 {
+        reader.next_frame(&mut buffer).unwrap();
+        let control = reader.info().frame_control().unwrap();
+        delays.push((control.delay_num, control.delay_den));
+    }
+
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(delays, vec![(500, 1000), (300, 1000), (2100, 1000)]);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn animations_can_ping_pong() -> Result<(), LSystemError> {
+    use crate::animation::Playback;
+    use crate::image_renderer::AnimationFormat;
+    use crate::renderer::{Renderer, VideoRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let path = std::env::temp_dir().join(format!("dcc-lsystem-pong-{}.png", std::process::id()));
+    let options = VideoRendererOptionsBuilder::new()
+        .filename(path.to_string_lossy())
+        .format(AnimationFormat::Apng)
+        .playback(Playback::PingPong)
+        .fps(10)
+        .hold_last(1.0)
+        .padding(2)
+        .thickness(1.0)
+        .build();
+
+    renderer.render(&system, &options)?;
+
+    let decoder = png::Decoder::new(std::fs::File::open(&path)?);
+    let mut reader = decoder.read_info().unwrap();
+    let frames = reader.info().animation_control().unwrap().num_frames;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let mut images = Vec::new();
+    let mut delays = Vec::new();
+
+    for _ in 0..frames {
+        reader.next_frame(&mut buffer).unwrap();
+        images.push(buffer.clone());
+        delays.push(reader.info().frame_control().unwrap().delay_num);
+    }
+
+    std::fs::remove_file(&path)?;
+
+    // The finished drawing is held in the middle, and the animation shrinks back afterwards
+    assert_eq!(frames, 4);
+    assert_eq!(delays, vec![100, 100, 1100, 100]);
+    assert_eq!(images[1], images[3]);
+    assert_ne!(images[0], images[2]);
+
+    Ok(())
+}
+
+#[cfg(feature = "image_renderer")]
+#[test]
+fn renders_report_progress() -> Result<(), LSystemError> {
+    use crate::image_renderer::AnimationFormat;
+    use crate::progress::RenderProgress;
+    use crate::renderer::{Renderer, VideoRendererOptionsBuilder};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+    use std::sync::{Arc, Mutex};
+
+    // Each update, with `None` when the render finished
+    type Log = Arc<Mutex<Vec<Option<(usize, usize)>>>>;
+
+    struct Updates(Log);
+
+    impl RenderProgress for Updates {
+        fn on_progress(&self, done: usize, total: usize) {
+            self.0.lock().unwrap().push(Some((done, total)));
+        }
+
+        fn finished(&self) {
+            self.0.lock().unwrap().push(None);
+        }
+    }
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("+", TurtleAction::Rotate(90.0))?
+        .axiom("F + F")?;
+
+    let (system, renderer) = builder.finish()?;
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let path =
+        std::env::temp_dir().join(format!("dcc-lsystem-progress-{}.png", std::process::id()));
+    let options = VideoRendererOptionsBuilder::new()
+        .filename(path.to_string_lossy())
+        .format(AnimationFormat::Apng)
+        .progress(Updates(updates.clone()))
+        .padding(2)
+        .thickness(1.0)
+        .build();
+
+    renderer.render(&system, &options)?;
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(
+        *updates.lock().unwrap(),
+        vec![Some((0, 2)), Some((1, 2)), Some((2, 2)), None]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_bindings_flatten_segments() {
+    use crate::wasm::TurtleSystem;
+
+    let mut system = TurtleSystem::new(
+        "
+        F = forward 10
+        + = rotate 90
+        axiom: F
+        F => F+F
+        ",
+    )
+    .unwrap();
+
+    system.step();
+    assert_eq!(system.state(), "F+F");
+    assert_eq!(system.segments().len(), 8);
+
+    system.step_by(2);
+    assert_eq!(system.segments().len(), 32);
+
+    system.reset();
+    assert_eq!(system.segments(), vec![0.0, 0.0, 10.0, 0.0]);
+}
+
+#[test]
+#[cfg(feature = "config")]
+fn turtle_configs_load_from_toml_and_json() -> Result<(), LSystemError> {
+    use crate::config::TurtleConfig;
+    use crate::renderer::{DataRendererOptions, Renderer};
+
+    let toml = TurtleConfig::parse(
+        r#"
+        axiom = "F"
+        rules = ["F => F + F"]
+        steps = 2
+        seed = 7
+
+        [tokens]
+        F = "forward 1..5"
+        "+" = "rotate 90"
+        "#,
+    )?;
+    let json = TurtleConfig::parse(
+        r#"{
+            "tokens": { "F": "forward 1..5", "+": "rotate 90" },
+            "axiom": "F",
+            "rules": ["F => F + F"],
+            "steps": 2,
+            "seed": 7
+        }"#,
+    )?;
+    assert_eq!(toml, json);
+
+    let (system, renderer) = toml.system()?;
+    assert_eq!(system.render(), "F+F+F+F");
+
+    // Seeded configs draw the same random lengths every time
+    let lines = renderer.render(&system, &DataRendererOptions::default());
+    let (system, renderer) = json.system()?;
+    assert_eq!(
+        lines,
+        renderer.render(&system, &DataRendererOptions::default())
+    );
+
+    // Mistakes in actions name the token they belong to
+    let error = TurtleConfig::from_toml("axiom = \"F\"\n[tokens]\nF = \"forwards 1\"")
+        .and_then(|config| config.builder())
+        .err()
+        .unwrap();
+    assert!(matches!(error, LSystemError::InvalidConfig(message) if message.contains("`F`")));
+
+    assert!(TurtleConfig::from_toml("axiom = \"F\"\ncolour = 1\n[tokens]").is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "scripting")]
+fn scripts_drive_the_turtle() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer};
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        F = script forward(4 / (depth + 1))
+        G = script if x > 100 { forward(1) } else { rotate(90); forward(2.5) }
+        [ = push
+        ] = pop
+        axiom: F [ F ] G
+        ",
+    )?;
+
+    let (system, renderer) = builder.finish()?;
+    assert_eq!(
+        renderer.render(&system, &DataRendererOptions::default()),
+        vec![
+            (0.0, 0.0, 4.0, 0.0),
+            (4.0, 0.0, 6.0, 0.0),
+            (4.0, 0.0, 4.0, 2.5),
+        ]
+    );
+
+    // Scripts can't change the state they're given, and those which fail don't do anything
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("X", TurtleAction::Script(String::from("x = 1; forward(1)")))?
+        .axiom("X X")?;
+
+    let (system, renderer) = builder.finish()?;
+    assert_eq!(renderer.compute(system.get_state()).script_errors(), 2);
+
+    // Scripts which don't compile are rejected straight away
+    let error = TurtleLSystemBuilder::from_grammar("F = script forward(\naxiom: F").err();
+    assert!(matches!(error, Some(LSystemError::ParseError { .. })));
+
+    let error = TurtleLSystemBuilder::new()
+        .token("Y", TurtleAction::Script(String::from("forward(")))
+        .err();
+    assert!(matches!(error, Some(LSystemError::InvalidScript(_))));
+
+    Ok(())
+}
+
+#[test]
+fn music_renderer_writes_a_midi_file() -> Result<(), LSystemError> {
+    use crate::music::{MidiRendererOptionsBuilder, MusicAction, MusicRenderer, Note, Scale};
+    use crate::renderer::Renderer;
+    use crate::LSystemBuilder;
+
+    let mut builder = LSystemBuilder::new();
+    let n = builder.token("N")?;
+    let up = builder.token("+")?;
+    let half = builder.token("/")?;
+    let push = builder.token("[")?;
+    let pop = builder.token("]")?;
+    let octave = builder.token("^")?;
+    builder.axiom(vec![push, up, up, half, octave, n, pop, n, up, n])?;
+
+    let system = builder.finish()?;
+
+    let mut renderer = MusicRenderer::new();
+    renderer
+        .token("N", MusicAction::Note)
+        .token("+", MusicAction::Up(1))
+        .token("/", MusicAction::MultiplyDuration(0.5))
+        .token("[", MusicAction::Push)
+        .token("]", MusicAction::Pop)
+        .token("^", MusicAction::Transpose(12))
+        .token("unused", MusicAction::Rest);
+
+    let note = |degree, transpose, start, duration| Note {
+        degree,
+        transpose,
+        start,
+        duration,
+    };
+    assert_eq!(
+        renderer.notes(&system, 1.0),
+        vec![
+            note(2, 12, 0.0, 0.5),
+            note(0, 0, 0.5, 1.0),
+            note(1, 0, 1.5, 1.0)
+        ]
+    );
+
+    // Degrees wrap around into the next octave
+    assert_eq!(Scale::Pentatonic.semitones(5), 12);
+    assert_eq!(Scale::Major.semitones(-1), -1);
+
+    let options = MidiRendererOptionsBuilder::new().tempo(60.0).build();
+    let midi = renderer.render(&system, &options);
+
+    assert_eq!(&midi[..14], b"MThd\0\0\0\x06\0\0\0\x01\x01\xe0");
+    assert_eq!(&midi[14..18], b"MTrk");
+    assert_eq!(
+        u32::from_be_bytes([midi[18], midi[19], midi[20], midi[21]]) as usize,
+        midi.len() - 22
+    );
+
+    // A beat lasts a second, and the first note (E, two degrees and an octave above middle C)
+    // lasts half a beat
+    assert_eq!(&midi[22..29], &[0x00, 0xff, 0x51, 0x03, 0x0f, 0x42, 0x40]);
+    assert_eq!(
+        &midi[32..44],
+        &[0x00, 0x90, 76, 100, 0x81, 0x70, 0x80, 76, 100, 0x00, 0x90, 60]
+    );
+    assert!(midi.ends_with(&[0x00, 0xff, 0x2f, 0x00]));
+
+    // Tempos too slow for a MIDI file are clamped
+    let options = MidiRendererOptionsBuilder::new().tempo(1.0).build();
+    let midi = renderer.render(&system, &options);
+    assert_eq!(&midi[22..29], &[0x00, 0xff, 0x51, 0x03, 0xff, 0xff, 0xff]);
+
+    Ok(())
+}
+
+#[test]
+fn derived_containers_are_simplified() -> Result<(), LSystemError> {
+    use crate::renderer::{DataRendererOptions, Renderer, TurtleRenderer};
+    use crate::turtle::{MovingTurtle, SimpleTurtle, Simplify};
+    use crate::LSystemBuilder;
+    use dcc_lsystem_derive::TurtleContainer;
+
+    #[derive(Clone, TurtleContainer)]
+    struct Container {
+        #[turtle]
+        turtle: SimpleTurtle,
+    }
+
+    let mut builder = LSystemBuilder::new();
+    let f = builder.token("F")?;
+    builder.axiom(vec![f, f, f])?;
+    let system = builder.finish()?;
+
+    let mut renderer = TurtleRenderer::new(Container {
+        turtle: SimpleTurtle::new(),
+    });
+    renderer.register(f, |state| state.turtle.forward(10.0));
+
+    let options = DataRendererOptions::default();
+    assert_eq!(renderer.render(&system, &options).len(), 3);
+
+    renderer.simplify(Simplify::Collinear);
+    assert_eq!(renderer.render(&system, &options).len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn rule_tables_only_replace_stochastic_rules_they_cover() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+    let a = builder.token("a")?;
+    let b = builder.token("b")?;
+    let c = builder.token("c")?;
+    builder.axiom(vec![a])?;
+    builder.stochastic_rule(a, vec![(1.0, vec![a, b])])?;
+    builder.rule_table(3.., vec![(a, vec![a])])?;
+    builder.rule_table(1.., vec![(b, vec![c])])?;
+
+    let mut system = builder.finish()?;
+    let mut states = Vec::new();
+
+    for _ in 0..4 {
+        system.step();
+        states.push(system.render());
+    }
+
+    // `a` keeps growing by its stochastic rule until a table has a rule for it
+    assert_eq!(states, vec!["ab", "abc", "abcc", "abcc"]);
+
+    Ok(())
+}
+
+#[test]
+fn set_rule_applies_within_rule_tables() -> Result<(), LSystemError> {
+    let mut builder = LSystemBuilder::new();
+    let a = builder.token("a")?;
+    let b = builder.token("b")?;
+    let c = builder.token("c")?;
+    builder.axiom(vec![a, b])?;
+    builder.transformation_rule(a, vec![a, b])?;
+    builder.rule_table(0.., vec![(b, vec![b, b])])?;
+
+    let mut system = builder.finish()?;
+    system.set_rule(a, vec![a, a])?;
+    system.set_rule(b, vec![c])?;
+
+    // The table falls back to the new rule for `a`, but keeps its own rule for `b`
+    system.step();
+    assert_eq!(system.render(), "aabb");
+
+    Ok(())
+}
+
+#[test]
+fn compact_notation() -> Result<(), LSystemError> {
+    use crate::turtle::{TurtleAction, TurtleLSystemBuilder};
+
+    let builder = TurtleLSystemBuilder::from_grammar(
+        "
+        F = forward 10
+        + = rotate 25
+        - = rotate -25
+        [ = push
+        ] = pop
+        axiom: X
+        X => F+[[X]-X]-F[-FX]+X
+        F => FF
+        ",
+    )?;
+
+    let (mut system, renderer) = builder.finish()?;
+    system.step();
+    assert_eq!(system.render(), "F+[[X]-X]-F[-FX]+X");
+    assert_eq!(renderer.metrics(&system).segments, 3);
+
+    let mut builder = TurtleLSystemBuilder::new();
+    builder
+        .token("F", TurtleAction::Forward(10.0))?
+        .token("-", TurtleAction::Rotate(-60.0))?
+        .token("+", TurtleAction::Rotate(60.0))?
+        .axiom("F--F--F")?
+        .rule("F => F+F--F+F")?;
+
+    let (mut system, renderer) = builder.finish()?;
+    system.step();
+    assert_eq!(renderer.metrics(&system).segments, 12);
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! Renders turtle output as a TikZ picture, for including in LaTeX documents.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::renderer::{Renderer, TikzRendererOptionsBuilder};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .token("-", TurtleAction::Rotate(-90.0))?
+//!     .axiom("F")?
+//!     .rule("F => F + F - F - F + F")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(2);
+//!
+//! let options = TikzRendererOptionsBuilder::new().scale(0.05).build();
+//! let picture = renderer.render(&system, &options);
+//!
+//! assert!(picture.starts_with("\\begin{tikzpicture}"));
+//! assert_eq!(picture.matches("\\draw").count(), 25);
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Write;
+
+use image::Rgb;
+
+use crate::renderer::{segments, Dasher, Renderer, StrokeStyle, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+pub struct TikzRendererOptionsBuilder {
+    options: TikzRendererOptions,
+}
+
+impl TikzRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: TikzRendererOptions {
+                scale: 0.1,
+                thickness: 2.0,
+                line_color: Rgb([0, 0, 0]),
+                standalone: false,
+                stroke_style: None,
+            },
+        }
+    }
+
+    /// Set the length, in centimetres, of a single turtle unit.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Set the thickness of lines without their own thickness, in turtle units.
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
+
+    /// Set the color of lines without their own color.
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
+
+    /// Wrap the picture in a complete `standalone` LaTeX document.
+    pub fn standalone(&mut self, standalone: bool) -> &mut Self {
+        self.options.standalone = standalone;
+        self
+    }
+
+    /// Draw lines with a dash pattern, measured in turtle units.
+    pub fn stroke_style(&mut self, stroke_style: StrokeStyle) -> &mut Self {
+        self.options.stroke_style = Some(stroke_style);
+        self
+    }
+
+    pub fn build(&mut self) -> TikzRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for TikzRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TikzRendererOptions {
+    scale: f64,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+    standalone: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    stroke_style: Option<StrokeStyle>,
+}
+
+impl TikzRendererOptions {
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+
+    pub fn standalone(&self) -> bool {
+        self.standalone
+    }
+
+    pub fn stroke_style(&self) -> Option<&StrokeStyle> {
+        self.stroke_style.as_ref()
+    }
+}
+
+/// Returns `color` in the syntax understood by `xcolor`.
+fn tikz_color(color: Rgb<u8>) -> String {
+    let Rgb([red, green, blue]) = color;
+    format!("{{rgb,255:red,{};green,{};blue,{}}}", red, green, blue)
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<TikzRendererOptions> for TurtleRenderer<Q> {
+    type Output = String;
+
+    fn render(&self, system: &LSystem, options: &TikzRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+        let mut output = String::new();
+
+        // unwrap (here and below): writing to a String never fails
+        if options.standalone {
+            writeln!(output, "\\documentclass[tikz]{{standalone}}").unwrap();
+            writeln!(output, "\\begin{{document}}").unwrap();
+        }
+
+        writeln!(
+            output,
+            "\\begin{{tikzpicture}}[x={}cm, y={}cm, line cap=round]",
+            options.scale, options.scale
+        )
+        .unwrap();
+
+        let mut dasher = options
+            .stroke_style
+            .as_ref()
+            .and_then(|style| Dasher::new(style, options.scale));
+
+        // TikZ wants the pattern as alternating `on` and `off` lengths
+        let dash_pattern = options
+            .stroke_style
+            .as_ref()
+            .map(|style| {
+                style
+                    .scaled_pattern(options.scale)
+                    .iter()
+                    .enumerate()
+                    .map(|(index, length)| {
+                        let kind = if index.is_multiple_of(2) { "on" } else { "off" };
+                        format!("{} {:.4}cm", kind, length)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+
+        for ((x1, y1, x2, y2), style) in segments(&state) {
+            let thickness = style.resolved_thickness(options.thickness);
+
+            if thickness > 0.0 {
+                let dash = match &mut dasher {
+                    Some(dasher) => format!(
+                        ", dash pattern={}, dash phase={:.4}cm",
+                        dash_pattern,
+                        dasher.phase(
+                            *x1 * options.scale,
+                            *y1 * options.scale,
+                            *x2 * options.scale,
+                            *y2 * options.scale
+                        )
+                    ),
+                    None => String::new(),
+                };
+
+                writeln!(
+                    output,
+                    "  \\draw[line width={:.4}cm, color={}{}] ({:.3}, {:.3}) -- ({:.3}, {:.3});",
+                    thickness * options.scale,
+                    tikz_color(style.color.unwrap_or(options.line_color)),
+                    dash,
+                    x1,
+                    y1,
+                    x2,
+                    y2
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(output, "\\end{{tikzpicture}}").unwrap();
+
+        if options.standalone {
+            writeln!(output, "\\end{{document}}").unwrap();
+        }
+
+        output
+    }
+}
@@ -1,9 +1,14 @@
-use crate::LSystemError;
+use crate::collections::HashMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{ArenaId, LSystemError};
 
 /// A token for use in an L-system.  In general, the `LSystem` owns the token,
 /// while the user can refer to the token via an `ArenaId`.  This means
 /// we don't have to deal with any tricky ownership issues.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     name: String,
 }
@@ -46,8 +51,47 @@ impl Token {
     }
 }
 
-impl std::fmt::Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+impl core::fmt::Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> Result<(), core::fmt::Error> {
         write!(f, "{}", self.name())
     }
 }
+
+/// Splits `text` into the tokens named in `tokens`.  Each whitespace separated word is split by
+/// repeatedly taking the longest token name that it starts with.
+pub(crate) fn tokenize(
+    text: &str,
+    tokens: &HashMap<String, ArenaId>,
+) -> Result<Vec<ArenaId>, LSystemError> {
+    let longest = tokens
+        .keys()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut ids = Vec::new();
+
+    for word in text.split_whitespace() {
+        let mut rest = word;
+
+        while !rest.is_empty() {
+            // The byte offsets of the ends of the prefixes of `rest`, longest first
+            let mut ends: Vec<usize> = rest
+                .char_indices()
+                .map(|(index, c)| index + c.len_utf8())
+                .take(longest)
+                .collect();
+            ends.reverse();
+
+            let (end, id) = ends
+                .into_iter()
+                .find_map(|end| tokens.get(&rest[..end]).map(|id| (end, *id)))
+                .ok_or_else(|| LSystemError::UnknownToken(rest.to_string()))?;
+
+            ids.push(id);
+            rest = &rest[end..];
+        }
+    }
+
+    Ok(ids)
+}
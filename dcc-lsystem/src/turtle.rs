@@ -1,16 +1,23 @@
 //! Contains a collection of turtles which can be used to interpret the state of an LSystem
 //! as a rendering.
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::rc::Rc;
 
-use rand::Rng;
-use regex::Regex;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
 
-use dcc_lsystem_derive::TurtleContainer;
-use lazy_static::lazy_static;
+use image::Rgb;
 
-use crate::renderer::TurtleRenderer;
+use crate::analysis::GrammarWarning;
+use crate::grammar::{self, Statement, Symbol};
+use crate::renderer::{merge_collinear, TurtleRenderer};
+use crate::scene::Transform;
+#[cfg(feature = "scripting")]
+use crate::scripting;
+use crate::token;
 use crate::{ArenaId, LSystem, LSystemBuilder, LSystemError};
-use std::f64::consts::FRAC_PI_2;
+use std::f64::consts::{FRAC_PI_2, PI};
 
 /// A simple Turtle trait.
 ///
@@ -71,7 +78,7 @@ pub trait MovingTurtle {
 /// use dcc_lsystem::turtle::{SimpleTurtle, TurtleContainer};
 /// use dcc_lsystem_derive::TurtleContainer;
 ///
-/// #[derive(TurtleContainer)]
+/// #[derive(Clone, TurtleContainer)]
 /// struct BasicContainer {
 ///     #[turtle]
 ///     inner: SimpleTurtle,
@@ -84,6 +91,7 @@ pub trait MovingTurtle {
 /// ```rust
 /// use dcc_lsystem::turtle::{MovingTurtle, SimpleTurtle, TurtleContainer};
 ///
+/// #[derive(Clone)]
 /// struct BasicContainer {
 ///     inner: SimpleTurtle,
 ///     /* <----- some other fields ----- */
@@ -92,15 +100,37 @@ pub trait MovingTurtle {
 /// impl TurtleContainer for BasicContainer {
 ///     type Item = <SimpleTurtle as MovingTurtle>::Item;
 ///
-///     fn inner(&self) -> &MovingTurtle<Item = Self::Item> {
+///     fn inner(&self) -> &dyn MovingTurtle<Item = Self::Item> {
 ///         &self.inner
 ///     }
+///
+///     fn turtles_mut(&mut self) -> Vec<&mut dyn MovingTurtle<Item = Self::Item>> {
+///         vec![&mut self.inner]
+///     }
 /// }
 /// ```
 pub trait TurtleContainer {
     type Item;
 
     fn inner(&self) -> &dyn MovingTurtle<Item = Self::Item>;
+
+    /// Returns every turtle in this container, in the order they should be drawn.
+    ///
+    /// Containers which drive more than one turtle should override this method.  By default
+    /// it only returns the turtle given by [`TurtleContainer::inner`].
+    fn turtles(&self) -> Vec<&dyn MovingTurtle<Item = Self::Item>> {
+        vec![self.inner()]
+    }
+
+    /// Returns mutable references to every turtle in this container, in the same order as
+    /// [`TurtleContainer::turtles`].
+    ///
+    /// This is used to tidy up the lines drawn by the turtles once rendering has finished
+    /// (see [`TurtleRenderer::simplify`]).  By default no turtles are returned, so the lines
+    /// are left untouched; `#[derive(TurtleContainer)]` returns the `#[turtle]` field.
+    fn turtles_mut(&mut self) -> Vec<&mut dyn MovingTurtle<Item = Self::Item>> {
+        Vec::new()
+    }
 }
 
 /// Every turtle contains a turtle.
@@ -110,14 +140,343 @@ impl<T> TurtleContainer for dyn MovingTurtle<Item = T> {
     fn inner(&self) -> &dyn MovingTurtle<Item = Self::Item> {
         self
     }
+
+    fn turtles_mut(&mut self) -> Vec<&mut dyn MovingTurtle<Item = Self::Item>> {
+        vec![self]
+    }
 }
 
 pub trait Stack: MovingTurtle {
+    /// The state of the turtle saved on the stack.
+    type Frame;
+
     /// Push the current state of this turtle onto a stack.
     fn push(&mut self);
 
-    /// Pop the current state of this turtle onto a stack.
-    fn pop(&mut self);
+    /// Pop the current state of this turtle off the stack, returning `false` (and leaving the
+    /// turtle unchanged) if the stack is empty.
+    fn pop(&mut self) -> bool;
+
+    /// Returns the number of states on the stack.
+    fn depth(&self) -> usize;
+
+    /// Returns the state on top of the stack, without popping it.
+    fn peek(&self) -> Option<&Self::Frame>;
+
+    /// Removes every state from the stack, without changing the turtle.
+    fn clear(&mut self);
+}
+
+/// The style of a single line segment drawn by a turtle.
+///
+/// Any property which is not set falls back to the corresponding renderer option
+/// (for example [`ImageRendererOptions`](crate::image_renderer::ImageRendererOptions)).
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct SegmentStyle {
+    /// The color of the segment.
+    pub color: Option<Rgb<u8>>,
+    /// The thickness of the segment.
+    pub thickness: Option<f64>,
+    /// An adjustment added to the thickness of the segment (or to the renderer's thickness, if
+    /// `thickness` is unset).
+    pub thickness_delta: f64,
+    /// The number of states on the turtle's stack when the segment was drawn.  For branching
+    /// systems this is how far the segment is from the trunk.
+    pub depth: usize,
+}
+
+impl SegmentStyle {
+    /// Returns the thickness this segment should be drawn with, given the renderer's
+    /// default thickness.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::SegmentStyle;
+    ///
+    /// let mut style = SegmentStyle::default();
+    /// assert_eq!(style.resolved_thickness(4.0), 4.0);
+    ///
+    /// style.thickness_delta = -1.5;
+    /// assert_eq!(style.resolved_thickness(4.0), 2.5);
+    ///
+    /// style.thickness = Some(10.0);
+    /// assert_eq!(style.resolved_thickness(4.0), 8.5);
+    /// ```
+    pub fn resolved_thickness(&self, default: f64) -> f64 {
+        self.thickness.unwrap_or(default) + self.thickness_delta
+    }
+}
+
+/// A single step taken by a turtle, recorded by [`BaseTurtle::record_events`].
+///
+/// Unlike the lines drawn by the turtle, the events describe everything the turtle did in the
+/// order that it did it, which is useful for driving other drawing backends.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TurtleEvent {
+    /// The turtle moved to `(x, y)` without drawing.
+    MoveTo { x: f64, y: f64 },
+    /// The turtle drew a line from its previous position to `(x, y)`.
+    LineTo { x: f64, y: f64 },
+    /// The turtle's pen was put down (`true`) or lifted up (`false`).
+    PenChange(bool),
+    /// The color of the lines drawn by the turtle changed.  This is recorded just before the
+    /// first line drawn in the new color.
+    ColorChange(Option<Rgb<u8>>),
+    /// The turtle's state was pushed onto its stack.
+    Push,
+    /// The turtle's state was popped off its stack.
+    Pop,
+}
+
+/// A filled shape drawn by a turtle, in turtle coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Shape {
+    /// A circle centred at `(x, y)`.
+    Dot { x: f64, y: f64, radius: f64 },
+    /// A leaf with its stalk at `(x, y)`, pointing in the direction `heading` (in radians).
+    Leaf {
+        x: f64,
+        y: f64,
+        heading: f64,
+        size: f64,
+    },
+}
+
+impl Shape {
+    /// The number of points in the outline of a shape.
+    const OUTLINE_POINTS: usize = 32;
+
+    /// Returns the outline of the shape as a polygon, in turtle coordinates.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::Shape;
+    ///
+    /// let dot = Shape::Dot { x: 1.0, y: 2.0, radius: 3.0 };
+    ///
+    /// for (x, y) in dot.outline() {
+    ///     assert!(((x - 1.0).hypot(y - 2.0) - 3.0).abs() < 1e-9);
+    /// }
+    /// ```
+    pub fn outline(&self) -> Vec<(f64, f64)> {
+        let n = Self::OUTLINE_POINTS;
+
+        match *self {
+            Shape::Dot { x, y, radius } => (0..n)
+                .map(|i| {
+                    let angle = 2.0 * PI * i as f64 / n as f64;
+                    (x + radius * angle.cos(), y + radius * angle.sin())
+                })
+                .collect(),
+            Shape::Leaf {
+                x,
+                y,
+                heading,
+                size,
+            } => {
+                let (sin, cos) = heading.sin_cos();
+
+                // Trace one side of the leaf from the stalk to the tip, then the other side back
+                (0..n)
+                    .map(|i| {
+                        let half = n / 2;
+                        let (t, side) = if i <= half {
+                            (i as f64 / half as f64, 1.0)
+                        } else {
+                            ((n - i) as f64 / half as f64, -1.0)
+                        };
+
+                        let along = t * size;
+                        let across = side * size * 0.25 * (PI * t).sin();
+
+                        (
+                            x + along * cos - across * sin,
+                            y + along * sin + across * cos,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Text written by a turtle, anchored at a point in turtle coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    /// The `x` coordinate of the start of the text.
+    pub x: f64,
+    /// The `y` coordinate of the start of the text.
+    pub y: f64,
+    /// The direction the turtle was facing when it wrote the text (in radians).
+    pub heading: f64,
+    /// The text.
+    pub text: String,
+}
+
+/// How the lines drawn by a turtle are simplified before they are rendered.
+///
+/// See [`TurtleRenderer::simplify`].
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Simplify {
+    /// Draw every line exactly as the turtle drew it.
+    #[default]
+    Off,
+    /// Merge consecutive lines with the same style which point in the same direction.
+    Collinear,
+    /// Merge collinear lines, and then drop any point closer than the given tolerance to the
+    /// simplified path (using the Ramer-Douglas-Peucker algorithm).
+    Tolerance(f64),
+}
+
+/// How the lines drawn by a turtle are smoothed before they are rendered.
+///
+/// See [`TurtleRenderer::smooth`].
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Smooth {
+    /// Draw every line exactly as the turtle drew it.
+    #[default]
+    Off,
+    /// Replace each line with a Catmull-Rom spline made up of the given number of lines.  The
+    /// spline passes through every point the turtle visited.
+    CatmullRom(usize),
+    /// Round off each corner with a cubic Bézier curve made up of the given number of lines.
+    /// The curves start and end halfway along the lines meeting at the corner, so they don't
+    /// pass through the corner itself.
+    Bezier(usize),
+}
+
+/// Returns the point `t` of the way along the cubic Bézier curve with the given control points.
+fn cubic_bezier(
+    p0: (f64, f64),
+    p1: (f64, f64),
+    p2: (f64, f64),
+    p3: (f64, f64),
+    t: f64,
+) -> (f64, f64) {
+    let s = 1.0 - t;
+    let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+
+    (
+        a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+        a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+    )
+}
+
+/// Interpolates `path` with a uniform Catmull-Rom spline, replacing each line with `steps`
+/// lines.  Closed paths are smoothed across the point where they join up.
+fn catmull_rom(path: &[(f64, f64)], steps: usize) -> Vec<(f64, f64)> {
+    let last = path.len() - 1;
+    let closed = last > 1 && (path[0].0 - path[last].0).hypot(path[0].1 - path[last].1) < 1e-9;
+
+    // The neighbours of each end of the path
+    let before = if closed { path[last - 1] } else { path[0] };
+    let after = if closed { path[1] } else { path[last] };
+
+    let mut points = vec![path[0]];
+
+    for index in 0..last {
+        let p0 = if index == 0 { before } else { path[index - 1] };
+        let (p1, p2) = (path[index], path[index + 1]);
+        let p3 = if index + 1 == last {
+            after
+        } else {
+            path[index + 2]
+        };
+
+        // The Bézier control points of the spline between p1 and p2
+        let c1 = (p1.0 + (p2.0 - p0.0) / 6.0, p1.1 + (p2.1 - p0.1) / 6.0);
+        let c2 = (p2.0 - (p3.0 - p1.0) / 6.0, p2.1 - (p3.1 - p1.1) / 6.0);
+
+        for step in 1..=steps {
+            points.push(cubic_bezier(p1, c1, c2, p2, step as f64 / steps as f64));
+        }
+    }
+
+    points
+}
+
+/// Rounds off each corner of `path` with a cubic Bézier curve made up of `steps` lines,
+/// running between the midpoints of the lines either side of the corner.
+fn bezier(path: &[(f64, f64)], steps: usize) -> Vec<(f64, f64)> {
+    let midpoint = |a: (f64, f64), b: (f64, f64)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+    let towards = |a: (f64, f64), b: (f64, f64)| {
+        (a.0 + 2.0 * (b.0 - a.0) / 3.0, a.1 + 2.0 * (b.1 - a.1) / 3.0)
+    };
+
+    let mut points = vec![path[0]];
+
+    for corner in path.windows(3) {
+        let start = midpoint(corner[0], corner[1]);
+        let end = midpoint(corner[1], corner[2]);
+        let (c1, c2) = (towards(start, corner[1]), towards(end, corner[1]));
+
+        points.push(start);
+        for step in 1..=steps {
+            points.push(cubic_bezier(start, c1, c2, end, step as f64 / steps as f64));
+        }
+    }
+
+    points.push(path[path.len() - 1]);
+    points.dedup();
+    points
+}
+
+/// Returns the distance from `point` to the line segment between `start` and `end`.
+fn distance_to_segment(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length_squared = dx * dx + dy * dy;
+
+    let t = if length_squared > 0.0 {
+        (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    (point.0 - (start.0 + t * dx)).hypot(point.1 - (start.1 + t * dy))
+}
+
+/// Simplifies `points` using the Ramer-Douglas-Peucker algorithm, keeping the first and last
+/// points and every point further than `tolerance` from the simplified path.
+fn douglas_peucker(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Work through the ranges with an explicit stack, since paths can be very long
+    let mut ranges = vec![(0, points.len() - 1)];
+
+    while let Some((start, end)) = ranges.pop() {
+        let mut furthest = (start, 0.0);
+
+        for (index, point) in points.iter().enumerate().take(end).skip(start + 1) {
+            let distance = distance_to_segment(*point, points[start], points[end]);
+
+            if distance > furthest.1 {
+                furthest = (index, distance);
+            }
+        }
+
+        if furthest.1 > tolerance {
+            keep[furthest.0] = true;
+            ranges.push((start, furthest.0));
+            ranges.push((furthest.0, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| if keep { Some(*point) } else { None })
+        .collect()
 }
 
 /// The basic work horse-turtle.  Keeps track of where it is, where it's been, and
@@ -151,11 +510,17 @@ pub struct BaseTurtle {
     x: f64,
     y: f64,
     lines: Vec<(f64, f64, f64, f64)>,
+    styles: Vec<SegmentStyle>,
+    shapes: Vec<(Shape, SegmentStyle)>,
+    labels: Vec<(Label, SegmentStyle)>,
+    style: SegmentStyle,
     max_x: f64,
     max_y: f64,
     min_x: f64,
     min_y: f64,
     pen_down: bool,
+    events: Option<Vec<TurtleEvent>>,
+    event_color: Option<Rgb<u8>>,
 }
 
 impl BaseTurtle {
@@ -174,11 +539,17 @@ impl BaseTurtle {
             x: 0.0,
             y: 0.0,
             lines: Vec::new(),
+            styles: Vec::new(),
+            shapes: Vec::new(),
+            labels: Vec::new(),
+            style: SegmentStyle::default(),
             max_x: 0.0,
             max_y: 0.0,
             min_x: 0.0,
             min_y: 0.0,
             pen_down: true,
+            events: None,
+            event_color: None,
         }
     }
 
@@ -232,6 +603,90 @@ impl BaseTurtle {
         &self.lines
     }
 
+    /// Returns a slice containing the style of each line traversed by the turtle, in the
+    /// same order as [`BaseTurtle::lines`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::BaseTurtle;
+    /// use image::Rgb;
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.delta_move(1.0, 0.0);
+    ///
+    /// turtle.style_mut().color = Some(Rgb([255, 0, 0]));
+    /// turtle.delta_move(1.0, 0.0);
+    ///
+    /// assert_eq!(turtle.styles()[0].color, None);
+    /// assert_eq!(turtle.styles()[1].color, Some(Rgb([255, 0, 0])));
+    /// ```
+    pub fn styles(&self) -> &[SegmentStyle] {
+        &self.styles
+    }
+
+    /// Returns a slice containing the shapes drawn by the turtle, together with their styles.
+    pub fn shapes(&self) -> &[(Shape, SegmentStyle)] {
+        &self.shapes
+    }
+
+    /// Draws a filled shape using the current style, if the pen is down.  The shape is
+    /// included in the [`bounds`](BaseTurtle::bounds) of the turtle.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{BaseTurtle, Shape};
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.draw_shape(Shape::Dot { x: 0.0, y: 0.0, radius: 2.0 });
+    ///
+    /// assert_eq!(turtle.shapes().len(), 1);
+    /// assert_eq!(turtle.bounds(), (4.0, 4.0, -2.0, -2.0));
+    /// ```
+    pub fn draw_shape(&mut self, shape: Shape) {
+        if !self.pen_down {
+            return;
+        }
+
+        for (x, y) in shape.outline() {
+            self.include(x, y);
+        }
+
+        self.shapes.push((shape, self.style));
+    }
+
+    /// Returns a slice containing the labels written by the turtle, together with their styles.
+    pub fn labels(&self) -> &[(Label, SegmentStyle)] {
+        &self.labels
+    }
+
+    /// Writes a label using the current style.  Labels are annotations rather than part of the
+    /// drawing, so they are written even while the pen is up.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{BaseTurtle, Label};
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.pen_up();
+    /// turtle.write(Label { x: 1.0, y: 2.0, heading: 0.0, text: String::from("A") });
+    ///
+    /// assert_eq!(turtle.labels()[0].0.text, "A");
+    /// ```
+    pub fn write(&mut self, label: Label) {
+        self.include(label.x, label.y);
+        self.labels.push((label, self.style));
+    }
+
+    /// Returns the style that will be used for lines drawn from now on.
+    pub fn style(&self) -> SegmentStyle {
+        self.style
+    }
+
+    /// Returns a mutable reference to the style that will be used for lines drawn from now on.
+    pub fn style_mut(&mut self) -> &mut SegmentStyle {
+        &mut self.style
+    }
+
     /// Set the current position of this turtle to `(x,y)`.
     ///
     /// # Example
@@ -248,13 +703,74 @@ impl BaseTurtle {
         self.x = x;
         self.y = y;
         self.update_bounds();
+        self.record(TurtleEvent::MoveTo { x, y });
+    }
+
+    /// Starts (or stops) recording the steps taken by the turtle, which are then available
+    /// from [`BaseTurtle::events`].  Stopping discards any recorded events.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{BaseTurtle, TurtleEvent};
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.record_events(true);
+    ///
+    /// turtle.delta_move(1.0, 0.0);
+    /// turtle.pen_up();
+    /// turtle.delta_move(0.0, 1.0);
+    ///
+    /// assert_eq!(
+    ///     turtle.events(),
+    ///     &[
+    ///         TurtleEvent::LineTo { x: 1.0, y: 0.0 },
+    ///         TurtleEvent::PenChange(false),
+    ///         TurtleEvent::MoveTo { x: 1.0, y: 1.0 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn record_events(&mut self, record: bool) {
+        self.events = if record { Some(Vec::new()) } else { None };
+        self.event_color = self.style.color;
+    }
+
+    /// Returns true if the turtle is recording its steps.
+    pub fn is_recording_events(&self) -> bool {
+        self.events.is_some()
+    }
+
+    /// Returns the steps taken by the turtle since it started
+    /// [recording](BaseTurtle::record_events) them.  These are the steps as the turtle took
+    /// them, before any simplification or smoothing of its lines.
+    pub fn events(&self) -> &[TurtleEvent] {
+        self.events.as_deref().unwrap_or(&[])
+    }
+
+    /// Records `event`, if the turtle is recording events.
+    fn record(&mut self, event: TurtleEvent) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
     }
 
     fn update_bounds(&mut self) {
-        self.min_x = self.min_x.min(self.x);
-        self.min_y = self.min_y.min(self.y);
-        self.max_x = self.max_x.max(self.x);
-        self.max_y = self.max_y.max(self.y);
+        self.include(self.x, self.y);
+    }
+
+    /// Shrinks the bounds of the turtle back to the origin, which they always contain.
+    fn reset_bounds(&mut self) {
+        self.min_x = 0.0;
+        self.min_y = 0.0;
+        self.max_x = 0.0;
+        self.max_y = 0.0;
+    }
+
+    /// Extends the bounds of the turtle to include `(x, y)`.
+    fn include(&mut self, x: f64, y: f64) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
     }
 
     /// Moves the turtle by `(dx,dy)`.
@@ -280,6 +796,20 @@ impl BaseTurtle {
 
         if self.pen_down {
             self.lines.push((self.x, self.y, x2, y2));
+            self.styles.push(self.style);
+        }
+
+        if self.events.is_some() {
+            if !self.pen_down {
+                self.record(TurtleEvent::MoveTo { x: x2, y: y2 });
+            } else {
+                if self.event_color != self.style.color {
+                    self.event_color = self.style.color;
+                    self.record(TurtleEvent::ColorChange(self.style.color));
+                }
+
+                self.record(TurtleEvent::LineTo { x: x2, y: y2 });
+            }
         }
 
         self.x = x2;
@@ -288,6 +818,133 @@ impl BaseTurtle {
         self.update_bounds();
     }
 
+    /// Moves everything the turtle has drawn, and the turtle itself, by `transform`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::scene::Transform;
+    /// use dcc_lsystem::turtle::BaseTurtle;
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.pen_down();
+    /// turtle.delta_move(3.0, 4.0);
+    ///
+    /// turtle.transform(&Transform::new().scale(2.0).translate(1.0, 1.0));
+    /// assert_eq!(turtle.lines(), &[(1.0, 1.0, 7.0, 9.0)]);
+    /// assert_eq!((turtle.x(), turtle.y()), (7.0, 9.0));
+    /// ```
+    pub fn transform(&mut self, transform: &Transform) {
+        let rotation = transform.rotation();
+        let scale = transform.scale_factor();
+
+        for line in &mut self.lines {
+            let (x1, y1) = transform.apply(line.0, line.1);
+            let (x2, y2) = transform.apply(line.2, line.3);
+            *line = (x1, y1, x2, y2);
+        }
+
+        for (shape, _) in &mut self.shapes {
+            match shape {
+                Shape::Dot { x, y, radius } => {
+                    (*x, *y) = transform.apply(*x, *y);
+                    *radius *= scale;
+                }
+                Shape::Leaf {
+                    x,
+                    y,
+                    heading,
+                    size,
+                } => {
+                    (*x, *y) = transform.apply(*x, *y);
+                    *heading += rotation;
+                    *size *= scale;
+                }
+            }
+        }
+
+        for (label, _) in &mut self.labels {
+            (label.x, label.y) = transform.apply(label.x, label.y);
+            label.heading += rotation;
+        }
+
+        if let Some(events) = &mut self.events {
+            for event in events {
+                if let TurtleEvent::MoveTo { x, y } | TurtleEvent::LineTo { x, y } = event {
+                    (*x, *y) = transform.apply(*x, *y);
+                }
+            }
+        }
+
+        (self.x, self.y) = transform.apply(self.x, self.y);
+
+        self.reset_bounds();
+
+        let points: Vec<(f64, f64)> = self
+            .lines
+            .iter()
+            .flat_map(|&(x1, y1, x2, y2)| [(x1, y1), (x2, y2)])
+            .chain(self.shapes.iter().flat_map(|(shape, _)| shape.outline()))
+            .chain(self.labels.iter().map(|(label, _)| (label.x, label.y)))
+            .chain([(self.x, self.y)])
+            .collect();
+
+        for (x, y) in points {
+            self.include(x, y);
+        }
+    }
+
+    /// Replaces everything the turtle has drawn with a copy for each of `transforms`.  The
+    /// turtle itself stays where it is.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::scene::Transform;
+    /// use dcc_lsystem::turtle::BaseTurtle;
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.pen_down();
+    /// turtle.delta_move(1.0, 0.0);
+    ///
+    /// turtle.replicate(&[Transform::new(), Transform::new().translate(0.0, 2.0)]);
+    /// assert_eq!(turtle.lines(), &[(0., 0., 1., 0.), (0., 2., 1., 2.)]);
+    /// assert_eq!(turtle.bounds(), (1.0, 2.0, 0.0, 0.0));
+    /// ```
+    pub fn replicate(&mut self, transforms: &[Transform]) {
+        let original = self.clone();
+
+        self.lines.clear();
+        self.styles.clear();
+        self.shapes.clear();
+        self.labels.clear();
+
+        if let Some(events) = &mut self.events {
+            events.clear();
+        }
+
+        self.reset_bounds();
+        self.update_bounds();
+
+        for transform in transforms {
+            let mut copy = original.clone();
+            copy.transform(transform);
+
+            self.lines.extend(copy.lines);
+            self.styles.extend(copy.styles);
+            self.shapes.extend(copy.shapes);
+            self.labels.extend(copy.labels);
+
+            // Each copy starts from wherever the original turtle started
+            if let (Some(events), Some(copied)) = (&mut self.events, copy.events) {
+                let (x, y) = transform.apply(0.0, 0.0);
+                events.push(TurtleEvent::MoveTo { x, y });
+                events.extend(copied);
+            }
+
+            self.include(copy.min_x, copy.min_y);
+            self.include(copy.max_x, copy.max_y);
+        }
+    }
+
     /// Returns `(total_width, total_height, min_x, min_y)`, where
     /// `total_width` (respectively `total_height) is the largest horizontal (respectively vertical) distance between any two points
     /// that the turtle visited, `min_x` (respectively `min_y`) is the smallest horizontal (respectively vertical) position that
@@ -333,6 +990,7 @@ impl BaseTurtle {
     /// ```
     pub fn pen_down(&mut self) {
         self.pen_down = true;
+        self.record(TurtleEvent::PenChange(true));
     }
 
     /// Pulls the turtles pen up.  While the pen is up the turtle will not draw lines
@@ -351,6 +1009,109 @@ impl BaseTurtle {
     /// ```
     pub fn pen_up(&mut self) {
         self.pen_down = false;
+        self.record(TurtleEvent::PenChange(false));
+    }
+
+    /// Simplifies the lines traversed by the turtle.  Only connected lines with the same style
+    /// are joined together, so the simplified lines look the same when rendered.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{BaseTurtle, Simplify};
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.delta_move(1.0, 0.0);
+    /// turtle.delta_move(1.0, 0.0);
+    /// turtle.delta_move(1.0, 0.1);
+    ///
+    /// turtle.simplify(Simplify::Collinear);
+    /// assert_eq!(turtle.lines(), &[(0., 0., 2., 0.), (2., 0., 3., 0.1)]);
+    ///
+    /// turtle.simplify(Simplify::Tolerance(0.5));
+    /// assert_eq!(turtle.lines(), &[(0., 0., 3., 0.1)]);
+    /// ```
+    pub fn simplify(&mut self, simplify: Simplify) {
+        if simplify == Simplify::Off || self.lines.is_empty() {
+            return;
+        }
+
+        for (style, points) in self.take_runs() {
+            let mut points = merge_collinear(points);
+
+            if let Simplify::Tolerance(tolerance) = simplify {
+                points = douglas_peucker(&points, tolerance);
+            }
+
+            self.push_run(style, &points);
+        }
+    }
+
+    /// Replaces the lines traversed by the turtle with smooth curves, each drawn as a sequence
+    /// of short lines.  As with [`BaseTurtle::simplify`], only connected lines with the same
+    /// style are smoothed together.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{BaseTurtle, Smooth};
+    ///
+    /// let mut turtle = BaseTurtle::new();
+    /// turtle.delta_move(1.0, 0.0);
+    /// turtle.delta_move(0.0, 1.0);
+    ///
+    /// turtle.smooth(Smooth::CatmullRom(4));
+    /// assert_eq!(turtle.lines().len(), 8);
+    ///
+    /// // The curve still passes through the corner
+    /// assert_eq!(turtle.lines()[3].2, 1.0);
+    /// assert_eq!(turtle.lines()[3].3, 0.0);
+    /// ```
+    pub fn smooth(&mut self, smooth: Smooth) {
+        let points = match smooth {
+            Smooth::Off => return,
+            Smooth::CatmullRom(points) | Smooth::Bezier(points) => points.max(1),
+        };
+
+        for (style, path) in self.take_runs() {
+            let path = match smooth {
+                Smooth::CatmullRom(_) => catmull_rom(&path, points),
+                _ => bezier(&path, points),
+            };
+
+            // Catmull-Rom curves can overshoot the path they pass through
+            for &(x, y) in &path {
+                self.include(x, y);
+            }
+
+            self.push_run(style, &path);
+        }
+    }
+
+    /// Removes the lines traversed by the turtle, returning them as runs of connected lines
+    /// with the same style.
+    fn take_runs(&mut self) -> Vec<(SegmentStyle, Vec<(f64, f64)>)> {
+        let mut runs: Vec<(SegmentStyle, Vec<(f64, f64)>)> = Vec::new();
+
+        for ((x1, y1, x2, y2), style) in self.lines.drain(..).zip(self.styles.drain(..)) {
+            match runs.last_mut() {
+                Some((run_style, points))
+                    if *run_style == style && points.last() == Some(&(x1, y1)) =>
+                {
+                    points.push((x2, y2))
+                }
+                _ => runs.push((style, vec![(x1, y1), (x2, y2)])),
+            }
+        }
+
+        runs
+    }
+
+    /// Adds a line between each pair of consecutive points in `points`.
+    fn push_run(&mut self, style: SegmentStyle, points: &[(f64, f64)]) {
+        for pair in points.windows(2) {
+            self.lines
+                .push((pair[0].0, pair[0].1, pair[1].0, pair[1].1));
+            self.styles.push(style);
+        }
     }
 }
 
@@ -451,7 +1212,7 @@ pub struct SimpleTurtle {
     turtle: BaseTurtle,
     heading: f64,
     stack: Vec<(f64, f64, f64)>,
-    pen_down: bool,
+    tropism: Option<(f64, f64, f64)>,
 }
 
 impl SimpleTurtle {
@@ -461,7 +1222,7 @@ impl SimpleTurtle {
             turtle: BaseTurtle::new(),
             heading: FRAC_PI_2,
             stack: Vec::new(),
-            pen_down: true,
+            tropism: None,
         }
     }
 
@@ -479,27 +1240,135 @@ impl SimpleTurtle {
     pub fn set_heading(&mut self, heading: f64) {
         self.heading = heading;
     }
-}
 
-impl Stack for SimpleTurtle {
-    /// Pushes the current position and heading of the turtle onto the stack.
-    fn push(&mut self) {
-        self.stack
-            .push((self.turtle.x(), self.turtle.y(), self.heading));
+    /// Returns the current heading of the turtle (in radians).
+    pub fn heading(&self) -> f64 {
+        self.heading
     }
 
-    /// Pops the position and heading off the stack.  If the stack is empty
-    /// then popping will do nothing.
-    fn pop(&mut self) {
-        if let Some((x, y, heading)) = self.stack.pop() {
-            self.turtle.set_position(x, y);
-            self.heading = heading;
-        }
-    }
+    /// Bends the turtle towards the vector `(dx, dy)` after each forward move, by an angle
+    /// proportional to `susceptibility` and to how far the turtle's heading is from the vector,
+    /// as in *The Algorithmic Beauty of Plants*.  Pointing the vector downwards makes branches
+    /// droop.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{MovingTurtle, SimpleTurtle};
+    ///
+    /// let mut turtle = SimpleTurtle::new();
+    /// turtle.set_heading(0.0);
+    /// turtle.set_tropism(0.0, -1.0, 0.25);
+    ///
+    /// // After moving east, the turtle turns a little towards the south
+    /// turtle.forward(10.0);
+    /// assert_eq!(turtle.heading(), -0.25);
+    /// ```
+    pub fn set_tropism(&mut self, dx: f64, dy: f64, susceptibility: f64) {
+        self.tropism = Some((dx, dy, susceptibility));
+    }
+
+    /// Moves the turtle along a circular arc with the given radius, turning through `angle`
+    /// (in radians, turning left for positive angles).  The arc is drawn as a sequence of short
+    /// lines.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{MovingTurtle, SimpleTurtle};
+    /// use std::f64::consts::PI;
+    ///
+    /// let mut turtle = SimpleTurtle::new();
+    /// turtle.set_heading(0.0);
+    ///
+    /// // A semicircle ends up directly above where it started
+    /// turtle.arc(10.0, PI);
+    /// assert!(turtle.inner().x().abs() < 1e-9);
+    /// assert!((turtle.inner().y() - 20.0).abs() < 1e-9);
+    /// ```
+    pub fn arc(&mut self, radius: f64, angle: f64) {
+        let steps = (angle.abs() / Self::ARC_STEP).ceil().max(1.0);
+        let step = angle / steps;
+        let chord = 2.0 * radius * (step / 2.0).sin().abs();
+
+        for _ in 0..steps as usize {
+            // Each chord points halfway between the headings at its ends
+            let heading = self.heading + step / 2.0;
+            self.turtle
+                .delta_move(chord * heading.cos(), chord * heading.sin());
+            self.heading += step;
+        }
+    }
+
+    /// The largest angle (in radians) turned through by a single line of an arc.
+    const ARC_STEP: f64 = PI / 36.0;
+
+    /// Moves the turtle forwards by the given distance without drawing a line.
+    pub fn skip(&mut self, distance: f64) {
+        let base = &mut self.turtle;
+        let x = base.x() + self.heading.cos() * distance;
+        let y = base.y() + self.heading.sin() * distance;
+
+        base.set_position(x, y);
+    }
+}
+
+impl Stack for SimpleTurtle {
+    /// The position and heading of the turtle, as `(x, y, heading)`.
+    type Frame = (f64, f64, f64);
+
+    /// Pushes the current position and heading of the turtle onto the stack.
+    fn push(&mut self) {
+        self.stack
+            .push((self.turtle.x(), self.turtle.y(), self.heading));
+        self.turtle.style_mut().depth = self.stack.len();
+        self.turtle.record(TurtleEvent::Push);
+    }
+
+    /// Pops the position and heading off the stack.  If the stack is empty
+    /// then popping will do nothing.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{MovingTurtle, SimpleTurtle, Stack};
+    ///
+    /// let mut turtle = SimpleTurtle::new();
+    /// turtle.push();
+    /// turtle.forward(10.0);
+    /// assert_eq!(turtle.depth(), 1);
+    /// assert_eq!(turtle.peek().map(|frame| frame.0), Some(0.0));
+    ///
+    /// assert!(turtle.pop());
+    /// assert!(!turtle.pop());
+    /// assert_eq!(turtle.depth(), 0);
+    /// ```
+    fn pop(&mut self) -> bool {
+        match self.stack.pop() {
+            Some((x, y, heading)) => {
+                self.turtle.record(TurtleEvent::Pop);
+                self.turtle.set_position(x, y);
+                self.heading = heading;
+                self.turtle.style_mut().depth = self.stack.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn peek(&self) -> Option<&Self::Frame> {
+        self.stack.last()
+    }
+
+    fn clear(&mut self) {
+        self.stack.clear();
+        self.turtle.style_mut().depth = 0;
+    }
 }
 
 impl MovingTurtle for SimpleTurtle {
-    type Item = i32;
+    type Item = f64;
 
     fn inner(&self) -> &BaseTurtle {
         &self.turtle
@@ -509,12 +1378,14 @@ impl MovingTurtle for SimpleTurtle {
         &mut self.turtle
     }
 
-    fn forward(&mut self, distance: i32) {
-        let dx = self.heading.cos() * (distance as f64);
-        let dy = self.heading.sin() * (distance as f64);
+    fn forward(&mut self, distance: f64) {
+        let (sin, cos) = self.heading.sin_cos();
 
-        if self.pen_down {
-            self.turtle.delta_move(dx, dy);
+        self.turtle.delta_move(cos * distance, sin * distance);
+
+        if let Some((dx, dy, susceptibility)) = self.tropism {
+            // The cross product of the heading with the tropism vector
+            self.heading += susceptibility * (cos * dy - sin * dx);
         }
     }
 }
@@ -525,26 +1396,215 @@ impl Default for SimpleTurtle {
     }
 }
 
+/// A single turtle driven by a [`TurtleLSystemState`], together with its heading and the
+/// length of a single step.
+#[derive(Clone, Debug)]
+struct NamedTurtle {
+    name: String,
+    angle: f64,
+    step: f64,
+    angle_stack: Vec<(f64, f64)>,
+    color_stack: Vec<Option<Rgb<u8>>>,
+    thickness_stack: Vec<(Option<f64>, f64)>,
+    turtle: SimpleTurtle,
+}
+
+impl NamedTurtle {
+    fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            angle: 0.0,
+            step: 1.0,
+            angle_stack: Vec::new(),
+            color_stack: Vec::new(),
+            thickness_stack: Vec::new(),
+            turtle: SimpleTurtle::new(),
+        }
+    }
+
+    /// Returns the heading of the turtle (in radians), after applying a global rotation.
+    fn heading(&self, global_rotate: f64) -> f64 {
+        (global_rotate + self.angle).to_radians()
+    }
+
+    /// Moves the turtle forwards by the given number of steps, keeping track of any bending
+    /// due to tropism.
+    fn forward(&mut self, distance: f64, global_rotate: f64) {
+        let heading = self.heading(global_rotate);
+
+        self.turtle.set_heading(heading);
+        self.turtle.forward(distance * self.step);
+        self.rotate((self.turtle.heading() - heading).to_degrees());
+    }
+
+    /// Rotates the turtle through the given angle (in degrees).
+    fn rotate(&mut self, angle: f64) {
+        self.angle = (self.angle + angle) % 360.0;
+    }
+}
+
 /// The state modified by a `TurtleLSystemRenderer`.  Each `TurtleAction` corresponds
 /// to a modifier of the form `Fn(&mut TurtleLSystemState)`.
-#[derive(TurtleContainer)]
+///
+/// The state can drive several independent turtles, each identified by name.  Actions
+/// apply to the active turtle, which is changed using [`TurtleAction::SwitchTurtle`].
+/// Initially the only turtle is the one named [`TurtleLSystemState::MAIN_TURTLE`].
+#[derive(Clone)]
 pub struct TurtleLSystemState {
-    angle: i32,
-    angle_stack: Vec<i32>,
-
-    #[turtle]
-    turtle: SimpleTurtle,
+    turtles: Vec<NamedTurtle>,
+    active: usize,
+    rng: StdRng,
+    tropism: Option<(f64, f64, f64)>,
+    scale: f64,
+    initial_heading: f64,
+    unbalanced_pops: usize,
+    #[cfg(feature = "scripting")]
+    script_errors: usize,
 }
 
 impl TurtleLSystemState {
+    /// The name of the turtle which is active when rendering begins.
+    pub const MAIN_TURTLE: &'static str = "main";
+
     /// Create a new state.
     pub fn new() -> Self {
         Self {
-            angle: 0,
-            angle_stack: Vec::new(),
-            turtle: SimpleTurtle::new(),
+            turtles: vec![NamedTurtle::new(Self::MAIN_TURTLE)],
+            active: 0,
+            rng: StdRng::from_entropy(),
+            tropism: None,
+            scale: 1.0,
+            initial_heading: 0.0,
+            unbalanced_pops: 0,
+            #[cfg(feature = "scripting")]
+            script_errors: 0,
+        }
+    }
+
+    /// Create a new state whose stochastic actions are driven by a random number generator
+    /// seeded with `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..Self::new()
+        }
+    }
+
+    fn current(&mut self) -> &mut NamedTurtle {
+        &mut self.turtles[self.active]
+    }
+
+    /// Makes the turtle called `name` the active turtle.  If there is no such turtle then
+    /// a new one is created at the origin.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::{TurtleContainer, TurtleLSystemState};
+    ///
+    /// let mut state = TurtleLSystemState::new();
+    /// assert_eq!(state.active_turtle(), "main");
+    ///
+    /// state.switch_turtle("roots");
+    /// assert_eq!(state.active_turtle(), "roots");
+    /// assert_eq!(state.turtles().len(), 2);
+    /// ```
+    pub fn switch_turtle(&mut self, name: &str) {
+        self.active = match self.turtles.iter().position(|t| t.name == name) {
+            Some(index) => index,
+            None => {
+                let mut turtle = NamedTurtle::new(name);
+                turtle.step = self.scale;
+
+                // New turtles record events if the main turtle does
+                if self.turtles[0].turtle.inner().is_recording_events() {
+                    turtle.turtle.inner_mut().record_events(true);
+                }
+                turtle.angle = self.initial_heading;
+
+                if let Some((dx, dy, susceptibility)) = self.tropism {
+                    turtle.turtle.set_tropism(dx, dy, susceptibility);
+                }
+
+                self.turtles.push(turtle);
+                self.turtles.len() - 1
+            }
+        };
+    }
+
+    /// Bends every turtle, including those created later, towards the vector `(dx, dy)`.  See
+    /// [`SimpleTurtle::set_tropism`].
+    pub fn set_tropism(&mut self, dx: f64, dy: f64, susceptibility: f64) {
+        self.tropism = Some((dx, dy, susceptibility));
+
+        for turtle in self.turtles.iter_mut() {
+            turtle.turtle.set_tropism(dx, dy, susceptibility);
+        }
+    }
+
+    /// Sets the length of a single step for every turtle, including those created later.  The
+    /// distances moved by turtle actions are measured in steps.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+
+        for turtle in self.turtles.iter_mut() {
+            turtle.step = scale;
+        }
+    }
+
+    /// Sets the heading (in degrees) that every turtle, including those created later, starts
+    /// with and returns to when it goes [home](TurtleAction::Home).
+    pub fn set_initial_heading(&mut self, heading: f64) {
+        self.initial_heading = heading;
+
+        for turtle in self.turtles.iter_mut() {
+            turtle.angle = heading;
         }
     }
+
+    /// Returns the number of times a turtle was popped while its stack was empty, which
+    /// happens when a grammar closes more branches than it opens.
+    pub fn unbalanced_pops(&self) -> usize {
+        self.unbalanced_pops
+    }
+
+    /// Returns the number of times a [`TurtleAction::Script`] failed to run.  Requires the
+    /// `scripting` feature.
+    #[cfg(feature = "scripting")]
+    pub fn script_errors(&self) -> usize {
+        self.script_errors
+    }
+
+    /// Returns the name of the active turtle.
+    pub fn active_turtle(&self) -> &str {
+        &self.turtles[self.active].name
+    }
+
+    /// Returns a mutable reference to the active turtle.
+    pub fn turtle_mut(&mut self) -> &mut SimpleTurtle {
+        &mut self.current().turtle
+    }
+}
+
+impl TurtleContainer for TurtleLSystemState {
+    type Item = <SimpleTurtle as MovingTurtle>::Item;
+
+    fn inner(&self) -> &dyn MovingTurtle<Item = Self::Item> {
+        &self.turtles[0].turtle
+    }
+
+    fn turtles(&self) -> Vec<&dyn MovingTurtle<Item = Self::Item>> {
+        self.turtles
+            .iter()
+            .map(|t| &t.turtle as &dyn MovingTurtle<Item = Self::Item>)
+            .collect()
+    }
+
+    fn turtles_mut(&mut self) -> Vec<&mut dyn MovingTurtle<Item = Self::Item>> {
+        self.turtles
+            .iter_mut()
+            .map(|t| &mut t.turtle as &mut dyn MovingTurtle<Item = Self::Item>)
+            .collect()
+    }
 }
 
 impl Default for TurtleLSystemState {
@@ -560,7 +1620,11 @@ pub struct TurtleLSystemBuilder {
     builder: LSystemBuilder,
     actions: HashMap<ArenaId, TurtleAction>,
     tokens: HashMap<String, ArenaId>,
-    global_rotate: i32,
+    global_rotate: f64,
+    seed: Option<u64>,
+    tropism: Option<(f64, f64, f64)>,
+    scale: f64,
+    initial_heading: f64,
 }
 
 impl TurtleLSystemBuilder {
@@ -570,14 +1634,266 @@ impl TurtleLSystemBuilder {
             builder: LSystemBuilder::new(),
             actions: HashMap::new(),
             tokens: HashMap::new(),
-            global_rotate: 0,
+            global_rotate: 0.0,
+            seed: None,
+            tropism: None,
+            scale: 1.0,
+            initial_heading: 0.0,
         }
     }
 
+    /// Constructs a builder from a grammar written in the text format described in
+    /// the [`grammar`](crate::grammar) module.
+    ///
+    /// Actions are declared using lines of the form `<symbol> = <action>`, where `<action>` is one of
+    ///
+    /// * `nothing`,
+    /// * `forward <distance>` or `rotate <angle>`,
+    /// * `forward <lower>..<upper>` or `rotate <lower>..<upper>`, which sample uniformly from the given range,
+    /// * `push` or `pop`,
+    /// * `pen_up` or `pen_down`,
+    /// * `skip <distance>`, `goto <x> <y>` or `home`, which move the turtle without drawing,
+    /// * `heading <angle>`, which turns the turtle to face the given heading,
+    /// * `arc <radius> <angle>`, which moves the turtle along a circular arc,
+    /// * `switch <name>`, which switches to the turtle called `<name>`,
+    /// * `color <red> <green> <blue>`, `push_color` or `pop_color`,
+    /// * `thickness <thickness>`, `thicker <amount>` or `thinner <amount>`,
+    /// * `multiply_step <factor>`, which scales the distances moved from now on,
+    /// * `dot <radius>` or `leaf <size>`, which draw filled shapes,
+    /// * `label <text>`, which writes some text,
+    /// * `script <code>`, which runs a [script](TurtleAction::Script), with the `scripting` feature.
+    ///
+    /// Symbols without a declared action do nothing.  Words in the axiom and rules are split
+    /// into symbols with declared actions where possible, so rules can be written compactly, as
+    /// described in the [`grammar`](crate::grammar) module.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::turtle::TurtleLSystemBuilder;
+    ///
+    /// let builder = TurtleLSystemBuilder::from_grammar("
+    ///     F = forward 30
+    ///     + = rotate 90
+    ///     - = rotate -90
+    ///     axiom: F
+    ///     F => F+F-F-F+F
+    /// ")?;
+    ///
+    /// let (mut system, renderer) = builder.finish()?;
+    /// system.step();
+    /// assert_eq!(system.render(), "F+F-F-F+F");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_grammar(grammar: &str) -> Result<Self, LSystemError> {
+        let mut statements = grammar::parse(grammar)?;
+        grammar::split_compact(&mut statements);
+
+        let mut builder = Self::new();
+        let mut actions = HashMap::new();
+
+        for statement in statements.iter() {
+            if let Statement::Action { symbol, action } = statement {
+                if actions.contains_key(symbol.name) {
+                    return Err(symbol.error("an action has already been declared for this symbol"));
+                }
+
+                actions.insert(symbol.name, parse_action(action)?);
+            }
+        }
+
+        for name in grammar::token_names(&statements) {
+            let action = actions.remove(name).unwrap_or(TurtleAction::Nothing);
+            builder.token(name, action)?;
+        }
+
+        let lookup = |symbols: &[Symbol]| -> Vec<ArenaId> {
+            symbols
+                .iter()
+                .map(|symbol| builder.tokens[symbol.name])
+                .collect()
+        };
+
+        let mut axiom = None;
+        let mut rules = Vec::new();
+
+        for statement in statements.iter() {
+            match statement {
+                Statement::Axiom(symbols) => axiom = Some(lookup(symbols)),
+                Statement::Rule {
+                    predecessor,
+                    successor,
+                } => rules.push((builder.tokens[predecessor.name], lookup(successor))),
+                Statement::Action { .. } => {}
+            }
+        }
+
+        if let Some(axiom) = axiom {
+            builder.builder.axiom(axiom)?;
+        }
+
+        for (predecessor, successor) in rules {
+            builder
+                .builder
+                .transformation_rule(predecessor, successor)?;
+        }
+
+        Ok(builder)
+    }
+
+    /// Constructs a builder from a TOML or JSON document, in the format described in the
+    /// [`config`](crate::config) module.  The document is read as JSON if it starts with `{`.
+    ///
+    /// The number of steps and renderer options in the document are ignored; use
+    /// [`TurtleConfig`](crate::config::TurtleConfig) to read those too.  Requires the `config`
+    /// feature.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::turtle::TurtleLSystemBuilder;
+    ///
+    /// let builder = TurtleLSystemBuilder::from_config(r#"
+    ///     axiom = "F"
+    ///     rules = ["F => F + F"]
+    ///
+    ///     [tokens]
+    ///     F = "forward 10"
+    ///     "+" = "rotate 120"
+    /// "#)?;
+    ///
+    /// let (mut system, _renderer) = builder.finish()?;
+    /// system.step();
+    /// assert_eq!(system.render(), "F+F");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "config")]
+    pub fn from_config(config: &str) -> Result<Self, LSystemError> {
+        crate::config::TurtleConfig::parse(config)?.builder()
+    }
+
+    /// Constructs a builder from the TOML or JSON document in the file at `path`, as in
+    /// [`TurtleLSystemBuilder::from_config`].  The file is read as JSON if it has a `.json`
+    /// extension.  Requires the `config` feature.
+    #[cfg(feature = "config")]
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Result<Self, LSystemError> {
+        crate::config::TurtleConfig::from_path(path)?.builder()
+    }
+
+    /// Seed the random number generator used by stochastic actions, so that rendering the
+    /// resulting system always produces the same output.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::renderer::{DataRendererOptions, Renderer};
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder, Uniform};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    /// builder
+    ///     .token("F", TurtleAction::StochasticForward(Box::new(Uniform::new(1, 100))))?
+    ///     .axiom("F F F F")?
+    ///     .seed(42);
+    ///
+    /// let render = || -> Result<_, LSystemError> {
+    ///     let (system, renderer) = builder.build()?;
+    ///     Ok(renderer.render(&system, &DataRendererOptions::default()))
+    /// };
+    ///
+    /// assert_eq!(render()?, render()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self.builder.seed(seed);
+
+        self
+    }
+
     /// Apply a global rotation to the builder.  This is useful for modifying the orientation
     /// of the data passed to a `Renderer`.
-    pub fn rotate(&mut self, angle: i32) -> &mut Self {
-        self.global_rotate = angle;
+    pub fn rotate<A: Into<f64>>(&mut self, angle: A) -> &mut Self {
+        self.global_rotate = angle.into();
+
+        self
+    }
+
+    /// Bend the turtle towards the vector `(dx, dy)` each time it moves forwards, by an angle
+    /// proportional to `susceptibility`.  The vector is given in the coordinates of the
+    /// rendered output, ignoring any [global rotation](TurtleLSystemBuilder::rotate), so
+    /// `tropism(0.0, -1.0, 0.1)` always makes branches droop downwards.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::renderer::{DataRendererOptions, Renderer};
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .axiom("F F F")?
+    ///     .tropism(0.0, -1.0, 0.2);
+    ///
+    /// let (system, renderer) = builder.finish()?;
+    /// let lines = renderer.render(&system, &DataRendererOptions::default());
+    ///
+    /// // Each line heads further downwards than the one before it
+    /// assert!(lines[2].3 < lines[1].3 && lines[1].3 < lines[0].3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tropism(&mut self, dx: f64, dy: f64, susceptibility: f64) -> &mut Self {
+        self.tropism = Some((dx, dy, susceptibility));
+
+        self
+    }
+
+    /// Set the heading (in degrees) that the turtle starts with.  Unlike
+    /// [`rotate`](TurtleLSystemBuilder::rotate), this doesn't rotate the rest of the system,
+    /// so [`TurtleAction::SetHeading`] and [`TurtleAction::Goto`] are unaffected by it.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::renderer::{DataRendererOptions, Renderer};
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .axiom("F")?
+    ///     .initial_heading(180.0);
+    ///
+    /// let (system, renderer) = builder.finish()?;
+    /// let lines = renderer.render(&system, &DataRendererOptions::default());
+    ///
+    /// // The turtle starts off facing west
+    /// assert_eq!(lines[0].2, -10.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn initial_heading(&mut self, heading: f64) -> &mut Self {
+        self.initial_heading = heading;
+
+        self
+    }
+
+    /// Multiply every distance moved by the turtle by `scale`.  This is a quick way to resize
+    /// a system without changing each of its actions, and is also the length that
+    /// [`TurtleAction::MultiplyStep`] starts shrinking from.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
 
         self
     }
@@ -590,6 +1906,9 @@ impl TurtleLSystemBuilder {
     ) -> Result<&mut Self, LSystemError> {
         let ident = token.into();
 
+        #[cfg(feature = "scripting")]
+        action.check_scripts()?;
+
         let token = self.builder.token(ident.clone())?;
 
         self.tokens.insert(ident, token);
@@ -599,14 +1918,10 @@ impl TurtleLSystemBuilder {
     }
 
     /// Set the axiom  for this builder.
+    ///
+    /// The axiom is split into tokens as described in [`TurtleLSystemBuilder::rule`].
     pub fn axiom(&mut self, ident: &str) -> Result<&mut Self, LSystemError> {
-        let mut axiom = Vec::new();
-
-        for part in ident.split_whitespace() {
-            let token = self.get_token(part)?;
-
-            axiom.push(token);
-        }
+        let axiom = self.tokenize(ident)?;
 
         assert_ne!(axiom.len(), 0);
 
@@ -615,101 +1930,216 @@ impl TurtleLSystemBuilder {
         Ok(self)
     }
 
-    fn get_token(&self, token: &str) -> Result<ArenaId, LSystemError> {
-        self.tokens
-            .get(token)
-            .cloned()
-            .ok_or_else(|| LSystemError::UnknownToken(token.to_string()))
+    /// Splits `text` into registered tokens.  Each whitespace separated word is split by
+    /// repeatedly taking the longest registered token that it starts with.
+    fn tokenize(&self, text: &str) -> Result<Vec<ArenaId>, LSystemError> {
+        token::tokenize(text, &self.tokens)
     }
 
     /// Add a transformation rule to the builder.
+    ///
+    /// Rules have the form `predecessor => successor`, where the predecessor is a single token
+    /// and the successor is any number of tokens.  Tokens can have names of any length, and
+    /// needn't be separated by whitespace: each word is split by repeatedly taking the longest
+    /// registered token it starts with.
+    ///
+    /// A rule can also be stochastic, with several successors separated by `|`, each optionally
+    /// prefixed by a weight and a colon, as in `F => 0.6: F [ + F ] | 0.4: F F`.  Every time the
+    /// predecessor is expanded, one of the successors is chosen at random with probability
+    /// proportional to its weight; successors without a weight have weight 1.  See
+    /// [`LSystemBuilder::stochastic_rule`](crate::LSystemBuilder::stochastic_rule) for details,
+    /// and [`TurtleLSystemBuilder::seed`] to make the choices reproducible.  The `|` and `:`
+    /// characters keep their usual meaning if they're registered as tokens.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .token("F1", TurtleAction::Forward(20.0))?
+    ///     .token("→", TurtleAction::Rotate(-90.0))?
+    ///     .axiom("F1")?
+    ///     .rule("F1 => F1→F F")?;
+    ///
+    /// let (mut system, _renderer) = builder.finish()?;
+    /// system.step();
+    /// assert_eq!(system.render(), "F1→FF");
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .token("+", TurtleAction::Rotate(25.0))?
+    ///     .token("[", TurtleAction::Push)?
+    ///     .token("]", TurtleAction::Pop)?
+    ///     .axiom("F")?
+    ///     .rule("F => 0.6: F [ + F ] | 0.4: F F")?
+    ///     .seed(3);
+    ///
+    /// let (mut system, _renderer) = builder.finish()?;
+    /// system.step();
+    /// assert!(["F[+F]", "FF"].contains(&system.render().as_str()));
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn rule<'a, S: Into<&'a str>>(&mut self, rule: S) -> Result<&mut Self, LSystemError> {
         let rule = rule.into();
 
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"\s*(\w)\s*=>\s*((?:\s*\S+\s*)*)\s*").unwrap();
-        }
-
-        let cap = RE
-            .captures(rule)
+        let (lhs, rhs) = rule
+            .split_once("=>")
             .ok_or_else(|| LSystemError::InvalidRule(rule.to_string()))?;
 
         // The LHS of our rule
-        let lhs = self.get_token(&cap[1])?;
-
-        // Construct the RHS of our rule
-        let mut rule = Vec::new();
-
-        for token in cap[2].split_whitespace() {
-            let token = self.get_token(token)?;
-
-            rule.push(token);
+        let lhs = match self.tokenize(lhs)?[..] {
+            [lhs] => lhs,
+            _ => return Err(LSystemError::InvalidRule(rule.to_string())),
+        };
+
+        // Construct the RHS of our rule, which may be split into weighted alternatives
+        let alternatives = if self.tokens.contains_key("|") {
+            vec![rhs]
+        } else {
+            rhs.split('|').collect()
+        };
+
+        let mut weighted = false;
+        let mut successors = Vec::with_capacity(alternatives.len());
+
+        for alternative in alternatives {
+            let (weight, successor) = self.weighted_successor(alternative)?;
+            weighted |= weight.is_some();
+            successors.push((weight.unwrap_or(1.0), successor));
         }
 
         // Add the rule to our builder
-        self.builder.transformation_rule(lhs, rule)?;
+        if successors.len() == 1 && !weighted {
+            let (_, rhs) = successors.remove(0);
+            self.builder.transformation_rule(lhs, rhs)?;
+        } else {
+            self.builder.stochastic_rule(lhs, successors)?;
+        }
 
         Ok(self)
     }
 
+    /// Splits an alternative of a stochastic rule into its weight, if it has one, and its
+    /// successor.
+    fn weighted_successor(&self, text: &str) -> Result<(Option<f64>, Vec<ArenaId>), LSystemError> {
+        if !self.tokens.contains_key(":") {
+            if let Some((weight, successor)) = text.split_once(':') {
+                if let Ok(weight) = weight.trim().parse::<f64>() {
+                    return Ok((Some(weight), self.tokenize(successor)?));
+                }
+            }
+        }
+
+        Ok((None, self.tokenize(text)?))
+    }
+
     /// Consumes the builder, returning the generated `LSystem` and a `Renderer`
     /// which can associate tokens in the `LSystem` to turtle actions.
-    pub fn finish(self) -> Result<(LSystem, TurtleRenderer<TurtleLSystemState>), LSystemError> {
-        let mut renderer = TurtleRenderer::new(TurtleLSystemState::new());
+    pub fn finish(mut self) -> Result<(LSystem, TurtleRenderer<TurtleLSystemState>), LSystemError> {
+        let mut interpretation = self.settings();
 
-        // Register the processing functions for each action
-        for (id, action) in self.actions.into_iter() {
-            match action {
-                TurtleAction::Push => {
-                    renderer.register(id, |state| {
-                        state.turtle.push();
-                        state.angle_stack.push(state.angle);
-                    });
-                }
-                TurtleAction::Pop => {
-                    renderer.register(id, |state| {
-                        state.turtle.pop();
-                        // popping from an empty stack doesn't do anything
-                        if let Some(angle) = state.angle_stack.pop() {
-                            state.angle = angle;
-                        }
-                    });
-                }
-                TurtleAction::Forward(distance) => {
-                    let current_global_rotate = self.global_rotate;
-
-                    renderer.register(id, move |state| {
-                        state.turtle.set_heading(
-                            ((current_global_rotate + state.angle) as f64).to_radians(),
-                        );
-                        state.turtle.forward(distance);
-                    });
-                }
-                TurtleAction::Rotate(angle) => {
-                    renderer.register(id, move |state| {
-                        state.angle = (state.angle + angle) % 360;
-                    });
-                }
-                TurtleAction::StochasticRotate(distribution) => {
-                    renderer.register(id, move |state| {
-                        state.angle = (state.angle + distribution.sample()) % 360;
-                    });
-                }
-                TurtleAction::StochasticForward(distribution) => {
-                    let current_global_rotate = self.global_rotate;
-
-                    renderer.register(id, move |state| {
-                        state.turtle.set_heading(
-                            ((current_global_rotate + state.angle) as f64).to_radians(),
-                        );
-                        state.turtle.forward(distribution.sample());
-                    });
-                }
-                TurtleAction::Nothing => {}
+        for (name, id) in self.tokens.iter() {
+            if let Some(action) = self.actions.remove(id) {
+                interpretation.actions.insert(name.clone(), action);
             }
         }
 
-        Ok((self.builder.finish()?, renderer))
+        let system = self.builder.finish()?;
+        let renderer = interpretation.into_renderer(&system);
+
+        Ok((system, renderer))
+    }
+
+    /// Returns the generated `LSystem` and a `Renderer` like [`TurtleLSystemBuilder::finish`],
+    /// but leaves the builder untouched so that it can be adjusted and built again.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::presets;
+    ///
+    /// let mut builder = presets::dragon_curve();
+    ///
+    /// for angle in [0, 45, 90] {
+    ///     builder.rotate(angle);
+    ///
+    ///     let (mut system, renderer) = builder.build()?;
+    ///     system.step_by(4);
+    ///     assert_eq!(renderer.metrics(&system).segments, 16);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build(&self) -> Result<(LSystem, TurtleRenderer<TurtleLSystemState>), LSystemError> {
+        self.clone().finish()
+    }
+
+    /// Checks the grammar for likely mistakes, as described in
+    /// [`LSystemBuilder::analyze`].  Tokens whose action is [`TurtleAction::Nothing`] are
+    /// taken to be variables, as they do nothing unless they're rewritten.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::analysis::GrammarWarning;
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// builder
+    ///     .token("X", TurtleAction::Nothing)?
+    ///     .token("Y", TurtleAction::Nothing)?
+    ///     .token("F", TurtleAction::Forward(10.0))?
+    ///     .token("+", TurtleAction::Rotate(90.0))?
+    ///     .axiom("F X")?
+    ///     .rule("X => X + Y F +")?;
+    ///
+    /// assert_eq!(
+    ///     builder.analyze(),
+    ///     vec![GrammarWarning::MissingRule(String::from("Y"))]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn analyze(&self) -> Vec<GrammarWarning> {
+        self.builder
+            .analyze_with(|id| matches!(self.actions.get(&id), None | Some(TurtleAction::Nothing)))
+    }
+
+    /// Returns the actions and settings of the builder as an [`Interpretation`], which can be
+    /// changed and used to render the system in a different way.
+    pub fn interpretation(&self) -> Interpretation {
+        let mut interpretation = self.settings();
+
+        for (name, id) in self.tokens.iter() {
+            if let Some(action) = self.actions.get(id) {
+                interpretation.actions.insert(name.clone(), action.clone());
+            }
+        }
+
+        interpretation
+    }
+
+    /// Returns an interpretation with the settings of the builder, but no actions.
+    fn settings(&self) -> Interpretation {
+        Interpretation {
+            actions: HashMap::new(),
+            global_rotate: self.global_rotate,
+            seed: self.seed,
+            tropism: self.tropism,
+            scale: self.scale,
+            initial_heading: self.initial_heading,
+        }
     }
 }
 
@@ -719,13 +2149,354 @@ impl Default for TurtleLSystemBuilder {
     }
 }
 
-/// An integer-valued probability distribution.
+/// Maps the names of tokens to the [`TurtleAction`]s they perform, independently of the rules
+/// of any grammar.  An interpretation is applied to a system using
+/// [`TurtleRenderer::with_interpretation`], so the same system can be drawn in several
+/// different ways.
+///
+/// Tokens which the interpretation doesn't mention do nothing, and actions for tokens which
+/// the system doesn't have are ignored.  The remaining settings behave like the methods of
+/// the same name on [`TurtleLSystemBuilder`].
+///
+/// # Example
+/// ```rust
+/// # use dcc_lsystem::LSystemError;
+/// # fn main() -> Result<(), LSystemError> {
+/// use dcc_lsystem::renderer::{DataRendererOptions, Renderer, TurtleRenderer};
+/// use dcc_lsystem::turtle::{Interpretation, TurtleAction};
+/// use dcc_lsystem::LSystemBuilder;
+///
+/// let mut builder = LSystemBuilder::new();
+/// let a = builder.token("A")?;
+/// let b = builder.token("B")?;
+/// builder.axiom(vec![a])?;
+/// builder.transformation_rule(a, vec![a, b])?;
+/// builder.transformation_rule(b, vec![a])?;
+///
+/// let mut system = builder.finish()?;
+/// system.step_by(4);
+///
+/// // Draw every symbol, or only the `B`s
+/// let mut every = Interpretation::new();
+/// every
+///     .token("A", TurtleAction::Forward(10.0))
+///     .token("B", TurtleAction::Forward(10.0));
+///
+/// let mut only_b = Interpretation::new();
+/// only_b.token("B", TurtleAction::Forward(10.0));
+///
+/// let options = DataRendererOptions::default();
+/// let every = TurtleRenderer::with_interpretation(&system, &every);
+/// let only_b = TurtleRenderer::with_interpretation(&system, &only_b);
+///
+/// assert_eq!(every.render(&system, &options).len(), 8);
+/// assert_eq!(only_b.render(&system, &options).len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Interpretation {
+    actions: HashMap<String, TurtleAction>,
+    global_rotate: f64,
+    seed: Option<u64>,
+    tropism: Option<(f64, f64, f64)>,
+    scale: f64,
+    initial_heading: f64,
+}
+
+impl Interpretation {
+    /// Create an interpretation without any actions.
+    pub fn new() -> Self {
+        Self {
+            actions: HashMap::new(),
+            global_rotate: 0.0,
+            seed: None,
+            tropism: None,
+            scale: 1.0,
+            initial_heading: 0.0,
+        }
+    }
+
+    /// Set the action performed by the token named `name`, replacing any previous action.
+    pub fn token<S: Into<String>>(&mut self, name: S, action: TurtleAction) -> &mut Self {
+        self.actions.insert(name.into(), action);
+
+        self
+    }
+
+    /// Returns the action performed by the token named `name`, if there is one.
+    pub fn action(&self, name: &str) -> Option<&TurtleAction> {
+        self.actions.get(name)
+    }
+
+    /// See [`TurtleLSystemBuilder::seed`].
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+
+        self
+    }
+
+    /// See [`TurtleLSystemBuilder::rotate`].
+    pub fn rotate<A: Into<f64>>(&mut self, angle: A) -> &mut Self {
+        self.global_rotate = angle.into();
+
+        self
+    }
+
+    /// See [`TurtleLSystemBuilder::tropism`].
+    pub fn tropism(&mut self, dx: f64, dy: f64, susceptibility: f64) -> &mut Self {
+        self.tropism = Some((dx, dy, susceptibility));
+
+        self
+    }
+
+    /// See [`TurtleLSystemBuilder::initial_heading`].
+    pub fn initial_heading(&mut self, heading: f64) -> &mut Self {
+        self.initial_heading = heading;
+
+        self
+    }
+
+    /// See [`TurtleLSystemBuilder::scale`].
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.scale = scale;
+
+        self
+    }
+
+    /// Consumes the interpretation, returning a renderer which applies it to `system`.
+    fn into_renderer(self, system: &LSystem) -> TurtleRenderer<TurtleLSystemState> {
+        let mut state = match self.seed {
+            Some(seed) => TurtleLSystemState::with_seed(seed),
+            None => TurtleLSystemState::new(),
+        };
+
+        if let Some((dx, dy, susceptibility)) = self.tropism {
+            state.set_tropism(dx, dy, susceptibility);
+        }
+
+        state.set_scale(self.scale);
+        state.set_initial_heading(self.initial_heading);
+
+        let mut renderer = TurtleRenderer::new(state);
+
+        // Register the processing functions for each action
+        for (name, action) in self.actions.into_iter() {
+            let id = match system.token_id(&name) {
+                Some(id) => id,
+                None => continue,
+            };
+
+            match action.branches().cmp(&0) {
+                Ordering::Greater => renderer.register_branch_open(id),
+                Ordering::Less => renderer.register_branch_close(id),
+                Ordering::Equal => {}
+            }
+
+            if !matches!(action, TurtleAction::Nothing) {
+                renderer.register(id, action.into_modifier(self.global_rotate));
+            }
+        }
+
+        renderer
+    }
+}
+
+impl Default for Interpretation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TurtleRenderer<TurtleLSystemState> {
+    /// Create a renderer which draws `system` using the actions of `interpretation`.
+    /// See [`Interpretation`] for an example.
+    pub fn with_interpretation(system: &LSystem, interpretation: &Interpretation) -> Self {
+        interpretation.clone().into_renderer(system)
+    }
+}
+
+/// Parses an action written as it would be in a grammar, such as `forward 10`.
+#[cfg(any(feature = "config", feature = "python"))]
+pub(crate) fn parse_action_text(text: &str) -> Result<TurtleAction, LSystemError> {
+    let words = grammar::symbols(text, 1, 1);
+
+    if words.is_empty() {
+        return Err(LSystemError::ParseError {
+            line: 1,
+            column: 1,
+            message: String::from("expected an action"),
+        });
+    }
+
+    parse_action(&words)
+}
+
+/// Parses the right hand side of an action declaration in a grammar.
+fn parse_action(words: &[Symbol]) -> Result<TurtleAction, LSystemError> {
+    // `grammar::parse` guarantees that there is at least one word
+    let name = words[0];
+    let arguments = &words[1..];
+
+    let expect_arguments = |count: usize| -> Result<(), LSystemError> {
+        if arguments.len() == count {
+            Ok(())
+        } else {
+            Err(name.error(format!(
+                "`{}` expects {} argument(s), but {} were given",
+                name.name,
+                count,
+                arguments.len()
+            )))
+        }
+    };
+
+    let parse = |symbol: &Symbol, text: &str| -> Result<f64, LSystemError> {
+        text.parse()
+            .map_err(|_| symbol.error(format!("`{}` is not a valid number", text)))
+    };
+
+    let number = |symbol: &Symbol| parse(symbol, symbol.name);
+
+    // Parses either `n` or a range `a..b`
+    let value = |symbol: &Symbol| -> Result<Result<f64, (f64, f64)>, LSystemError> {
+        match symbol.name.find("..") {
+            Some(index) => {
+                let lower = parse(symbol, &symbol.name[..index])?;
+                let upper = parse(symbol, &symbol.name[index + 2..])?;
+
+                if lower > upper {
+                    return Err(
+                        symbol.error("the lower end of a range must not exceed the upper end")
+                    );
+                }
+
+                Ok(Err((lower, upper)))
+            }
+            None => Ok(Ok(number(symbol)?)),
+        }
+    };
+
+    match name.name {
+        "nothing" | "push" | "pop" | "push_color" | "pop_color" | "pen_up" | "pen_down"
+        | "home" => {
+            expect_arguments(0)?;
+
+            Ok(match name.name {
+                "nothing" => TurtleAction::Nothing,
+                "push" => TurtleAction::Push,
+                "pop" => TurtleAction::Pop,
+                "push_color" => TurtleAction::PushColor,
+                "pop_color" => TurtleAction::PopColor,
+                "pen_up" => TurtleAction::PenUp,
+                "pen_down" => TurtleAction::PenDown,
+                _ => TurtleAction::Home,
+            })
+        }
+        "color" => {
+            expect_arguments(3)?;
+            let mut channels = [0u8; 3];
+
+            for (channel, symbol) in channels.iter_mut().zip(arguments) {
+                *channel = symbol.name.parse().map_err(|_| {
+                    symbol.error(format!("`{}` is not a valid color channel", symbol.name))
+                })?;
+            }
+
+            Ok(TurtleAction::SetColor(Rgb(channels)))
+        }
+        "thickness" | "thicker" | "thinner" | "multiply_step" | "heading" | "dot" | "leaf" => {
+            expect_arguments(1)?;
+            let value = number(&arguments[0])?;
+
+            Ok(match name.name {
+                "thickness" => TurtleAction::SetThickness(value),
+                "thicker" => TurtleAction::IncrementThickness(value),
+                "thinner" => TurtleAction::DecrementThickness(value),
+                "multiply_step" => TurtleAction::MultiplyStep(value),
+                "heading" => TurtleAction::SetHeading(value),
+                "dot" => TurtleAction::Dot(value),
+                _ => TurtleAction::Leaf(value),
+            })
+        }
+        "forward" | "rotate" => {
+            expect_arguments(1)?;
+            let forward = name.name == "forward";
+
+            Ok(match (value(&arguments[0])?, forward) {
+                (Ok(n), true) => TurtleAction::Forward(n),
+                (Ok(n), false) => TurtleAction::Rotate(n),
+                (Err((lower, upper)), true) => {
+                    TurtleAction::StochasticForward(Box::new(Uniform::new(lower, upper)))
+                }
+                (Err((lower, upper)), false) => {
+                    TurtleAction::StochasticRotate(Box::new(Uniform::new(lower, upper)))
+                }
+            })
+        }
+        "skip" => {
+            expect_arguments(1)?;
+            Ok(TurtleAction::Skip(number(&arguments[0])?))
+        }
+        "goto" => {
+            expect_arguments(2)?;
+            let mut position = [0.0; 2];
+
+            for (coordinate, symbol) in position.iter_mut().zip(arguments) {
+                *coordinate = number(symbol)?;
+            }
+
+            Ok(TurtleAction::Goto(position[0], position[1]))
+        }
+        "arc" => {
+            expect_arguments(2)?;
+
+            Ok(TurtleAction::Arc {
+                radius: number(&arguments[0])?,
+                angle: number(&arguments[1])?,
+            })
+        }
+        "label" => {
+            if arguments.is_empty() {
+                return Err(name.error("`label` expects some text"));
+            }
+
+            let words: Vec<_> = arguments.iter().map(|symbol| symbol.name).collect();
+            Ok(TurtleAction::Label(words.join(" ")))
+        }
+        #[cfg(feature = "scripting")]
+        "script" => {
+            let words: Vec<_> = arguments.iter().map(|symbol| symbol.name).collect();
+            let script = words.join(" ");
+
+            scripting::check(&script).map_err(|e| name.error(e.to_string()))?;
+            Ok(TurtleAction::Script(script))
+        }
+        "switch" => {
+            expect_arguments(1)?;
+            Ok(TurtleAction::SwitchTurtle(arguments[0].name.to_string()))
+        }
+        other => Err(name.error(format!("unknown action `{}`", other))),
+    }
+}
+
+/// A probability distribution of distances or angles.
 ///
 /// We need to be able to clone Box<dyn Distribution>, so we use the wonderful
 /// `dyn_clone` crate to allow for this.
 pub trait Distribution: dyn_clone::DynClone {
     /// Take a sample from this distribution.
-    fn sample(&self) -> i32;
+    fn sample(&self) -> f64;
+
+    /// Take a sample from this distribution using the provided random number generator.
+    ///
+    /// Seeded renders (see [`TurtleLSystemBuilder::seed`]) are only reproducible for
+    /// distributions which implement this method.  The default implementation ignores
+    /// `rng` and calls [`Distribution::sample`].
+    fn sample_with(&self, rng: &mut dyn RngCore) -> f64 {
+        let _ = rng;
+        self.sample()
+    }
 }
 
 dyn_clone::clone_trait_object!(Distribution);
@@ -733,43 +2504,409 @@ dyn_clone::clone_trait_object!(Distribution);
 /// A uniform distribution on a closed interval.
 #[derive(Clone)]
 pub struct Uniform {
-    lower: i32,
-    upper: i32,
+    lower: f64,
+    upper: f64,
 }
 
 impl Uniform {
-    /// Creates a new uniform distribution on the interval [lower, upper].
+    /// Creates a new uniform distribution on the interval [lower, upper].  The bounds can be
+    /// integers, or any other type that converts to `f64`.
     ///
     /// # Panics
-    /// Will panic is `lower` > `upper`
-    pub fn new(lower: i32, upper: i32) -> Self {
+    /// Will panic is `lower` > `upper`, or if either bound is NaN
+    pub fn new<T: Into<f64>>(lower: T, upper: T) -> Self {
+        let (lower, upper) = (lower.into(), upper.into());
         assert!(lower <= upper);
         Self { lower, upper }
     }
 }
 
 impl Distribution for Uniform {
-    fn sample(&self) -> i32 {
-        let mut rng = rand::thread_rng();
+    fn sample(&self) -> f64 {
+        self.sample_with(&mut rand::thread_rng())
+    }
+
+    fn sample_with(&self, rng: &mut dyn RngCore) -> f64 {
         rng.gen_range(self.lower..=self.upper)
     }
 }
 
 /// A constant distribution
-impl Distribution for i32 {
-    fn sample(&self) -> i32 {
+impl Distribution for f64 {
+    fn sample(&self) -> f64 {
         *self
     }
 }
 
+/// A constant distribution
+impl Distribution for i32 {
+    fn sample(&self) -> f64 {
+        f64::from(*self)
+    }
+}
+
 /// The possible actions we can associate to tokens in our `LSystem`.
 #[derive(Clone)]
 pub enum TurtleAction {
     Nothing,
-    Rotate(i32),
-    Forward(i32),
+    /// Rotate the turtle through the given angle (in degrees, turning left for positive
+    /// angles).
+    Rotate(f64),
+    /// Move the turtle forwards by the given distance.
+    Forward(f64),
     StochasticRotate(Box<dyn Distribution>),
     StochasticForward(Box<dyn Distribution>),
     Push,
     Pop,
+    /// Make the turtle with the given name the active turtle, creating it if needed.
+    SwitchTurtle(String),
+    /// Set the color of the lines drawn by the turtle from now on.
+    SetColor(Rgb<u8>),
+    /// Push the turtle's current color onto the color stack.
+    PushColor,
+    /// Pop the turtle's color off the color stack.
+    PopColor,
+    /// Set the thickness of the lines drawn by the turtle from now on.
+    SetThickness(f64),
+    /// Increase the thickness of the lines drawn by the turtle by the given amount.
+    IncrementThickness(f64),
+    /// Decrease the thickness of the lines drawn by the turtle by the given amount.  Lines
+    /// whose thickness drops to zero or below are not drawn.
+    DecrementThickness(f64),
+    /// Lift the turtle's pen, so that it moves without drawing until the pen is put down.
+    PenUp,
+    /// Put the turtle's pen down, so that it draws a line whenever it moves.
+    PenDown,
+    /// Move the turtle forwards by the given distance without drawing a line.
+    Skip(f64),
+    /// Move the turtle to the given position without drawing a line.  The position is
+    /// rotated along with the rest of the system by [`TurtleLSystemBuilder::rotate`].
+    Goto(f64, f64),
+    /// Move the turtle back to the origin without drawing a line, and reset its heading to the
+    /// [initial heading](TurtleLSystemBuilder::initial_heading).
+    Home,
+    /// Turn the turtle to face the given heading, in degrees anticlockwise from east.  The
+    /// heading is rotated along with the rest of the system by [`TurtleLSystemBuilder::rotate`].
+    SetHeading(f64),
+    /// Move the turtle along a circular arc with the given radius, turning through the given
+    /// angle (in degrees, turning left for positive angles).
+    Arc {
+        radius: f64,
+        angle: f64,
+    },
+    /// Multiply the length of the turtle's steps by the given factor, which scales the
+    /// distance moved by every action from now on.  The step length is saved and restored by
+    /// [`TurtleAction::Push`] and [`TurtleAction::Pop`], so a factor below one makes each
+    /// level of branching smaller than the last.
+    MultiplyStep(f64),
+    /// Draw a filled circle with the given radius, centred on the turtle.
+    Dot(f64),
+    /// Draw a filled leaf of the given length, growing from the turtle in the direction it
+    /// is facing.
+    Leaf(f64),
+    /// Write the given text next to the turtle.  Labels are drawn by the image renderer if it
+    /// has been given a font (see
+    /// [`ImageRendererOptionsBuilder::font`](crate::renderer::ImageRendererOptionsBuilder::font)).
+    Label(String),
+    /// Perform each of the given actions in turn.
+    Sequence(Vec<TurtleAction>),
+    /// Modify the state using the given function.  The function is reference counted so
+    /// that the builder can be cloned.
+    Custom(Rc<dyn Fn(&mut TurtleLSystemState)>),
+    /// Run the given [rhai](https://rhai.rs) script.  Requires the `scripting` feature.
+    ///
+    /// The script can read the state of the active turtle through the constants `x`, `y`,
+    /// `heading` (in degrees), `step` (see [`TurtleAction::MultiplyStep`]) and `depth` (the
+    /// number of pushes which haven't been popped yet).  It moves the turtle by calling
+    /// `forward`, `rotate`, `skip`, `goto`, `heading`, `push`, `pop`, `pen_up`, `pen_down`,
+    /// `color`, `thickness`, `multiply_step`, `dot` and `leaf`, which behave like the actions
+    /// of the same names in a [grammar](TurtleLSystemBuilder::from_grammar), and are
+    /// performed once the script has finished.
+    ///
+    /// Scripts which fail to run don't do anything, and are counted by
+    /// [`TurtleLSystemState::script_errors`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use dcc_lsystem::LSystemError;
+    /// # fn main() -> Result<(), LSystemError> {
+    /// use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+    ///
+    /// let mut builder = TurtleLSystemBuilder::new();
+    ///
+    /// // Branches get shorter the deeper they are
+    /// builder
+    ///     .token("F", TurtleAction::Script(String::from("forward(10.0 / (depth + 1))")))?
+    ///     .token("+", TurtleAction::Rotate(25.0))?
+    ///     .token("[", TurtleAction::Push)?
+    ///     .token("]", TurtleAction::Pop)?
+    ///     .axiom("F")?
+    ///     .rule("F => F [ + F ] F")?;
+    ///
+    /// let (mut system, renderer) = builder.finish()?;
+    /// system.step_by(3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "scripting")]
+    Script(String),
+}
+
+/// A function applying a [`TurtleAction`] to the state.
+type Modifier = Box<dyn FnMut(&mut TurtleLSystemState)>;
+
+/// Boxes `f`, which makes sure the type of its argument can be inferred.
+fn modifier<F: 'static + FnMut(&mut TurtleLSystemState)>(f: F) -> Modifier {
+    Box::new(f)
+}
+
+impl TurtleAction {
+    /// Returns a [`TurtleAction::Forward`] action, converting the distance from an integer
+    /// (or any other type that converts to `f64`).
+    ///
+    /// # Example
+    /// ```rust
+    /// use dcc_lsystem::turtle::TurtleAction;
+    ///
+    /// assert!(matches!(TurtleAction::forward(30), TurtleAction::Forward(d) if d == 30.0));
+    /// ```
+    pub fn forward<D: Into<f64>>(distance: D) -> Self {
+        TurtleAction::Forward(distance.into())
+    }
+
+    /// Returns a [`TurtleAction::Rotate`] action, converting the angle from an integer (or any
+    /// other type that converts to `f64`).
+    pub fn rotate<A: Into<f64>>(angle: A) -> Self {
+        TurtleAction::Rotate(angle.into())
+    }
+
+    /// Checks that every script run by the action is valid.
+    #[cfg(feature = "scripting")]
+    fn check_scripts(&self) -> Result<(), LSystemError> {
+        match self {
+            TurtleAction::Script(script) => scripting::check(script),
+            TurtleAction::Sequence(actions) => actions.iter().try_for_each(Self::check_scripts),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the number of branches opened by the action, less the number it closes.
+    fn branches(&self) -> isize {
+        match self {
+            TurtleAction::Push => 1,
+            TurtleAction::Pop => -1,
+            TurtleAction::Sequence(actions) => actions.iter().map(TurtleAction::branches).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Converts the action into a function modifying the state, for a system rotated by
+    /// `global_rotate` degrees.
+    fn into_modifier(self, global_rotate: f64) -> Modifier {
+        match self {
+            TurtleAction::Push => modifier(|state| {
+                let current = state.current();
+                current.turtle.push();
+                current.angle_stack.push((current.angle, current.step));
+
+                let style = current.turtle.inner().style();
+                current
+                    .thickness_stack
+                    .push((style.thickness, style.thickness_delta));
+            }),
+            TurtleAction::Pop => {
+                modifier(|state| {
+                    let current = state.current();
+
+                    // popping from an empty stack doesn't do anything
+                    if !current.turtle.pop() {
+                        state.unbalanced_pops += 1;
+                        return;
+                    }
+
+                    if let Some((angle, step)) = current.angle_stack.pop() {
+                        current.angle = angle;
+                        current.step = step;
+                    }
+
+                    if let Some((thickness, delta)) = current.thickness_stack.pop() {
+                        let style = current.turtle.inner_mut().style_mut();
+                        style.thickness = thickness;
+                        style.thickness_delta = delta;
+                    }
+                })
+            }
+            TurtleAction::Forward(distance) => {
+                modifier(move |state| state.current().forward(distance, global_rotate))
+            }
+            TurtleAction::Rotate(angle) => modifier(move |state| state.current().rotate(angle)),
+            TurtleAction::StochasticRotate(distribution) => modifier(move |state| {
+                let angle = distribution.sample_with(&mut state.rng);
+                state.current().rotate(angle);
+            }),
+            TurtleAction::StochasticForward(distribution) => modifier(move |state| {
+                let distance = distribution.sample_with(&mut state.rng);
+                state.current().forward(distance, global_rotate);
+            }),
+            TurtleAction::SwitchTurtle(name) => modifier(move |state| state.switch_turtle(&name)),
+            TurtleAction::SetColor(color) => modifier(move |state| {
+                state.current().turtle.inner_mut().style_mut().color = Some(color);
+            }),
+            TurtleAction::SetThickness(thickness) => modifier(move |state| {
+                let style = state.current().turtle.inner_mut().style_mut();
+                style.thickness = Some(thickness);
+                style.thickness_delta = 0.0;
+            }),
+            TurtleAction::IncrementThickness(amount) => modifier(move |state| {
+                state
+                    .current()
+                    .turtle
+                    .inner_mut()
+                    .style_mut()
+                    .thickness_delta += amount;
+            }),
+            TurtleAction::DecrementThickness(amount) => modifier(move |state| {
+                state
+                    .current()
+                    .turtle
+                    .inner_mut()
+                    .style_mut()
+                    .thickness_delta -= amount;
+            }),
+            TurtleAction::PushColor => modifier(|state| {
+                let current = state.current();
+                let color = current.turtle.inner().style().color;
+                current.color_stack.push(color);
+            }),
+            TurtleAction::PopColor => {
+                modifier(|state| {
+                    let current = state.current();
+                    // popping from an empty stack doesn't do anything
+                    if let Some(color) = current.color_stack.pop() {
+                        current.turtle.inner_mut().style_mut().color = color;
+                    }
+                })
+            }
+            TurtleAction::Skip(distance) => modifier(move |state| {
+                let current = state.current();
+                let heading = current.heading(global_rotate);
+                current.turtle.set_heading(heading);
+                current.turtle.skip(distance * current.step);
+            }),
+            TurtleAction::Goto(x, y) => {
+                let (sin, cos) = global_rotate.to_radians().sin_cos();
+
+                modifier(move |state| {
+                    state
+                        .current()
+                        .turtle
+                        .inner_mut()
+                        .set_position(x * cos - y * sin, x * sin + y * cos);
+                })
+            }
+            TurtleAction::Home => modifier(|state| {
+                let heading = state.initial_heading;
+                let current = state.current();
+                current.angle = heading;
+                current.turtle.inner_mut().set_position(0.0, 0.0);
+            }),
+            TurtleAction::SetHeading(heading) => modifier(move |state| {
+                state.current().angle = heading % 360.0;
+            }),
+            TurtleAction::PenUp => modifier(|state| state.current().turtle.inner_mut().pen_up()),
+            TurtleAction::PenDown => {
+                modifier(|state| state.current().turtle.inner_mut().pen_down())
+            }
+            TurtleAction::Nothing => modifier(|_| {}),
+            TurtleAction::Arc { radius, angle } => modifier(move |state| {
+                let current = state.current();
+                let heading = current.heading(global_rotate);
+                current.turtle.set_heading(heading);
+                current
+                    .turtle
+                    .arc(radius * current.step, angle.to_radians());
+                current.rotate(angle);
+            }),
+            TurtleAction::MultiplyStep(factor) => modifier(move |state| {
+                state.current().step *= factor;
+            }),
+            TurtleAction::Dot(radius) => modifier(move |state| {
+                let base = state.current().turtle.inner_mut();
+                let (x, y) = (base.x(), base.y());
+                base.draw_shape(Shape::Dot { x, y, radius });
+            }),
+            TurtleAction::Leaf(size) => modifier(move |state| {
+                let current = state.current();
+                let heading = current.heading(global_rotate);
+                let base = current.turtle.inner_mut();
+                let (x, y) = (base.x(), base.y());
+
+                base.draw_shape(Shape::Leaf {
+                    x,
+                    y,
+                    heading,
+                    size,
+                });
+            }),
+            TurtleAction::Label(text) => modifier(move |state| {
+                let current = state.current();
+                let heading = current.heading(global_rotate);
+                let base = current.turtle.inner_mut();
+                let (x, y) = (base.x(), base.y());
+
+                base.write(Label {
+                    x,
+                    y,
+                    heading,
+                    text: text.clone(),
+                });
+            }),
+            TurtleAction::Sequence(actions) => {
+                let mut modifiers: Vec<Modifier> = actions
+                    .into_iter()
+                    .map(|action| action.into_modifier(global_rotate))
+                    .collect();
+
+                modifier(move |state| {
+                    for apply in modifiers.iter_mut() {
+                        apply(state);
+                    }
+                })
+            }
+            TurtleAction::Custom(custom) => modifier(move |state| custom(state)),
+            #[cfg(feature = "scripting")]
+            TurtleAction::Script(script) => {
+                let queue = scripting::Queue::default();
+                let engine = scripting::engine(&queue);
+                let ast = engine.compile(&script).ok();
+
+                modifier(move |state| {
+                    let current = state.current();
+                    let base = current.turtle.inner();
+                    let mut scope = scripting::scope(
+                        base.x(),
+                        base.y(),
+                        current.angle,
+                        current.step,
+                        current.angle_stack.len(),
+                    );
+
+                    let ran = ast
+                        .as_ref()
+                        .is_some_and(|ast| engine.run_ast_with_scope(&mut scope, ast).is_ok());
+                    let actions: Vec<TurtleAction> = queue.borrow_mut().drain(..).collect();
+
+                    // A script which fails part way through doesn't do anything
+                    if !ran {
+                        state.script_errors += 1;
+                        return;
+                    }
+
+                    for action in actions {
+                        action.into_modifier(global_rotate)(state);
+                    }
+                })
+            }
+        }
+    }
 }
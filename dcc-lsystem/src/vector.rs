@@ -0,0 +1,400 @@
+//! Renders turtle output as an EPS or PDF file, which can be printed at any size.
+//!
+//! # Example
+//! ```rust
+//! # use dcc_lsystem::LSystemError;
+//! # fn main() -> Result<(), LSystemError> {
+//! use dcc_lsystem::renderer::{Renderer, VectorRendererOptionsBuilder};
+//! use dcc_lsystem::turtle::{TurtleAction, TurtleLSystemBuilder};
+//! use dcc_lsystem::vector::VectorFormat;
+//!
+//! let mut builder = TurtleLSystemBuilder::new();
+//!
+//! builder
+//!     .token("F", TurtleAction::Forward(10.0))?
+//!     .token("+", TurtleAction::Rotate(90.0))?
+//!     .token("-", TurtleAction::Rotate(-90.0))?
+//!     .axiom("F")?
+//!     .rule("F => F + F - F - F + F")?;
+//!
+//! let (mut system, renderer) = builder.finish()?;
+//! system.step_by(3);
+//!
+//! let options = VectorRendererOptionsBuilder::new()
+//!     .format(VectorFormat::Pdf)
+//!     .scale(2.0)
+//!     .build();
+//!
+//! let pdf = renderer.render(&system, &options);
+//! assert!(pdf.starts_with(b"%PDF-1.4"));
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Write;
+
+use image::Rgb;
+
+use crate::renderer::{bounds, segments, shapes, Dasher, Renderer, StrokeStyle, TurtleRenderer};
+use crate::turtle::TurtleContainer;
+use crate::LSystem;
+
+/// The file formats supported by the vector renderer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VectorFormat {
+    /// Encapsulated PostScript.
+    Eps,
+    /// Portable Document Format.
+    Pdf,
+}
+
+pub struct VectorRendererOptionsBuilder {
+    options: VectorRendererOptions,
+}
+
+impl VectorRendererOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: VectorRendererOptions {
+                format: VectorFormat::Pdf,
+                scale: 1.0,
+                padding: 20.0,
+                thickness: 2.0,
+                fill_color: None,
+                line_color: Rgb([0, 0, 0]),
+                stroke_style: None,
+            },
+        }
+    }
+
+    pub fn format(&mut self, format: VectorFormat) -> &mut Self {
+        self.options.format = format;
+        self
+    }
+
+    /// Set the length, in points, of a single turtle unit.
+    pub fn scale(&mut self, scale: f64) -> &mut Self {
+        self.options.scale = scale;
+        self
+    }
+
+    /// Set the space around the drawing, in points.
+    pub fn padding(&mut self, padding: f64) -> &mut Self {
+        self.options.padding = padding;
+        self
+    }
+
+    /// Set the thickness of lines without their own thickness, in turtle units.
+    pub fn thickness(&mut self, thickness: f64) -> &mut Self {
+        self.options.thickness = thickness;
+        self
+    }
+
+    /// Fill the background with a color.  By default the background is left transparent.
+    pub fn fill_color(&mut self, fill_color: Rgb<u8>) -> &mut Self {
+        self.options.fill_color = Some(fill_color);
+        self
+    }
+
+    /// Set the color of lines without their own color.
+    pub fn line_color(&mut self, line_color: Rgb<u8>) -> &mut Self {
+        self.options.line_color = line_color;
+        self
+    }
+
+    /// Draw lines with a dash pattern, measured in turtle units.
+    pub fn stroke_style(&mut self, stroke_style: StrokeStyle) -> &mut Self {
+        self.options.stroke_style = Some(stroke_style);
+        self
+    }
+
+    pub fn build(&mut self) -> VectorRendererOptions {
+        self.options.clone()
+    }
+}
+
+impl Default for VectorRendererOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VectorRendererOptions {
+    format: VectorFormat,
+    scale: f64,
+    padding: f64,
+    thickness: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb_option"))]
+    fill_color: Option<Rgb<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::serde_rgb"))]
+    line_color: Rgb<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    stroke_style: Option<StrokeStyle>,
+}
+
+impl VectorRendererOptions {
+    pub fn format(&self) -> VectorFormat {
+        self.format
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn padding(&self) -> f64 {
+        self.padding
+    }
+
+    pub fn thickness(&self) -> f64 {
+        self.thickness
+    }
+
+    pub fn fill_color(&self) -> Option<Rgb<u8>> {
+        self.fill_color
+    }
+
+    pub fn line_color(&self) -> Rgb<u8> {
+        self.line_color
+    }
+
+    pub fn stroke_style(&self) -> Option<&StrokeStyle> {
+        self.stroke_style.as_ref()
+    }
+}
+
+/// The drawing operators of PostScript and PDF, which differ only in spelling.
+struct Operators {
+    color: &'static str,
+    fill_color: &'static str,
+    line_width: &'static str,
+    move_to: &'static str,
+    line_to: &'static str,
+    stroke: &'static str,
+    dash: &'static str,
+    fill: &'static str,
+    rectangle: &'static str,
+}
+
+const POSTSCRIPT: Operators = Operators {
+    color: "setrgbcolor",
+    fill_color: "setrgbcolor",
+    line_width: "setlinewidth",
+    move_to: "moveto",
+    line_to: "lineto",
+    stroke: "stroke",
+    dash: "setdash",
+    fill: "closepath fill",
+    rectangle: "rectfill",
+};
+
+const PDF: Operators = Operators {
+    color: "RG",
+    fill_color: "rg",
+    line_width: "w",
+    move_to: "m",
+    line_to: "l",
+    stroke: "S",
+    dash: "d",
+    fill: "h f",
+    rectangle: "re f",
+};
+
+/// Returns `color` as the three numbers between `0` and `1` used by PostScript and PDF.
+fn components(color: Rgb<u8>) -> String {
+    let Rgb([red, green, blue]) = color;
+    format!(
+        "{:.3} {:.3} {:.3}",
+        red as f64 / 255.0,
+        green as f64 / 255.0,
+        blue as f64 / 255.0
+    )
+}
+
+/// Returns the drawing commands for `state`, together with the width and height of the page.
+fn draw<Q: TurtleContainer>(
+    state: &Q,
+    options: &VectorRendererOptions,
+    operators: &Operators,
+) -> (String, f64, f64) {
+    let (turtle_width, turtle_height, min_x, min_y) = bounds(state);
+
+    let width = turtle_width * options.scale + 2.0 * options.padding;
+    let height = turtle_height * options.scale + 2.0 * options.padding;
+
+    let xp = |x: f64| (x - min_x) * options.scale + options.padding;
+    let yp = |y: f64| (y - min_y) * options.scale + options.padding;
+
+    // unwrap (here and below): writing to a String never fails
+    let mut commands = String::new();
+
+    if let Some(fill_color) = options.fill_color {
+        writeln!(
+            commands,
+            "{} {}\n0 0 {:.3} {:.3} {}",
+            components(fill_color),
+            operators.fill_color,
+            width,
+            height,
+            operators.rectangle
+        )
+        .unwrap();
+    }
+
+    let mut dasher = options
+        .stroke_style
+        .as_ref()
+        .and_then(|style| Dasher::new(style, options.scale));
+
+    let dash_array = options
+        .stroke_style
+        .as_ref()
+        .map(|style| {
+            style
+                .scaled_pattern(options.scale)
+                .iter()
+                .map(|length| format!("{:.3}", length))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+
+    for ((x1, y1, x2, y2), style) in segments(state) {
+        let thickness = style.resolved_thickness(options.thickness);
+
+        if thickness > 0.0 {
+            // Each line is stroked separately, so the dash pattern is started at the point
+            // reached by the previous line to keep it flowing around corners
+            if let Some(dasher) = &mut dasher {
+                let phase = dasher.phase(xp(*x1), yp(*y1), xp(*x2), yp(*y2));
+                writeln!(commands, "[{}] {:.3} {}", dash_array, phase, operators.dash).unwrap();
+            }
+
+            writeln!(
+                commands,
+                "{} {}\n{:.3} {}\n{:.3} {:.3} {}\n{:.3} {:.3} {}\n{}",
+                components(style.color.unwrap_or(options.line_color)),
+                operators.color,
+                thickness * options.scale,
+                operators.line_width,
+                xp(*x1),
+                yp(*y1),
+                operators.move_to,
+                xp(*x2),
+                yp(*y2),
+                operators.line_to,
+                operators.stroke
+            )
+            .unwrap();
+        }
+    }
+
+    for (shape, style) in shapes(state) {
+        writeln!(
+            commands,
+            "{} {}",
+            components(style.color.unwrap_or(options.line_color)),
+            operators.fill_color
+        )
+        .unwrap();
+
+        for (index, (x, y)) in shape.outline().into_iter().enumerate() {
+            let operator = if index == 0 {
+                operators.move_to
+            } else {
+                operators.line_to
+            };
+
+            writeln!(commands, "{:.3} {:.3} {}", xp(x), yp(y), operator).unwrap();
+        }
+
+        writeln!(commands, "{}", operators.fill).unwrap();
+    }
+
+    (commands, width, height)
+}
+
+/// Wraps `commands` in an EPS file.
+fn eps(commands: &str, width: f64, height: f64) -> Vec<u8> {
+    let mut output = String::new();
+
+    writeln!(output, "%!PS-Adobe-3.0 EPSF-3.0").unwrap();
+    writeln!(
+        output,
+        "%%BoundingBox: 0 0 {} {}",
+        width.ceil(),
+        height.ceil()
+    )
+    .unwrap();
+    writeln!(output, "%%HiResBoundingBox: 0 0 {:.3} {:.3}", width, height).unwrap();
+    writeln!(output, "%%EndComments").unwrap();
+    writeln!(output, "1 setlinecap\n1 setlinejoin").unwrap();
+    output.push_str(commands);
+    writeln!(output, "showpage\n%%EOF").unwrap();
+
+    output.into_bytes()
+}
+
+/// Wraps `commands` in a single page PDF file.
+fn pdf(commands: &str, width: f64, height: f64) -> Vec<u8> {
+    let content = format!("1 J\n1 j\n{}", commands);
+
+    let objects = [
+        String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+        String::from("<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] /Contents 4 0 R >>",
+            width, height
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ),
+    ];
+
+    let mut output = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(output.len());
+        writeln!(output, "{} 0 obj\n{}\nendobj", index + 1, object).unwrap();
+    }
+
+    let xref = output.len();
+    writeln!(output, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1).unwrap();
+
+    for offset in offsets {
+        writeln!(output, "{:010} 00000 n ", offset).unwrap();
+    }
+
+    writeln!(
+        output,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF",
+        objects.len() + 1,
+        xref
+    )
+    .unwrap();
+
+    output.into_bytes()
+}
+
+impl<Q: TurtleContainer + Clone> Renderer<VectorRendererOptions> for TurtleRenderer<Q> {
+    type Output = Vec<u8>;
+
+    fn render(&self, system: &LSystem, options: &VectorRendererOptions) -> Self::Output {
+        let state = self.compute(system.get_state());
+
+        match options.format {
+            VectorFormat::Eps => {
+                let (commands, width, height) = draw(&state, options, &POSTSCRIPT);
+                eps(&commands, width, height)
+            }
+            VectorFormat::Pdf => {
+                let (commands, width, height) = draw(&state, options, &PDF);
+                pdf(&commands, width, height)
+            }
+        }
+    }
+}
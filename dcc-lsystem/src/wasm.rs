@@ -0,0 +1,82 @@
+//! Bindings for driving turtle systems from JavaScript, with `wasm-bindgen`.
+//!
+//! Requires the `wasm` feature.  The image renderers aren't available in the browser, so build
+//! for `wasm32-unknown-unknown` without the default features:
+//!
+//! ```text
+//! cargo build --target wasm32-unknown-unknown --no-default-features --features wasm
+//! ```
+//!
+//! The lines drawn by the system can then be drawn however suits the page, for example on a
+//! canvas:
+//!
+//! ```text
+//! const system = new TurtleSystem(`
+//!     F = forward 10
+//!     + = rotate 90
+//!     - = rotate -90
+//!     axiom: F
+//!     F => F+F-F-F+F
+//! `);
+//! system.step_by(3);
+//!
+//! const lines = system.segments();
+//! for (let i = 0; i < lines.length; i += 4) {
+//!     context.moveTo(lines[i], lines[i + 1]);
+//!     context.lineTo(lines[i + 2], lines[i + 3]);
+//! }
+//! ```
+use wasm_bindgen::prelude::*;
+
+use crate::renderer::{DataRendererOptions, Renderer, TurtleRenderer};
+use crate::turtle::{TurtleLSystemBuilder, TurtleLSystemState};
+use crate::LSystem;
+
+/// A turtle system, together with the renderer that draws it.
+#[wasm_bindgen]
+pub struct TurtleSystem {
+    system: LSystem,
+    renderer: TurtleRenderer<TurtleLSystemState>,
+}
+
+#[wasm_bindgen]
+impl TurtleSystem {
+    /// Builds a system from a grammar in the format accepted by
+    /// [`TurtleLSystemBuilder::from_grammar`].  Invalid grammars throw an error.
+    #[wasm_bindgen(constructor)]
+    pub fn new(grammar: &str) -> Result<TurtleSystem, JsError> {
+        let (system, renderer) = TurtleLSystemBuilder::from_grammar(grammar)?.finish()?;
+
+        Ok(Self { system, renderer })
+    }
+
+    /// Applies the rules of the system once.
+    pub fn step(&mut self) {
+        self.system.step();
+    }
+
+    /// Applies the rules of the system `n` times.
+    pub fn step_by(&mut self, n: usize) {
+        self.system.step_by(n);
+    }
+
+    /// Resets the system to its axiom.
+    pub fn reset(&mut self) {
+        self.system.reset();
+    }
+
+    /// Returns the current state of the system as a string of tokens.
+    pub fn state(&self) -> String {
+        self.system.render()
+    }
+
+    /// Returns the lines drawn by the current state of the system, flattened into
+    /// `x1, y1, x2, y2` for each line in turn.  JavaScript receives this as a `Float64Array`.
+    pub fn segments(&self) -> Vec<f64> {
+        self.renderer
+            .render(&self.system, &DataRendererOptions::default())
+            .into_iter()
+            .flat_map(|(x1, y1, x2, y2)| [x1, y1, x2, y2])
+            .collect()
+    }
+}